@@ -0,0 +1,102 @@
+//! C-CDA (HL7 CDA R2 / C-CDA R2.1) document inspection and validation.
+
+mod rules;
+mod validate;
+
+use std::fs;
+use std::sync::LazyLock;
+
+#[allow(unused_imports)]
+pub use rules::{DocumentRule, RuleSet, SectionRule, US_REALM_HEADER};
+#[allow(unused_imports)]
+pub use validate::{CDA_NAMESPACE, DocumentValidation, SectionSummary, validate_document};
+
+use crate::validate::{
+    FailurePolicy, OperationOutcome, ThemeRules, ValidateError, ValidationReport,
+    build_local_report, is_failure, print_report,
+};
+
+static THEMES: LazyLock<ThemeRules> = LazyLock::new(|| {
+    ThemeRules::from_toml(include_str!("themes.toml")).expect("bundled C-CDA themes are valid")
+});
+
+/// Validates a C-CDA document against the bundled rules and builds a report.
+pub fn validate_text(text: &str, file: &str) -> ValidationReport {
+    let validation = validate_document(text, RuleSet::bundled());
+    let mut outcome = OperationOutcome::empty();
+    outcome.issue = validation.issues.clone();
+    build_local_report(
+        &outcome,
+        "C-CDA Validation",
+        file,
+        &validation.describe(),
+        &THEMES,
+    )
+}
+
+/// `clinlogix cda validate`: prints the report and fails per `policy`.
+pub fn run_validate_file(path: &str, policy: &FailurePolicy) -> Result<(), ValidateError> {
+    let text = read_document(path)?;
+    let mut report = validate_text(&text, path);
+    report.policy = policy.clone();
+    print_report(&report);
+    if is_failure(&report) {
+        return Err(ValidateError::Failed("C-CDA"));
+    }
+    Ok(())
+}
+
+/// `clinlogix cda inspect`: prints the document template and its sections.
+pub fn run_inspect_file(path: &str) -> Result<(), ValidateError> {
+    let text = read_document(path)?;
+    let validation = validate_document(&text, RuleSet::bundled());
+    print!("{}", format_inspection(&validation));
+    Ok(())
+}
+
+pub fn format_inspection(validation: &DocumentValidation) -> String {
+    let mut output = format!(
+        "Document: {}\n",
+        validation
+            .document
+            .as_deref()
+            .unwrap_or("unrecognized template")
+    );
+    if let Some(title) = &validation.title {
+        output.push_str(&format!("Title: {title}\n"));
+    }
+    if !validation.templates.is_empty() {
+        output.push_str(&format!("Templates: {}\n", validation.templates.join(", ")));
+    }
+    output.push_str(&format!("Sections: {}\n", validation.sections.len()));
+    for section in &validation.sections {
+        output.push_str(&format!(
+            "- {} (code: {}, entries: {}, line {})\n",
+            section.name,
+            section.code.as_deref().unwrap_or("none"),
+            section.entries,
+            section.line
+        ));
+    }
+    let errors = validation
+        .issues
+        .iter()
+        .filter(|issue| matches!(issue.severity.as_deref(), Some("error" | "fatal")))
+        .count();
+    output.push_str(&format!(
+        "Issues: {} ({errors} errors); run `clinlogix cda validate` for details\n",
+        validation.issues.len()
+    ));
+    output
+}
+
+fn read_document(path: &str) -> Result<String, ValidateError> {
+    let text =
+        fs::read_to_string(path).map_err(|err| ValidateError::Input(format!("{path}: {err}")))?;
+    if !text.contains("ClinicalDocument") {
+        return Err(ValidateError::Input(format!(
+            "{path}: no CDA ClinicalDocument found"
+        )));
+    }
+    Ok(text)
+}
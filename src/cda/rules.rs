@@ -0,0 +1,119 @@
+use std::sync::LazyLock;
+
+use serde::Deserialize;
+
+/// US Realm Header, which every C-CDA document template builds on.
+pub const US_REALM_HEADER: &str = "2.16.840.1.113883.10.20.22.1.1";
+
+/// A document template and the sections it requires (`[[document]]`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DocumentRule {
+    pub name: String,
+    pub templates: Vec<String>,
+    /// LOINC document type code expected in `ClinicalDocument/code`.
+    pub code: String,
+    #[serde(default)]
+    pub required: Vec<String>,
+    #[serde(default)]
+    pub recommended: Vec<String>,
+}
+
+/// A section template (`[[section]]`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SectionRule {
+    pub key: String,
+    pub name: String,
+    pub templates: Vec<String>,
+    /// LOINC section code.
+    pub code: String,
+    #[serde(default)]
+    pub entries_required: bool,
+    #[serde(default)]
+    pub entry_templates: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleSet {
+    #[serde(default)]
+    pub document: Vec<DocumentRule>,
+    #[serde(default)]
+    pub section: Vec<SectionRule>,
+}
+
+impl RuleSet {
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let rules: RuleSet = toml::from_str(text).map_err(|err| err.to_string())?;
+        for document in &rules.document {
+            for key in document.required.iter().chain(&document.recommended) {
+                if rules.section_by_key(key).is_none() {
+                    return Err(format!(
+                        "document '{}': unknown section '{key}'",
+                        document.name
+                    ));
+                }
+            }
+        }
+        Ok(rules)
+    }
+
+    /// The bundled C-CDA R2.1 rules.
+    pub fn bundled() -> &'static RuleSet {
+        &BUNDLED
+    }
+
+    pub fn document_by_template(&self, root: &str) -> Option<&DocumentRule> {
+        self.document
+            .iter()
+            .find(|document| document.templates.iter().any(|template| template == root))
+    }
+
+    pub fn section_by_key(&self, key: &str) -> Option<&SectionRule> {
+        self.section.iter().find(|section| section.key == key)
+    }
+
+    pub fn section_by_template(&self, root: &str) -> Option<&SectionRule> {
+        self.section
+            .iter()
+            .find(|section| section.templates.iter().any(|template| template == root))
+    }
+
+    pub fn section_by_code(&self, code: &str) -> Option<&SectionRule> {
+        self.section.iter().find(|section| section.code == code)
+    }
+}
+
+static BUNDLED: LazyLock<RuleSet> = LazyLock::new(|| {
+    RuleSet::from_toml(include_str!("rules.toml")).expect("bundled C-CDA rules are valid")
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_rules_cover_the_supported_documents() {
+        let rules = RuleSet::bundled();
+        let ccd = rules
+            .document_by_template("2.16.840.1.113883.10.20.22.1.2")
+            .expect("CCD rule");
+        assert!(ccd.required.contains(&"allergies".to_string()));
+        assert!(
+            rules
+                .document_by_template("2.16.840.1.113883.10.20.22.1.8")
+                .is_some()
+        );
+        assert_eq!(
+            rules
+                .section_by_template("2.16.840.1.113883.10.20.22.2.6")
+                .map(|section| section.key.as_str()),
+            Some("allergies")
+        );
+
+        let broken =
+            "[[document]]\nname = \"X\"\ntemplates = []\ncode = \"1\"\nrequired = [\"nope\"]\n";
+        assert!(RuleSet::from_toml(broken).unwrap_err().contains("nope"));
+    }
+}
@@ -0,0 +1,673 @@
+use roxmltree::{Document, Node};
+
+use crate::cda::rules::{DocumentRule, RuleSet, SectionRule, US_REALM_HEADER};
+use crate::validate::Issue;
+
+pub const CDA_NAMESPACE: &str = "urn:hl7-org:v3";
+
+/// Version extension carried by C-CDA R2.1 templateIds.
+const R21_EXTENSION: &str = "2015-08-01";
+
+/// Header elements the US Realm Header requires, in document order.
+const HEADER_ELEMENTS: &[&str] = &[
+    "id",
+    "code",
+    "title",
+    "effectiveTime",
+    "confidentialityCode",
+    "recordTarget",
+    "author",
+    "custodian",
+];
+
+/// A top-level section found in the structured body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionSummary {
+    /// Rule key when the section was recognized.
+    pub key: Option<String>,
+    /// Rule name, else the section title.
+    pub name: String,
+    pub templates: Vec<String>,
+    pub code: Option<String>,
+    pub entries: usize,
+    pub line: usize,
+}
+
+/// Result of checking one C-CDA document.
+#[derive(Debug, Clone, Default)]
+pub struct DocumentValidation {
+    /// Name of the recognized document template.
+    pub document: Option<String>,
+    pub title: Option<String>,
+    pub templates: Vec<String>,
+    pub sections: Vec<SectionSummary>,
+    pub issues: Vec<Issue>,
+}
+
+impl DocumentValidation {
+    /// Short label for the report's validator line, e.g. `C-CDA Discharge Summary`.
+    pub fn describe(&self) -> String {
+        match &self.document {
+            Some(document) => format!("C-CDA {document}"),
+            None => "C-CDA (unrecognized document template)".to_string(),
+        }
+    }
+}
+
+/// Parses `text` and checks it against `rules`.
+///
+/// XML that is not well-formed yields a single fatal issue at the failing line.
+pub fn validate_document(text: &str, rules: &RuleSet) -> DocumentValidation {
+    let mut validation = DocumentValidation::default();
+    let document = match Document::parse(text) {
+        Ok(document) => document,
+        Err(err) => {
+            validation.issues.push(
+                Issue::local(
+                    "fatal",
+                    "structure",
+                    format!("XML is not well-formed: {err}"),
+                    "",
+                )
+                .with_line(err.pos().row),
+            );
+            return validation;
+        }
+    };
+    let mut checker = Checker {
+        document: &document,
+        rules,
+        issues: Vec::new(),
+    };
+    let root = document.root_element();
+    if root.tag_name().name() != "ClinicalDocument"
+        || root.tag_name().namespace() != Some(CDA_NAMESPACE)
+    {
+        checker.issue(
+            "fatal",
+            "structure",
+            format!(
+                "Root element is {}; expected ClinicalDocument in the {CDA_NAMESPACE} namespace",
+                root.tag_name().name()
+            ),
+            root,
+        );
+        validation.issues = checker.issues;
+        return validation;
+    }
+
+    validation.templates = template_ids(root)
+        .map(|(template, _, _)| template.to_string())
+        .collect();
+    validation.title = child(root, "title")
+        .and_then(|title| title.text())
+        .map(str::to_string);
+    let rule = checker.check_header(root);
+    validation.document = rule.map(|rule| rule.name.clone());
+    validation.sections = checker.check_body(root, rule);
+    validation.issues = checker.issues;
+    validation
+}
+
+struct Checker<'a, 'input> {
+    document: &'a Document<'input>,
+    rules: &'a RuleSet,
+    issues: Vec<Issue>,
+}
+
+impl<'a, 'input> Checker<'a, 'input> {
+    fn issue(&mut self, severity: &str, code: &str, message: String, node: Node) {
+        let line = self.document.text_pos_at(node.range().start).row;
+        self.issues
+            .push(Issue::local(severity, code, message, &path(node)).with_line(line));
+    }
+
+    /// Checks the US Realm Header and returns the recognized document template.
+    fn check_header(&mut self, root: Node<'a, 'input>) -> Option<&'a DocumentRule> {
+        let templates: Vec<_> = template_ids(root).collect();
+        match templates
+            .iter()
+            .find(|(template, _, _)| *template == US_REALM_HEADER)
+        {
+            Some((_, extension, node)) => {
+                self.check_extension(US_REALM_HEADER, "US Realm Header", *extension, *node)
+            }
+            None => self.issue(
+                "error",
+                "required",
+                format!(
+                    "US Realm Header templateId ({US_REALM_HEADER}) is missing from the header"
+                ),
+                root,
+            ),
+        }
+
+        for name in HEADER_ELEMENTS {
+            if child(root, name).is_none() {
+                self.issue(
+                    "error",
+                    "required",
+                    format!("Header element {name} is missing"),
+                    root,
+                );
+            }
+        }
+        if let Some(record_target) = child(root, "recordTarget") {
+            match child(record_target, "patientRole") {
+                Some(role) if child(role, "patient").is_none() => self.issue(
+                    "error",
+                    "required",
+                    "Header recordTarget/patientRole has no patient".to_string(),
+                    role,
+                ),
+                Some(_) => {}
+                None => self.issue(
+                    "error",
+                    "required",
+                    "Header recordTarget has no patientRole".to_string(),
+                    record_target,
+                ),
+            }
+        }
+        if let Some(time) = child(root, "effectiveTime") {
+            self.check_timestamp(time);
+        }
+
+        let found = templates.iter().find_map(|(template, extension, node)| {
+            self.rules
+                .document_by_template(template)
+                .map(|rule| (rule, *template, *extension, *node))
+        });
+        let Some((rule, template, extension, node)) = found else {
+            let declared = templates
+                .iter()
+                .map(|(template, _, _)| *template)
+                .filter(|template| *template != US_REALM_HEADER)
+                .collect::<Vec<_>>();
+            let declared = if declared.is_empty() {
+                "none".to_string()
+            } else {
+                declared.join(", ")
+            };
+            self.issue(
+                "warning",
+                "not-supported",
+                format!(
+                    "Document template not recognized from templateId ({declared}); only header and section checks were applied"
+                ),
+                root,
+            );
+            return None;
+        };
+        self.check_extension(template, &rule.name, extension, node);
+        if let Some(code) = child(root, "code") {
+            let value = code.attribute("code").unwrap_or_default();
+            if value != rule.code {
+                self.issue(
+                    "warning",
+                    "code-invalid",
+                    format!(
+                        "Document code '{value}' does not match {} (LOINC {})",
+                        rule.name, rule.code
+                    ),
+                    code,
+                );
+            }
+        }
+        Some(rule)
+    }
+
+    fn check_extension(&mut self, template: &str, name: &str, extension: Option<&str>, node: Node) {
+        if extension.is_none() {
+            self.issue(
+                "information",
+                "informational",
+                format!(
+                    "templateId {template} ({name}) has no version extension; C-CDA R2.1 uses extension=\"{R21_EXTENSION}\""
+                ),
+                node,
+            );
+        }
+    }
+
+    fn check_timestamp(&mut self, node: Node) {
+        let value = node.attribute("value").unwrap_or_default();
+        if node.attribute("nullFlavor").is_none() && !is_timestamp(value) {
+            self.issue(
+                "error",
+                "value",
+                format!("effectiveTime value '{value}' is not a valid HL7 timestamp"),
+                node,
+            );
+        }
+    }
+
+    fn check_body(
+        &mut self,
+        root: Node<'a, 'input>,
+        rule: Option<&'a DocumentRule>,
+    ) -> Vec<SectionSummary> {
+        let Some(component) = child(root, "component") else {
+            self.issue(
+                "error",
+                "required",
+                "Document has no component (body)".to_string(),
+                root,
+            );
+            return Vec::new();
+        };
+        let Some(body) = child(component, "structuredBody") else {
+            if child(component, "nonXMLBody").is_some() {
+                self.issue(
+                    "information",
+                    "informational",
+                    "Document has an unstructured body (nonXMLBody); section rules were not applied"
+                        .to_string(),
+                    component,
+                );
+            } else {
+                self.issue(
+                    "error",
+                    "structure",
+                    "Document body has no structuredBody".to_string(),
+                    component,
+                );
+            }
+            return Vec::new();
+        };
+
+        let sections: Vec<SectionSummary> = children(body, "component")
+            .filter_map(|component| child(component, "section"))
+            .map(|section| self.check_section(section))
+            .collect();
+
+        if let Some(rule) = rule {
+            for (keys, severity, code, label) in [
+                (&rule.required, "error", "required", "Required"),
+                (
+                    &rule.recommended,
+                    "information",
+                    "incomplete",
+                    "Recommended",
+                ),
+            ] {
+                for key in keys {
+                    if sections
+                        .iter()
+                        .any(|section| section.key.as_deref() == Some(key.as_str()))
+                    {
+                        continue;
+                    }
+                    let Some(section) = self.rules.section_by_key(key) else {
+                        continue;
+                    };
+                    self.issue(
+                        severity,
+                        code,
+                        format!(
+                            "{label} section {} ({}) is missing from {}",
+                            section.name, section.code, rule.name
+                        ),
+                        body,
+                    );
+                }
+            }
+        }
+        sections
+    }
+
+    fn check_section(&mut self, section: Node<'a, 'input>) -> SectionSummary {
+        let templates: Vec<_> = template_ids(section).collect();
+        let code = child(section, "code").and_then(|code| code.attribute("code"));
+        let title = child(section, "title").and_then(|title| title.text());
+        let by_template = templates
+            .iter()
+            .find_map(|(template, _, _)| self.rules.section_by_template(template));
+        let rule = match by_template {
+            Some(rule) => Some(rule),
+            None => {
+                let rule = code.and_then(|code| self.rules.section_by_code(code));
+                if let Some(rule) = rule {
+                    self.issue(
+                        "warning",
+                        "structure",
+                        format!(
+                            "Section with LOINC code {} ({}) does not declare templateId {}",
+                            rule.code, rule.name, rule.templates[0]
+                        ),
+                        section,
+                    );
+                } else if templates.is_empty() {
+                    self.issue(
+                        "warning",
+                        "required",
+                        format!(
+                            "Section '{}' has no templateId",
+                            title.unwrap_or("(untitled)")
+                        ),
+                        section,
+                    );
+                }
+                rule
+            }
+        };
+        let name = rule
+            .map(|rule| rule.name.clone())
+            .or_else(|| title.map(str::to_string))
+            .unwrap_or_else(|| "(untitled)".to_string());
+        let null_flavor = section.attribute("nullFlavor").is_some();
+
+        match (code, rule) {
+            (None, _) => self.issue(
+                "error",
+                "required",
+                format!("Section {name} has no code"),
+                section,
+            ),
+            (Some(code), Some(rule)) if code != rule.code => self.issue(
+                "warning",
+                "code-invalid",
+                format!(
+                    "Section {name} has code '{code}'; expected LOINC {}",
+                    rule.code
+                ),
+                section,
+            ),
+            _ => {}
+        }
+        if title.is_none() {
+            self.issue(
+                "error",
+                "required",
+                format!("Section {name} has no title"),
+                section,
+            );
+        }
+        if child(section, "text").is_none() && !null_flavor {
+            self.issue(
+                "error",
+                "required",
+                format!("Section {name} has no narrative text"),
+                section,
+            );
+        }
+
+        let entries: Vec<_> = children(section, "entry").collect();
+        if let Some(rule) = rule {
+            if rule.entries_required && entries.is_empty() && !null_flavor {
+                self.issue(
+                    "error",
+                    "required",
+                    format!(
+                        "Section {name} requires at least one entry (or nullFlavor=\"NI\" when there is no information)"
+                    ),
+                    section,
+                );
+            }
+            for (index, entry) in entries.iter().enumerate() {
+                self.check_entry(*entry, index + 1, rule);
+            }
+        }
+
+        SectionSummary {
+            key: rule.map(|rule| rule.key.clone()),
+            name,
+            templates: templates
+                .iter()
+                .map(|(template, _, _)| template.to_string())
+                .collect(),
+            code: code.map(str::to_string),
+            entries: entries.len(),
+            line: self.document.text_pos_at(section.range().start).row as usize,
+        }
+    }
+
+    fn check_entry(&mut self, entry: Node, number: usize, rule: &SectionRule) {
+        let Some(statement) = entry.children().find(Node::is_element) else {
+            self.issue(
+                "error",
+                "required",
+                format!("Entry {number} in {} has no clinical statement", rule.name),
+                entry,
+            );
+            return;
+        };
+        let templates: Vec<_> = template_ids(statement)
+            .map(|(template, _, _)| template)
+            .collect();
+        if templates.is_empty() {
+            self.issue(
+                "error",
+                "required",
+                format!(
+                    "Entry {number} in {} ({}) has no templateId",
+                    rule.name,
+                    statement.tag_name().name()
+                ),
+                statement,
+            );
+        } else if !rule.entry_templates.is_empty()
+            && !templates
+                .iter()
+                .any(|template| rule.entry_templates.iter().any(|wanted| wanted == template))
+        {
+            self.issue(
+                "warning",
+                "structure",
+                format!(
+                    "Entry {number} in {} declares {}; expected {}",
+                    rule.name,
+                    templates.join(", "),
+                    rule.entry_templates.join(" or ")
+                ),
+                statement,
+            );
+        }
+    }
+}
+
+/// `(root, extension, node)` for each `templateId` child.
+fn template_ids<'a, 'input>(
+    node: Node<'a, 'input>,
+) -> impl Iterator<Item = (&'a str, Option<&'a str>, Node<'a, 'input>)> {
+    children(node, "templateId").filter_map(|template| {
+        Some((
+            template.attribute("root")?,
+            template.attribute("extension"),
+            template,
+        ))
+    })
+}
+
+fn children<'a, 'input>(
+    node: Node<'a, 'input>,
+    name: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(move |candidate| {
+        candidate.is_element()
+            && candidate.tag_name().name() == name
+            && candidate.tag_name().namespace() == Some(CDA_NAMESPACE)
+    })
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &'static str) -> Option<Node<'a, 'input>> {
+    children(node, name).next()
+}
+
+/// Element path such as `ClinicalDocument/component/structuredBody/component[2]/section`.
+fn path(node: Node) -> String {
+    let mut parts = Vec::new();
+    for ancestor in node.ancestors().filter(Node::is_element) {
+        let name = ancestor.tag_name().name();
+        let same_name = |sibling: &Node| sibling.is_element() && sibling.tag_name().name() == name;
+        // Both sibling iterators start at the node itself.
+        let position = ancestor.prev_siblings().filter(same_name).count();
+        let total = position + ancestor.next_siblings().filter(same_name).count() - 1;
+        if total > 1 {
+            parts.push(format!("{name}[{position}]"))
+        } else {
+            parts.push(name.to_string())
+        }
+    }
+    parts.reverse();
+    parts.join("/")
+}
+
+/// HL7 v3 TS: `YYYY[MM[DD[HH[MM[SS[.S+]]]]]][+/-ZZZZ]`.
+fn is_timestamp(value: &str) -> bool {
+    let (stamp, offset) = match value.find(['+', '-']) {
+        Some(index) => (&value[..index], Some(&value[index + 1..])),
+        None => (value, None),
+    };
+    let (digits, fraction) = match stamp.split_once('.') {
+        Some((digits, fraction)) => (digits, Some(fraction)),
+        None => (stamp, None),
+    };
+    let all_digits = |text: &str| !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit());
+    all_digits(digits)
+        && matches!(digits.len(), 4 | 6 | 8 | 10 | 12 | 14)
+        && fraction.is_none_or(|fraction| digits.len() == 14 && all_digits(fraction))
+        && offset.is_none_or(|offset| offset.len() == 4 && all_digits(offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CCD: &str = r#"<?xml version="1.0"?>
+<ClinicalDocument xmlns="urn:hl7-org:v3">
+  <templateId root="2.16.840.1.113883.10.20.22.1.1" extension="2015-08-01"/>
+  <templateId root="2.16.840.1.113883.10.20.22.1.2" extension="2015-08-01"/>
+  <id root="1.2.3" extension="doc1"/>
+  <code code="34133-9" codeSystem="2.16.840.1.113883.6.1"/>
+  <title>Summary</title>
+  <effectiveTime value="20260107080112-0500"/>
+  <confidentialityCode code="N"/>
+  <recordTarget><patientRole><patient/></patientRole></recordTarget>
+  <author/>
+  <custodian/>
+  <component><structuredBody>
+    <component><section>
+      <templateId root="2.16.840.1.113883.10.20.22.2.6.1" extension="2015-08-01"/>
+      <code code="48765-2"/>
+      <title>Allergies</title>
+      <text>None known</text>
+      <entry><act><templateId root="2.16.840.1.113883.10.20.22.4.30" extension="2015-08-01"/></act></entry>
+    </section></component>
+    <component><section>
+      <code code="10160-0"/>
+      <title>Medications</title>
+      <text/>
+    </section></component>
+  </structuredBody></component>
+</ClinicalDocument>"#;
+
+    fn messages(validation: &DocumentValidation) -> Vec<String> {
+        validation
+            .issues
+            .iter()
+            .map(|issue| issue.diagnostics.clone().unwrap_or_default())
+            .collect()
+    }
+
+    #[test]
+    fn identifies_ccd_and_reports_missing_sections_and_entries() {
+        let validation = validate_document(CCD, RuleSet::bundled());
+        assert_eq!(
+            validation.document.as_deref(),
+            Some("Continuity of Care Document (CCD)")
+        );
+        assert_eq!(validation.sections.len(), 2);
+        assert_eq!(validation.sections[0].entries, 1);
+        let messages = messages(&validation);
+        assert!(
+            messages
+                .iter()
+                .any(|m| m.contains("does not declare templateId 2.16.840.1.113883.10.20.22.2.1.1"))
+        );
+        assert!(
+            messages
+                .iter()
+                .any(|m| m.contains("Section Medications requires at least one entry"))
+        );
+        assert!(
+            messages
+                .iter()
+                .any(|m| m.starts_with("Required section Problems (11450-4) is missing"))
+        );
+        assert!(
+            !messages.iter().any(|m| m.contains("Allergies")),
+            "{messages:?}"
+        );
+        let missing = validation
+            .issues
+            .iter()
+            .find(|issue| {
+                issue
+                    .diagnostics
+                    .as_deref()
+                    .is_some_and(|m| m.starts_with("Required section Results"))
+            })
+            .unwrap();
+        assert_eq!(
+            missing.expression,
+            vec!["ClinicalDocument/component/structuredBody".to_string()]
+        );
+    }
+
+    #[test]
+    fn reports_header_problems_and_unknown_templates() {
+        let text = CCD
+            .replace("<title>Summary</title>", "")
+            .replace("20260107080112-0500", "2026-01-07")
+            .replace(
+                "<templateId root=\"2.16.840.1.113883.10.20.22.1.2\" extension=\"2015-08-01\"/>",
+                "",
+            )
+            .replace(
+                "<templateId root=\"2.16.840.1.113883.10.20.22.1.1\" extension=\"2015-08-01\"/>",
+                "<templateId root=\"2.16.840.1.113883.10.20.22.1.1\"/>",
+            );
+        let validation = validate_document(&text, RuleSet::bundled());
+        assert_eq!(validation.document, None);
+        let messages = messages(&validation);
+        assert!(messages.contains(&"Header element title is missing".to_string()));
+        assert!(
+            messages
+                .iter()
+                .any(|m| m.contains("'2026-01-07' is not a valid"))
+        );
+        assert!(messages.iter().any(|m| m.contains("no version extension")));
+        assert!(
+            messages
+                .iter()
+                .any(|m| m.starts_with("Document template not recognized"))
+        );
+    }
+
+    #[test]
+    fn malformed_xml_is_fatal_with_line() {
+        let validation = validate_document(
+            "<ClinicalDocument>\n<title>\n</ClinicalDocument>",
+            RuleSet::bundled(),
+        );
+        assert_eq!(validation.issues.len(), 1);
+        assert_eq!(validation.issues[0].severity.as_deref(), Some("fatal"));
+        assert_eq!(validation.issues[0].line(), Some(3));
+
+        let validation = validate_document(
+            "<Bundle xmlns=\"http://hl7.org/fhir\"/>",
+            RuleSet::bundled(),
+        );
+        assert!(messages(&validation)[0].starts_with("Root element is Bundle"));
+    }
+
+    #[test]
+    fn checks_hl7_timestamps() {
+        assert!(is_timestamp("2026"));
+        assert!(is_timestamp("20260107080112.123+0100"));
+        assert!(!is_timestamp("202601070801121"));
+        assert!(!is_timestamp("20260107.5"));
+        assert!(!is_timestamp(""));
+    }
+}
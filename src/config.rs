@@ -0,0 +1,466 @@
+//! `clinlogix.toml`: settings shared by every invocation, so `--base-url`,
+//! failure policy flags and `--rules` need not be repeated.
+//!
+//! The user-level file (`~/.config/clinlogix/clinlogix.toml`) is read first,
+//! then the project-level `clinlogix.toml` in the current directory or the
+//! nearest parent; settings in the project file win. Flags on the command line
+//! win over both.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::report::ReportFormat;
+use crate::validate::{FailOn, FailurePolicy};
+
+pub const FILE_NAME: &str = "clinlogix.toml";
+
+/// FHIR server used when neither a flag nor the configuration names one.
+pub const DEFAULT_BASE_URL: &str = "https://server.fire.ly";
+
+/// How commands with a machine-readable mode print their results by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            other => Err(format!(
+                "unknown output format '{other}' (expected text or json)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OutputFormat::Text => "text",
+            OutputFormat::Json => "json",
+        })
+    }
+}
+
+/// The effective settings after layering the configuration files.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Files the settings were read from, in the order they were applied.
+    pub sources: Vec<PathBuf>,
+    /// Named FHIR servers (`[server.local]`) by name, as base URLs.
+    pub servers: BTreeMap<String, String>,
+    /// Server used when no `--base-url` or `--server` is given.
+    pub default_server: Option<String>,
+    /// `scan` output: text, or JSON as with `--json`.
+    pub output: OutputFormat,
+    /// `report` format when neither `--format` nor the output file name decides.
+    pub report_format: Option<ReportFormat>,
+    /// Failure policy for `validate`, `hl7v2 validate`, `cda validate` and `report`.
+    pub policy: FailurePolicy,
+    /// Category rule file for `scan` and `report`, resolved against the file
+    /// that named it.
+    pub scan_rules: Option<String>,
+    /// Files that could not be read or parsed, with the reason; their
+    /// settings are left out.
+    pub problems: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    default_server: Option<String>,
+    #[serde(default)]
+    server: BTreeMap<String, ServerTable>,
+    #[serde(default)]
+    output: OutputTable,
+    #[serde(default)]
+    policy: PolicyTable,
+    #[serde(default)]
+    scan: ScanTable,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ServerTable {
+    base_url: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OutputTable {
+    format: Option<String>,
+    report: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyTable {
+    fail_on: Option<String>,
+    max_errors: Option<usize>,
+    max_warnings: Option<usize>,
+    non_fatal_themes: Option<Vec<String>>,
+    fail_themes: Option<Vec<String>>,
+    fail_codes: Option<Vec<String>>,
+    ignore_http_status: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScanTable {
+    rules: Option<String>,
+}
+
+impl Config {
+    /// Reads the user-level and project-level files that exist; a file that
+    /// cannot be read or parsed is an error.
+    pub fn load() -> Result<Config, String> {
+        let config = Config::discover();
+        match config.problems.first() {
+            Some(problem) => Err(problem.clone()),
+            None => Ok(config),
+        }
+    }
+
+    /// Like [`Config::load`], but skips files that cannot be read or parsed
+    /// and records why in `problems`, so `config show` can print the rest.
+    pub fn discover() -> Config {
+        let mut config = Config::default();
+        let user = user_path();
+        let project = env::current_dir().ok().and_then(|dir| project_path(&dir));
+        for path in [
+            user.clone(),
+            project.filter(|path| Some(path) != user.as_ref()),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if path.is_file() {
+                let layered = fs::read_to_string(&path)
+                    .map_err(|err| err.to_string())
+                    .and_then(|text| {
+                        let mut layered = config.clone();
+                        layered.layer(&text, &path)?;
+                        Ok(layered)
+                    });
+                match layered {
+                    Ok(layered) => config = layered,
+                    Err(err) => config.problems.push(format!("{}: {err}", path.display())),
+                }
+            }
+        }
+        config
+    }
+
+    /// Applies one file's settings over the ones read so far; `path` is where
+    /// the text came from, for relative paths and `config show`.
+    pub fn layer(&mut self, text: &str, path: &Path) -> Result<(), String> {
+        let file: ConfigFile =
+            toml::from_str(text).map_err(|err| format!("invalid configuration: {err}"))?;
+        for (name, server) in file.server {
+            self.servers.insert(name, server.base_url);
+        }
+        if file.default_server.is_some() {
+            self.default_server = file.default_server;
+        }
+        if let Some(format) = file.output.format {
+            self.output = format.parse()?;
+        }
+        if let Some(format) = file.output.report {
+            self.report_format = Some(format.parse()?);
+        }
+
+        let policy = file.policy;
+        if let Some(fail_on) = policy.fail_on {
+            self.policy.fail_on = fail_on.parse::<FailOn>()?;
+        }
+        if let Some(max_errors) = policy.max_errors {
+            self.policy.max_errors = max_errors;
+        }
+        if let Some(max_warnings) = policy.max_warnings {
+            self.policy.max_warnings = max_warnings;
+        }
+        if let Some(themes) = policy.non_fatal_themes {
+            self.policy.non_fatal_themes = themes;
+        }
+        if let Some(themes) = policy.fail_themes {
+            self.policy.fail_themes = themes;
+        }
+        if let Some(codes) = policy.fail_codes {
+            self.policy.fail_codes = codes;
+        }
+        if let Some(ignore) = policy.ignore_http_status {
+            self.policy.ignore_http_status = ignore;
+        }
+
+        if let Some(rules) = file.scan.rules {
+            let dir = path.parent().unwrap_or(Path::new(""));
+            self.scan_rules = Some(dir.join(rules).to_string_lossy().into_owned());
+        }
+        self.sources.push(path.to_path_buf());
+        Ok(())
+    }
+
+    /// The base URL of a configured server.
+    pub fn server(&self, name: &str) -> Result<&str, String> {
+        match self.servers.get(name) {
+            Some(base_url) => Ok(base_url),
+            None if self.servers.is_empty() => {
+                Err(format!("unknown server '{name}' (no servers configured)"))
+            }
+            None => Err(format!(
+                "unknown server '{name}' (configured: {})",
+                self.servers.keys().cloned().collect::<Vec<_>>().join(", ")
+            )),
+        }
+    }
+
+    /// The FHIR server to use: `--base-url`, else the `--server` named, else
+    /// the configured default, else [`DEFAULT_BASE_URL`].
+    pub fn base_url(
+        &self,
+        base_url: Option<String>,
+        server: Option<&str>,
+    ) -> Result<String, String> {
+        if let Some(base_url) = base_url {
+            return Ok(base_url);
+        }
+        match (server, &self.default_server) {
+            (Some(name), _) => self.server(name).map(str::to_string),
+            (None, Some(name)) => self
+                .server(name)
+                .map(str::to_string)
+                .map_err(|err| format!("default_server: {err}")),
+            (None, None) => Ok(DEFAULT_BASE_URL.to_string()),
+        }
+    }
+
+    /// The effective settings as TOML, for `clinlogix config show`.
+    pub fn show(&self) -> String {
+        let quote = |text: &str| toml::Value::String(text.to_string()).to_string();
+        let list = |items: &[String]| {
+            format!(
+                "[{}]",
+                items
+                    .iter()
+                    .map(|item| quote(item))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        };
+        let mut output = String::from("# Effective ClinLogix settings\n");
+        if self.sources.is_empty() {
+            output.push_str("# No configuration files read; showing the defaults.\n");
+        }
+        for source in &self.sources {
+            output.push_str(&format!("# from {}\n", source.display()));
+        }
+        for problem in &self.problems {
+            // TOML errors span several lines; keep all of them commented.
+            let problem = problem.trim_end().replace('\n', "\n#   ");
+            output.push_str(&format!("# skipped {problem}\n"));
+        }
+        output.push('\n');
+        match &self.default_server {
+            Some(name) => output.push_str(&format!("default_server = {}\n", quote(name))),
+            None => output.push_str("# default_server is not set\n"),
+        }
+        output.push_str(&format!(
+            "# base URL without --base-url or --server: {}\n",
+            self.base_url(None, None)
+                .unwrap_or_else(|err| format!("error: {err}"))
+        ));
+        for (name, base_url) in &self.servers {
+            output.push_str(&format!(
+                "\n[server.{}]\nbase_url = {}\n",
+                if name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                {
+                    name.clone()
+                } else {
+                    quote(name)
+                },
+                quote(base_url)
+            ));
+        }
+
+        output.push_str("\n[output]\n");
+        output.push_str(&format!("format = {}\n", quote(&self.output.to_string())));
+        match self.report_format {
+            Some(format) => output.push_str(&format!("report = {}\n", quote(format.as_str()))),
+            None => {
+                output.push_str("# report is not set (html unless the output file ends in .md)\n")
+            }
+        }
+
+        let policy = &self.policy;
+        output.push_str("\n[policy]\n");
+        output.push_str(&format!(
+            "fail_on = {}\n",
+            quote(&policy.fail_on.to_string())
+        ));
+        output.push_str(&format!("max_errors = {}\n", policy.max_errors));
+        output.push_str(&format!("max_warnings = {}\n", policy.max_warnings));
+        output.push_str(&format!(
+            "non_fatal_themes = {}\n",
+            list(&policy.non_fatal_themes)
+        ));
+        output.push_str(&format!("fail_themes = {}\n", list(&policy.fail_themes)));
+        output.push_str(&format!("fail_codes = {}\n", list(&policy.fail_codes)));
+        output.push_str(&format!(
+            "ignore_http_status = {}\n",
+            policy.ignore_http_status
+        ));
+
+        output.push_str("\n[scan]\n");
+        match &self.scan_rules {
+            Some(rules) => output.push_str(&format!("rules = {}\n", quote(rules))),
+            None => output.push_str("# rules is not set (built-in categories)\n"),
+        }
+        output
+    }
+}
+
+/// `$XDG_CONFIG_HOME/clinlogix/clinlogix.toml`, falling back to `~/.config`
+/// (`%APPDATA%` on Windows).
+fn user_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            if cfg!(windows) {
+                env::var_os("APPDATA").map(PathBuf::from)
+            } else {
+                env::var_os("HOME").map(|home| PathBuf::from(home).join(".config"))
+            }
+        })?;
+    Some(base.join("clinlogix").join(FILE_NAME))
+}
+
+/// The nearest `clinlogix.toml` in `dir` or its parents.
+fn project_path(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn project_settings_layer_over_user_settings() {
+        let mut config = Config::default();
+        config
+            .layer(
+                r#"
+default_server = "firely"
+
+[server.firely]
+base_url = "https://server.fire.ly"
+
+[policy]
+fail_on = "warning"
+non_fatal_themes = ["profile resolution"]
+"#,
+                Path::new("/home/me/.config/clinlogix/clinlogix.toml"),
+            )
+            .unwrap();
+        config
+            .layer(
+                r#"
+default_server = "local"
+
+[server.local]
+base_url = "http://localhost:8080/fhir"
+
+[output]
+format = "json"
+report = "md"
+
+[policy]
+max_warnings = 10
+
+[scan]
+rules = "ops/categories.toml"
+"#,
+                Path::new("/work/project/clinlogix.toml"),
+            )
+            .unwrap();
+
+        assert_eq!(config.servers.len(), 2);
+        assert_eq!(
+            config.base_url(None, None).unwrap(),
+            "http://localhost:8080/fhir"
+        );
+        assert_eq!(
+            config.base_url(None, Some("firely")).unwrap(),
+            "https://server.fire.ly"
+        );
+        assert_eq!(
+            config
+                .base_url(
+                    Some("https://hapi.fhir.org/baseR4".to_string()),
+                    Some("firely")
+                )
+                .unwrap(),
+            "https://hapi.fhir.org/baseR4"
+        );
+        assert_eq!(config.output, OutputFormat::Json);
+        assert_eq!(config.report_format, Some(ReportFormat::Markdown));
+        assert_eq!(config.policy.fail_on, FailOn::Warning);
+        assert_eq!(config.policy.max_warnings, 10);
+        assert_eq!(config.policy.non_fatal_themes, ["profile resolution"]);
+        assert_eq!(
+            config.scan_rules.as_deref(),
+            Some("/work/project/ops/categories.toml")
+        );
+
+        let shown = config.show();
+        assert!(shown.contains("# from /work/project/clinlogix.toml\n"));
+        assert!(shown.contains("[server.local]\nbase_url = \"http://localhost:8080/fhir\"\n"));
+        assert!(shown.contains("non_fatal_themes = [\"profile resolution\"]\n"));
+    }
+
+    #[test]
+    fn rejects_unknown_servers_and_settings() {
+        let mut config = Config::default();
+        config
+            .layer("default_server = \"local\"\n", Path::new("clinlogix.toml"))
+            .unwrap();
+        assert_eq!(
+            config.base_url(None, None).unwrap_err(),
+            "default_server: unknown server 'local' (no servers configured)"
+        );
+
+        let err = Config::default()
+            .layer(
+                "[policy]\nfail_on = \"sometimes\"\n",
+                Path::new("clinlogix.toml"),
+            )
+            .unwrap_err();
+        assert!(err.contains("unknown severity 'sometimes'"));
+
+        let err = Config::default()
+            .layer("base_url = \"http://x\"\n", Path::new("clinlogix.toml"))
+            .unwrap_err();
+        assert!(err.starts_with("invalid configuration:"));
+
+        assert_eq!(
+            Config::default().base_url(None, None).unwrap(),
+            DEFAULT_BASE_URL
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! `clinlogix deidentify`: Safe Harbor–style de-identification of FHIR JSON so
+//! failing resources can be shared in bug reports.
+//!
+//! The output keeps resource types, codes, profiles and the shape of every
+//! element, so `$validate` on the copy reports the same structural and
+//! terminology issues as on the original.
+
+mod profile;
+mod transform;
+
+use std::fs;
+use std::path::Path;
+
+use serde_json::Value;
+
+#[allow(unused_imports)]
+pub use profile::{Action, BirthDate, ElementPath, Profile};
+#[allow(unused_imports)]
+pub use transform::{Changes, Deidentified, Deidentifier};
+
+use crate::error::Error;
+use crate::scan::{SECRET_ENV, load_or_create_secret, random_secret};
+
+/// Settings for `clinlogix deidentify`.
+#[derive(Debug, Clone, Default)]
+pub struct DeidentifyOptions {
+    /// Profile layered over the bundled Safe Harbor profile.
+    pub profile: Option<String>,
+    /// Secret for pseudonyms and date offsets; falls back to [`SECRET_ENV`],
+    /// then to a random secret for this run only.
+    pub secret_file: Option<String>,
+    /// Write here instead of stdout.
+    pub output: Option<String>,
+}
+
+/// De-identifies `path` and prints a summary of the changes to stderr.
+pub fn run_deidentify(path: &str, options: &DeidentifyOptions) -> Result<(), Error> {
+    let profile = match &options.profile {
+        Some(profile_path) => {
+            let text = fs::read_to_string(profile_path)
+                .map_err(|err| Error::Input(format!("{profile_path}: {err}")))?;
+            Profile::from_toml(&text)
+                .map_err(|err| Error::Input(format!("{profile_path}: {err}")))?
+        }
+        None => Profile::bundled(),
+    };
+    let secret = match (&options.secret_file, std::env::var(SECRET_ENV)) {
+        (Some(secret_path), _) => load_or_create_secret(Path::new(secret_path))
+            .map_err(|err| Error::Input(format!("{secret_path}: {err}")))?,
+        (None, Ok(secret)) if !secret.trim().is_empty() => secret.trim().as_bytes().to_vec(),
+        (None, _) => {
+            eprintln!(
+                "warning: no --secret-file or {SECRET_ENV}; using a random secret, so pseudonyms and date shifts will not match other runs"
+            );
+            random_secret()
+                .map_err(|err| Error::Input(format!("cannot create a secret: {err}")))?
+                .into_bytes()
+        }
+    };
+
+    let text = fs::read_to_string(path).map_err(|err| Error::Input(format!("{path}: {err}")))?;
+    let input: Value = serde_json::from_str(&text)
+        .map_err(|err| Error::Input(format!("{path}: invalid JSON: {err}")))?;
+    let Some(resource_type) = input.get("resourceType").and_then(Value::as_str) else {
+        return Err(Error::Input(format!(
+            "{path}: not a FHIR resource (no resourceType)"
+        )));
+    };
+    let resource_type = resource_type.to_string();
+
+    let deidentifier = Deidentifier::new(profile, &secret);
+    let result = deidentifier.deidentify(&input);
+    let mut json = serde_json::to_string_pretty(&result.resource)
+        .map_err(|err| Error::Input(err.to_string()))?;
+    json.push('\n');
+    match &options.output {
+        Some(output) => {
+            fs::write(output, json).map_err(|err| Error::Input(format!("{output}: {err}")))?
+        }
+        None => print!("{json}"),
+    }
+
+    let mut summary = format!(
+        "De-identified {resource_type} with profile {} ({})",
+        deidentifier.profile().name,
+        describe(&result)
+    );
+    if let Some(output) = &options.output {
+        summary.push_str(&format!(" to {output}"));
+    }
+    eprintln!("{summary}");
+    Ok(())
+}
+
+fn describe(result: &Deidentified) -> String {
+    let changes = &result.changes;
+    let counts = [
+        (result.patients, "patients"),
+        (changes.removed, "removed"),
+        (changes.redacted, "redacted"),
+        (changes.generalized, "generalized"),
+        (changes.pseudonymized, "pseudonymized"),
+        (changes.dates_shifted, "dates shifted"),
+        (changes.ids, "ids"),
+        (changes.references, "references rewritten"),
+    ];
+    let parts: Vec<String> = counts
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, label)| format!("{label}: {count}"))
+        .collect();
+    if parts.is_empty() {
+        "nothing to change".to_string()
+    } else {
+        parts.join(", ")
+    }
+}
@@ -0,0 +1,258 @@
+//! De-identification profiles: which elements to remove, redact, generalize or
+//! pseudonymize, and how to treat dates and ids.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+/// What happens to a matched element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Remove,
+    Redact,
+    Generalize,
+    Pseudonymize,
+    Keep,
+}
+
+/// How `birthDate` is de-identified.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BirthDate {
+    /// Keep the year only; ages over 89 are aggregated into one year.
+    #[default]
+    Year,
+    /// Shift like every other date of the patient.
+    Shift,
+    Keep,
+}
+
+/// Which elements a rule applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ElementPath {
+    /// `Patient.name`: a top-level element of one resource type.
+    Resource(String, String),
+    /// `*.text`: a top-level element of any resource.
+    AnyResource(String),
+    /// `telecom`: an element with this name at any depth.
+    Anywhere(String),
+}
+
+impl FromStr for ElementPath {
+    type Err = String;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let valid =
+            |name: &str| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric());
+        match path.split_once('.') {
+            Some(("*", element)) if valid(element) => Ok(ElementPath::AnyResource(element.into())),
+            Some((resource, element)) if valid(resource) && valid(element) => Ok(
+                ElementPath::Resource(resource.to_string(), element.to_string()),
+            ),
+            None if valid(path) => Ok(ElementPath::Anywhere(path.to_string())),
+            _ => Err(format!(
+                "invalid element path '{path}' (expected Resource.element, *.element or element)"
+            )),
+        }
+    }
+}
+
+/// A parsed profile; start from [`Profile::bundled`] or [`Profile::from_toml`].
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub name: String,
+    pub shift_dates: bool,
+    pub max_shift_days: u32,
+    pub birth_date: BirthDate,
+    pub pseudonymize_ids: bool,
+    pub drop_reference_display: bool,
+    elements: Vec<(ElementPath, Action)>,
+    extensions: BTreeMap<String, Action>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfileFile {
+    name: Option<String>,
+    /// Start from an empty profile instead of layering on the bundled one.
+    #[serde(default)]
+    replace_builtin: bool,
+    #[serde(default)]
+    dates: DatesTable,
+    #[serde(default)]
+    ids: IdsTable,
+    #[serde(default)]
+    references: ReferencesTable,
+    #[serde(default)]
+    elements: BTreeMap<String, Action>,
+    #[serde(default)]
+    extensions: BTreeMap<String, Action>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DatesTable {
+    shift: Option<bool>,
+    max_shift_days: Option<u32>,
+    birth_date: Option<BirthDate>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct IdsTable {
+    pseudonymize: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ReferencesTable {
+    drop_display: Option<bool>,
+}
+
+impl Profile {
+    /// The bundled Safe Harbor–style profile (`safe-harbor.toml`).
+    pub fn bundled() -> Profile {
+        let empty = Profile::empty();
+        let file = toml::from_str(include_str!("safe-harbor.toml"))
+            .expect("bundled de-identification profile is valid");
+        empty
+            .layer(file)
+            .expect("bundled de-identification profile is valid")
+    }
+
+    /// Parses a profile file and layers it over the bundled profile.
+    ///
+    /// Settings present in the file win; element and extension rules with the
+    /// same path replace the bundled ones, and `keep` disables them.
+    pub fn from_toml(text: &str) -> Result<Profile, String> {
+        let file: ProfileFile =
+            toml::from_str(text).map_err(|err| format!("invalid profile: {err}"))?;
+        let base = if file.replace_builtin {
+            Profile::empty()
+        } else {
+            Profile::bundled()
+        };
+        base.layer(file)
+    }
+
+    fn empty() -> Profile {
+        Profile {
+            name: "custom".to_string(),
+            shift_dates: false,
+            max_shift_days: 365,
+            birth_date: BirthDate::Keep,
+            pseudonymize_ids: false,
+            drop_reference_display: false,
+            elements: Vec::new(),
+            extensions: BTreeMap::new(),
+        }
+    }
+
+    fn layer(mut self, file: ProfileFile) -> Result<Profile, String> {
+        if let Some(name) = file.name {
+            self.name = name;
+        }
+        self.shift_dates = file.dates.shift.unwrap_or(self.shift_dates);
+        self.max_shift_days = file.dates.max_shift_days.unwrap_or(self.max_shift_days);
+        if self.shift_dates && self.max_shift_days == 0 {
+            return Err("dates.max_shift_days must be at least 1".to_string());
+        }
+        self.birth_date = file.dates.birth_date.unwrap_or(self.birth_date);
+        self.pseudonymize_ids = file.ids.pseudonymize.unwrap_or(self.pseudonymize_ids);
+        self.drop_reference_display = file
+            .references
+            .drop_display
+            .unwrap_or(self.drop_reference_display);
+        for (path, action) in file.elements {
+            let path: ElementPath = path.parse()?;
+            self.elements.retain(|(existing, _)| *existing != path);
+            self.elements.push((path, action));
+        }
+        self.extensions.extend(file.extensions);
+        Ok(self)
+    }
+
+    /// The rule for `element` of a `resource_type` resource; `top_level` is
+    /// false for elements nested inside other elements.
+    pub fn action(&self, resource_type: &str, element: &str, top_level: bool) -> Option<Action> {
+        let mut found = None;
+        for (path, action) in &self.elements {
+            // The most specific path wins: Resource.element, *.element, element.
+            let rank = match path {
+                ElementPath::Resource(resource, name)
+                    if top_level && resource == resource_type && name == element =>
+                {
+                    3
+                }
+                ElementPath::AnyResource(name) if top_level && name == element => 2,
+                ElementPath::Anywhere(name) if name == element => 1,
+                _ => continue,
+            };
+            if found.is_none_or(|(best, _)| rank > best) {
+                found = Some((rank, *action));
+            }
+        }
+        found
+            .map(|(_, action)| action)
+            .filter(|action| *action != Action::Keep)
+    }
+
+    /// The rule for an extension with this `url`.
+    pub fn extension_action(&self, url: &str) -> Option<Action> {
+        self.extensions
+            .get(url)
+            .copied()
+            .filter(|action| *action != Action::Keep)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_profiles_layer_over_the_bundled_one() {
+        let bundled = Profile::bundled();
+        assert_eq!(bundled.name, "Safe Harbor");
+        assert_eq!(
+            bundled.action("Patient", "name", true),
+            Some(Action::Redact)
+        );
+        assert_eq!(bundled.action("Patient", "name", false), None);
+        assert_eq!(bundled.action("Organization", "name", true), None);
+        assert_eq!(
+            bundled.action("Observation", "identifier", false),
+            Some(Action::Pseudonymize)
+        );
+
+        let custom = Profile::from_toml(
+            r#"
+            [dates]
+            max_shift_days = 30
+            [elements]
+            "Patient.name" = "remove"
+            "telecom" = "keep"
+            "Observation.identifier" = "keep"
+            "#,
+        )
+        .expect("profile");
+        assert_eq!(custom.max_shift_days, 30);
+        assert!(custom.shift_dates);
+        assert_eq!(custom.action("Patient", "name", true), Some(Action::Remove));
+        assert_eq!(custom.action("Patient", "telecom", true), None);
+        assert_eq!(custom.action("Observation", "identifier", true), None);
+        assert_eq!(
+            custom.action("Encounter", "identifier", true),
+            Some(Action::Pseudonymize)
+        );
+
+        let replaced = Profile::from_toml("replace_builtin = true").expect("profile");
+        assert!(!replaced.shift_dates);
+        assert_eq!(replaced.action("Patient", "name", true), None);
+
+        assert!(Profile::from_toml("[elements]\n\"Patient.name.given\" = \"remove\"").is_err());
+        assert!(Profile::from_toml("[elements]\nname = \"hide\"").is_err());
+    }
+}
@@ -0,0 +1,920 @@
+//! Applies a [`Profile`] to a FHIR resource or Bundle.
+//!
+//! Everything derived from the input (pseudonyms, new ids, date offsets) is a
+//! keyed hash, so the same secret turns the same patient into the same output
+//! and a Bundle's references still resolve after its ids change.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::sync::LazyLock;
+
+use hmac::{Hmac, Mac};
+use regex::Regex;
+use serde_json::{Map, Value};
+use sha2::Sha256;
+
+use crate::civil;
+use crate::deidentify::profile::{Action, BirthDate, Profile};
+
+const REDACTED: &str = "REDACTED";
+const REDACTED_DIV: &str = "<div xmlns=\"http://www.w3.org/1999/xhtml\">Redacted</div>";
+
+/// Elements holding codes, URLs and structure rather than free text.
+const STRUCTURAL: &[&str] = &[
+    "resourceType",
+    "id",
+    "url",
+    "system",
+    "code",
+    "display",
+    "use",
+    "type",
+    "status",
+    "version",
+    "reference",
+    "valueCode",
+];
+
+/// Address parts that survive generalization (plus `postalCode`, cut to 3 digits).
+const ADDRESS_KEPT: &[&str] = &["use", "type", "state", "country", "period"];
+
+/// Three-digit ZIP prefixes covering 20,000 people or fewer (2000 Census),
+/// which Safe Harbor requires to become `000`.
+const RESTRICTED_ZIP3: &[&str] = &[
+    "036", "059", "063", "102", "203", "556", "692", "790", "821", "823", "830", "831", "878",
+    "879", "884", "890", "893",
+];
+
+/// Elements naming the patient a resource is about.
+const PATIENT_ELEMENTS: &[&str] = &["subject", "patient", "beneficiary"];
+
+static FULL_DATE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(\d{4})-(\d{2})-(\d{2})(T\d{2}:\d{2}(?::\d{2}(?:\.\d+)?)?(?:Z|[+-]\d{2}:\d{2})?)?$",
+    )
+    .unwrap()
+});
+static PARTIAL_DATE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\d{4})(?:-(\d{2}))?$").unwrap());
+static LITERAL_REFERENCE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?P<base>.*?/)?(?P<type>[A-Z][A-Za-z]+)/(?P<id>[A-Za-z0-9\-.]{1,64})(?P<history>/_history/[A-Za-z0-9\-.]{1,64})?$",
+    )
+    .unwrap()
+});
+static CONDITIONAL_REFERENCE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?P<type>[A-Z][A-Za-z]+)\?(?P<query>.*)$").unwrap());
+
+/// Counts of what changed, for the summary line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Changes {
+    pub removed: usize,
+    pub redacted: usize,
+    pub generalized: usize,
+    pub pseudonymized: usize,
+    pub dates_shifted: usize,
+    pub ids: usize,
+    pub references: usize,
+}
+
+/// The de-identified copy of the input.
+#[derive(Debug, Clone)]
+pub struct Deidentified {
+    pub resource: Value,
+    pub changes: Changes,
+    /// Distinct patients whose dates were shifted independently.
+    pub patients: usize,
+}
+
+/// Applies a profile with a secret key.
+#[derive(Debug, Clone)]
+pub struct Deidentifier {
+    profile: Profile,
+    key: Vec<u8>,
+}
+
+impl Deidentifier {
+    pub fn new(profile: Profile, secret: &[u8]) -> Self {
+        Self {
+            profile,
+            key: secret.to_vec(),
+        }
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    /// De-identifies a resource or Bundle; the input is left untouched.
+    pub fn deidentify(&self, input: &Value) -> Deidentified {
+        let mut patients = Patients::default();
+        patients.collect(input);
+        let mut output = input.clone();
+        let mut pass = Pass {
+            deidentifier: self,
+            patients: &patients,
+            changes: Changes::default(),
+            current_year: civil::today().0,
+        };
+        if let Value::Object(resource) = &mut output {
+            let patient = patients.patient_of(resource, None);
+            pass.resource(resource, &patient, false);
+        }
+        Deidentified {
+            resource: output,
+            changes: pass.changes,
+            patients: patients.keys.len(),
+        }
+    }
+
+    /// Days every date of `patient` moves back: `1..=max_shift_days`.
+    pub fn date_offset(&self, patient: &str) -> i64 {
+        let digest = self.digest("date-shift", patient);
+        let value = u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]);
+        i64::from(value % self.profile.max_shift_days.max(1)) + 1
+    }
+
+    fn digest(&self, domain: &str, value: &str) -> [u8; 32] {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        mac.update(domain.as_bytes());
+        mac.update(b":");
+        mac.update(value.as_bytes());
+        mac.finalize().into_bytes().into()
+    }
+
+    fn hex(&self, domain: &str, value: &str, bytes: usize) -> String {
+        let mut hex = String::with_capacity(bytes * 2);
+        for byte in &self.digest(domain, value)[..bytes] {
+            let _ = write!(hex, "{byte:02x}");
+        }
+        hex
+    }
+
+    fn resource_id(&self, resource_type: &str, id: &str) -> String {
+        self.hex("id", &format!("{resource_type}/{id}"), 8)
+    }
+
+    /// Contained resources are referenced as `#id`, without their type.
+    fn local_id(&self, id: &str) -> String {
+        self.hex("local-id", id, 8)
+    }
+
+    fn identifier_value(&self, system: &str, value: &str) -> String {
+        self.hex("identifier", &format!("{system}|{value}"), 8)
+    }
+
+    fn uuid(&self, uuid: &str) -> String {
+        let hex = self.hex("uuid", &uuid.to_ascii_lowercase(), 16);
+        format!(
+            "urn:uuid:{}-{}-4{}-a{}-{}",
+            &hex[..8],
+            &hex[8..12],
+            &hex[13..16],
+            &hex[17..20],
+            &hex[20..32]
+        )
+    }
+}
+
+/// The patients in the input and the references that point at them.
+#[derive(Debug, Default)]
+struct Patients {
+    keys: Vec<String>,
+    aliases: HashMap<String, String>,
+}
+
+impl Patients {
+    fn collect(&mut self, value: &Value) {
+        match value {
+            Value::Object(object) => {
+                let full_url = object.get("fullUrl").and_then(Value::as_str);
+                if let Some(Value::Object(resource)) = object.get("resource")
+                    && resource_type(resource) == "Patient"
+                    && let Some(key) = patient_key(resource, full_url)
+                {
+                    if let Some(full_url) = full_url {
+                        self.aliases.insert(full_url.to_string(), key.clone());
+                    }
+                    self.add(key);
+                }
+                if resource_type(object) == "Patient"
+                    && let Some(key) = patient_key(object, None)
+                {
+                    self.add(key);
+                }
+                object.values().for_each(|child| self.collect(child));
+            }
+            Value::Array(items) => items.iter().for_each(|item| self.collect(item)),
+            _ => {}
+        }
+    }
+
+    fn add(&mut self, key: String) {
+        self.aliases.insert(key.clone(), key.clone());
+        if !self.keys.contains(&key) {
+            self.keys.push(key);
+        }
+    }
+
+    /// The patient whose date offset applies to `resource`.
+    ///
+    /// Resources that name no patient use the only patient in the input, or a
+    /// shared offset when there are several.
+    fn patient_of(&self, resource: &Map<String, Value>, full_url: Option<&str>) -> String {
+        if resource_type(resource) == "Patient"
+            && let Some(key) = patient_key(resource, full_url)
+        {
+            return key;
+        }
+        for element in PATIENT_ELEMENTS {
+            let reference = resource
+                .get(*element)
+                .and_then(|value| value.get("reference"))
+                .and_then(Value::as_str);
+            if let Some(key) = reference.and_then(|reference| self.resolve(reference)) {
+                return key;
+            }
+        }
+        match self.keys.as_slice() {
+            [only] => only.clone(),
+            _ => String::new(),
+        }
+    }
+
+    fn resolve(&self, reference: &str) -> Option<String> {
+        let reference = reference
+            .split_once("/_history/")
+            .map_or(reference, |(current, _)| current);
+        if let Some(key) = self.aliases.get(reference) {
+            return Some(key.clone());
+        }
+        let caps = LITERAL_REFERENCE.captures(reference)?;
+        (&caps["type"] == "Patient").then(|| format!("Patient/{}", &caps["id"]))
+    }
+}
+
+fn resource_type(resource: &Map<String, Value>) -> &str {
+    resource
+        .get("resourceType")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+}
+
+fn patient_key(patient: &Map<String, Value>, full_url: Option<&str>) -> Option<String> {
+    match patient.get("id").and_then(Value::as_str) {
+        Some(id) => Some(format!("Patient/{id}")),
+        None => full_url.map(str::to_string),
+    }
+}
+
+/// One walk over the input.
+struct Pass<'a> {
+    deidentifier: &'a Deidentifier,
+    patients: &'a Patients,
+    changes: Changes,
+    current_year: i64,
+}
+
+impl Pass<'_> {
+    fn profile(&self) -> &Profile {
+        &self.deidentifier.profile
+    }
+
+    /// `contained` resources get the new local id their `#id` references point to.
+    fn resource(&mut self, resource: &mut Map<String, Value>, patient: &str, contained: bool) {
+        let resource_type = resource_type(resource).to_string();
+        let keys: Vec<String> = resource.keys().cloned().collect();
+        for key in keys {
+            match key.as_str() {
+                "resourceType" => continue,
+                "id" => {
+                    if self.profile().pseudonymize_ids
+                        && let Some(Value::String(id)) = resource.get_mut("id")
+                    {
+                        *id = if contained {
+                            self.deidentifier.local_id(id)
+                        } else {
+                            self.deidentifier.resource_id(&resource_type, id)
+                        };
+                        self.changes.ids += 1;
+                    }
+                    continue;
+                }
+                "entry" if resource_type == "Bundle" => {
+                    if let Some(Value::Array(entries)) = resource.get_mut("entry") {
+                        for entry in entries {
+                            if let Value::Object(entry) = entry {
+                                self.entry(entry);
+                            }
+                        }
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            match self.profile().action(&resource_type, &key, true) {
+                Some(action) => self.apply(&resource_type, resource, &key, action, patient),
+                None => {
+                    if let Some(value) = resource.get_mut(&key) {
+                        self.element(&resource_type, &key, value, patient);
+                    }
+                }
+            }
+            prune(resource, &key);
+        }
+    }
+
+    fn entry(&mut self, entry: &mut Map<String, Value>) {
+        let full_url = entry
+            .get("fullUrl")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let patient = match entry.get("resource") {
+            Some(Value::Object(resource)) => {
+                self.patients.patient_of(resource, full_url.as_deref())
+            }
+            _ => self.patients.patient_of(&Map::new(), None),
+        };
+        let keys: Vec<String> = entry.keys().cloned().collect();
+        for key in keys {
+            let Some(value) = entry.get_mut(&key) else {
+                continue;
+            };
+            match (key.as_str(), value) {
+                ("fullUrl", Value::String(url)) => self.reference(url),
+                ("resource", Value::Object(resource)) => self.resource(resource, &patient, false),
+                ("request" | "response", Value::Object(exchange)) => {
+                    for (name, value) in exchange.iter_mut() {
+                        let Value::String(text) = value else {
+                            continue;
+                        };
+                        match name.as_str() {
+                            "url" | "location" => self.reference(text),
+                            "ifNoneExist" => *text = self.query(text),
+                            _ => self.string(name, text, &patient),
+                        }
+                    }
+                }
+                (key, value) => self.element("Bundle", key, value, &patient),
+            }
+        }
+    }
+
+    /// An element no rule applies to: looks inside it for rules, dates and references.
+    fn element(&mut self, resource_type: &str, key: &str, value: &mut Value, patient: &str) {
+        match value {
+            Value::String(text) => self.string(key, text, patient),
+            Value::Array(items) => {
+                if key == "extension" || key == "modifierExtension" {
+                    let before = items.len();
+                    items.retain(|item| {
+                        extension_url(item).and_then(|url| self.profile().extension_action(url))
+                            != Some(Action::Remove)
+                    });
+                    self.changes.removed += before - items.len();
+                }
+                for item in items {
+                    self.element(resource_type, key, item, patient);
+                }
+            }
+            Value::Object(object) => self.object(resource_type, key, object, patient),
+            _ => {}
+        }
+    }
+
+    fn object(
+        &mut self,
+        resource_type: &str,
+        key: &str,
+        object: &mut Map<String, Value>,
+        patient: &str,
+    ) {
+        if object.contains_key("resourceType") {
+            // Contained resources belong to the container's patient.
+            self.resource(object, patient, true);
+            return;
+        }
+        if self.profile().drop_reference_display
+            && object.contains_key("reference")
+            && object.remove("display").is_some()
+        {
+            self.changes.removed += 1;
+        }
+        if (key == "extension" || key == "modifierExtension")
+            && let Some(action) = object
+                .get("url")
+                .and_then(Value::as_str)
+                .and_then(|url| self.profile().extension_action(url))
+        {
+            let values: Vec<String> = object
+                .keys()
+                .filter(|name| name.starts_with("value"))
+                .cloned()
+                .collect();
+            for name in values {
+                self.apply(resource_type, object, &name, action, patient);
+            }
+            return;
+        }
+        let keys: Vec<String> = object.keys().cloned().collect();
+        for name in keys {
+            match self.profile().action(resource_type, &name, false) {
+                Some(action) => self.apply(resource_type, object, &name, action, patient),
+                None => {
+                    if let Some(value) = object.get_mut(&name) {
+                        self.element(resource_type, &name, value, patient);
+                    }
+                }
+            }
+            prune(object, &name);
+        }
+    }
+
+    fn apply(
+        &mut self,
+        resource_type: &str,
+        parent: &mut Map<String, Value>,
+        key: &str,
+        action: Action,
+        patient: &str,
+    ) {
+        if action == Action::Remove {
+            if parent.remove(key).is_some() {
+                self.changes.removed += 1;
+            }
+            return;
+        }
+        let Some(value) = parent.get_mut(key) else {
+            return;
+        };
+        match action {
+            Action::Redact => {
+                self.redact(key, value, patient);
+                self.changes.redacted += 1;
+            }
+            Action::Generalize => {
+                self.generalize(key, value, patient);
+                self.changes.generalized += 1;
+            }
+            Action::Pseudonymize => {
+                self.pseudonymize(resource_type, value, patient);
+                self.changes.pseudonymized += 1;
+            }
+            Action::Remove | Action::Keep => {}
+        }
+    }
+
+    /// Replaces free text, keeping codes, dates (shifted) and the element's shape
+    /// so cardinality and invariants still hold.
+    fn redact(&mut self, key: &str, value: &mut Value, patient: &str) {
+        match value {
+            Value::String(text) => {
+                if key == "div" {
+                    *text = REDACTED_DIV.to_string();
+                } else if STRUCTURAL.contains(&key) || self.shift(key, text, patient) {
+                    // Kept, or shifted as a date.
+                } else {
+                    *text = REDACTED.to_string();
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.redact(key, item, patient);
+                }
+            }
+            Value::Object(object) => {
+                for (name, child) in object.iter_mut() {
+                    self.redact(name, child, patient);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Addresses keep state, country and a 3-digit ZIP; dates keep their year.
+    fn generalize(&mut self, key: &str, value: &mut Value, patient: &str) {
+        match value {
+            Value::String(text) => {
+                if STRUCTURAL.contains(&key) {
+                    return;
+                }
+                match PARTIAL_DATE
+                    .captures(text)
+                    .or_else(|| FULL_DATE.captures(text))
+                {
+                    Some(caps) => *text = caps[1].to_string(),
+                    None => *text = REDACTED.to_string(),
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.generalize(key, item, patient);
+                }
+            }
+            Value::Object(object) if is_address(object) => {
+                object.retain(|name, _| {
+                    ADDRESS_KEPT.contains(&name.as_str()) || name == "postalCode"
+                });
+                if let Some(Value::String(zip)) = object.get_mut("postalCode") {
+                    *zip = zip3(zip);
+                }
+                if let Some(period) = object.get_mut("period") {
+                    self.element("", "period", period, patient);
+                }
+            }
+            Value::Object(object) => {
+                for (name, child) in object.iter_mut() {
+                    self.generalize(name, child, patient);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Identifier values (and plain strings) become keyed hashes.
+    fn pseudonymize(&mut self, resource_type: &str, value: &mut Value, patient: &str) {
+        match value {
+            Value::String(text) => *text = self.deidentifier.hex("value", text, 8),
+            Value::Array(items) => {
+                for item in items {
+                    self.pseudonymize(resource_type, item, patient);
+                }
+            }
+            Value::Object(object) => {
+                let system = object
+                    .get("system")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string();
+                if let Some(Value::String(text)) = object.get_mut("value") {
+                    *text = self.deidentifier.identifier_value(&system, text);
+                }
+                for (name, child) in object.iter_mut() {
+                    if name != "value" {
+                        self.element(resource_type, name, child, patient);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn string(&mut self, key: &str, text: &mut String, patient: &str) {
+        match key {
+            "reference" => self.reference(text),
+            "birthDate" => self.birth_date(text, patient),
+            _ if STRUCTURAL.contains(&key) => {}
+            _ => {
+                self.shift(key, text, patient);
+            }
+        }
+    }
+
+    /// Moves a date back by the patient's offset.
+    ///
+    /// A year or year-month date keeps its precision and moves with its first
+    /// day (its last day for a period's `end`), so it stays in order with the
+    /// full dates around it. Returns whether `text` is a date at all.
+    fn shift(&mut self, key: &str, text: &mut String, patient: &str) -> bool {
+        let offset = self.deidentifier.date_offset(patient);
+        let shifted = if let Some(caps) = FULL_DATE.captures(text) {
+            let parse = |index: usize| caps[index].parse::<u32>().unwrap_or(1);
+            let days = civil::days_from_civil(i64::from(parse(1)), parse(2), parse(3)) - offset;
+            let (year, month, day) = civil::civil_from_days(days);
+            let time = caps.get(4).map_or("", |time| time.as_str());
+            format!("{year:04}-{month:02}-{day:02}{time}")
+        } else if let Some(caps) = PARTIAL_DATE.captures(text) {
+            let year: i64 = caps[1].parse().unwrap_or_default();
+            let month = caps.get(2).map(|month| month.as_str().parse().unwrap_or(1));
+            let days = if key == "end" {
+                // The day before the next month (or year) starts.
+                let (year, month) = match month {
+                    Some(12) | None => (year + 1, 1),
+                    Some(month) => (year, month + 1),
+                };
+                civil::days_from_civil(year, month, 1) - 1
+            } else {
+                civil::days_from_civil(year, month.unwrap_or(1), 1)
+            };
+            let (year, shifted_month, _) = civil::civil_from_days(days - offset);
+            match month {
+                Some(_) => format!("{year:04}-{shifted_month:02}"),
+                None => format!("{year:04}"),
+            }
+        } else {
+            return false;
+        };
+        if self.profile().shift_dates && shifted != *text {
+            *text = shifted;
+            self.changes.dates_shifted += 1;
+        }
+        true
+    }
+
+    fn birth_date(&mut self, text: &mut String, patient: &str) {
+        match self.profile().birth_date {
+            BirthDate::Keep => {}
+            BirthDate::Shift => {
+                self.shift("birthDate", text, patient);
+            }
+            BirthDate::Year => {
+                let Some(year) = text.get(..4).and_then(|year| year.parse::<i64>().ok()) else {
+                    return;
+                };
+                // Ages over 89 are aggregated into a single category.
+                let year = year.max(self.current_year - 90);
+                *text = format!("{year:04}");
+                self.changes.generalized += 1;
+            }
+        }
+    }
+
+    /// Rewrites `Type/id`, absolute, `urn:uuid` and contained `#id` references to the new ids.
+    fn reference(&mut self, text: &mut String) {
+        let rewritten = if let Some(caps) = CONDITIONAL_REFERENCE.captures(text) {
+            Some(format!("{}?{}", &caps["type"], self.query(&caps["query"])))
+        } else if !self.profile().pseudonymize_ids {
+            None
+        } else if let Some(uuid) = text.strip_prefix("urn:uuid:") {
+            Some(self.deidentifier.uuid(uuid))
+        } else if let Some(id) = text.strip_prefix('#') {
+            // A bare `#` points back at the container.
+            (!id.is_empty()).then(|| format!("#{}", self.deidentifier.local_id(id)))
+        } else {
+            LITERAL_REFERENCE.captures(text).map(|caps| {
+                format!(
+                    "{}{}/{}{}",
+                    caps.name("base").map_or("", |base| base.as_str()),
+                    &caps["type"],
+                    self.deidentifier.resource_id(&caps["type"], &caps["id"]),
+                    caps.name("history").map_or("", |history| history.as_str()),
+                )
+            })
+        };
+        if let Some(rewritten) = rewritten
+            && rewritten != *text
+        {
+            *text = rewritten;
+            self.changes.references += 1;
+        }
+    }
+
+    /// Search parameters of conditional references: identifiers are pseudonymized
+    /// like `Identifier.value` so the search still matches, other values are redacted.
+    fn query(&self, query: &str) -> String {
+        query
+            .split('&')
+            .map(|parameter| match parameter.split_once('=') {
+                Some(("identifier", value)) => match value.rsplit_once('|') {
+                    Some((system, value)) => format!(
+                        "identifier={system}|{}",
+                        self.deidentifier.identifier_value(system, value)
+                    ),
+                    None => format!(
+                        "identifier={}",
+                        self.deidentifier.identifier_value("", value)
+                    ),
+                },
+                Some((name, _)) if name.starts_with('_') => parameter.to_string(),
+                Some((name, _)) => format!("{name}={REDACTED}"),
+                None => parameter.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+}
+
+/// FHIR JSON has no empty arrays or objects; drop what de-identification emptied.
+fn prune(parent: &mut Map<String, Value>, key: &str) {
+    let empty = match parent.get_mut(key) {
+        Some(Value::Array(items)) => {
+            items.retain(|item| !matches!(item, Value::Object(object) if object.is_empty()));
+            items.is_empty()
+        }
+        Some(Value::Object(object)) => object.is_empty(),
+        _ => false,
+    };
+    if empty {
+        parent.remove(key);
+    }
+}
+
+fn extension_url(item: &Value) -> Option<&str> {
+    item.get("url").and_then(Value::as_str)
+}
+
+fn is_address(object: &Map<String, Value>) -> bool {
+    ["line", "city", "district", "postalCode", "state", "country"]
+        .iter()
+        .any(|name| object.contains_key(*name))
+}
+
+fn zip3(zip: &str) -> String {
+    let digits: String = zip.chars().filter(char::is_ascii_digit).take(3).collect();
+    if digits.len() < 3 || RESTRICTED_ZIP3.contains(&digits.as_str()) {
+        "000".to_string()
+    } else {
+        digits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn deidentify(input: &Value) -> Deidentified {
+        Deidentifier::new(Profile::bundled(), b"test secret").deidentify(input)
+    }
+
+    fn bundle() -> Value {
+        json!({
+            "resourceType": "Bundle",
+            "type": "transaction",
+            "entry": [
+                {
+                    "fullUrl": "urn:uuid:8b1a9b5b-9a7d-4d0d-8f3e-1b5e0ab56b6a",
+                    "resource": {
+                        "resourceType": "Patient",
+                        "id": "p1",
+                        "text": {"status": "generated", "div": "<div xmlns=\"http://www.w3.org/1999/xhtml\">Jane Doe</div>"},
+                        "extension": [
+                            {"url": "http://hl7.org/fhir/StructureDefinition/patient-mothersMaidenName", "valueString": "Smith"}
+                        ],
+                        "identifier": [{"system": "http://hl7.org/fhir/sid/us-ssn", "value": "999-12-3456"}],
+                        "name": [{"use": "official", "family": "Doe", "given": ["Jane"], "period": {"start": "2001-03-01"}}],
+                        "telecom": [{"system": "phone", "value": "555-123-4567", "use": "home"}],
+                        "gender": "female",
+                        "birthDate": "1925-05-20",
+                        "address": [{"line": ["1 Main St"], "city": "Boston", "state": "MA", "postalCode": "02118", "country": "US"}]
+                    },
+                    "request": {"method": "POST", "url": "Patient", "ifNoneExist": "identifier=http://hl7.org/fhir/sid/us-ssn|999-12-3456"}
+                },
+                {
+                    "fullUrl": "urn:uuid:1f2e3d4c-0000-4000-8000-000000000001",
+                    "resource": {
+                        "resourceType": "Observation",
+                        "id": "o1",
+                        "status": "final",
+                        "code": {"coding": [{"system": "http://loinc.org", "code": "8867-4", "display": "Heart rate"}]},
+                        "subject": {"reference": "urn:uuid:8b1a9b5b-9a7d-4d0d-8f3e-1b5e0ab56b6a", "display": "Jane Doe"},
+                        "performer": [{"reference": "Practitioner?identifier=http://hl7.org/fhir/sid/us-npi|1234567893"}],
+                        "effectiveDateTime": "2026-01-07T08:00:00-05:00",
+                        "note": [{"text": "Called Jane at home"}]
+                    },
+                    "request": {"method": "PUT", "url": "Observation/o1"}
+                }
+            ]
+        })
+    }
+
+    #[test]
+    fn removes_redacts_and_generalizes_patient_details() {
+        let result = deidentify(&bundle());
+        let patient = &result.resource["entry"][0]["resource"];
+        assert_eq!(patient["name"][0]["family"], "REDACTED");
+        assert_eq!(patient["name"][0]["given"][0], "REDACTED");
+        assert_eq!(patient["name"][0]["use"], "official");
+        assert_eq!(patient["telecom"][0]["value"], "REDACTED");
+        assert_eq!(patient["telecom"][0]["system"], "phone");
+        assert_eq!(patient["text"]["div"], REDACTED_DIV);
+        assert!(patient.get("extension").is_none());
+        assert_eq!(
+            patient["address"][0],
+            json!({"state": "MA", "postalCode": "021", "country": "US"})
+        );
+        assert_eq!(patient["gender"], "female");
+        // Born 1925: over 89, so aggregated.
+        let year: i64 = patient["birthDate"].as_str().unwrap().parse().unwrap();
+        assert_eq!(year, civil::today().0 - 90);
+
+        let observation = &result.resource["entry"][1]["resource"];
+        assert!(observation.get("note").is_none());
+        assert!(observation["subject"].get("display").is_none());
+        assert_eq!(observation["code"]["coding"][0]["display"], "Heart rate");
+        assert_eq!(result.patients, 1);
+    }
+
+    #[test]
+    fn rewrites_ids_and_references_consistently() {
+        let result = deidentify(&bundle());
+        let entries = &result.resource["entry"];
+        let patient_url = entries[0]["fullUrl"].as_str().unwrap();
+        assert_ne!(patient_url, "urn:uuid:8b1a9b5b-9a7d-4d0d-8f3e-1b5e0ab56b6a");
+        assert_eq!(entries[1]["resource"]["subject"]["reference"], patient_url);
+
+        let observation_id = entries[1]["resource"]["id"].as_str().unwrap();
+        assert_ne!(observation_id, "o1");
+        assert_eq!(
+            entries[1]["request"]["url"],
+            format!("Observation/{observation_id}")
+        );
+
+        let ssn = entries[0]["resource"]["identifier"][0]["value"]
+            .as_str()
+            .unwrap();
+        assert_eq!(
+            entries[0]["request"]["ifNoneExist"],
+            format!("identifier=http://hl7.org/fhir/sid/us-ssn|{ssn}")
+        );
+        let npi = entries[1]["resource"]["performer"][0]["reference"]
+            .as_str()
+            .unwrap();
+        assert!(npi.starts_with("Practitioner?identifier=http://hl7.org/fhir/sid/us-npi|"));
+        assert!(!npi.contains("1234567893"));
+
+        // Same secret, same output.
+        assert_eq!(deidentify(&bundle()).resource, result.resource);
+    }
+
+    #[test]
+    fn shifts_dates_per_patient() {
+        let deidentifier = Deidentifier::new(Profile::bundled(), b"test secret");
+        let result = deidentifier.deidentify(&bundle());
+        let offset = deidentifier.date_offset("Patient/p1");
+        assert!((1..=365).contains(&offset));
+
+        let shifted = |date: &str, offset: i64| {
+            let (year, month, day) = civil::civil_from_days(
+                civil::days_from_civil(
+                    date[..4].parse().unwrap(),
+                    date[5..7].parse().unwrap(),
+                    date[8..10].parse().unwrap(),
+                ) - offset,
+            );
+            format!("{year:04}-{month:02}-{day:02}")
+        };
+        let entries = &result.resource["entry"];
+        assert_eq!(
+            entries[0]["resource"]["name"][0]["period"]["start"],
+            shifted("2001-03-01", offset)
+        );
+        assert_eq!(
+            entries[1]["resource"]["effectiveDateTime"],
+            format!("{}T08:00:00-05:00", shifted("2026-01-07", offset))
+        );
+        assert_ne!(
+            deidentifier.date_offset("Patient/p1"),
+            deidentifier.date_offset("Patient/p2")
+        );
+        assert_eq!(zip3("03601"), "000");
+    }
+
+    #[test]
+    fn keeps_contained_references_resolvable() {
+        let observation = json!({
+            "resourceType": "Observation",
+            "id": "o1",
+            "status": "final",
+            "contained": [{"resourceType": "Patient", "id": "c", "gender": "female"}],
+            "subject": {"reference": "#c"},
+            "focus": [{"reference": "#"}]
+        });
+        let result = deidentify(&observation);
+        let contained_id = result.resource["contained"][0]["id"].as_str().unwrap();
+        assert_ne!(contained_id, "c");
+        assert_eq!(
+            result.resource["subject"]["reference"],
+            format!("#{contained_id}")
+        );
+        assert_eq!(result.resource["focus"][0]["reference"], "#");
+    }
+
+    #[test]
+    fn shifts_partial_dates_with_their_precision() {
+        let deidentifier = Deidentifier::new(Profile::bundled(), b"test secret");
+        let encounter = json!({
+            "resourceType": "Encounter",
+            "id": "e1",
+            "status": "finished",
+            "subject": {"reference": "Patient/p1"},
+            "period": {"start": "2020-01", "end": "2020-01-20"}
+        });
+        let result = deidentifier.deidentify(&encounter);
+        let period = &result.resource["period"];
+        let start = period["start"].as_str().unwrap();
+        let end = period["end"].as_str().unwrap();
+        assert_eq!(start.len(), "2020-01".len());
+        assert!(start <= &end[..7], "{start} after {end}");
+
+        let offset = deidentifier.date_offset("Patient/p1");
+        let (year, month, _) = civil::civil_from_days(civil::days_from_civil(2020, 1, 1) - offset);
+        assert_eq!(start, format!("{year:04}-{month:02}"));
+
+        let reversed = json!({
+            "resourceType": "Encounter",
+            "id": "e2",
+            "status": "finished",
+            "subject": {"reference": "Patient/p1"},
+            "period": {"start": "2020-01-20", "end": "2020-01"}
+        });
+        let result = deidentifier.deidentify(&reversed);
+        let period = &result.resource["period"];
+        let start = period["start"].as_str().unwrap();
+        let end = period["end"].as_str().unwrap();
+        assert!(&start[..7] <= end, "{start} after {end}");
+        assert_eq!(zip3("9021"), "902");
+    }
+}
@@ -0,0 +1,89 @@
+//! Errors that end a CLI run, and the exit code each one maps to.
+
+use std::fmt;
+
+use crate::validate::ValidateError;
+
+pub const EXIT_VALIDATION_FAILED: u8 = 1;
+pub const EXIT_BAD_INPUT: u8 = 2;
+pub const EXIT_TRANSPORT_FAILED: u8 = 3;
+
+/// Why a command did not succeed, so callers (and CI) can react differently.
+#[derive(Debug)]
+pub enum Error {
+    /// A file, an option or a rule could not be read or parsed.
+    Input(String),
+    /// A server, MLLP peer or resource source could not be reached.
+    Transport(String),
+    /// Something was checked and failed; holds what ("FHIR", "HL7 v2").
+    Failed(&'static str),
+    /// `scan --follow` stopped at an error rate alert (`--alert exit`).
+    Alert,
+}
+
+impl Error {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Failed(_) | Error::Alert => EXIT_VALIDATION_FAILED,
+            Error::Input(_) => EXIT_BAD_INPUT,
+            Error::Transport(_) => EXIT_TRANSPORT_FAILED,
+        }
+    }
+}
+
+impl From<ValidateError> for Error {
+    fn from(err: ValidateError) -> Self {
+        match err {
+            ValidateError::Input(message) => Error::Input(message),
+            ValidateError::Transport(message) => Error::Transport(message),
+            ValidateError::Failed(kind) => Error::Failed(kind),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Input(message) => write!(f, "invalid input: {message}"),
+            Error::Transport(message) => write!(f, "transport error: {message}"),
+            Error::Failed(kind) => write!(f, "{kind} validation failed"),
+            Error::Alert => write!(f, "stopped at an error rate alert"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Exit code for an error returned from a command: [`Error`] and
+/// [`ValidateError`] carry their own, anything else is bad input.
+pub fn exit_code(err: &(dyn std::error::Error + 'static)) -> u8 {
+    if let Some(err) = err.downcast_ref::<Error>() {
+        return err.exit_code();
+    }
+    err.downcast_ref::<ValidateError>()
+        .map(ValidateError::exit_code)
+        // Anything else is an unreadable file or similar, never a
+        // validation verdict.
+        .unwrap_or(EXIT_BAD_INPUT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_both_error_types_to_exit_codes() {
+        let alert: Box<dyn std::error::Error> = Box::new(Error::Alert);
+        let transport: Box<dyn std::error::Error> =
+            Box::new(ValidateError::Transport("refused".to_string()));
+        let io: Box<dyn std::error::Error> = Box::new(std::io::Error::other("unreadable"));
+
+        assert_eq!(exit_code(alert.as_ref()), EXIT_VALIDATION_FAILED);
+        assert_eq!(exit_code(transport.as_ref()), EXIT_TRANSPORT_FAILED);
+        assert_eq!(exit_code(io.as_ref()), EXIT_BAD_INPUT);
+        assert_eq!(
+            Error::from(ValidateError::Failed("FHIR")).exit_code(),
+            EXIT_VALIDATION_FAILED
+        );
+    }
+}
@@ -0,0 +1,614 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// HL7 v2 version id from MSH-12, compared numerically (`2.5.1` > `2.5`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Hl7Version(pub u8, pub u8, pub u8);
+
+impl Hl7Version {
+    /// Rules used when MSH-12 is missing or names a version we have no definitions for.
+    pub const FALLBACK: Hl7Version = Hl7Version(2, 5, 1);
+
+    pub fn is_supported(self) -> bool {
+        SUPPORTED_VERSIONS.contains(&self)
+    }
+}
+
+pub const SUPPORTED_VERSIONS: &[Hl7Version] = &[
+    Hl7Version(2, 3, 0),
+    Hl7Version(2, 3, 1),
+    Hl7Version(2, 4, 0),
+    Hl7Version(2, 5, 0),
+    Hl7Version(2, 5, 1),
+    Hl7Version(2, 6, 0),
+    Hl7Version(2, 7, 0),
+    Hl7Version(2, 7, 1),
+    Hl7Version(2, 8, 0),
+];
+
+impl FromStr for Hl7Version {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut parts = value.trim().split('.');
+        let mut next = |required: bool| -> Result<u8, String> {
+            match parts.next() {
+                Some(part) => part
+                    .parse()
+                    .map_err(|_| format!("'{value}' is not an HL7 v2 version id")),
+                None if required => Err(format!("'{value}' is not an HL7 v2 version id")),
+                None => Ok(0),
+            }
+        };
+        let version = Hl7Version(next(true)?, next(true)?, next(false)?);
+        if version.0 != 2 {
+            return Err(format!("'{value}' is not an HL7 v2 version id"));
+        }
+        Ok(version)
+    }
+}
+
+impl fmt::Display for Hl7Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.2 == 0 {
+            write!(f, "{}.{}", self.0, self.1)
+        } else {
+            write!(f, "{}.{}.{}", self.0, self.1, self.2)
+        }
+    }
+}
+
+/// Optionality of a field (HL7 "R", "O", "B", "W").
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Usage {
+    Required,
+    Optional,
+    /// Retained for backward compatibility only.
+    Backward,
+    Withdrawn,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataType {
+    /// Free text (ST, TX, FT) and composites whose components are not checked.
+    Text,
+    /// Coded value checked against an HL7 table (ID) or a user-defined table (IS).
+    Coded {
+        table: &'static str,
+        hl7_table: bool,
+    },
+    SequenceId,
+    /// TS/DTM: `YYYY[MM[DD[HH[MM[SS[.S[S[S[S]]]]]]]]][+/-ZZZZ]`.
+    DateTime,
+}
+
+pub struct FieldDef {
+    pub seq: usize,
+    pub name: &'static str,
+    pub datatype: DataType,
+    pub repeats: bool,
+    /// Usage by version: the last entry whose version is `<=` the message version applies.
+    pub usage: &'static [(Hl7Version, Usage)],
+}
+
+impl FieldDef {
+    pub fn usage_in(&self, version: Hl7Version) -> Usage {
+        self.usage
+            .iter()
+            .rev()
+            .find(|(since, _)| *since <= version)
+            .map(|(_, usage)| *usage)
+            .unwrap_or(Usage::Optional)
+    }
+}
+
+pub struct SegmentDef {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub fields: &'static [FieldDef],
+}
+
+const V23: Hl7Version = Hl7Version(2, 3, 0);
+const V231: Hl7Version = Hl7Version(2, 3, 1);
+const V24: Hl7Version = Hl7Version(2, 4, 0);
+const V25: Hl7Version = Hl7Version(2, 5, 0);
+const V27: Hl7Version = Hl7Version(2, 7, 0);
+
+const R: &[(Hl7Version, Usage)] = &[(V23, Usage::Required)];
+const O: &[(Hl7Version, Usage)] = &[(V23, Usage::Optional)];
+const B_SINCE_231: &[(Hl7Version, Usage)] = &[
+    (V23, Usage::Optional),
+    (V231, Usage::Backward),
+    (V27, Usage::Withdrawn),
+];
+
+const fn field(
+    seq: usize,
+    name: &'static str,
+    datatype: DataType,
+    repeats: bool,
+    usage: &'static [(Hl7Version, Usage)],
+) -> FieldDef {
+    FieldDef {
+        seq,
+        name,
+        datatype,
+        repeats,
+        usage,
+    }
+}
+
+const fn id(table: &'static str) -> DataType {
+    DataType::Coded {
+        table,
+        hl7_table: true,
+    }
+}
+
+const fn is(table: &'static str) -> DataType {
+    DataType::Coded {
+        table,
+        hl7_table: false,
+    }
+}
+
+/// Fields ClinLogix checks; fields not listed here are accepted as-is.
+pub const SEGMENTS: &[SegmentDef] = &[
+    SegmentDef {
+        id: "MSH",
+        name: "Message Header",
+        fields: &[
+            field(1, "Field Separator", DataType::Text, false, R),
+            field(2, "Encoding Characters", DataType::Text, false, R),
+            field(3, "Sending Application", DataType::Text, false, O),
+            field(4, "Sending Facility", DataType::Text, false, O),
+            field(
+                7,
+                "Date/Time of Message",
+                DataType::DateTime,
+                false,
+                &[(V23, Usage::Optional), (V25, Usage::Required)],
+            ),
+            field(9, "Message Type", DataType::Text, false, R),
+            field(10, "Message Control ID", DataType::Text, false, R),
+            field(11, "Processing ID", id("0103"), false, R),
+            field(12, "Version ID", id("0104"), false, R),
+        ],
+    },
+    SegmentDef {
+        id: "EVN",
+        name: "Event Type",
+        fields: &[
+            field(
+                1,
+                "Event Type Code",
+                DataType::Text,
+                false,
+                &[
+                    (V23, Usage::Required),
+                    (V24, Usage::Backward),
+                    (V27, Usage::Withdrawn),
+                ],
+            ),
+            field(2, "Recorded Date/Time", DataType::DateTime, false, R),
+            field(6, "Event Occurred", DataType::DateTime, false, O),
+        ],
+    },
+    SegmentDef {
+        id: "PID",
+        name: "Patient Identification",
+        fields: &[
+            field(1, "Set ID - PID", DataType::SequenceId, false, O),
+            field(2, "Patient ID", DataType::Text, false, B_SINCE_231),
+            field(3, "Patient Identifier List", DataType::Text, true, R),
+            field(
+                4,
+                "Alternate Patient ID - PID",
+                DataType::Text,
+                true,
+                B_SINCE_231,
+            ),
+            field(5, "Patient Name", DataType::Text, true, R),
+            field(7, "Date/Time of Birth", DataType::DateTime, false, O),
+            field(8, "Administrative Sex", is("0001"), false, O),
+            field(11, "Patient Address", DataType::Text, true, O),
+            field(13, "Phone Number - Home", DataType::Text, true, O),
+            field(
+                29,
+                "Patient Death Date and Time",
+                DataType::DateTime,
+                false,
+                O,
+            ),
+        ],
+    },
+    SegmentDef {
+        id: "PV1",
+        name: "Patient Visit",
+        fields: &[
+            field(1, "Set ID - PV1", DataType::SequenceId, false, O),
+            field(2, "Patient Class", is("0004"), false, R),
+            field(3, "Assigned Patient Location", DataType::Text, false, O),
+            field(19, "Visit Number", DataType::Text, false, O),
+            field(44, "Admit Date/Time", DataType::DateTime, false, O),
+            field(45, "Discharge Date/Time", DataType::DateTime, false, O),
+        ],
+    },
+    SegmentDef {
+        id: "ORC",
+        name: "Common Order",
+        fields: &[
+            field(1, "Order Control", id("0119"), false, R),
+            field(2, "Placer Order Number", DataType::Text, false, O),
+            field(3, "Filler Order Number", DataType::Text, false, O),
+            field(9, "Date/Time of Transaction", DataType::DateTime, false, O),
+        ],
+    },
+    SegmentDef {
+        id: "OBR",
+        name: "Observation Request",
+        fields: &[
+            field(1, "Set ID - OBR", DataType::SequenceId, false, O),
+            field(2, "Placer Order Number", DataType::Text, false, O),
+            field(3, "Filler Order Number", DataType::Text, false, O),
+            field(4, "Universal Service Identifier", DataType::Text, false, R),
+            field(7, "Observation Date/Time", DataType::DateTime, false, O),
+            field(
+                22,
+                "Results Rpt/Status Chng - Date/Time",
+                DataType::DateTime,
+                false,
+                O,
+            ),
+            field(25, "Result Status", id("0123"), false, O),
+        ],
+    },
+    SegmentDef {
+        id: "OBX",
+        name: "Observation/Result",
+        fields: &[
+            field(1, "Set ID - OBX", DataType::SequenceId, false, O),
+            field(2, "Value Type", id("0125"), false, O),
+            field(3, "Observation Identifier", DataType::Text, false, R),
+            field(4, "Observation Sub-ID", DataType::Text, false, O),
+            field(6, "Units", DataType::Text, false, O),
+            field(11, "Observation Result Status", id("0085"), false, R),
+            field(
+                14,
+                "Date/Time of the Observation",
+                DataType::DateTime,
+                false,
+                O,
+            ),
+        ],
+    },
+    SegmentDef {
+        id: "MSA",
+        name: "Message Acknowledgment",
+        fields: &[
+            field(1, "Acknowledgment Code", id("0008"), false, R),
+            field(2, "Message Control ID", DataType::Text, false, R),
+            field(3, "Text Message", DataType::Text, false, B_SINCE_231),
+        ],
+    },
+];
+
+pub fn segment_def(id: &str) -> Option<&'static SegmentDef> {
+    SEGMENTS.iter().find(|segment| segment.id == id)
+}
+
+/// HL7 and user-defined tables used by the checked fields.
+pub const TABLES: &[(&str, &[&str])] = &[
+    ("0001", &["A", "F", "M", "N", "O", "U"]),
+    ("0004", &["B", "C", "E", "I", "N", "O", "P", "R", "U"]),
+    ("0008", &["AA", "AE", "AR", "CA", "CE", "CR"]),
+    (
+        "0085",
+        &["C", "D", "F", "I", "N", "O", "P", "R", "S", "U", "W", "X"],
+    ),
+    ("0103", &["D", "P", "T"]),
+    (
+        "0104",
+        &[
+            "2.1", "2.2", "2.3", "2.3.1", "2.4", "2.5", "2.5.1", "2.6", "2.7", "2.7.1", "2.8",
+            "2.8.1", "2.8.2", "2.9",
+        ],
+    ),
+    (
+        "0119",
+        &[
+            "AF", "CA", "CH", "CN", "CR", "DC", "DE", "DF", "DR", "FU", "HD", "HR", "LI", "NA",
+            "NW", "OC", "OD", "OE", "OF", "OH", "OK", "OP", "OR", "PA", "PR", "PY", "RE", "RF",
+            "RL", "RO", "RP", "RQ", "RR", "RU", "SC", "SN", "SR", "SS", "UA", "UC", "UD", "UF",
+            "UH", "UM", "UN", "UR", "UX", "XO", "XR", "XX",
+        ],
+    ),
+    (
+        "0123",
+        &["A", "C", "F", "I", "O", "P", "R", "S", "X", "Y", "Z"],
+    ),
+    (
+        "0125",
+        &[
+            "AD", "CE", "CF", "CK", "CN", "CNE", "CP", "CWE", "CX", "DT", "DTM", "ED", "FT", "GTS",
+            "ID", "IS", "MA", "MO", "NA", "NM", "PN", "RP", "SN", "ST", "TM", "TN", "TS", "TX",
+            "XAD", "XCN", "XON", "XPN", "XTN",
+        ],
+    ),
+];
+
+pub fn table_values(table: &str) -> Option<&'static [&'static str]> {
+    TABLES
+        .iter()
+        .find(|(id, _)| *id == table)
+        .map(|(_, values)| *values)
+}
+
+/// Segment grouping rules of an abstract message structure.
+pub enum Node {
+    Segment {
+        id: &'static str,
+        required: bool,
+        repeats: bool,
+    },
+    Group {
+        name: &'static str,
+        required: bool,
+        repeats: bool,
+        children: &'static [Node],
+    },
+}
+
+impl Node {
+    pub fn required(&self) -> bool {
+        match self {
+            Node::Segment { required, .. } | Node::Group { required, .. } => *required,
+        }
+    }
+
+    pub fn repeats(&self) -> bool {
+        match self {
+            Node::Segment { repeats, .. } | Node::Group { repeats, .. } => *repeats,
+        }
+    }
+}
+
+const fn seg(id: &'static str) -> Node {
+    Node::Segment {
+        id,
+        required: true,
+        repeats: false,
+    }
+}
+
+const fn opt(id: &'static str) -> Node {
+    Node::Segment {
+        id,
+        required: false,
+        repeats: false,
+    }
+}
+
+const fn any(id: &'static str) -> Node {
+    Node::Segment {
+        id,
+        required: false,
+        repeats: true,
+    }
+}
+
+const fn group(
+    name: &'static str,
+    required: bool,
+    repeats: bool,
+    children: &'static [Node],
+) -> Node {
+    Node::Group {
+        name,
+        required,
+        repeats,
+        children,
+    }
+}
+
+pub struct Structure {
+    pub id: &'static str,
+    pub nodes: &'static [Node],
+}
+
+/// Abstract message structures (v2.5 shapes, which earlier versions are compatible with).
+pub const STRUCTURES: &[Structure] = &[
+    Structure {
+        id: "ADT_A01",
+        nodes: &[
+            seg("MSH"),
+            any("SFT"),
+            seg("EVN"),
+            seg("PID"),
+            opt("PD1"),
+            any("ROL"),
+            any("NK1"),
+            seg("PV1"),
+            opt("PV2"),
+            any("ROL"),
+            any("DB1"),
+            any("OBX"),
+            any("AL1"),
+            any("DG1"),
+            opt("DRG"),
+            group("PROCEDURE", false, true, &[seg("PR1"), any("ROL")]),
+            any("GT1"),
+            group(
+                "INSURANCE",
+                false,
+                true,
+                &[seg("IN1"), opt("IN2"), any("IN3"), any("ROL")],
+            ),
+            opt("ACC"),
+            opt("UB1"),
+            opt("UB2"),
+            opt("PDA"),
+        ],
+    },
+    Structure {
+        id: "ORU_R01",
+        nodes: &[
+            seg("MSH"),
+            any("SFT"),
+            group(
+                "PATIENT_RESULT",
+                true,
+                true,
+                &[
+                    group(
+                        "PATIENT",
+                        false,
+                        false,
+                        &[
+                            seg("PID"),
+                            opt("PD1"),
+                            any("NTE"),
+                            any("NK1"),
+                            group("VISIT", false, false, &[seg("PV1"), opt("PV2")]),
+                        ],
+                    ),
+                    group(
+                        "ORDER_OBSERVATION",
+                        true,
+                        true,
+                        &[
+                            opt("ORC"),
+                            seg("OBR"),
+                            any("NTE"),
+                            group("TIMING_QTY", false, true, &[seg("TQ1"), any("TQ2")]),
+                            opt("CTD"),
+                            group("OBSERVATION", false, true, &[seg("OBX"), any("NTE")]),
+                            any("FT1"),
+                            any("CTI"),
+                            group("SPECIMEN", false, true, &[seg("SPM"), any("OBX")]),
+                        ],
+                    ),
+                ],
+            ),
+            opt("DSC"),
+        ],
+    },
+    Structure {
+        id: "ORM_O01",
+        nodes: &[
+            seg("MSH"),
+            any("NTE"),
+            group(
+                "PATIENT",
+                false,
+                false,
+                &[
+                    seg("PID"),
+                    opt("PD1"),
+                    any("NTE"),
+                    group("PATIENT_VISIT", false, false, &[seg("PV1"), opt("PV2")]),
+                    group(
+                        "INSURANCE",
+                        false,
+                        true,
+                        &[seg("IN1"), opt("IN2"), opt("IN3")],
+                    ),
+                    opt("GT1"),
+                    any("AL1"),
+                ],
+            ),
+            group(
+                "ORDER",
+                true,
+                true,
+                &[
+                    seg("ORC"),
+                    group(
+                        "ORDER_DETAIL",
+                        false,
+                        false,
+                        &[
+                            seg("OBR"),
+                            any("NTE"),
+                            opt("CTD"),
+                            any("DG1"),
+                            group("OBSERVATION", false, true, &[seg("OBX"), any("NTE")]),
+                        ],
+                    ),
+                    any("FT1"),
+                    any("CTI"),
+                    opt("BLG"),
+                ],
+            ),
+        ],
+    },
+    Structure {
+        id: "ACK",
+        nodes: &[seg("MSH"), any("SFT"), seg("MSA"), any("ERR")],
+    },
+];
+
+/// Resolves MSH-9 to an abstract message structure.
+///
+/// Uses MSH-9.3 when the sender filled it in, otherwise the event-to-structure
+/// mapping for the message types ClinLogix knows.
+pub fn structure_for(
+    message_code: &str,
+    trigger: Option<&str>,
+    structure: Option<&str>,
+) -> Option<&'static Structure> {
+    let id = match structure {
+        Some(id) => id,
+        None => match (message_code, trigger.unwrap_or_default()) {
+            ("ADT", "A01" | "A04" | "A05" | "A08" | "A13" | "A14") => "ADT_A01",
+            ("ORU", "R01") => "ORU_R01",
+            ("ORM", "O01") => "ORM_O01",
+            ("ACK", _) => "ACK",
+            _ => return None,
+        },
+    };
+    STRUCTURES.iter().find(|candidate| candidate.id == id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_orders_versions() {
+        let v251: Hl7Version = "2.5.1".parse().expect("2.5.1");
+        let v25: Hl7Version = "2.5".parse().expect("2.5");
+        assert!(v251 > v25);
+        assert_eq!(v25.to_string(), "2.5");
+        assert!(v251.is_supported());
+        assert!("3.0".parse::<Hl7Version>().is_err());
+        assert!("abc".parse::<Hl7Version>().is_err());
+    }
+
+    #[test]
+    fn field_usage_changes_across_versions() {
+        let pid = segment_def("PID").expect("PID");
+        let patient_id = pid.fields.iter().find(|field| field.seq == 2).unwrap();
+        assert_eq!(patient_id.usage_in(Hl7Version(2, 3, 0)), Usage::Optional);
+        assert_eq!(patient_id.usage_in(Hl7Version(2, 5, 1)), Usage::Backward);
+        assert_eq!(patient_id.usage_in(Hl7Version(2, 8, 0)), Usage::Withdrawn);
+
+        let msh = segment_def("MSH").expect("MSH");
+        let timestamp = msh.fields.iter().find(|field| field.seq == 7).unwrap();
+        assert_eq!(timestamp.usage_in(Hl7Version(2, 4, 0)), Usage::Optional);
+        assert_eq!(timestamp.usage_in(Hl7Version(2, 5, 0)), Usage::Required);
+    }
+
+    #[test]
+    fn maps_triggers_to_structures() {
+        assert_eq!(
+            structure_for("ADT", Some("A08"), None).map(|s| s.id),
+            Some("ADT_A01")
+        );
+        assert_eq!(
+            structure_for("ORU", Some("R01"), Some("ORU_R01")).map(|s| s.id),
+            Some("ORU_R01")
+        );
+        assert!(structure_for("SIU", Some("S12"), None).is_none());
+    }
+}
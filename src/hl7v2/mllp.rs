@@ -0,0 +1,623 @@
+//! Minimal Lower Layer Protocol (MLLP) endpoints for testing HL7 v2 interfaces.
+//!
+//! Frames are `<VT> message <FS><CR>`. The listener validates every message and
+//! answers with an ACK (AA) or NAK (AE for validation errors, AR when the
+//! message cannot be parsed), logging each step in the `LEVEL date time message`
+//! format `clinlogix scan` reads.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::hl7v2::parser::{Message, parse_message};
+use crate::hl7v2::validate::{MessageValidation, validate_message};
+use crate::validate::{Issue, ValidateError};
+
+const FRAME_START: u8 = 0x0b;
+const FRAME_END: u8 = 0x1c;
+const CARRIAGE_RETURN: u8 = 0x0d;
+
+/// Wraps an ER7 message in an MLLP frame.
+pub fn frame(message: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(message.len() + 3);
+    bytes.push(FRAME_START);
+    bytes.extend_from_slice(message.as_bytes());
+    bytes.push(FRAME_END);
+    bytes.push(CARRIAGE_RETURN);
+    bytes
+}
+
+/// Reads the next framed message; `Ok(None)` when the peer closed the connection.
+///
+/// Bytes before the start block are ignored, as most engines do.
+pub fn read_frame(reader: &mut impl Read) -> io::Result<Option<String>> {
+    let mut message = Vec::new();
+    let mut started = false;
+    let mut byte = [0u8; 1];
+    loop {
+        if reader.read(&mut byte)? == 0 {
+            if started {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "connection closed inside an MLLP frame",
+                ));
+            }
+            return Ok(None);
+        }
+        match byte[0] {
+            FRAME_START => {
+                started = true;
+                message.clear();
+            }
+            FRAME_END if started => {
+                // The trailing carriage return is optional in practice.
+                return Ok(Some(String::from_utf8_lossy(&message).into_owned()));
+            }
+            other if started => message.push(other),
+            _ => {}
+        }
+    }
+}
+
+/// Acknowledgment code for a validated message: AA, or AE when it has errors.
+pub fn ack_code(validation: &MessageValidation) -> &'static str {
+    if validation.issues.iter().any(is_error) {
+        "AE"
+    } else {
+        "AA"
+    }
+}
+
+fn is_error(issue: &Issue) -> bool {
+    matches!(issue.severity.as_deref(), Some("error" | "fatal"))
+}
+
+/// Builds an ACK for `message` with MSA-2 = MSH-10 and one ERR segment per error.
+pub fn build_ack(message: &Message, code: &str, text: &str, errors: &[Issue]) -> String {
+    let msh = message.segment("MSH");
+    let value = |field: usize| {
+        msh.and_then(|msh| msh.field(field))
+            .map(|field| field.to_er7(&message.delimiters))
+            .unwrap_or_default()
+    };
+    let control_id = message.control_id().unwrap_or_default();
+    let trigger = msh.and_then(|msh| msh.value(9, 2)).unwrap_or_default();
+    let version = message.version().unwrap_or("2.5.1");
+    let delimiters = &message.delimiters;
+    let f = delimiters.field;
+    let c = delimiters.component;
+
+    let mut ack = format!(
+        "MSH{f}{}{f}{}{f}{}{f}{}{f}{}{f}{}{f}{f}ACK{c}{trigger}{c}ACK{f}ACK{control_id}{f}{}{f}{version}\r",
+        delimiters.encoding_characters(),
+        value(5),
+        value(6),
+        value(3),
+        value(4),
+        hl7_timestamp(SystemTime::now()),
+        message
+            .segment("MSH")
+            .and_then(|msh| msh.value(11, 1))
+            .unwrap_or("P"),
+    );
+    ack.push_str(&format!(
+        "MSA{f}{code}{f}{control_id}{f}{}\r",
+        crate::hl7v2::escape(text, delimiters)
+    ));
+    for issue in errors {
+        let diagnostics = issue.diagnostics.as_deref().unwrap_or_default();
+        let location = issue
+            .expression
+            .first()
+            .map(|expression| error_location(expression, c))
+            .unwrap_or_default();
+        ack.push_str(&format!(
+            "ERR{f}{f}{location}{f}{}{f}E{f}{f}{f}{f}{}\r",
+            hl7_error_code(issue.code.as_deref().unwrap_or_default(), c),
+            crate::hl7v2::escape(diagnostics, delimiters)
+        ));
+    }
+    ack
+}
+
+/// Minimal AR acknowledgment for input that could not be parsed as a message.
+pub fn build_reject(raw: &str, reason: &str) -> String {
+    let control_id = raw
+        .split('\r')
+        .next()
+        .filter(|header| header.starts_with("MSH"))
+        .and_then(|header| {
+            let separator = header.chars().nth(3)?;
+            header.split(separator).nth(9).map(str::to_string)
+        })
+        .unwrap_or_default();
+    format!(
+        "MSH|^~\\&|||||{}||ACK|ACK{control_id}|P|2.5.1\rMSA|AR|{control_id}|{}\r",
+        hl7_timestamp(SystemTime::now()),
+        crate::hl7v2::escape(reason, &Default::default())
+    )
+}
+
+/// ERR-2 error location (`PID^1^3`) from an expression such as `PID-3` or `OBX[2]-5`.
+fn error_location(expression: &str, component: char) -> String {
+    let (segment, field) = expression.split_once('-').unwrap_or((expression, ""));
+    let (id, occurrence) = match segment.split_once('[') {
+        Some((id, rest)) => (id, rest.trim_end_matches(']')),
+        None => (segment, "1"),
+    };
+    if field.is_empty() {
+        format!("{id}{component}{occurrence}")
+    } else {
+        format!("{id}{component}{occurrence}{component}{field}")
+    }
+}
+
+/// ERR-3 code from HL7 table 0357 for an issue code.
+fn hl7_error_code(code: &str, component: char) -> String {
+    let (number, text) = match code {
+        "required" => ("101", "Required field missing"),
+        "value" => ("102", "Data type error"),
+        "code-invalid" => ("103", "Table value not found"),
+        "structure" => ("100", "Segment sequence error"),
+        "not-supported" => ("200", "Unsupported message type"),
+        _ => ("207", "Application internal error"),
+    };
+    format!("{number}{component}{text}{component}HL70357")
+}
+
+/// HL7 DTM in UTC, e.g. `20260107080112+0000`.
+fn hl7_timestamp(time: SystemTime) -> String {
+    let (date, clock) = utc_parts(time);
+    format!("{}{}+0000", date.replace('-', ""), clock.replace(':', ""))
+}
+
+/// `YYYY-MM-DD` and `HH:MM:SS` in UTC.
+fn utc_parts(time: SystemTime) -> (String, String) {
+    let seconds = crate::civil::unix_seconds(time);
+    let (year, month, day) = crate::civil::civil_from_days(seconds.div_euclid(86_400));
+    let rest = seconds.rem_euclid(86_400);
+
+    (
+        format!("{year:04}-{month:02}-{day:02}"),
+        format!(
+            "{:02}:{:02}:{:02}",
+            rest / 3600,
+            rest % 3600 / 60,
+            rest % 60
+        ),
+    )
+}
+
+/// Writes `LEVEL YYYY-MM-DD HH:MM:SS message` lines to stdout and an optional file.
+#[derive(Clone)]
+struct EventLog {
+    file: Option<Arc<Mutex<File>>>,
+}
+
+impl EventLog {
+    fn write(&self, level: &str, message: &str) {
+        let (date, clock) = utc_parts(SystemTime::now());
+        let line = format!("{level} {date} {clock} {message}");
+        println!("{line}");
+        if let Some(file) = &self.file
+            && let Ok(mut file) = file.lock()
+        {
+            let _ = writeln!(file, "{line}");
+        }
+    }
+}
+
+/// Settings for `clinlogix mllp listen`.
+#[derive(Debug, Clone)]
+pub struct ListenOptions {
+    pub host: String,
+    /// Port to listen on; `0` picks a free port (printed at startup).
+    pub port: u16,
+    /// Appends the event log to this file as well as printing it.
+    pub log: Option<String>,
+    /// Stops after acknowledging this many messages.
+    pub max_messages: Option<usize>,
+}
+
+pub fn run_listen(options: &ListenOptions) -> Result<(), ValidateError> {
+    let listener = TcpListener::bind((options.host.as_str(), options.port)).map_err(|err| {
+        ValidateError::Transport(format!("{}:{}: {err}", options.host, options.port))
+    })?;
+    let address = listener
+        .local_addr()
+        .map_err(|err| ValidateError::Transport(err.to_string()))?;
+    let file = match &options.log {
+        Some(path) => Some(Arc::new(Mutex::new(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|err| ValidateError::Input(format!("{path}: {err}")))?,
+        ))),
+        None => None,
+    };
+    let log = EventLog { file };
+    log.write("INFO", &format!("HL7 listener listening on {address}"));
+    let _ = io::stdout().flush();
+
+    listener
+        .set_nonblocking(true)
+        .map_err(|err| ValidateError::Transport(err.to_string()))?;
+    // Messages given one of the `limit` slots, and those actually acknowledged.
+    let reserved = Arc::new(AtomicUsize::new(0));
+    let handled = Arc::new(AtomicUsize::new(0));
+    let limit = options.max_messages.unwrap_or(usize::MAX);
+    // Each connection's thread, with a handle on its socket to end it at shutdown.
+    let mut connections: Vec<(thread::JoinHandle<()>, TcpStream)> = Vec::new();
+    while reserved.load(Ordering::SeqCst) < limit {
+        connections.retain(|(connection, _)| !connection.is_finished());
+        match listener.accept() {
+            Ok((stream, peer)) => {
+                let socket = stream
+                    .try_clone()
+                    .map_err(|err| ValidateError::Transport(err.to_string()))?;
+                let log = log.clone();
+                let reserved = Arc::clone(&reserved);
+                let handled = Arc::clone(&handled);
+                let connection = thread::spawn(move || {
+                    log.write("INFO", &format!("HL7 listener connected from {peer}"));
+                    if let Err(err) = serve_connection(stream, &log, &reserved, &handled, limit) {
+                        log.write(
+                            "ERROR",
+                            &format!("HL7 connection from {peer} failed: {err}"),
+                        );
+                    }
+                    log.write("INFO", &format!("HL7 listener disconnected from {peer}"));
+                });
+                connections.push((connection, socket));
+            }
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(20));
+            }
+            Err(err) => return Err(ValidateError::Transport(err.to_string())),
+        }
+    }
+    // Connections still waiting for a message will not get one answered; ending
+    // their reads lets every thread finish writing its ACK and log lines.
+    for (connection, socket) in connections {
+        let _ = socket.shutdown(Shutdown::Read);
+        let _ = connection.join();
+    }
+    log.write(
+        "INFO",
+        &format!(
+            "HL7 listener stopped after {} messages",
+            handled.load(Ordering::SeqCst)
+        ),
+    );
+    Ok(())
+}
+
+/// Answers messages on one connection until the peer hangs up or the listener
+/// has handed out all `limit` slots.
+///
+/// A message only gets an ACK once it has reserved a slot in `reserved`, so
+/// concurrent connections never answer more than `limit` messages between them;
+/// `handled` counts the ACKs actually sent.
+fn serve_connection(
+    stream: TcpStream,
+    log: &EventLog,
+    reserved: &AtomicUsize,
+    handled: &AtomicUsize,
+    limit: usize,
+) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    loop {
+        let raw = match read_frame(&mut reader) {
+            Ok(Some(raw)) => raw,
+            Ok(None) => break,
+            // Senders that close right after reading the ACK (leaving its trailing
+            // CR unread) reset the connection; that is an ordinary hang-up.
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted
+                ) =>
+            {
+                break;
+            }
+            Err(err) => return Err(err),
+        };
+        let Ok(slot) = reserved.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+            (count < limit).then_some(count + 1)
+        }) else {
+            // Another connection took the last slot; close without answering.
+            break;
+        };
+        let ack = respond(&raw, log);
+        writer.write_all(&frame(&ack))?;
+        writer.flush()?;
+        handled.fetch_add(1, Ordering::SeqCst);
+        if slot + 1 >= limit {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Validates one received message, logs the result and returns the ACK to send.
+fn respond(raw: &str, log: &EventLog) -> String {
+    let message = match parse_message(raw) {
+        Ok(message) => message,
+        Err(err) => {
+            log.write("ERROR", &format!("HL7 message rejected (AR): {err}"));
+            return build_reject(raw, &err.to_string());
+        }
+    };
+    let label = format!(
+        "{} {}",
+        message.message_type().unwrap_or_else(|| "?".to_string()),
+        message.control_id().unwrap_or("?")
+    );
+    log.write("INFO", &format!("HL7 message received: {label}"));
+
+    let validation = validate_message(&message);
+    let errors: Vec<Issue> = validation
+        .issues
+        .iter()
+        .filter(|i| is_error(i))
+        .cloned()
+        .collect();
+    let warnings = validation
+        .issues
+        .iter()
+        .filter(|issue| issue.severity.as_deref() == Some("warning"))
+        .count();
+    let code = ack_code(&validation);
+    let text = if errors.is_empty() {
+        String::new()
+    } else {
+        plural(errors.len(), "validation error")
+    };
+    for issue in &errors {
+        log.write(
+            "ERROR",
+            &format!(
+                "HL7 validation error in {label} at {}: {}",
+                issue.expression.join(", "),
+                issue.diagnostics.as_deref().unwrap_or_default()
+            ),
+        );
+    }
+    if warnings > 0 {
+        log.write(
+            "WARNING",
+            &format!(
+                "HL7 message {label} has {}",
+                plural(warnings, "validation warning")
+            ),
+        );
+    }
+    let level = if code == "AA" { "INFO" } else { "ERROR" };
+    log.write(level, &format!("HL7 ACK sent: {code} for {label}"));
+    build_ack(&message, code, &text, &errors)
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {noun}")
+    } else {
+        format!("{count} {noun}s")
+    }
+}
+
+/// Settings for `clinlogix mllp send`.
+#[derive(Debug, Clone)]
+pub struct SendOptions {
+    pub host: String,
+    pub port: u16,
+    /// How long to wait for each ACK.
+    pub timeout: Duration,
+}
+
+/// Sends one framed message and returns the ACK text.
+pub fn send_message(stream: &mut TcpStream, message: &str) -> io::Result<String> {
+    stream.write_all(&frame(message))?;
+    stream.flush()?;
+    read_frame(stream)?.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "connection closed before an ACK arrived",
+        )
+    })
+}
+
+/// `clinlogix mllp send`: sends every message in `files` over one connection.
+///
+/// Fails with [`ValidateError::Failed`] if any message is not acknowledged with
+/// AA/CA, and with [`ValidateError::Transport`] on connection errors or timeouts.
+pub fn run_send(files: &[String], options: &SendOptions) -> Result<(), ValidateError> {
+    let mut messages = Vec::new();
+    for path in files {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| ValidateError::Input(format!("{path}: {err}")))?;
+        for parsed in crate::hl7v2::parse_messages(&text) {
+            let message = parsed.map_err(|err| ValidateError::Input(format!("{path}: {err}")))?;
+            messages.push((path.clone(), message));
+        }
+    }
+    if messages.is_empty() {
+        return Err(ValidateError::Input(
+            "no HL7 v2 messages to send".to_string(),
+        ));
+    }
+
+    let target = format!("{}:{}", options.host, options.port);
+    let address = target
+        .to_socket_addrs()
+        .map_err(|err| ValidateError::Transport(format!("{target}: {err}")))?
+        .next()
+        .ok_or_else(|| ValidateError::Transport(format!("{target}: no address")))?;
+    let mut stream = TcpStream::connect_timeout(&address, options.timeout)
+        .map_err(|err| ValidateError::Transport(format!("{target}: {err}")))?;
+    stream
+        .set_read_timeout(Some(options.timeout))
+        .map_err(|err| ValidateError::Transport(err.to_string()))?;
+
+    let mut rejected = 0;
+    for (path, message) in &messages {
+        let label = format!(
+            "{} {}",
+            message.message_type().unwrap_or_else(|| "?".to_string()),
+            message.control_id().unwrap_or("?")
+        );
+        let ack = send_message(&mut stream, &message.to_er7()).map_err(|err| {
+            let reason = match err.kind() {
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => format!(
+                    "no ACK for {label} within {}s",
+                    options.timeout.as_secs_f32()
+                ),
+                _ => format!("{label}: {err}"),
+            };
+            ValidateError::Transport(reason)
+        })?;
+        let (code, text) = match parse_message(&ack) {
+            Ok(ack) => {
+                let msa = ack.segment("MSA");
+                (
+                    msa.and_then(|msa| msa.value(1, 1))
+                        .unwrap_or("?")
+                        .to_string(),
+                    msa.and_then(|msa| msa.value(3, 1))
+                        .unwrap_or_default()
+                        .to_string(),
+                )
+            }
+            Err(err) => ("?".to_string(), format!("unreadable ACK: {err}")),
+        };
+        if code != "AA" && code != "CA" {
+            rejected += 1;
+        }
+        if text.is_empty() {
+            println!("{path}: {label} -> {code}");
+        } else {
+            println!("{path}: {label} -> {code} ({text})");
+        }
+    }
+
+    println!(
+        "Sent {} messages to {target}: {} accepted, {rejected} rejected",
+        messages.len(),
+        messages.len() - rejected
+    );
+    if rejected > 0 {
+        return Err(ValidateError::Failed("MLLP delivery"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADT: &str = "MSH|^~\\&|EHR|HOSP|LIS|LAB|20260107080112||ADT^A01^ADT_A01|MSG00001|P|2.5.1\r\
+EVN||20260107080112\r\
+PID|1||123^^^HOSP^MR||Doe^Jane\r\
+PV1|1|I\r";
+
+    #[test]
+    fn frames_round_trip() {
+        let framed = frame(ADT);
+        assert_eq!(framed[0], FRAME_START);
+        let mut noisy = b"garbage".to_vec();
+        noisy.extend(&framed);
+        noisy.extend(frame("MSH|^~\\&|second\r"));
+        let mut reader = noisy.as_slice();
+        assert_eq!(read_frame(&mut reader).unwrap().as_deref(), Some(ADT));
+        assert_eq!(
+            read_frame(&mut reader).unwrap().as_deref(),
+            Some("MSH|^~\\&|second\r")
+        );
+        assert_eq!(read_frame(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn ack_swaps_applications_and_echoes_control_id() {
+        let message = parse_message(ADT).expect("parse");
+        let ack = parse_message(&build_ack(&message, "AA", "", &[])).expect("ack parse");
+        let msh = ack.segment("MSH").unwrap();
+        assert_eq!(msh.value(3, 1), Some("LIS"));
+        assert_eq!(msh.value(5, 1), Some("EHR"));
+        assert_eq!(ack.message_type().as_deref(), Some("ACK^A01"));
+        let msa = ack.segment("MSA").unwrap();
+        assert_eq!(msa.value(1, 1), Some("AA"));
+        assert_eq!(msa.value(2, 1), Some("MSG00001"));
+    }
+
+    #[test]
+    fn nak_lists_errors_with_locations() {
+        let message =
+            parse_message(&ADT.replace("PID|1||123^^^HOSP^MR|", "PID|1|||")).expect("parse");
+        let validation = validate_message(&message);
+        assert_eq!(ack_code(&validation), "AE");
+        let ack = build_ack(&message, "AE", "1 validation error", &validation.issues);
+        let ack = parse_message(&ack).expect("ack parse");
+        let err = ack.segment("ERR").expect("ERR segment");
+        assert_eq!(err.field(2).unwrap().to_er7(&ack.delimiters), "PID^1^3");
+        assert_eq!(err.value(3, 1), Some("101"));
+
+        let reject = build_reject(
+            "MSH|^~\\&|A|B|C|D|x||ADT^A01|CTRL9|P|2.5\rgarbage",
+            "bad segment",
+        );
+        assert!(reject.contains("MSA|AR|CTRL9|bad segment"));
+    }
+
+    #[test]
+    fn listener_acknowledges_over_localhost() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().expect("accept");
+            let (reserved, handled) = (AtomicUsize::new(0), AtomicUsize::new(0));
+            let log = EventLog { file: None };
+            serve_connection(stream, &log, &reserved, &handled, usize::MAX).expect("serve");
+            handled.load(Ordering::SeqCst)
+        });
+
+        let mut stream = TcpStream::connect(address).expect("connect");
+        let ack = send_message(&mut stream, ADT).expect("ack");
+        assert!(ack.contains("MSA|AA|MSG00001"));
+        drop(stream);
+        assert_eq!(server.join().unwrap(), 1);
+    }
+
+    #[test]
+    fn listener_closes_without_ack_once_every_slot_is_taken() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().expect("accept");
+            // Another connection already reserved the only slot.
+            let (reserved, handled) = (AtomicUsize::new(1), AtomicUsize::new(0));
+            serve_connection(stream, &EventLog { file: None }, &reserved, &handled, 1)
+                .expect("serve");
+            handled.load(Ordering::SeqCst)
+        });
+
+        let mut stream = TcpStream::connect(address).expect("connect");
+        assert!(send_message(&mut stream, ADT).is_err());
+        assert_eq!(server.join().unwrap(), 0);
+    }
+
+    #[test]
+    fn formats_utc_timestamps() {
+        let time = std::time::UNIX_EPOCH + Duration::from_secs(1_767_772_872);
+        assert_eq!(
+            utc_parts(time),
+            ("2026-01-07".to_string(), "08:01:12".to_string())
+        );
+        assert_eq!(hl7_timestamp(time), "20260107080112+0000");
+    }
+}
@@ -0,0 +1,163 @@
+//! HL7 v2 (ER7 pipe-delimited) parsing and validation.
+
+mod definitions;
+mod mllp;
+mod parser;
+mod to_fhir;
+mod validate;
+
+use std::fs;
+use std::sync::LazyLock;
+
+#[allow(unused_imports)]
+pub use definitions::{Hl7Version, SUPPORTED_VERSIONS};
+#[allow(unused_imports)]
+pub use mllp::{
+    ListenOptions, SendOptions, ack_code, build_ack, build_reject, frame, read_frame, run_listen,
+    run_send, send_message,
+};
+#[allow(unused_imports)]
+pub use parser::{
+    Delimiters, Field, MLLP_END, MLLP_START, Message, ParseError, Repetition, Segment, escape,
+    parse_message, parse_messages, unescape,
+};
+#[allow(unused_imports)]
+pub use to_fhir::{Conversion, ConvertOptions, convert_message, convert_messages};
+#[allow(unused_imports)]
+pub use validate::{MessageValidation, validate_message};
+
+use crate::validate::{
+    FailurePolicy, Issue, OperationOutcome, ThemeRules, ValidateError, ValidationReport,
+    build_local_report, is_failure, print_report,
+};
+
+static THEMES: LazyLock<ThemeRules> = LazyLock::new(|| {
+    ThemeRules::from_toml(include_str!("themes.toml")).expect("bundled HL7 v2 themes are valid")
+});
+
+/// Validates every message in `text` and collects the issues into one report.
+///
+/// Messages that cannot be parsed are reported as fatal `structure` issues at
+/// the line where parsing stopped.
+pub fn validate_text(text: &str, file: &str) -> ValidationReport {
+    let mut outcome = OperationOutcome::empty();
+    let mut validators = Vec::new();
+    let messages = parse_messages(text);
+    let count = messages.len();
+    for parsed in messages {
+        match parsed {
+            Ok(message) => {
+                let validation = validate_message(&message);
+                validators.push(validation.describe());
+                outcome.issue.extend(validation.issues);
+            }
+            Err(err) => outcome.issue.push(
+                Issue::local("fatal", "structure", err.message, "").with_line(err.line as u32),
+            ),
+        }
+    }
+    validators.dedup();
+    let validator = match validators.len() {
+        0 => "HL7 v2".to_string(),
+        1 => validators.remove(0),
+        _ => format!("HL7 v2 ({count} messages)"),
+    };
+    build_local_report(&outcome, "HL7 v2 Validation", file, &validator, &THEMES)
+}
+
+/// `clinlogix hl7v2 validate`: prints the report and fails per `policy`.
+pub fn run_validate_file(path: &str, policy: &FailurePolicy) -> Result<(), ValidateError> {
+    let text =
+        fs::read_to_string(path).map_err(|err| ValidateError::Input(format!("{path}: {err}")))?;
+    if !text.contains("MSH") {
+        return Err(ValidateError::Input(format!(
+            "{path}: no HL7 v2 message (MSH segment) found"
+        )));
+    }
+    let mut report = validate_text(&text, path);
+    report.policy = policy.clone();
+    print_report(&report);
+    if is_failure(&report) {
+        return Err(ValidateError::Failed("HL7 v2"));
+    }
+    Ok(())
+}
+
+/// `clinlogix convert hl7v2-to-fhir`: writes a transaction Bundle to `output` or stdout.
+///
+/// Notes about data that could not be mapped go to stderr so stdout stays valid JSON.
+pub fn run_convert_file(
+    path: &str,
+    output: Option<&str>,
+    options: &ConvertOptions,
+) -> Result<(), ValidateError> {
+    let text =
+        fs::read_to_string(path).map_err(|err| ValidateError::Input(format!("{path}: {err}")))?;
+    let messages = parse_messages(&text)
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| ValidateError::Input(format!("{path}: {err}")))?;
+    if messages.is_empty() {
+        return Err(ValidateError::Input(format!(
+            "{path}: no HL7 v2 message (MSH segment) found"
+        )));
+    }
+
+    let conversion = convert_messages(&messages, options);
+    for note in &conversion.notes {
+        eprintln!("Note: {note}");
+    }
+    let mut json = serde_json::to_string_pretty(&conversion.bundle)
+        .map_err(|err| ValidateError::Input(err.to_string()))?;
+    json.push('\n');
+    match output {
+        Some(output) => {
+            fs::write(output, json)
+                .map_err(|err| ValidateError::Input(format!("{output}: {err}")))?;
+            let entries = conversion.bundle["entry"].as_array().map_or(0, Vec::len);
+            eprintln!("Wrote transaction Bundle with {entries} entries to {output}");
+        }
+        None => print!("{json}"),
+    }
+    Ok(())
+}
+
+/// `clinlogix hl7v2 parse`: prints each segment with its numbered, non-empty fields.
+pub fn run_parse_file(path: &str) -> Result<(), ValidateError> {
+    let text =
+        fs::read_to_string(path).map_err(|err| ValidateError::Input(format!("{path}: {err}")))?;
+    for (index, parsed) in parse_messages(&text).into_iter().enumerate() {
+        let message = parsed.map_err(|err| ValidateError::Input(format!("{path}: {err}")))?;
+        print!("{}", format_message(&message, index + 1));
+    }
+    Ok(())
+}
+
+pub fn format_message(message: &Message, number: usize) -> String {
+    let mut output = format!(
+        "Message {}: {} (control id: {}, version: {})\n",
+        number,
+        message.message_type().unwrap_or_else(|| "?".to_string()),
+        message.control_id().unwrap_or("?"),
+        message.version().unwrap_or("?")
+    );
+    for segment in &message.segments {
+        match definitions::segment_def(&segment.id) {
+            Some(definition) => output.push_str(&format!(
+                "{} {} (line {})\n",
+                segment.id, definition.name, segment.line
+            )),
+            None => output.push_str(&format!("{} (line {})\n", segment.id, segment.line)),
+        }
+        for (index, field) in segment.fields.iter().enumerate() {
+            let value = match (segment.id.as_str(), index) {
+                ("MSH", 0) => message.delimiters.field.to_string(),
+                ("MSH", 1) => message.delimiters.encoding_characters(),
+                _ if field.is_empty() => continue,
+                _ => field.to_er7(&message.delimiters),
+            };
+            output.push_str(&format!("  {}-{}: {}\n", segment.id, index + 1, value));
+        }
+    }
+    output
+}
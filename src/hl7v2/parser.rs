@@ -0,0 +1,525 @@
+use std::fmt;
+
+/// MLLP framing bytes that may surround a message captured off the wire.
+pub const MLLP_START: char = '\u{0b}';
+pub const MLLP_END: char = '\u{1c}';
+
+/// Delimiters declared in MSH-1 and MSH-2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Delimiters {
+    pub field: char,
+    pub component: char,
+    pub repetition: char,
+    pub escape: char,
+    pub subcomponent: char,
+    /// Truncation character introduced in v2.7 (`#`), when present.
+    pub truncation: Option<char>,
+}
+
+impl Default for Delimiters {
+    fn default() -> Self {
+        Self {
+            field: '|',
+            component: '^',
+            repetition: '~',
+            escape: '\\',
+            subcomponent: '&',
+            truncation: None,
+        }
+    }
+}
+
+impl Delimiters {
+    /// The MSH-2 value for these delimiters.
+    pub fn encoding_characters(&self) -> String {
+        let mut value: String = [
+            self.component,
+            self.repetition,
+            self.escape,
+            self.subcomponent,
+        ]
+        .iter()
+        .collect();
+        if let Some(truncation) = self.truncation {
+            value.push(truncation);
+        }
+        value
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A parsed ER7 message. Values are stored unescaped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub delimiters: Delimiters,
+    pub segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub id: String,
+    /// `fields[0]` is field 1 (for MSH: the field separator itself).
+    pub fields: Vec<Field>,
+    /// 1-based line in the source text, for reporting.
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Field {
+    pub repetitions: Vec<Repetition>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Repetition {
+    /// `components[0]` is component 1; each component holds its subcomponents.
+    pub components: Vec<Vec<String>>,
+}
+
+impl Message {
+    pub fn segment(&self, id: &str) -> Option<&Segment> {
+        self.segments.iter().find(|segment| segment.id == id)
+    }
+
+    pub fn segments_named<'a>(&'a self, id: &'a str) -> impl Iterator<Item = &'a Segment> {
+        self.segments.iter().filter(move |segment| segment.id == id)
+    }
+
+    /// MSH-12 version id, e.g. `2.5.1`.
+    pub fn version(&self) -> Option<&str> {
+        self.segment("MSH")?.value(12, 1)
+    }
+
+    /// MSH-9 as `TYPE^TRIGGER` (e.g. `ADT^A01`).
+    pub fn message_type(&self) -> Option<String> {
+        let msh = self.segment("MSH")?;
+        let code = msh.value(9, 1)?;
+        Some(match msh.value(9, 2) {
+            Some(trigger) => format!("{code}^{trigger}"),
+            None => code.to_string(),
+        })
+    }
+
+    /// MSH-10 message control id.
+    pub fn control_id(&self) -> Option<&str> {
+        self.segment("MSH")?.value(10, 1)
+    }
+
+    /// Serializes the message back to ER7 with `\r` segment separators.
+    pub fn to_er7(&self) -> String {
+        self.segments
+            .iter()
+            .map(|segment| segment.to_er7(&self.delimiters))
+            .collect::<Vec<_>>()
+            .join("\r")
+            + "\r"
+    }
+}
+
+impl Segment {
+    pub fn field(&self, number: usize) -> Option<&Field> {
+        self.fields.get(number.checked_sub(1)?)
+    }
+
+    /// First repetition, given component, first subcomponent; `None` when empty.
+    pub fn value(&self, field: usize, component: usize) -> Option<&str> {
+        self.field(field)?.component(component)
+    }
+
+    pub fn to_er7(&self, delimiters: &Delimiters) -> String {
+        let mut output = self.id.clone();
+        let skip = if self.id == "MSH" {
+            output.push(delimiters.field);
+            output.push_str(&delimiters.encoding_characters());
+            2
+        } else {
+            0
+        };
+        for field in self.fields.iter().skip(skip) {
+            output.push(delimiters.field);
+            output.push_str(&field.to_er7(delimiters));
+        }
+        output
+    }
+}
+
+impl Field {
+    pub fn is_empty(&self) -> bool {
+        self.repetitions.iter().all(Repetition::is_empty)
+    }
+
+    pub fn component(&self, number: usize) -> Option<&str> {
+        self.repetitions.first()?.component(number)
+    }
+
+    pub fn to_er7(&self, delimiters: &Delimiters) -> String {
+        self.repetitions
+            .iter()
+            .map(|repetition| repetition.to_er7(delimiters))
+            .collect::<Vec<_>>()
+            .join(&delimiters.repetition.to_string())
+    }
+}
+
+impl Repetition {
+    pub fn is_empty(&self) -> bool {
+        self.components.iter().flatten().all(String::is_empty)
+    }
+
+    pub fn component(&self, number: usize) -> Option<&str> {
+        self.components
+            .get(number.checked_sub(1)?)?
+            .first()
+            .map(String::as_str)
+            .filter(|value| !value.is_empty())
+    }
+
+    /// Subcomponent `sub` of component `number` (both 1-based); `None` when empty.
+    pub fn subcomponent(&self, number: usize, sub: usize) -> Option<&str> {
+        self.components
+            .get(number.checked_sub(1)?)?
+            .get(sub.checked_sub(1)?)
+            .map(String::as_str)
+            .filter(|value| !value.is_empty())
+    }
+
+    fn to_er7(&self, delimiters: &Delimiters) -> String {
+        self.components
+            .iter()
+            .map(|component| {
+                component
+                    .iter()
+                    .map(|value| escape(value, delimiters))
+                    .collect::<Vec<_>>()
+                    .join(&delimiters.subcomponent.to_string())
+            })
+            .collect::<Vec<_>>()
+            .join(&delimiters.component.to_string())
+    }
+}
+
+/// Splits text that may hold several messages (or MLLP frames) at each `MSH` segment.
+pub fn parse_messages(text: &str) -> Vec<Result<Message, ParseError>> {
+    let mut chunks: Vec<Vec<(usize, &str)>> = Vec::new();
+    for (line, segment) in segment_lines(text) {
+        if segment.starts_with("MSH") || chunks.is_empty() {
+            chunks.push(Vec::new());
+        }
+        if let Some(lines) = chunks.last_mut() {
+            lines.push((line, segment));
+        }
+    }
+    chunks.iter().map(|lines| parse_lines(lines)).collect()
+}
+
+/// Parses a single ER7 message.
+pub fn parse_message(text: &str) -> Result<Message, ParseError> {
+    parse_lines(&segment_lines(text))
+}
+
+/// Non-empty segments with their 1-based line numbers.
+///
+/// Accepts `\r`, `\n` or `\r\n` separators and strips MLLP framing characters.
+fn segment_lines(text: &str) -> Vec<(usize, &str)> {
+    let mut lines = Vec::new();
+    let mut number = 0;
+    for physical in text.split('\n') {
+        let physical = physical.strip_suffix('\r').unwrap_or(physical);
+        for segment in physical.split('\r') {
+            number += 1;
+            let segment = segment.trim_matches(|ch| ch == MLLP_START || ch == MLLP_END);
+            if !segment.trim().is_empty() {
+                lines.push((number, segment));
+            }
+        }
+    }
+    lines
+}
+
+fn parse_lines(lines: &[(usize, &str)]) -> Result<Message, ParseError> {
+    let (first_line, header) = lines.first().copied().unwrap_or((1, ""));
+    let delimiters = parse_delimiters(header).map_err(|message| ParseError {
+        line: first_line,
+        message,
+    })?;
+
+    let segments = lines
+        .iter()
+        .map(|(line, text)| parse_segment(text, &delimiters, *line))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Message {
+        delimiters,
+        segments,
+    })
+}
+
+fn parse_delimiters(header: &str) -> Result<Delimiters, String> {
+    if !header.starts_with("MSH") {
+        return Err("message does not start with an MSH segment".to_string());
+    }
+    let mut chars = header.chars().skip(3);
+    let field = chars
+        .next()
+        .ok_or_else(|| "MSH is missing the field separator (MSH-1)".to_string())?;
+    let encoding: Vec<char> = chars.take_while(|ch| *ch != field).collect();
+    if encoding.len() < 4 {
+        return Err(format!(
+            "MSH-2 must declare four encoding characters, found '{}'",
+            encoding.iter().collect::<String>()
+        ));
+    }
+
+    let delimiters = Delimiters {
+        field,
+        component: encoding[0],
+        repetition: encoding[1],
+        escape: encoding[2],
+        subcomponent: encoding[3],
+        truncation: encoding.get(4).copied(),
+    };
+    let mut all = vec![
+        delimiters.field,
+        delimiters.component,
+        delimiters.repetition,
+        delimiters.escape,
+        delimiters.subcomponent,
+    ];
+    all.extend(delimiters.truncation);
+    let count = all.len();
+    all.sort_unstable();
+    all.dedup();
+    if all.len() != count || all.iter().any(|ch| ch.is_alphanumeric()) {
+        return Err(
+            "MSH-1/MSH-2 delimiters must be distinct, non-alphanumeric characters".to_string(),
+        );
+    }
+    Ok(delimiters)
+}
+
+fn parse_segment(text: &str, delimiters: &Delimiters, line: usize) -> Result<Segment, ParseError> {
+    let mut parts = text.split(delimiters.field);
+    let id = parts.next().unwrap_or_default().trim().to_string();
+    if id.len() != 3
+        || !id
+            .chars()
+            .all(|ch| ch.is_ascii_uppercase() || ch.is_ascii_digit())
+    {
+        return Err(ParseError {
+            line,
+            message: format!("'{id}' is not a valid segment id"),
+        });
+    }
+
+    let mut fields = Vec::new();
+    if id == "MSH" {
+        fields.push(literal_field(delimiters.field.to_string()));
+        fields.push(literal_field(parts.next().unwrap_or_default().to_string()));
+    }
+    fields.extend(parts.map(|raw| parse_field(raw, delimiters)));
+
+    Ok(Segment { id, fields, line })
+}
+
+fn literal_field(value: String) -> Field {
+    Field {
+        repetitions: vec![Repetition {
+            components: vec![vec![value]],
+        }],
+    }
+}
+
+fn parse_field(raw: &str, delimiters: &Delimiters) -> Field {
+    Field {
+        repetitions: raw
+            .split(delimiters.repetition)
+            .map(|repetition| Repetition {
+                components: repetition
+                    .split(delimiters.component)
+                    .map(|component| {
+                        component
+                            .split(delimiters.subcomponent)
+                            .map(|value| unescape(value, delimiters))
+                            .collect()
+                    })
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+/// Resolves `\F\`, `\S\`, `\T\`, `\R\`, `\E\`, `\Xhh..\` and `\.br\`; other sequences
+/// (highlighting, character set switches) are kept verbatim.
+pub fn unescape(value: &str, delimiters: &Delimiters) -> String {
+    let escape = delimiters.escape;
+    if !value.contains(escape) {
+        return value.to_string();
+    }
+
+    let mut output = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find(escape) {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + escape.len_utf8()..];
+        let Some(end) = after.find(escape) else {
+            output.push_str(&rest[start..]);
+            return output;
+        };
+        let sequence = &after[..end];
+        match sequence {
+            "F" => output.push(delimiters.field),
+            "S" => output.push(delimiters.component),
+            "T" => output.push(delimiters.subcomponent),
+            "R" => output.push(delimiters.repetition),
+            "E" => output.push(escape),
+            "P" if delimiters.truncation.is_some() => {
+                output.extend(delimiters.truncation);
+            }
+            ".br" => output.push('\n'),
+            hex if hex.starts_with('X') && hex.len() > 1 && hex.len() % 2 == 1 => {
+                match decode_hex(&hex[1..]) {
+                    Some(decoded) => output.push_str(&decoded),
+                    None => {
+                        output.push(escape);
+                        output.push_str(sequence);
+                        output.push(escape);
+                    }
+                }
+            }
+            _ => {
+                output.push(escape);
+                output.push_str(sequence);
+                output.push(escape);
+            }
+        }
+        rest = &after[end + escape.len_utf8()..];
+    }
+    output.push_str(rest);
+    output
+}
+
+fn decode_hex(hex: &str) -> Option<String> {
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+/// Escapes delimiter characters in a value for ER7 output.
+pub fn escape(value: &str, delimiters: &Delimiters) -> String {
+    let mut output = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            _ if ch == delimiters.escape => output.push_str(&format!("{0}E{0}", delimiters.escape)),
+            _ if ch == delimiters.field => output.push_str(&format!("{0}F{0}", delimiters.escape)),
+            _ if ch == delimiters.component => {
+                output.push_str(&format!("{0}S{0}", delimiters.escape))
+            }
+            _ if ch == delimiters.subcomponent => {
+                output.push_str(&format!("{0}T{0}", delimiters.escape))
+            }
+            _ if ch == delimiters.repetition => {
+                output.push_str(&format!("{0}R{0}", delimiters.escape))
+            }
+            '\n' => output.push_str(&format!("{0}.br{0}", delimiters.escape)),
+            _ => output.push(ch),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADT: &str = "MSH|^~\\&|EHR|HOSP|HL7LISTENER|HOSP|20260107080112||ADT^A01^ADT_A01|MSG00001|P|2.5.1\r\
+EVN|A01|20260107080112\r\
+PID|1||123^^^HOSP^MR~456^^^SSA^SS||Doe^Jane^Q||19850520|F|||1 Main St^^Boston^MA^02115\r\
+PV1|1|I|WARD1^101^A\r";
+
+    #[test]
+    fn parses_msh_encoding_and_field_numbering() {
+        let message = parse_message(ADT).expect("parse");
+        assert_eq!(message.delimiters, Delimiters::default());
+        let msh = message.segment("MSH").expect("msh");
+        assert_eq!(msh.value(1, 1), Some("|"));
+        assert_eq!(msh.value(2, 1), Some("^~\\&"));
+        assert_eq!(msh.value(3, 1), Some("EHR"));
+        assert_eq!(message.message_type().as_deref(), Some("ADT^A01"));
+        assert_eq!(message.control_id(), Some("MSG00001"));
+        assert_eq!(message.version(), Some("2.5.1"));
+    }
+
+    #[test]
+    fn splits_repetitions_components_and_subcomponents() {
+        let message = parse_message(ADT).expect("parse");
+        let pid = message.segment("PID").expect("pid");
+        let identifiers = pid.field(3).expect("PID-3");
+        assert_eq!(identifiers.repetitions.len(), 2);
+        assert_eq!(identifiers.repetitions[1].component(1), Some("456"));
+        assert_eq!(identifiers.repetitions[1].component(5), Some("SS"));
+        assert_eq!(pid.value(5, 2), Some("Jane"));
+        assert_eq!(pid.line, 3);
+    }
+
+    #[test]
+    fn unescapes_delimiters_hex_and_line_breaks() {
+        let delimiters = Delimiters::default();
+        assert_eq!(
+            unescape("A\\F\\B\\S\\C\\T\\D\\R\\E\\E\\F", &delimiters),
+            "A|B^C&D~E\\F"
+        );
+        assert_eq!(unescape("line1\\.br\\line2", &delimiters), "line1\nline2");
+        assert_eq!(unescape("\\X414243\\", &delimiters), "ABC");
+        assert_eq!(unescape("\\H\\bold\\N\\", &delimiters), "\\H\\bold\\N\\");
+        assert_eq!(escape("a|b^c", &delimiters), "a\\F\\b\\S\\c");
+    }
+
+    #[test]
+    fn honours_custom_delimiters_and_mllp_framing() {
+        let framed = "\u{0b}MSH#*@!%#APP#FAC#####ADT*A08#1#P#2.4\rPID###A*B@C\r\u{1c}\r";
+        let message = parse_message(framed).expect("parse");
+        assert_eq!(message.delimiters.field, '#');
+        assert_eq!(message.delimiters.component, '*');
+        assert_eq!(message.delimiters.escape, '!');
+        assert_eq!(message.delimiters.subcomponent, '%');
+        assert_eq!(message.message_type().as_deref(), Some("ADT^A08"));
+        let pid = message.segment("PID").expect("pid");
+        assert_eq!(pid.field(3).unwrap().repetitions.len(), 2);
+        assert_eq!(pid.value(3, 2), Some("B"));
+    }
+
+    #[test]
+    fn rejects_missing_msh_and_bad_segment_ids() {
+        assert!(parse_message("PID|1\r").is_err());
+        assert!(parse_message("MSH|^~\r").is_err());
+        let err = parse_message("MSH|^~\\&|A\rpid|1\r").expect_err("lowercase id");
+        assert_eq!(err.line, 2);
+    }
+
+    #[test]
+    fn round_trips_to_er7() {
+        let message = parse_message(ADT).expect("parse");
+        assert_eq!(message.to_er7(), ADT);
+    }
+
+    #[test]
+    fn splits_batches_at_each_msh() {
+        let text = format!("{ADT}\n{ADT}");
+        let messages = parse_messages(&text);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].as_ref().expect("second").segments[0].line, 5);
+    }
+}
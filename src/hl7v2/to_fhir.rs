@@ -0,0 +1,1005 @@
+//! HL7 v2 → FHIR R4 mapping, following the HL7 v2-to-FHIR IG where practical.
+//!
+//! PID → Patient, PV1 → Encounter, OBR → DiagnosticReport (result messages
+//! only) and OBX → Observation, packaged as a transaction Bundle with
+//! `urn:uuid` references between the entries.
+
+use serde_json::{Map, Value, json};
+
+use crate::hl7v2::parser::{Message, Repetition, Segment};
+use crate::validate::ValidateError;
+
+const V2_TABLE_PREFIX: &str = "http://terminology.hl7.org/CodeSystem/v2-";
+const ACT_CODE_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/v3-ActCode";
+const PARTICIPATION_TYPE_SYSTEM: &str =
+    "http://terminology.hl7.org/CodeSystem/v3-ParticipationType";
+const INTERPRETATION_SYSTEM: &str =
+    "http://terminology.hl7.org/CodeSystem/v3-ObservationInterpretation";
+const OBSERVATION_CATEGORY_SYSTEM: &str =
+    "http://terminology.hl7.org/CodeSystem/observation-category";
+const UCUM_SYSTEM: &str = "http://unitsofmeasure.org";
+
+/// Settings for [`convert_messages`].
+#[derive(Debug, Clone, Default)]
+pub struct ConvertOptions {
+    /// UTC offset (`+01:00`, `-05:00`) assumed for v2 timestamps without one.
+    ///
+    /// FHIR dateTimes with a time need a timezone, so without this option such
+    /// timestamps are truncated to their date.
+    pub timezone: Option<String>,
+}
+
+impl ConvertOptions {
+    /// Options for a `--timezone` given as `±HHMM` or `±HH:MM`.
+    pub fn with_timezone(timezone: Option<&str>) -> Result<Self, ValidateError> {
+        let timezone = timezone
+            .map(|value| {
+                let well_formed = match value.len() {
+                    5 => true,
+                    6 => value.as_bytes()[3] == b':',
+                    _ => false,
+                };
+                well_formed
+                    .then(|| utc_offset(value))
+                    .flatten()
+                    .filter(|offset| offset != "Z")
+                    .ok_or_else(|| {
+                        ValidateError::Input(format!(
+                            "invalid --timezone '{value}' (expected ±HHMM or ±HH:MM)"
+                        ))
+                    })
+            })
+            .transpose()?;
+        Ok(Self { timezone })
+    }
+}
+
+/// A transaction Bundle plus notes about data that could not be carried over.
+#[derive(Debug, Clone)]
+pub struct Conversion {
+    pub bundle: Value,
+    pub notes: Vec<String>,
+}
+
+pub fn convert_message(message: &Message, options: &ConvertOptions) -> Conversion {
+    convert_messages(std::slice::from_ref(message), options)
+}
+
+/// Converts every message into one transaction Bundle.
+pub fn convert_messages(messages: &[Message], options: &ConvertOptions) -> Conversion {
+    let mut converter = Converter {
+        options,
+        entries: Vec::new(),
+        notes: Vec::new(),
+    };
+    for (index, message) in messages.iter().enumerate() {
+        converter.message(message, index);
+    }
+
+    Conversion {
+        bundle: json!({
+            "resourceType": "Bundle",
+            "type": "transaction",
+            "entry": converter.entries,
+        }),
+        notes: converter.notes,
+    }
+}
+
+struct Converter<'a> {
+    options: &'a ConvertOptions,
+    entries: Vec<Value>,
+    notes: Vec<String>,
+}
+
+/// Message-level context shared by the resources built from one message.
+struct Context {
+    seed: String,
+    patient: Option<String>,
+    encounter: Option<String>,
+    laboratory: bool,
+}
+
+impl Converter<'_> {
+    fn message(&mut self, message: &Message, index: usize) {
+        let message_code = message
+            .segment("MSH")
+            .and_then(|msh| msh.value(9, 1))
+            .unwrap_or_default();
+        let mut context = Context {
+            seed: format!("{}#{}", message.control_id().unwrap_or_default(), index + 1),
+            patient: None,
+            encounter: None,
+            laboratory: message_code == "ORU",
+        };
+
+        let mut report: Option<PendingReport> = None;
+        for segment in &message.segments {
+            match segment.id.as_str() {
+                "PID" if context.patient.is_none() => {
+                    let patient = self.patient(segment);
+                    context.patient = Some(self.add(&context, segment, patient));
+                }
+                "PV1" if context.encounter.is_none() => {
+                    let encounter = self.encounter(segment, &context);
+                    context.encounter = Some(self.add(&context, segment, encounter));
+                }
+                "OBR" if context.laboratory => {
+                    self.finish_report(&context, report.take());
+                    report = Some(PendingReport {
+                        resource: self.diagnostic_report(segment, &context),
+                        results: Vec::new(),
+                        line: segment.line,
+                    });
+                }
+                "OBR" => self.note(format!(
+                    "OBR at line {} skipped: only ORU results map to DiagnosticReport",
+                    segment.line
+                )),
+                "OBX" => {
+                    let effective = report
+                        .as_ref()
+                        .and_then(|report| report.resource.get("effectiveDateTime").cloned());
+                    let observation = self.observation(segment, &context, effective);
+                    let url = self.add(&context, segment, observation);
+                    if let Some(report) = &mut report {
+                        report.results.push(json!({ "reference": url }));
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.finish_report(&context, report);
+    }
+
+    /// Adds a DiagnosticReport once all of its OBX results have been converted.
+    fn finish_report(&mut self, context: &Context, report: Option<PendingReport>) {
+        let Some(PendingReport {
+            mut resource,
+            results,
+            line,
+        }) = report
+        else {
+            return;
+        };
+        set_array(&mut resource, "result", results);
+        let url = full_url(&format!("{}/DiagnosticReport/{line}", context.seed));
+        self.entries.push(entry(&url, resource));
+    }
+
+    fn note(&mut self, note: String) {
+        if !self.notes.contains(&note) {
+            self.notes.push(note);
+        }
+    }
+
+    /// Appends an entry and returns its `urn:uuid` full URL.
+    fn add(
+        &mut self,
+        context: &Context,
+        segment: &Segment,
+        resource: Map<String, Value>,
+    ) -> String {
+        let resource_type = resource
+            .get("resourceType")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        let url = full_url(&format!(
+            "{}/{resource_type}/{}",
+            context.seed, segment.line
+        ));
+        self.entries.push(entry(&url, resource));
+        url
+    }
+
+    fn patient(&mut self, pid: &Segment) -> Map<String, Value> {
+        let mut patient = resource("Patient");
+        let identifiers: Vec<Value> = repetitions(pid, 3).filter_map(identifier).collect();
+        set_array(&mut patient, "identifier", identifiers);
+        set_array(
+            &mut patient,
+            "name",
+            repetitions(pid, 5).filter_map(human_name).collect(),
+        );
+        let mut telecom: Vec<Value> = repetitions(pid, 13)
+            .filter_map(|xtn| contact_point(xtn, "home"))
+            .collect();
+        telecom.extend(repetitions(pid, 14).filter_map(|xtn| contact_point(xtn, "work")));
+        set_array(&mut patient, "telecom", telecom);
+        if let Some(gender) = pid.value(8, 1) {
+            let gender = match gender {
+                "F" => "female",
+                "M" => "male",
+                "O" => "other",
+                _ => "unknown",
+            };
+            patient.insert("gender".to_string(), json!(gender));
+        }
+        set(&mut patient, "birthDate", pid.value(7, 1).and_then(date));
+        if let Some(deceased) = pid.value(29, 1) {
+            set(&mut patient, "deceasedDateTime", self.date_time(deceased));
+        } else if let Some(flag) = pid.value(30, 1) {
+            patient.insert("deceasedBoolean".to_string(), json!(flag == "Y"));
+        }
+        set_array(
+            &mut patient,
+            "address",
+            repetitions(pid, 11).filter_map(address).collect(),
+        );
+        set(
+            &mut patient,
+            "maritalStatus",
+            first(pid, 16).and_then(|cwe| codeable_concept(cwe, Some("0002"))),
+        );
+        patient
+    }
+
+    fn encounter(&mut self, pv1: &Segment, context: &Context) -> Map<String, Value> {
+        let mut encounter = resource("Encounter");
+        let discharged = pv1.value(45, 1);
+        encounter.insert(
+            "status".to_string(),
+            json!(if discharged.is_some() {
+                "finished"
+            } else {
+                "in-progress"
+            }),
+        );
+        let class = match pv1.value(2, 1) {
+            Some("I") => {
+                json!({"system": ACT_CODE_SYSTEM, "code": "IMP", "display": "inpatient encounter"})
+            }
+            Some("O") => json!({"system": ACT_CODE_SYSTEM, "code": "AMB", "display": "ambulatory"}),
+            Some("E") => json!({"system": ACT_CODE_SYSTEM, "code": "EMER", "display": "emergency"}),
+            Some("P") => {
+                json!({"system": ACT_CODE_SYSTEM, "code": "PRENC", "display": "pre-admission"})
+            }
+            Some(code) => json!({"system": format!("{V2_TABLE_PREFIX}0004"), "code": code}),
+            None => json!({"system": ACT_CODE_SYSTEM, "code": "AMB", "display": "ambulatory"}),
+        };
+        encounter.insert("class".to_string(), class);
+        set_array(
+            &mut encounter,
+            "identifier",
+            first(pv1, 19).and_then(identifier).into_iter().collect(),
+        );
+        set(&mut encounter, "subject", reference(&context.patient));
+
+        let participants: Vec<Value> = [(7, "ATND", "attender"), (8, "REF", "referrer"), (17, "ADM", "admitter")]
+            .into_iter()
+            .flat_map(|(field, code, display)| {
+                repetitions(pv1, field).filter_map(move |xcn| {
+                    let name = person_name(xcn)?;
+                    Some(json!({
+                        "type": [{"coding": [{"system": PARTICIPATION_TYPE_SYSTEM, "code": code, "display": display}]}],
+                        "individual": {"display": name},
+                    }))
+                })
+            })
+            .collect();
+        set_array(&mut encounter, "participant", participants);
+
+        let mut period = Map::new();
+        set(
+            &mut period,
+            "start",
+            pv1.value(44, 1).and_then(|value| self.date_time(value)),
+        );
+        set(
+            &mut period,
+            "end",
+            discharged.and_then(|value| self.date_time(value)),
+        );
+        if !period.is_empty() {
+            encounter.insert("period".to_string(), Value::Object(period));
+        }
+        if let Some(location) = first(pv1, 3).and_then(location_display) {
+            encounter.insert(
+                "location".to_string(),
+                json!([{"location": {"display": location}}]),
+            );
+        }
+        encounter
+    }
+
+    fn diagnostic_report(&mut self, obr: &Segment, context: &Context) -> Map<String, Value> {
+        let mut report = resource("DiagnosticReport");
+        let identifiers: Vec<Value> = [(2, "PLAC", "Placer Identifier"), (3, "FILL", "Filler Identifier")]
+            .into_iter()
+            .filter_map(|(field, code, display)| {
+                let value = obr.value(field, 1)?;
+                Some(json!({
+                    "type": {"coding": [{"system": format!("{V2_TABLE_PREFIX}0203"), "code": code, "display": display}]},
+                    "value": value,
+                }))
+            })
+            .collect();
+        set_array(&mut report, "identifier", identifiers);
+        let status = match obr.value(25, 1) {
+            Some("O" | "I" | "S") => "registered",
+            Some("P") => "preliminary",
+            Some("A" | "R") => "partial",
+            Some("C") => "corrected",
+            Some("F") => "final",
+            Some("X") => "cancelled",
+            _ => "unknown",
+        };
+        report.insert("status".to_string(), json!(status));
+        report.insert(
+            "category".to_string(),
+            category(
+                "LAB",
+                "Laboratory",
+                "http://terminology.hl7.org/CodeSystem/v2-0074",
+            ),
+        );
+        report.insert(
+            "code".to_string(),
+            first(obr, 4)
+                .and_then(|cwe| codeable_concept(cwe, None))
+                .unwrap_or_else(|| json!({"text": "unknown"})),
+        );
+        set(&mut report, "subject", reference(&context.patient));
+        set(&mut report, "encounter", reference(&context.encounter));
+        set(
+            &mut report,
+            "effectiveDateTime",
+            obr.value(7, 1).and_then(|value| self.date_time(value)),
+        );
+        set(
+            &mut report,
+            "issued",
+            obr.value(22, 1).and_then(|value| self.instant(value)),
+        );
+        report
+    }
+
+    fn observation(
+        &mut self,
+        obx: &Segment,
+        context: &Context,
+        report_effective: Option<Value>,
+    ) -> Map<String, Value> {
+        let mut observation = resource("Observation");
+        let status = match obx.value(11, 1) {
+            Some("C") => "corrected",
+            Some("D" | "W") => "entered-in-error",
+            Some("F" | "U") => "final",
+            Some("I" | "O") => "registered",
+            Some("P" | "R" | "S") => "preliminary",
+            Some("X") => "cancelled",
+            _ => "unknown",
+        };
+        observation.insert("status".to_string(), json!(status));
+        if context.laboratory {
+            observation.insert(
+                "category".to_string(),
+                category("laboratory", "Laboratory", OBSERVATION_CATEGORY_SYSTEM),
+            );
+        }
+        observation.insert(
+            "code".to_string(),
+            first(obx, 3)
+                .and_then(|cwe| codeable_concept(cwe, None))
+                .unwrap_or_else(|| json!({"text": "unknown"})),
+        );
+        set(&mut observation, "subject", reference(&context.patient));
+        set(&mut observation, "encounter", reference(&context.encounter));
+        let effective = obx
+            .value(14, 1)
+            .and_then(|value| self.date_time(value))
+            .or(report_effective);
+        set(&mut observation, "effectiveDateTime", effective);
+
+        let units = first(obx, 6);
+        if let Some(value) = first(obx, 5) {
+            let (key, value) = match obx.value(2, 1).unwrap_or("ST") {
+                "NM" => match quantity(value.component(1).unwrap_or_default(), units) {
+                    Some(quantity) => ("valueQuantity", quantity),
+                    None => ("valueString", json!(value.component(1))),
+                },
+                "SN" => match structured_numeric(value, units) {
+                    Some(quantity) => ("valueQuantity", quantity),
+                    None => ("valueString", json!(joined(value))),
+                },
+                "CE" | "CWE" | "CNE" => match codeable_concept(value, None) {
+                    Some(concept) => ("valueCodeableConcept", concept),
+                    None => ("valueString", json!(joined(value))),
+                },
+                "DT" | "DTM" | "TS" => {
+                    match value.component(1).and_then(|value| self.date_time(value)) {
+                        Some(date_time) => ("valueDateTime", date_time),
+                        None => ("valueString", json!(joined(value))),
+                    }
+                }
+                _ => ("valueString", json!(joined(value))),
+            };
+            observation.insert(key.to_string(), value);
+        }
+
+        if let Some(interpretation) = obx.value(8, 1) {
+            observation.insert(
+                "interpretation".to_string(),
+                json!([{"coding": [{"system": INTERPRETATION_SYSTEM, "code": interpretation}]}]),
+            );
+        }
+        if let Some(range) = obx.value(7, 1) {
+            observation.insert(
+                "referenceRange".to_string(),
+                json!([reference_range(range, units)]),
+            );
+        }
+        observation
+    }
+
+    /// FHIR dateTime for a v2 TS/DTM, applying the configured offset when the value has none.
+    fn date_time(&mut self, value: &str) -> Option<Value> {
+        let parts = Timestamp::parse(value)?;
+        if parts.time.is_none() {
+            return Some(json!(parts.date));
+        }
+        let offset = parts.offset.or_else(|| self.options.timezone.clone());
+        match offset {
+            Some(offset) => Some(json!(format!(
+                "{}T{}{}",
+                parts.date,
+                parts.time.unwrap_or_default(),
+                offset
+            ))),
+            None => {
+                self.note(
+                    "Timestamps without a UTC offset were truncated to their date (use --timezone)"
+                        .to_string(),
+                );
+                Some(json!(parts.date))
+            }
+        }
+    }
+
+    /// FHIR instant; dropped unless the value has a time and a known offset.
+    fn instant(&mut self, value: &str) -> Option<Value> {
+        self.date_time(value)
+            .filter(|value| value.as_str().is_some_and(|text| text.contains('T')))
+    }
+}
+
+struct PendingReport {
+    resource: Map<String, Value>,
+    results: Vec<Value>,
+    line: usize,
+}
+
+struct Timestamp {
+    /// `YYYY`, `YYYY-MM` or `YYYY-MM-DD`.
+    date: String,
+    /// `HH:MM:SS[.S]`.
+    time: Option<String>,
+    offset: Option<String>,
+}
+
+impl Timestamp {
+    fn parse(value: &str) -> Option<Timestamp> {
+        let (main, offset) = match value.find(['+', '-']) {
+            Some(index) => (&value[..index], Some(&value[index..])),
+            None => (value, None),
+        };
+        let (digits, fraction) = match main.split_once('.') {
+            Some((digits, fraction)) => (digits, Some(fraction)),
+            None => (main, None),
+        };
+        if digits.len() < 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let date = match digits.len() {
+            4 | 5 => digits[..4].to_string(),
+            6 | 7 => format!("{}-{}", &digits[..4], &digits[4..6]),
+            _ => format!("{}-{}-{}", &digits[..4], &digits[4..6], &digits[6..8]),
+        };
+        let time = (digits.len() >= 12).then(|| {
+            let seconds = digits.get(12..14).unwrap_or("00");
+            let mut time = format!("{}:{}:{}", &digits[8..10], &digits[10..12], seconds);
+            if let Some(fraction) = fraction.filter(|_| digits.len() >= 14) {
+                time.push('.');
+                time.push_str(fraction);
+            }
+            time
+        });
+        Some(Timestamp {
+            date,
+            time,
+            offset: offset.and_then(utc_offset),
+        })
+    }
+}
+
+/// Normalizes `+0100`, `-05:00` or `Z` to a FHIR offset (`+01:00`, `-05:00`, `Z`).
+fn utc_offset(value: &str) -> Option<String> {
+    if value == "Z" {
+        return Some(value.to_string());
+    }
+    let (sign, rest) = value.split_at_checked(1)?;
+    if sign != "+" && sign != "-" {
+        return None;
+    }
+    let digits: String = rest.chars().filter(|c| *c != ':').collect();
+    if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some(format!("{sign}{}:{}", &digits[..2], &digits[2..]))
+}
+
+fn date(value: &str) -> Option<Value> {
+    Timestamp::parse(value).map(|parts| json!(parts.date))
+}
+
+fn resource(resource_type: &str) -> Map<String, Value> {
+    let mut resource = Map::new();
+    resource.insert("resourceType".to_string(), json!(resource_type));
+    resource
+}
+
+fn entry(url: &str, resource: Map<String, Value>) -> Value {
+    let resource_type = resource.get("resourceType").cloned().unwrap_or(Value::Null);
+    let mut request = json!({"method": "POST", "url": resource_type});
+    if resource_type == "Patient"
+        && let Some(search) = identifier_search(&resource)
+    {
+        request["ifNoneExist"] = json!(search);
+    }
+    json!({
+        "fullUrl": url,
+        "resource": resource,
+        "request": request,
+    })
+}
+
+/// Conditional-create search so re-running a conversion does not duplicate the patient.
+fn identifier_search(patient: &Map<String, Value>) -> Option<String> {
+    let identifier = patient.get("identifier")?.get(0)?;
+    let value = identifier.get("value")?.as_str()?;
+    Some(match identifier.get("system").and_then(Value::as_str) {
+        Some(system) => format!("identifier={system}|{value}"),
+        None => format!("identifier={value}"),
+    })
+}
+
+/// Deterministic `urn:uuid` so repeated conversions of a message produce the same Bundle.
+fn full_url(seed: &str) -> String {
+    let high = fnv1a(seed.as_bytes(), 0xcbf2_9ce4_8422_2325);
+    let low = fnv1a(seed.as_bytes(), high);
+    let hex = format!("{high:016x}{low:016x}");
+    format!(
+        "urn:uuid:{}-{}-4{}-a{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[13..16],
+        &hex[17..20],
+        &hex[20..32]
+    )
+}
+
+fn fnv1a(bytes: &[u8], basis: u64) -> u64 {
+    bytes.iter().fold(basis, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn set(object: &mut Map<String, Value>, key: &str, value: Option<Value>) {
+    if let Some(value) = value {
+        object.insert(key.to_string(), value);
+    }
+}
+
+fn set_array(object: &mut Map<String, Value>, key: &str, values: Vec<Value>) {
+    if !values.is_empty() {
+        object.insert(key.to_string(), Value::Array(values));
+    }
+}
+
+fn repetitions(segment: &Segment, field: usize) -> impl Iterator<Item = &Repetition> {
+    segment
+        .field(field)
+        .into_iter()
+        .flat_map(|field| field.repetitions.iter())
+        .filter(|repetition| !repetition.is_empty())
+}
+
+fn first(segment: &Segment, field: usize) -> Option<&Repetition> {
+    repetitions(segment, field).next()
+}
+
+fn reference(url: &Option<String>) -> Option<Value> {
+    url.as_ref().map(|url| json!({ "reference": url }))
+}
+
+fn category(code: &str, display: &str, system: &str) -> Value {
+    json!([{"coding": [{"system": system, "code": code, "display": display}]}])
+}
+
+fn joined(repetition: &Repetition) -> String {
+    repetition
+        .components
+        .iter()
+        .map(|component| component.join(" "))
+        .filter(|text| !text.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Maps v2 coding system names (CWE.3/CWE.6) to FHIR system URIs.
+fn coding_system(name: &str) -> Option<String> {
+    let system = match name {
+        "LN" => "http://loinc.org",
+        "SCT" | "SNM" | "SNOMED" => "http://snomed.info/sct",
+        "UCUM" => UCUM_SYSTEM,
+        "I10" | "I10C" => "http://hl7.org/fhir/sid/icd-10-cm",
+        "I9" | "I9C" => "http://hl7.org/fhir/sid/icd-9-cm",
+        "RXN" | "RXNORM" => "http://www.nlm.nih.gov/research/umls/rxnorm",
+        "CVX" => "http://hl7.org/fhir/sid/cvx",
+        "NDC" => "http://hl7.org/fhir/sid/ndc",
+        "CPT" | "C4" => "http://www.ama-assn.org/go/cpt",
+        _ => {
+            let table = name.strip_prefix("HL7")?;
+            return Some(format!("{V2_TABLE_PREFIX}{table}"));
+        }
+    };
+    Some(system.to_string())
+}
+
+/// CWE/CE/CNE → CodeableConcept with the primary and alternate codings.
+///
+/// `table` supplies the system for HL7-table-bound fields that omit CWE.3.
+fn codeable_concept(cwe: &Repetition, table: Option<&str>) -> Option<Value> {
+    let mut codings = Vec::new();
+    for offset in [0, 3] {
+        let Some(code) = cwe.component(offset + 1) else {
+            continue;
+        };
+        let mut coding = Map::new();
+        let system = cwe
+            .component(offset + 3)
+            .and_then(coding_system)
+            .or_else(|| {
+                table
+                    .filter(|_| offset == 0)
+                    .map(|table| format!("{V2_TABLE_PREFIX}{table}"))
+            });
+        set(&mut coding, "system", system.map(Value::from));
+        coding.insert("code".to_string(), json!(code));
+        set(
+            &mut coding,
+            "display",
+            cwe.component(offset + 2).map(Value::from),
+        );
+        codings.push(Value::Object(coding));
+    }
+    let text = cwe.component(9).or(cwe.component(2));
+    if codings.is_empty() && text.is_none() {
+        return None;
+    }
+    let mut concept = Map::new();
+    set_array(&mut concept, "coding", codings);
+    set(&mut concept, "text", text.map(Value::from));
+    Some(Value::Object(concept))
+}
+
+/// CX → Identifier; CX.4 (assigning authority) becomes the system when it is an OID or URI.
+fn identifier(cx: &Repetition) -> Option<Value> {
+    let value = cx.component(1)?;
+    let mut identifier = Map::new();
+    let universal = cx.subcomponent(4, 2);
+    let system = match (universal, cx.subcomponent(4, 3)) {
+        (Some(oid), Some("ISO")) => Some(format!("urn:oid:{oid}")),
+        (Some(uri), Some("URI")) => Some(uri.to_string()),
+        _ => None,
+    };
+    set(&mut identifier, "system", system.map(Value::from));
+    identifier.insert("value".to_string(), json!(value));
+    if let Some(code) = cx.component(5) {
+        identifier.insert(
+            "type".to_string(),
+            json!({"coding": [{"system": format!("{V2_TABLE_PREFIX}0203"), "code": code}]}),
+        );
+    }
+    if let Some(authority) = cx.subcomponent(4, 1) {
+        identifier.insert("assigner".to_string(), json!({"display": authority}));
+    }
+    Some(Value::Object(identifier))
+}
+
+/// XPN → HumanName.
+fn human_name(xpn: &Repetition) -> Option<Value> {
+    let mut name = Map::new();
+    set(&mut name, "family", xpn.subcomponent(1, 1).map(Value::from));
+    let given: Vec<Value> = [2, 3]
+        .into_iter()
+        .filter_map(|component| xpn.component(component))
+        .flat_map(|names| names.split(' '))
+        .filter(|given| !given.is_empty())
+        .map(Value::from)
+        .collect();
+    set_array(&mut name, "given", given);
+    set_array(
+        &mut name,
+        "suffix",
+        xpn.component(4).map(Value::from).into_iter().collect(),
+    );
+    set_array(
+        &mut name,
+        "prefix",
+        xpn.component(5).map(Value::from).into_iter().collect(),
+    );
+    let usage = match xpn.component(7) {
+        Some("L") => Some("official"),
+        Some("D") => Some("usual"),
+        Some("M") => Some("maiden"),
+        Some("N") => Some("nickname"),
+        Some("A") => Some("anonymous"),
+        _ => None,
+    };
+    set(&mut name, "use", usage.map(Value::from));
+    (!name.is_empty()).then_some(Value::Object(name))
+}
+
+/// XCN (person) as display text, e.g. `Dr John Smith`.
+fn person_name(xcn: &Repetition) -> Option<String> {
+    let parts: Vec<&str> = [6, 3, 4, 2, 5]
+        .into_iter()
+        .filter_map(|component| match component {
+            2 => xcn.subcomponent(2, 1),
+            _ => xcn.component(component),
+        })
+        .collect();
+    if parts.is_empty() {
+        return xcn.component(1).map(str::to_string);
+    }
+    Some(parts.join(" "))
+}
+
+/// XAD → Address.
+fn address(xad: &Repetition) -> Option<Value> {
+    let mut address = Map::new();
+    let lines: Vec<Value> = [xad.subcomponent(1, 1), xad.component(2)]
+        .into_iter()
+        .flatten()
+        .map(Value::from)
+        .collect();
+    set_array(&mut address, "line", lines);
+    set(&mut address, "city", xad.component(3).map(Value::from));
+    set(&mut address, "state", xad.component(4).map(Value::from));
+    set(
+        &mut address,
+        "postalCode",
+        xad.component(5).map(Value::from),
+    );
+    set(&mut address, "country", xad.component(6).map(Value::from));
+    let usage = match xad.component(7) {
+        Some("H" | "L") => Some("home"),
+        Some("B" | "O") => Some("work"),
+        Some("C") => Some("temp"),
+        Some("BA") => Some("old"),
+        _ => None,
+    };
+    set(&mut address, "use", usage.map(Value::from));
+    (!address.is_empty()).then_some(Value::Object(address))
+}
+
+/// XTN → ContactPoint; `default_use` applies when XTN.2 is empty.
+fn contact_point(xtn: &Repetition, default_use: &str) -> Option<Value> {
+    let equipment = xtn.component(3);
+    let email = xtn
+        .component(4)
+        .filter(|_| equipment == Some("Internet") || equipment == Some("X.400"));
+    let number = match (xtn.component(6), xtn.component(7)) {
+        (Some(area), Some(local)) => Some(format!("({area}) {local}")),
+        (None, Some(local)) => Some(local.to_string()),
+        _ => xtn.component(1).map(str::to_string),
+    };
+    let (system, value) = match (email, number) {
+        (Some(email), _) => ("email", email.to_string()),
+        (None, Some(number)) => {
+            let system = match equipment {
+                Some("FX") => "fax",
+                Some("BP") => "pager",
+                _ => "phone",
+            };
+            (system, number)
+        }
+        (None, None) => return None,
+    };
+    let usage = match (xtn.component(2), equipment) {
+        (_, Some("CP")) => "mobile",
+        (Some("WPN"), _) => "work",
+        (Some("PRN" | "ORN" | "VHN"), _) => "home",
+        _ => default_use,
+    };
+    Some(json!({"system": system, "value": value, "use": usage}))
+}
+
+fn location_display(pl: &Repetition) -> Option<String> {
+    let parts: Vec<&str> = (1..=4)
+        .filter_map(|component| pl.component(component))
+        .collect();
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
+
+/// Number plus OBX-6 units as a Quantity (UCUM when OBX-6.3 says so).
+fn quantity(value: &str, units: Option<&Repetition>) -> Option<Value> {
+    let number: f64 = value.trim().parse().ok()?;
+    let mut quantity = Map::new();
+    quantity.insert("value".to_string(), json!(number));
+    if let Some(units) = units {
+        let code = units.component(1);
+        set(
+            &mut quantity,
+            "unit",
+            units.component(2).or(code).map(Value::from),
+        );
+        if let Some(code) = code
+            && matches!(units.component(3), Some("UCUM" | "ANS+"))
+        {
+            quantity.insert("system".to_string(), json!(UCUM_SYSTEM));
+            quantity.insert("code".to_string(), json!(code));
+        }
+    }
+    Some(Value::Object(quantity))
+}
+
+/// SN (`<^5`, `>=^10`, `^7.2`) → Quantity with a comparator; ranges and ratios are not mapped.
+fn structured_numeric(sn: &Repetition, units: Option<&Repetition>) -> Option<Value> {
+    if sn.component(3).is_some() {
+        return None;
+    }
+    let mut quantity = quantity(sn.component(2)?, units)?;
+    if let Some(comparator) = sn.component(1) {
+        if !matches!(comparator, "<" | "<=" | ">" | ">=") {
+            return None;
+        }
+        quantity["comparator"] = json!(comparator);
+    }
+    Some(quantity)
+}
+
+/// OBX-7 (`70-99`, `<5`, `>10`) → referenceRange, falling back to text.
+fn reference_range(range: &str, units: Option<&Repetition>) -> Value {
+    let bound = |value: &str| quantity(value, units);
+    let parsed = if let Some(high) = range.strip_prefix('<') {
+        bound(high.trim_start_matches('=')).map(|high| json!({"high": high}))
+    } else if let Some(low) = range.strip_prefix('>') {
+        bound(low.trim_start_matches('=')).map(|low| json!({"low": low}))
+    } else {
+        range
+            .split_once('-')
+            .and_then(|(low, high)| Some(json!({"low": bound(low)?, "high": bound(high)?})))
+    };
+    parsed.unwrap_or_else(|| json!({ "text": range }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hl7v2::parser::parse_message;
+
+    const ORU: &str = "MSH|^~\\&|LAB|HOSP|EHR|HOSP|20260107093000-0500||ORU^R01^ORU_R01|LAB0001|P|2.5.1\r\
+PID|1||123^^^HOSP&2.16.840.1.113883.19.5&ISO^MR||Doe^Jane^Q||19850520|F|||1 Main St^^Boston^MA^02115^USA^H||^PRN^PH^^^617^5550101\r\
+PV1|1|O|CLINIC^2^B||||1234^Smith^John^^^Dr\r\
+OBR|1|ORD1|FIL1|24323-8^Metabolic panel^LN|||202601070900-0500||||||||||||||||||F\r\
+OBX|1|NM|2345-7^Glucose^LN||95|mg/dL^mg/dL^UCUM|70-99|N|||F\r\
+OBX|2|ST|8251-1^Comment^LN||hemolyzed||||||F\r";
+
+    fn resources(conversion: &Conversion) -> Vec<&Value> {
+        conversion.bundle["entry"]
+            .as_array()
+            .expect("entries")
+            .iter()
+            .map(|entry| &entry["resource"])
+            .collect()
+    }
+
+    #[test]
+    fn maps_oru_into_linked_transaction_bundle() {
+        let message = parse_message(ORU).expect("parse");
+        let conversion = convert_message(&message, &ConvertOptions::default());
+        let bundle = &conversion.bundle;
+        assert_eq!(bundle["type"], "transaction");
+        let types: Vec<&str> = resources(&conversion)
+            .iter()
+            .map(|resource| resource["resourceType"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            types,
+            [
+                "Patient",
+                "Encounter",
+                "Observation",
+                "Observation",
+                "DiagnosticReport"
+            ]
+        );
+
+        let entries = bundle["entry"].as_array().unwrap();
+        let patient_url = entries[0]["fullUrl"].as_str().unwrap();
+        assert!(patient_url.starts_with("urn:uuid:"));
+        assert_eq!(
+            entries[0]["request"]["ifNoneExist"],
+            "identifier=urn:oid:2.16.840.1.113883.19.5|123"
+        );
+        let report = &entries[4]["resource"];
+        assert_eq!(report["status"], "final");
+        assert_eq!(report["subject"]["reference"], patient_url);
+        assert_eq!(report["result"].as_array().unwrap().len(), 2);
+        assert_eq!(report["result"][0]["reference"], entries[2]["fullUrl"]);
+        assert_eq!(report["effectiveDateTime"], "2026-01-07T09:00:00-05:00");
+    }
+
+    #[test]
+    fn maps_patient_and_observation_fields() {
+        let message = parse_message(ORU).expect("parse");
+        let conversion = convert_message(&message, &ConvertOptions::default());
+        let all = resources(&conversion);
+        let patient = all[0];
+        assert_eq!(patient["name"][0]["family"], "Doe");
+        assert_eq!(patient["name"][0]["given"], json!(["Jane", "Q"]));
+        assert_eq!(patient["gender"], "female");
+        assert_eq!(patient["birthDate"], "1985-05-20");
+        assert_eq!(patient["address"][0]["use"], "home");
+        assert_eq!(patient["telecom"][0]["value"], "(617) 5550101");
+
+        let encounter = all[1];
+        assert_eq!(encounter["class"]["code"], "AMB");
+        assert_eq!(
+            encounter["participant"][0]["individual"]["display"],
+            "Dr John Smith"
+        );
+
+        let glucose = all[2];
+        assert_eq!(glucose["valueQuantity"]["value"], 95.0);
+        assert_eq!(glucose["valueQuantity"]["system"], UCUM_SYSTEM);
+        assert_eq!(glucose["code"]["coding"][0]["system"], "http://loinc.org");
+        assert_eq!(glucose["referenceRange"][0]["high"]["value"], 99.0);
+        assert_eq!(glucose["effectiveDateTime"], "2026-01-07T09:00:00-05:00");
+        assert_eq!(all[3]["valueString"], "hemolyzed");
+    }
+
+    #[test]
+    fn truncates_timestamps_without_offset_unless_timezone_given() {
+        let text = ORU.replace("202601070900-0500", "202601070900");
+        let message = parse_message(&text).expect("parse");
+
+        let truncated = convert_message(&message, &ConvertOptions::default());
+        assert_eq!(resources(&truncated)[4]["effectiveDateTime"], "2026-01-07");
+        assert_eq!(truncated.notes.len(), 1);
+
+        let options = ConvertOptions::with_timezone(Some("+0100")).expect("offset");
+        let zoned = convert_message(&message, &options);
+        assert_eq!(
+            resources(&zoned)[4]["effectiveDateTime"],
+            "2026-01-07T09:00:00+01:00"
+        );
+    }
+
+    #[test]
+    fn rejects_timezones_that_are_not_utc_offsets() {
+        for timezone in ["-05:00", "+0530"] {
+            assert!(ConvertOptions::with_timezone(Some(timezone)).is_ok());
+        }
+        for timezone in ["Europe/Berlin", "Z", "+1", "+01:0:0", "0100", "+01000"] {
+            assert!(
+                matches!(
+                    ConvertOptions::with_timezone(Some(timezone)),
+                    Err(ValidateError::Input(_))
+                ),
+                "{timezone}"
+            );
+        }
+    }
+
+    #[test]
+    fn conversion_is_deterministic() {
+        let message = parse_message(ORU).expect("parse");
+        let first = convert_message(&message, &ConvertOptions::default());
+        let second = convert_message(&message, &ConvertOptions::default());
+        assert_eq!(first.bundle, second.bundle);
+    }
+}
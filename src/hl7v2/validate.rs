@@ -0,0 +1,594 @@
+use std::collections::HashMap;
+
+use crate::hl7v2::definitions::{
+    DataType, Hl7Version, Node, Structure, Usage, segment_def, structure_for, table_values,
+};
+use crate::hl7v2::parser::{Message, Segment};
+use crate::validate::Issue;
+
+/// Result of checking one message against the bundled definitions.
+#[derive(Debug, Clone)]
+pub struct MessageValidation {
+    /// Version whose rules were applied (MSH-12, or the fallback).
+    pub version: Hl7Version,
+    pub structure: Option<&'static str>,
+    pub control_id: Option<String>,
+    pub issues: Vec<Issue>,
+}
+
+impl MessageValidation {
+    /// Short description for report headers, e.g. `HL7 v2.5.1 ADT_A01`.
+    pub fn describe(&self) -> String {
+        match self.structure {
+            Some(structure) => format!("HL7 v{} {}", self.version, structure),
+            None => format!("HL7 v{}", self.version),
+        }
+    }
+}
+
+pub fn validate_message(message: &Message) -> MessageValidation {
+    let mut issues = Vec::new();
+    let msh = message.segment("MSH");
+    let msh_line = msh.map(|segment| segment.line).unwrap_or(1);
+
+    let version = match message.version().map(str::parse::<Hl7Version>) {
+        Some(Ok(version)) if version.is_supported() => version,
+        Some(Ok(version)) => {
+            issues.push(issue(
+                "warning",
+                "not-supported",
+                format!(
+                    "HL7 v{version} has no bundled definitions; checked against v{} rules",
+                    Hl7Version::FALLBACK
+                ),
+                "MSH-12",
+                msh_line,
+            ));
+            Hl7Version::FALLBACK
+        }
+        // A missing or malformed MSH-12 is reported by the MSH field checks.
+        _ => Hl7Version::FALLBACK,
+    };
+
+    let structure = msh.and_then(|msh| {
+        let code = msh.value(9, 1)?;
+        let found = structure_for(code, msh.value(9, 2), msh.value(9, 3));
+        if found.is_none() {
+            issues.push(issue(
+                "warning",
+                "not-supported",
+                format!(
+                    "No message structure is defined for {}; only segment fields were checked",
+                    message.message_type().unwrap_or_default()
+                ),
+                "MSH-9",
+                msh.line,
+            ));
+        }
+        found
+    });
+
+    if let Some(structure) = structure {
+        check_structure(message, structure, &mut issues);
+    }
+
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    for segment in &message.segments {
+        let total = message.segments_named(&segment.id).count();
+        let occurrence = seen.entry(segment.id.as_str()).or_default();
+        *occurrence += 1;
+        let name = if total > 1 {
+            format!("{}[{}]", segment.id, occurrence)
+        } else {
+            segment.id.clone()
+        };
+        check_fields(segment, &name, version, &mut issues);
+        check_conditions(message, segment, &name, version, &mut issues);
+    }
+
+    MessageValidation {
+        version,
+        structure: structure.map(|structure| structure.id),
+        control_id: message.control_id().map(str::to_string),
+        issues,
+    }
+}
+
+fn issue(severity: &str, code: &str, message: String, expression: &str, line: usize) -> Issue {
+    Issue::local(severity, code, message, expression).with_line(line as u32)
+}
+
+fn check_fields(segment: &Segment, name: &str, version: Hl7Version, issues: &mut Vec<Issue>) {
+    let Some(definition) = segment_def(&segment.id) else {
+        return;
+    };
+    for field in definition.fields {
+        let expression = format!("{name}-{}", field.seq);
+        let value = segment.field(field.seq).filter(|value| !value.is_empty());
+        match (field.usage_in(version), value) {
+            (Usage::Required, None) => issues.push(issue(
+                "error",
+                "required",
+                format!(
+                    "{}-{} ({}) is required in v{version}",
+                    segment.id, field.seq, field.name
+                ),
+                &expression,
+                segment.line,
+            )),
+            (Usage::Withdrawn, Some(_)) => issues.push(issue(
+                "warning",
+                "not-supported",
+                format!(
+                    "{}-{} ({}) is withdrawn in v{version} and should not be sent",
+                    segment.id, field.seq, field.name
+                ),
+                &expression,
+                segment.line,
+            )),
+            (Usage::Backward, Some(_)) => issues.push(issue(
+                "information",
+                "informational",
+                format!(
+                    "{}-{} ({}) is retained for backward compatibility only in v{version}",
+                    segment.id, field.seq, field.name
+                ),
+                &expression,
+                segment.line,
+            )),
+            _ => {}
+        }
+        let Some(value) = value else {
+            continue;
+        };
+
+        let repetitions: Vec<_> = value
+            .repetitions
+            .iter()
+            .filter(|repetition| !repetition.is_empty())
+            .collect();
+        if !field.repeats && repetitions.len() > 1 {
+            issues.push(issue(
+                "error",
+                "structure",
+                format!(
+                    "{}-{} ({}) does not repeat but has {} repetitions",
+                    segment.id,
+                    field.seq,
+                    field.name,
+                    repetitions.len()
+                ),
+                &expression,
+                segment.line,
+            ));
+        }
+        for repetition in repetitions {
+            if let Some(value) = repetition.component(1)
+                && let Some((severity, code, problem)) = check_value(field.datatype, value)
+            {
+                issues.push(issue(
+                    severity,
+                    code,
+                    format!("{}-{} ({}): {problem}", segment.id, field.seq, field.name),
+                    &expression,
+                    segment.line,
+                ));
+            }
+        }
+    }
+}
+
+fn check_value(datatype: DataType, value: &str) -> Option<(&'static str, &'static str, String)> {
+    match datatype {
+        DataType::Text => None,
+        DataType::DateTime if !is_datetime(value) => Some((
+            "error",
+            "value",
+            format!(
+                "'{value}' is not a valid HL7 date/time (YYYY[MM[DD[HH[MM[SS[.S]]]]]][+/-ZZZZ])"
+            ),
+        )),
+        DataType::SequenceId if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) => {
+            Some(("error", "value", format!("'{value}' is not a sequence id")))
+        }
+        DataType::Coded { table, hl7_table } => {
+            let values = table_values(table)?;
+            if values.contains(&value) {
+                return None;
+            }
+            let severity = if hl7_table { "error" } else { "warning" };
+            Some((
+                severity,
+                "code-invalid",
+                format!("'{value}' is not in HL7 table {table}"),
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// Rules that depend on more than one field.
+fn check_conditions(
+    message: &Message,
+    segment: &Segment,
+    name: &str,
+    version: Hl7Version,
+    issues: &mut Vec<Issue>,
+) {
+    match segment.id.as_str() {
+        "MSH"
+            if version >= Hl7Version(2, 5, 0)
+                && segment.value(9, 1).is_some()
+                && segment.value(9, 3).is_none() =>
+        {
+            issues.push(issue(
+                "warning",
+                "required",
+                format!("MSH-9.3 (Message Structure) should be valued in v{version}"),
+                "MSH-9",
+                segment.line,
+            ));
+        }
+        "EVN" => {
+            let trigger = message.segment("MSH").and_then(|msh| msh.value(9, 2));
+            if let (Some(event), Some(trigger)) = (segment.value(1, 1), trigger)
+                && event != trigger
+            {
+                issues.push(issue(
+                    "warning",
+                    "value",
+                    format!("EVN-1 ({event}) does not match the MSH-9 trigger event ({trigger})"),
+                    &format!("{name}-1"),
+                    segment.line,
+                ));
+            }
+        }
+        "OBX" => {
+            let value_type = segment.value(2, 1);
+            let has_value = segment.field(5).is_some_and(|field| !field.is_empty());
+            if has_value && value_type.is_none() {
+                issues.push(issue(
+                    "error",
+                    "required",
+                    "OBX-2 (Value Type) is required when OBX-5 (Observation Value) is valued"
+                        .to_string(),
+                    &format!("{name}-2"),
+                    segment.line,
+                ));
+            }
+            if value_type == Some("NM")
+                && let Some(value) = segment.value(5, 1)
+                && !is_numeric(value)
+            {
+                issues.push(issue(
+                    "error",
+                    "value",
+                    format!("OBX-5 ('{value}') is not a number but OBX-2 is NM"),
+                    &format!("{name}-5"),
+                    segment.line,
+                ));
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_numeric(value: &str) -> bool {
+    let digits = value.strip_prefix(['+', '-']).unwrap_or(value);
+    !digits.is_empty()
+        && digits.bytes().any(|b| b.is_ascii_digit())
+        && digits.bytes().all(|b| b.is_ascii_digit() || b == b'.')
+        && digits.bytes().filter(|b| *b == b'.').count() <= 1
+}
+
+fn is_datetime(value: &str) -> bool {
+    let (main, offset) = match value.find(['+', '-']) {
+        Some(index) => (&value[..index], Some(&value[index + 1..])),
+        None => (value, None),
+    };
+    if let Some(offset) = offset
+        && (offset.len() != 4 || !offset.bytes().all(|b| b.is_ascii_digit()))
+    {
+        return false;
+    }
+    let (digits, fraction) = match main.split_once('.') {
+        Some((digits, fraction)) => (digits, Some(fraction)),
+        None => (main, None),
+    };
+    if let Some(fraction) = fraction
+        && (digits.len() != 14
+            || fraction.is_empty()
+            || fraction.len() > 4
+            || !fraction.bytes().all(|b| b.is_ascii_digit()))
+    {
+        return false;
+    }
+    if !matches!(digits.len(), 4 | 6 | 8 | 10 | 12 | 14)
+        || !digits.bytes().all(|b| b.is_ascii_digit())
+    {
+        return false;
+    }
+    let part = |start: usize| {
+        digits
+            .get(start..start + 2)
+            .map(|text| text.parse::<u32>().unwrap_or(99))
+    };
+    let in_range = |start: usize, min: u32, max: u32| {
+        part(start).is_none_or(|value| (min..=max).contains(&value))
+    };
+    in_range(4, 1, 12)
+        && in_range(6, 1, 31)
+        && in_range(8, 0, 23)
+        && in_range(10, 0, 59)
+        && in_range(12, 0, 59)
+}
+
+fn check_structure(message: &Message, structure: &Structure, issues: &mut Vec<Issue>) {
+    // Z-segments are site-defined and may appear anywhere.
+    let segments: Vec<&Segment> = message
+        .segments
+        .iter()
+        .filter(|segment| !segment.id.starts_with('Z'))
+        .collect();
+    let mut matcher = Matcher {
+        segments: &segments,
+        position: 0,
+        structure: structure.id,
+        issues,
+    };
+    matcher.match_nodes(structure.nodes, None);
+
+    for segment in &segments[matcher.position..] {
+        let (severity, message) = if defines(structure.nodes, &segment.id) {
+            (
+                "error",
+                format!(
+                    "Segment {} is out of order or repeated where {} does not allow it",
+                    segment.id, structure.id
+                ),
+            )
+        } else {
+            (
+                "warning",
+                format!("Segment {} is not defined for {}", segment.id, structure.id),
+            )
+        };
+        matcher.issues.push(issue(
+            severity,
+            "structure",
+            message,
+            &segment.id,
+            segment.line,
+        ));
+    }
+}
+
+struct Matcher<'a> {
+    segments: &'a [&'a Segment],
+    position: usize,
+    structure: &'static str,
+    issues: &'a mut Vec<Issue>,
+}
+
+impl Matcher<'_> {
+    fn match_nodes(&mut self, nodes: &[Node], group: Option<&str>) {
+        for node in nodes {
+            let mut count = 0;
+            while let Some(next) = self.segments.get(self.position) {
+                let matched = match node {
+                    Node::Segment { id, .. } if next.id == *id => {
+                        self.position += 1;
+                        true
+                    }
+                    Node::Group { name, children, .. } if starts(children, &next.id) => {
+                        self.match_nodes(children, Some(name));
+                        true
+                    }
+                    _ => false,
+                };
+                if !matched {
+                    break;
+                }
+                count += 1;
+                if !node.repeats() {
+                    break;
+                }
+            }
+            if count == 0 && node.required() {
+                self.missing(node, group);
+            }
+        }
+    }
+
+    fn missing(&mut self, node: &Node, group: Option<&str>) {
+        let what = match node {
+            Node::Segment { id, .. } => format!("segment {id}"),
+            Node::Group { name, .. } => format!("group {name}"),
+        };
+        let within = match group {
+            Some(group) => format!("{} group {group}", self.structure),
+            None => self.structure.to_string(),
+        };
+        let (expression, line) = match self.segments.get(self.position) {
+            Some(next) => (next.id.clone(), next.line),
+            None => (
+                self.structure.to_string(),
+                self.segments
+                    .last()
+                    .map(|segment| segment.line)
+                    .unwrap_or(1),
+            ),
+        };
+        self.issues.push(issue(
+            "error",
+            "required",
+            format!("Required {what} is missing from {within}"),
+            &expression,
+            line,
+        ));
+    }
+}
+
+/// Whether a group can start with segment `id` (its leading optional children or first required one).
+fn starts(nodes: &[Node], id: &str) -> bool {
+    for node in nodes {
+        let found = match node {
+            Node::Segment { id: candidate, .. } => *candidate == id,
+            Node::Group { children, .. } => starts(children, id),
+        };
+        if found {
+            return true;
+        }
+        if node.required() {
+            return false;
+        }
+    }
+    false
+}
+
+fn defines(nodes: &[Node], id: &str) -> bool {
+    nodes.iter().any(|node| match node {
+        Node::Segment { id: candidate, .. } => *candidate == id,
+        Node::Group { children, .. } => defines(children, id),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hl7v2::parser::parse_message;
+
+    const ADT: &str = "MSH|^~\\&|EHR|HOSP|LIS|HOSP|20260107080112||ADT^A01^ADT_A01|MSG00001|P|2.5.1\r\
+EVN||20260107080112\r\
+PID|1||123^^^HOSP^MR||Doe^Jane||19850520|F\r\
+PV1|1|I|WARD1^101^A\r";
+
+    const ORU: &str = "MSH|^~\\&|LAB|HOSP|EHR|HOSP|20260107093000||ORU^R01^ORU_R01|LAB0001|P|2.5.1\r\
+PID|1||123^^^HOSP^MR||Doe^Jane\r\
+OBR|1|ORD1|FIL1|2345-7^Glucose^LN|||20260107090000\r\
+OBX|1|NM|2345-7^Glucose^LN||95|mg/dL|||||F\r\
+OBX|2|NM|2951-2^Sodium^LN||high|mmol/L|||||F\r";
+
+    fn check(text: &str) -> MessageValidation {
+        validate_message(&parse_message(text).expect("parse"))
+    }
+
+    fn messages(validation: &MessageValidation) -> Vec<String> {
+        validation
+            .issues
+            .iter()
+            .map(|issue| issue.diagnostics.clone().unwrap_or_default())
+            .collect()
+    }
+
+    #[test]
+    fn clean_adt_a01_has_no_issues() {
+        let validation = check(ADT);
+        assert_eq!(validation.structure, Some("ADT_A01"));
+        assert_eq!(validation.describe(), "HL7 v2.5.1 ADT_A01");
+        assert!(validation.issues.is_empty(), "{:?}", messages(&validation));
+    }
+
+    #[test]
+    fn reports_missing_required_fields_and_segments_with_lines() {
+        let text = ADT
+            .replace("PID|1||123^^^HOSP^MR||Doe^Jane", "PID|1||||")
+            .replace("PV1|1|I|WARD1^101^A\r", "");
+        let validation = check(&text);
+        let found = messages(&validation);
+        assert!(
+            found.contains(&"PID-3 (Patient Identifier List) is required in v2.5.1".to_string())
+        );
+        assert!(found.contains(&"Required segment PV1 is missing from ADT_A01".to_string()));
+        let pid = validation
+            .issues
+            .iter()
+            .find(|issue| issue.expression == ["PID-3"])
+            .expect("PID-3 issue");
+        assert_eq!(pid.line(), Some(3));
+        assert_eq!(pid.code.as_deref(), Some("required"));
+    }
+
+    #[test]
+    fn applies_version_specific_usage() {
+        // MSH-7 only became required in v2.5; PID-2 is withdrawn from v2.7.
+        let old = check(
+            &ADT.replace("20260107080112||ADT^A01^ADT_A01", "||ADT^A01")
+                .replace("2.5.1", "2.3")
+                .replace("EVN||", "EVN|A01|"),
+        );
+        assert!(old.issues.is_empty(), "{:?}", messages(&old));
+
+        let new = check(&ADT.replace("20260107080112||ADT", "||ADT"));
+        assert!(
+            messages(&new)
+                .contains(&"MSH-7 (Date/Time of Message) is required in v2.5.1".to_string())
+        );
+
+        let withdrawn = check(
+            &ADT.replace("2.5.1", "2.7")
+                .replace("PID|1||", "PID|1|OLD1|"),
+        );
+        assert!(
+            withdrawn
+                .issues
+                .iter()
+                .any(|issue| issue.expression == ["PID-2"]
+                    && issue.severity.as_deref() == Some("warning"))
+        );
+    }
+
+    #[test]
+    fn checks_tables_datatypes_and_obx_conditions() {
+        let validation = check(ORU);
+        assert_eq!(validation.structure, Some("ORU_R01"));
+        assert_eq!(
+            messages(&validation),
+            vec!["OBX-5 ('high') is not a number but OBX-2 is NM"]
+        );
+        assert_eq!(validation.issues[0].expression, vec!["OBX[2]-5"]);
+
+        let bad = check(
+            &ORU.replace("||||F\rOBX|2", "||||Q\rOBX|2")
+                .replace("|20260107090000", "|2026-01-07"),
+        );
+        let found = messages(&bad);
+        assert!(
+            found
+                .iter()
+                .any(|m| m.contains("'Q' is not in HL7 table 0085"))
+        );
+        assert!(
+            found
+                .iter()
+                .any(|m| m.contains("'2026-01-07' is not a valid HL7 date/time"))
+        );
+    }
+
+    #[test]
+    fn flags_out_of_order_and_unknown_segments_but_ignores_z_segments() {
+        let text = "MSH|^~\\&|||||20260107||ORM^O01|1|P|2.3\r\
+ORC|NW|ORD1\r\
+ZXT|custom\r\
+OBR|1|ORD1||CBC^Blood count\r\
+PID|1||123||Doe^Jane\r\
+IAM|1\r";
+        let validation = check(text);
+        let found = messages(&validation);
+        assert_eq!(validation.structure, Some("ORM_O01"));
+        assert!(found.contains(
+            &"Segment PID is out of order or repeated where ORM_O01 does not allow it".to_string()
+        ));
+        assert!(found.contains(&"Segment IAM is not defined for ORM_O01".to_string()));
+        assert!(!found.iter().any(|m| m.contains("ZXT")));
+    }
+
+    #[test]
+    fn validates_datetimes() {
+        assert!(is_datetime("2026"));
+        assert!(is_datetime("20260107080112.1234-0500"));
+        assert!(!is_datetime("20261307"));
+        assert!(!is_datetime("202601070801121"));
+        assert!(!is_datetime("20260107+05"));
+    }
+}
@@ -0,0 +1,43 @@
+//! Building blocks for the self-contained HTML pages ClinLogix writes
+//! (incident reports, validation reports), styled like the project site.
+
+/// The project site's stylesheet, so pages match the docs.
+const SITE_CSS: &str = include_str!("../../docs/assets/styles.css");
+/// Tables, stats, issue groups and source listings, on top of the site's palette.
+const REPORT_CSS: &str = include_str!("report.css");
+
+/// A complete page around `body`, with the stylesheets inlined.
+pub fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!doctype html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\" />\n<meta name=\"viewport\" content=\"width=device-width,initial-scale=1\" />\n<title>{}</title>\n<style>\n{SITE_CSS}\n{REPORT_CSS}</style>\n</head>\n<body>\n<main class=\"container report\">\n{body}</main>\n</body>\n</html>\n",
+        escape(title)
+    )
+}
+
+/// A coloured label for `error`, `warning` and other levels; `-` for none.
+pub fn severity_pill(severity: Option<&str>) -> String {
+    match severity {
+        Some(severity) => format!(
+            "<span class=\"pill {}\">{}</span>",
+            escape(severity),
+            escape(severity)
+        ),
+        None => "-".to_string(),
+    }
+}
+
+/// Text safe to place in HTML content and attribute values.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
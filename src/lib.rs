@@ -2,7 +2,15 @@
 //!
 //! This exposes programmatic entry points without changing CLI behavior.
 
+pub mod cda;
+mod civil;
+pub mod config;
+pub mod deidentify;
+pub mod error;
+pub mod hl7v2;
+mod html;
 pub mod library;
+pub mod report;
 pub mod scan;
 pub mod validate;
 
@@ -5,11 +5,23 @@
 
 use std::io;
 
+pub use crate::cda::{
+    DocumentValidation as CdaValidation, validate_document as validate_cda_document,
+    validate_text as validate_cda_text,
+};
+pub use crate::hl7v2::{
+    Conversion, ConvertOptions, Hl7Version, Message as Hl7Message, MessageValidation,
+    convert_messages, parse_message as parse_hl7v2_message, parse_messages as parse_hl7v2_messages,
+    validate_message as validate_hl7v2_message, validate_text as validate_hl7v2_text,
+};
 pub use crate::scan::run_scan;
 pub use crate::validate::{
-    CodeableConcept, FhirResource, Issue, IssueSummary, OperationOutcome, PROFILE_RESOLUTION_THEME,
-    ValidateRequest, ValidateResponse, ValidationReport, build_report, format_report, is_failure,
-    load_request, parse_operation_outcome, post_validate, print_report, run_validate,
+    Baseline, BaselineEntry, CodeableConcept, Coding, Extension, FailOn, FailurePolicy,
+    FhirResource, FhirVersion, Issue, IssueSummary, Meta, OperationOutcome,
+    PROFILE_RESOLUTION_THEME, ValidateOptions, ValidateRequest, ValidateResponse, ValidationReport,
+    apply_baseline, build_local_report, build_report, check_structure, detect_version,
+    format_html_report, format_report, is_failure, load_request, parse_operation_outcome,
+    post_validate, print_report, run_validate, run_validate_files, run_validate_tui,
 };
 
 pub fn scan_log(logfile: &str, errors_only: bool, json: bool) -> io::Result<()> {
@@ -20,5 +32,6 @@ pub async fn validate_file(
     fhir_file: &str,
     base_url: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    crate::validate::run_validate(fhir_file, base_url).await
+    crate::validate::run_validate(fhir_file, base_url, &ValidateOptions::default()).await?;
+    Ok(())
 }
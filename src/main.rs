@@ -1,7 +1,8 @@
-use clap::{Parser, Subcommand};
+use std::process::ExitCode;
 
-mod scan;
-mod validate;
+use clap::{Args, Parser, Subcommand};
+
+use clinlogix::{cda, config, deidentify, error, hl7v2, report, scan, validate};
 
 /// ClinLogix: Health IT utility CLI (log scan + FHIR validation)
 #[derive(Parser, Debug)]
@@ -14,8 +15,9 @@ struct Cli {
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Scan a log file and summarize errors/warnings (your original feature)
+    #[command(group(clap::ArgGroup::new("payloads").args(["extract", "correlate"])))]
     Scan {
-        /// Path to the log file
+        /// Log file (plain, gzip or zstd), directory, or quoted glob such as 'logs/app.log*'
         logfile: String,
 
         /// Print only error lines
@@ -25,34 +27,753 @@ enum Commands {
         /// Output summary as JSON
         #[arg(long)]
         json: bool,
+
+        /// Plain-text output even when clinlogix.toml sets output.format = "json"
+        #[arg(long, conflicts_with = "json")]
+        text: bool,
+
+        /// Write each HL7 v2 message and FHIR JSON resource found in the log to this directory
+        #[arg(long, value_name = "DIR", conflicts_with = "errors_only")]
+        extract: Option<String>,
+
+        /// Validate extracted payloads or correlated resources (HL7 v2 locally, FHIR against --base-url)
+        #[arg(long, requires = "payloads")]
+        validate: bool,
+
+        /// FHIR base URL for validating extracted or correlated FHIR resources (default: from clinlogix.toml, else https://server.fire.ly)
+        #[arg(long, requires = "validate")]
+        base_url: Option<String>,
+
+        /// Server from clinlogix.toml ([server.NAME]) to validate against
+        #[arg(
+            long,
+            value_name = "NAME",
+            requires = "validate",
+            conflicts_with = "base_url"
+        )]
+        server: Option<String>,
+
+        /// Report lines that contain PHI (SSNs, identifiers, dates, emails, phones, MRNs, names) without printing the values
+        #[arg(long, conflicts_with_all = ["errors_only", "extract"])]
+        phi: bool,
+
+        /// Extra regex recognizing MRNs; a capture group limits the finding to the group (repeatable)
+        #[arg(long = "mrn-pattern", value_name = "REGEX", requires = "phi")]
+        mrn_patterns: Vec<String>,
+
+        /// Only lines stamped at or after this time (YYYY-MM-DD[ HH:MM[:SS]] or HH:MM on the log's first date)
+        #[arg(long, conflicts_with = "extract")]
+        since: Option<scan::TimeBound>,
+
+        /// Only lines stamped before this time (same forms as --since)
+        #[arg(long, conflicts_with = "extract")]
+        until: Option<scan::TimeBound>,
+
+        /// Count errors and warnings per minute or hour
+        #[arg(long, value_name = "BUCKET", conflicts_with_all = ["errors_only", "extract"])]
+        histogram: Option<scan::Bucket>,
+
+        /// Number of most frequent error and warning signatures to report (0 to leave them out)
+        #[arg(long, default_value_t = 5)]
+        top: usize,
+
+        /// Log format: auto, plain, json (JSON lines), syslog (RFC 5424) or log4j
+        #[arg(long, default_value = "auto", conflicts_with = "extract")]
+        format: scan::LogFormat,
+
+        /// Grok-style line pattern tried before --format, e.g. '%{TIMESTAMP_ISO8601:timestamp} %{LOGLEVEL:level} %{GREEDYDATA:message}' (repeatable)
+        #[arg(long = "pattern", value_name = "GROK", conflicts_with = "extract")]
+        patterns: Vec<String>,
+
+        /// Regex matching the first line of a record; other lines are joined to the record before them (default: continuation lines such as stack frames are recognized by their shape)
+        #[arg(long, value_name = "REGEX", conflicts_with = "extract")]
+        record_start: Option<String>,
+
+        /// TOML file of category rules (name, severity, patterns) layered over the built-in categories (default: scan.rules from clinlogix.toml)
+        #[arg(long, value_name = "FILE", conflicts_with = "extract")]
+        rules: Option<String>,
+
+        /// Report the FHIR resources (Type/id) named in error and warning records, with where they were found and, with --validate, how they validate
+        #[arg(long, conflicts_with_all = ["errors_only", "phi", "follow"])]
+        correlate: bool,
+
+        /// Directory of FHIR JSON resources or FHIR base URL to look correlated resources up in (default: only copies logged with the error)
+        #[arg(long, value_name = "DIR|URL", requires = "correlate")]
+        resources: Option<scan::ResourceSource>,
+
+        /// Keep reading lines as they are written (through truncation and rotation) and report errors as they come
+        #[arg(long, conflicts_with_all = ["extract", "errors_only", "phi", "since", "until", "histogram"])]
+        follow: bool,
+
+        /// Sliding window for --follow, in seconds
+        #[arg(
+            long,
+            value_name = "SECONDS",
+            default_value_t = 60,
+            value_parser = clap::value_parser!(u64).range(1..),
+            requires = "follow"
+        )]
+        window: u64,
+
+        /// Alert when this many errors fall within the window (--follow)
+        #[arg(
+            long,
+            value_name = "ERRORS",
+            value_parser = clap::value_parser!(u64).range(1..),
+            requires = "follow"
+        )]
+        threshold: Option<u64>,
+
+        /// Alert action: print, exit, command:CMD (alert JSON in $CLINLOGIX_ALERT) or webhook:FILE (appends the payload) (repeatable; default print)
+        #[arg(long = "alert", value_name = "ACTION", requires = "threshold")]
+        alerts: Vec<scan::AlertAction>,
+    },
+
+    /// Rewrite a log with PHI replaced by placeholders or consistent pseudonyms
+    Redact {
+        /// Path to the log file
+        logfile: String,
+
+        /// placeholder ([SSN], [MRN], ...) or pseudonym ([MRN-3f9a2c1b], same value -> same token)
+        #[arg(long, default_value = "placeholder")]
+        mode: scan::RedactMode,
+
+        /// Secret for pseudonyms; created with a random value if missing (default: $CLINLOGIX_REDACT_SECRET)
+        #[arg(long)]
+        secret_file: Option<String>,
+
+        /// Write the redacted log to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<String>,
+
+        /// Extra regex recognizing MRNs (repeatable, as for `scan --phi`)
+        #[arg(long = "mrn-pattern", value_name = "REGEX")]
+        mrn_patterns: Vec<String>,
+    },
+
+    /// Remove, generalize or pseudonymize PHI in a FHIR resource or Bundle so it can be shared
+    Deidentify {
+        /// Path to a FHIR JSON resource or Bundle
+        file: String,
+
+        /// TOML profile layered over the bundled Safe Harbor profile
+        #[arg(long)]
+        profile: Option<String>,
+
+        /// Secret for pseudonyms and date offsets; created if missing (default: $CLINLOGIX_REDACT_SECRET, else random per run)
+        #[arg(long)]
+        secret_file: Option<String>,
+
+        /// Write the de-identified JSON to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<String>,
+    },
+
+    /// Validate FHIR JSON resources using a FHIR server ($validate)
+    Validate {
+        /// FHIR JSON files (e.g., examples/patient.json)
+        #[arg(required = true)]
+        fhir_files: Vec<String>,
+
+        /// FHIR base URL (default: from clinlogix.toml, else https://server.fire.ly)
+        #[arg(long)]
+        base_url: Option<String>,
+
+        /// Server from clinlogix.toml ([server.NAME]) to validate against
+        #[arg(long, value_name = "NAME", conflicts_with = "base_url")]
+        server: Option<String>,
+
+        /// FHIR version of the resource (R4, R4B, R5 or a release like 4.0.1); detected from the resource when omitted
+        #[arg(long)]
+        fhir_version: Option<validate::FhirVersion>,
+
+        /// TOML file with theme rules that extend or override the built-in taxonomy
+        #[arg(long)]
+        themes: Option<String>,
+
+        /// Baseline file of accepted issues; matching issues are reported but do not fail the run
+        #[arg(long, conflicts_with = "write_baseline")]
+        baseline: Option<String>,
+
+        /// Write all issues from this run to a baseline file
+        #[arg(long)]
+        write_baseline: Option<String>,
+
+        /// Also write the reports as one self-contained HTML page
+        #[arg(long, value_name = "FILE")]
+        html: Option<String>,
+
+        /// Browse the issues in an interactive terminal view instead of printing the report
+        #[arg(long, conflicts_with = "html")]
+        tui: bool,
+
+        #[command(flatten)]
+        policy: PolicyArgs,
+    },
+
+    /// Write an incident report (HTML or Markdown) combining a log scan with FHIR validation results
+    Report {
+        /// Log file (plain, gzip or zstd), directory, or quoted glob
+        logfile: String,
+
+        /// FHIR JSON files to validate and include
+        fhir_files: Vec<String>,
+
+        /// html or markdown (default: markdown for a .md --output, html for .html, else output.report from clinlogix.toml or html)
+        #[arg(long)]
+        format: Option<report::ReportFormat>,
+
+        /// Write the report to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<String>,
+
+        /// Report title
+        #[arg(long, default_value = "Incident report")]
+        title: String,
+
+        /// FHIR base URL the files are validated against (default: from clinlogix.toml, else https://server.fire.ly)
+        #[arg(long)]
+        base_url: Option<String>,
+
+        /// Server from clinlogix.toml ([server.NAME]) to validate against
+        #[arg(long, value_name = "NAME", conflicts_with = "base_url")]
+        server: Option<String>,
+
+        /// Number of most frequent error and warning signatures to list
+        #[arg(long, default_value_t = 5)]
+        top: usize,
+
+        /// Error timeline bucket: minute or hour
+        #[arg(long, default_value = "minute")]
+        bucket: scan::Bucket,
+
+        /// TOML file of category rules layered over the built-in categories (as for `scan --rules`; default: scan.rules from clinlogix.toml)
+        #[arg(long, value_name = "FILE")]
+        rules: Option<String>,
+    },
+
+    /// Convert between message formats
+    Convert {
+        #[command(subcommand)]
+        command: ConvertCommand,
+    },
+
+    /// Parse and validate HL7 v2 (pipe-delimited ER7) messages
+    Hl7v2 {
+        #[command(subcommand)]
+        command: Hl7v2Command,
     },
 
-    /// Validate a FHIR JSON resource using a FHIR server ($validate)
+    /// Inspect and validate C-CDA documents (CCD, Discharge Summary, Referral Note)
+    Cda {
+        #[command(subcommand)]
+        command: CdaCommand,
+    },
+
+    /// Exchange HL7 v2 messages over MLLP (TCP) for interface testing
+    Mllp {
+        #[command(subcommand)]
+        command: MllpCommand,
+    },
+
+    /// Inspect the settings read from clinlogix.toml
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ConvertCommand {
+    /// Map PID/PV1/OBR/OBX segments to a FHIR R4 transaction Bundle
+    #[command(name = "hl7v2-to-fhir")]
+    Hl7v2ToFhir {
+        /// Path to a file with one or more HL7 v2 messages
+        file: String,
+
+        /// Write the Bundle to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<String>,
+
+        /// UTC offset (e.g. -0500) for timestamps that carry none; otherwise they are truncated to dates
+        #[arg(long, allow_hyphen_values = true)]
+        timezone: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum Hl7v2Command {
+    /// Validate messages against the bundled segment and message definitions
     Validate {
-        /// Path to a FHIR JSON file (e.g., examples/patient.json)
-        fhir_file: String,
+        /// Path to a file with one or more HL7 v2 messages
+        file: String,
 
-        /// FHIR base URL (defaults to public HAPI R4 server)
-        #[arg(long, default_value = "https://server.fire.ly")]
-        base_url: String,
+        #[command(flatten)]
+        policy: PolicyArgs,
+    },
+
+    /// Print the segments and fields of each message
+    Parse {
+        /// Path to a file with one or more HL7 v2 messages
+        file: String,
     },
 }
 
+#[derive(Subcommand, Debug)]
+enum CdaCommand {
+    /// Check the header, required sections and entry templates against the bundled C-CDA R2.1 rules
+    Validate {
+        /// Path to a C-CDA XML document
+        file: String,
+
+        #[command(flatten)]
+        policy: PolicyArgs,
+    },
+
+    /// Print the document template and its sections with entry counts
+    Inspect {
+        /// Path to a C-CDA XML document
+        file: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum MllpCommand {
+    /// Accept messages, validate them and reply with ACK (AA) or NAK (AE/AR)
+    Listen {
+        /// Port to listen on (0 picks a free port)
+        #[arg(long, default_value_t = 2575)]
+        port: u16,
+
+        /// Address to bind
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+
+        /// Also append the event log to this file (readable by `scan`)
+        #[arg(long)]
+        log: Option<String>,
+
+        /// Exit after acknowledging this many messages
+        #[arg(long)]
+        max_messages: Option<usize>,
+    },
+
+    /// Send the messages in each file and wait for their acknowledgments
+    Send {
+        /// Files with one or more HL7 v2 messages
+        #[arg(required = true)]
+        files: Vec<String>,
+
+        /// Port of the receiving system
+        #[arg(long, default_value_t = 2575)]
+        port: u16,
+
+        /// Host of the receiving system
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+
+        /// Seconds to wait for each ACK
+        #[arg(long, default_value_t = 10.0)]
+        timeout: f64,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Print the effective settings and the files they came from
+    Show,
+}
+
+/// Failure policy flags for `validate`, `hl7v2 validate` and `cda validate`.
+///
+/// Exit codes: 0 pass, 1 validation failed, 2 bad input, 3 server unreachable or
+/// answered without an OperationOutcome.
+#[derive(Args, Debug)]
+struct PolicyArgs {
+    /// Lowest severity that fails the run (error, warning, information; default: error)
+    #[arg(long)]
+    fail_on: Option<validate::FailOn>,
+
+    /// Number of errors tolerated before the run fails (default: 0)
+    #[arg(long)]
+    max_errors: Option<usize>,
+
+    /// Number of warnings tolerated before the run fails (with --fail-on warning; default: 0)
+    #[arg(long)]
+    max_warnings: Option<usize>,
+
+    /// Theme whose issues never fail the run, e.g. "profile resolution" (repeatable)
+    #[arg(long = "non-fatal-theme")]
+    non_fatal_themes: Vec<String>,
+
+    /// Only issues in this theme can fail the run (repeatable)
+    #[arg(long = "fail-theme")]
+    fail_themes: Vec<String>,
+
+    /// Only issues with this OperationOutcome code can fail the run (repeatable)
+    #[arg(long = "fail-code")]
+    fail_codes: Vec<String>,
+
+    /// Judge non-2xx responses that carry an OperationOutcome by their issues only
+    #[arg(long)]
+    ignore_http_status: bool,
+
+    /// Fail on non-2xx responses even when clinlogix.toml sets ignore_http_status
+    #[arg(long, conflicts_with = "ignore_http_status")]
+    no_ignore_http_status: bool,
+}
+
+impl PolicyArgs {
+    /// The flags given on the command line over the policy from clinlogix.toml.
+    fn over(self, config: &validate::FailurePolicy) -> validate::FailurePolicy {
+        let or_config = |flags: Vec<String>, configured: &Vec<String>| {
+            if flags.is_empty() {
+                configured.clone()
+            } else {
+                flags
+            }
+        };
+        validate::FailurePolicy {
+            fail_on: self.fail_on.unwrap_or(config.fail_on),
+            max_errors: self.max_errors.unwrap_or(config.max_errors),
+            max_warnings: self.max_warnings.unwrap_or(config.max_warnings),
+            non_fatal_themes: or_config(self.non_fatal_themes, &config.non_fatal_themes),
+            fail_themes: or_config(self.fail_themes, &config.fail_themes),
+            fail_codes: or_config(self.fail_codes, &config.fail_codes),
+            ignore_http_status: !self.no_ignore_http_status
+                && (self.ignore_http_status || config.ignore_http_status),
+        }
+    }
+}
+
+/// `--rules FILE`, else the rules named in clinlogix.toml, else the built-in categories.
+fn category_rules(
+    rules: Option<String>,
+    config: &config::Config,
+) -> Result<scan::CategoryRules, error::Error> {
+    match rules.or_else(|| config.scan_rules.clone()) {
+        Some(path) => scan::CategoryRules::load(&path).map_err(error::Error::Input),
+        None => Ok(scan::CategoryRules::default()),
+    }
+}
+
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() -> ExitCode {
     let cli = Cli::parse();
 
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            ExitCode::from(error::exit_code(err.as_ref()))
+        }
+    }
+}
+
+/// Reads clinlogix.toml; only commands that use its settings call this, so a
+/// broken file does not get in the way of the others.
+fn load_config() -> Result<config::Config, error::Error> {
+    config::Config::load().map_err(error::Error::Input)
+}
+
+/// `--base-url`, else the `--server` named, else the configured default server.
+fn base_url(
+    config: &config::Config,
+    base_url: Option<String>,
+    server: Option<String>,
+) -> Result<String, error::Error> {
+    config
+        .base_url(base_url, server.as_deref())
+        .map_err(error::Error::Input)
+}
+
+async fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
     match cli.command {
         Commands::Scan {
             logfile,
             errors_only,
             json,
-        } => scan::run_scan(&logfile, errors_only, json)?,
+            text,
+            extract,
+            validate,
+            base_url: base_url_flag,
+            server,
+            phi,
+            mrn_patterns,
+            since,
+            until,
+            histogram,
+            top,
+            format,
+            patterns,
+            record_start,
+            rules,
+            correlate,
+            resources,
+            follow,
+            window,
+            threshold,
+            alerts,
+        } => {
+            let config = load_config()?;
+            let json = json || (!text && config.output == config::OutputFormat::Json);
+            // Only --validate talks to a server, so a broken server setting does
+            // not stop a plain scan unless --server names one.
+            let named = server.is_some();
+            let base_url = match base_url(&config, base_url_flag, server) {
+                Ok(base_url) => base_url,
+                Err(err) if validate || named => return Err(err.into()),
+                Err(_) => config::DEFAULT_BASE_URL.to_string(),
+            };
+            match extract {
+                Some(dir) => {
+                    let options = scan::ExtractOptions {
+                        dir,
+                        validate,
+                        base_url,
+                        json,
+                    };
+                    scan::run_extract(&logfile, &options).await?
+                }
+                None => {
+                    let phi = if phi {
+                        Some(
+                            scan::PhiDetector::with_mrn_patterns(&mrn_patterns)
+                                .map_err(error::Error::Input)?,
+                        )
+                    } else {
+                        None
+                    };
+                    let patterns = patterns
+                        .iter()
+                        .map(|pattern| scan::GrokPattern::compile(pattern))
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(error::Error::Input)?;
+                    let record_start = record_start
+                        .map(|pattern| {
+                            regex::Regex::new(&pattern).map_err(|err| {
+                                error::Error::Input(format!(
+                                    "invalid --record-start '{pattern}': {err}"
+                                ))
+                            })
+                        })
+                        .transpose()?;
+                    let rules = category_rules(rules, &config)?;
+                    if follow {
+                        let options = scan::FollowOptions {
+                            json,
+                            format,
+                            patterns,
+                            record_start,
+                            rules,
+                            window: std::time::Duration::from_secs(window),
+                            threshold,
+                            alerts: if alerts.is_empty() {
+                                vec![scan::AlertAction::Print]
+                            } else {
+                                alerts
+                            },
+                            ..Default::default()
+                        };
+                        return Ok(scan::run_follow(&logfile, &options)?);
+                    }
+                    let options = scan::ScanOptions {
+                        errors_only,
+                        json,
+                        phi,
+                        since,
+                        until,
+                        histogram,
+                        top,
+                        format,
+                        patterns,
+                        record_start,
+                        rules,
+                        correlate,
+                    };
+                    if correlate {
+                        let correlate = scan::CorrelateOptions {
+                            resources,
+                            validate,
+                            base_url,
+                        };
+                        return Ok(scan::run_correlate(&logfile, &options, &correlate).await?);
+                    }
+                    scan::run_scan_with(&logfile, &options)
+                        .map_err(|err| error::Error::Input(format!("{logfile}: {err}")))?
+                }
+            }
+        }
+
+        Commands::Redact {
+            logfile,
+            mode,
+            secret_file,
+            output,
+            mrn_patterns,
+        } => {
+            let options = scan::RedactOptions {
+                mode,
+                secret_file,
+                output,
+                detector: scan::PhiDetector::with_mrn_patterns(&mrn_patterns)
+                    .map_err(error::Error::Input)?,
+            };
+            scan::run_redact(&logfile, &options)?
+        }
+
+        Commands::Deidentify {
+            file,
+            profile,
+            secret_file,
+            output,
+        } => {
+            let options = deidentify::DeidentifyOptions {
+                profile,
+                secret_file,
+                output,
+            };
+            deidentify::run_deidentify(&file, &options)?
+        }
 
         Commands::Validate {
-            fhir_file,
-            base_url,
-        } => validate::run_validate(&fhir_file, &base_url).await?,
+            fhir_files,
+            base_url: base_url_flag,
+            server,
+            fhir_version,
+            themes,
+            baseline,
+            write_baseline,
+            html,
+            tui,
+            policy,
+        } => {
+            let config = load_config()?;
+            let options = validate::ValidateOptions {
+                fhir_version,
+                themes,
+                baseline,
+                write_baseline,
+                policy: policy.over(&config.policy),
+            };
+            let base_url = base_url(&config, base_url_flag, server)?;
+            if tui {
+                let [fhir_file] = fhir_files.as_slice() else {
+                    return Err(error::Error::Input("--tui takes a single file".to_string()).into());
+                };
+                validate::run_validate_tui(fhir_file, &base_url, &options).await?
+            } else {
+                validate::run_validate_files(&fhir_files, &base_url, &options, html.as_deref())
+                    .await?
+            }
+        }
+
+        Commands::Report {
+            logfile,
+            fhir_files,
+            format,
+            output,
+            title,
+            base_url: base_url_flag,
+            server,
+            top,
+            bucket,
+            rules,
+        } => {
+            let config = load_config()?;
+            // The server is only needed for FHIR files to validate.
+            let named = server.is_some();
+            let base_url = match base_url(&config, base_url_flag, server) {
+                Ok(base_url) => base_url,
+                Err(err) if !fhir_files.is_empty() || named => return Err(err.into()),
+                Err(_) => config::DEFAULT_BASE_URL.to_string(),
+            };
+            let options = report::ReportOptions {
+                format,
+                default_format: config.report_format.unwrap_or(report::ReportFormat::Html),
+                output,
+                title,
+                base_url,
+                top,
+                bucket,
+                rules: category_rules(rules, &config)?,
+                policy: config.policy.clone(),
+            };
+            report::run_report(&logfile, &fhir_files, &options).await?
+        }
+
+        Commands::Convert { command } => match command {
+            ConvertCommand::Hl7v2ToFhir {
+                file,
+                output,
+                timezone,
+            } => {
+                let options = hl7v2::ConvertOptions::with_timezone(timezone.as_deref())?;
+                hl7v2::run_convert_file(&file, output.as_deref(), &options)?
+            }
+        },
+
+        Commands::Hl7v2 { command } => match command {
+            Hl7v2Command::Validate { file, policy } => {
+                hl7v2::run_validate_file(&file, &policy.over(&load_config()?.policy))?
+            }
+            Hl7v2Command::Parse { file } => hl7v2::run_parse_file(&file)?,
+        },
+
+        Commands::Cda { command } => match command {
+            CdaCommand::Validate { file, policy } => {
+                cda::run_validate_file(&file, &policy.over(&load_config()?.policy))?
+            }
+            CdaCommand::Inspect { file } => cda::run_inspect_file(&file)?,
+        },
+
+        Commands::Mllp { command } => match command {
+            MllpCommand::Listen {
+                port,
+                host,
+                log,
+                max_messages,
+            } => {
+                let options = hl7v2::ListenOptions {
+                    host,
+                    port,
+                    log,
+                    max_messages,
+                };
+                hl7v2::run_listen(&options)?
+            }
+            MllpCommand::Send {
+                files,
+                port,
+                host,
+                timeout,
+            } => {
+                if !timeout.is_finite() || timeout <= 0.0 {
+                    return Err(error::Error::Input(
+                        "--timeout must be a positive number of seconds".to_string(),
+                    )
+                    .into());
+                }
+                let options = hl7v2::SendOptions {
+                    host,
+                    port,
+                    timeout: std::time::Duration::from_secs_f64(timeout),
+                };
+                hl7v2::run_send(&files, &options)?
+            }
+        },
+
+        Commands::Config { command } => match command {
+            ConfigCommand::Show => {
+                // Shows what could be read even when a file is broken, then
+                // fails so scripts notice.
+                let config = config::Config::discover();
+                print!("{}", config.show());
+                if let Some(problem) = config.problems.first() {
+                    return Err(error::Error::Input(problem.clone()).into());
+                }
+            }
+        },
     }
 
     Ok(())
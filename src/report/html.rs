@@ -0,0 +1,209 @@
+//! Self-contained HTML incident reports, styled like the project site.
+
+use serde_json::Value;
+
+use crate::html::{escape, page, severity_pill};
+use crate::report::{IncidentReport, display_bucket, display_seen};
+
+pub fn render(report: &IncidentReport) -> String {
+    let scan = &report.scan;
+    let mut body = String::new();
+    body.push_str(&format!(
+        "<header class=\"report-header\">\n<p class=\"eyebrow\">ClinLogix incident report</p>\n<h1>{}</h1>\n<p>Generated {} UTC by ClinLogix {} from <code class=\"inline-code\">{}</code></p>\n</header>\n",
+        escape(&report.title),
+        report.generated,
+        env!("CARGO_PKG_VERSION"),
+        escape(scan["file"].as_str().unwrap_or_default())
+    ));
+    body.push_str(&summary_section(scan));
+    body.push_str(&timeline_section(report));
+    body.push_str(&signatures_section(scan));
+    body.push_str(&validation_section(report));
+    page(&report.title, &body)
+}
+
+fn summary_section(scan: &Value) -> String {
+    let mut html = String::from(
+        "<section class=\"section-card\" id=\"summary\">\n<h2>Log summary</h2>\n<div class=\"stats\">\n",
+    );
+    for (label, key) in [
+        ("Lines", "total_lines"),
+        ("Records", "records"),
+        ("Errors", "errors"),
+        ("Warnings", "warnings"),
+    ] {
+        html.push_str(&format!(
+            "<div class=\"stat\"><strong>{}</strong><span>{label}</span></div>\n",
+            scan[key]
+        ));
+    }
+    html.push_str("</div>\n");
+    let categories = scan["categories"].as_array().map_or(&[][..], Vec::as_slice);
+    if !categories.is_empty() {
+        html.push_str("<table>\n<thead><tr><th>Category</th><th class=\"number\">Records</th><th>Severity</th></tr></thead>\n<tbody>\n");
+        for category in categories {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td class=\"number\">{}</td><td>{}</td></tr>\n",
+                escape(category["name"].as_str().unwrap_or_default()),
+                category["count"],
+                severity_pill(category["severity"].as_str())
+            ));
+        }
+        html.push_str("</tbody>\n</table>\n");
+    }
+    html.push_str("</section>\n");
+    html
+}
+
+fn timeline_section(report: &IncidentReport) -> String {
+    let histogram = &report.scan["histogram"];
+    let bucket_width = histogram["bucket"].as_str().unwrap_or("minute");
+    let mut html = format!(
+        "<section class=\"section-card\" id=\"timeline\">\n<h2>Error timeline</h2>\n<p>Errors and warnings per {bucket_width}.</p>\n"
+    );
+    let buckets = histogram["buckets"]
+        .as_array()
+        .map_or(&[][..], Vec::as_slice);
+    if buckets.is_empty() {
+        html.push_str("<p>No timestamped errors or warnings.</p>\n");
+    } else {
+        let count = |bucket: &Value, key: &str| bucket[key].as_u64().unwrap_or_default();
+        let peak = buckets
+            .iter()
+            .map(|bucket| count(bucket, "errors") + count(bucket, "warnings"))
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        html.push_str("<div class=\"timeline\">\n");
+        for bucket in buckets {
+            let (errors, warnings) = (count(bucket, "errors"), count(bucket, "warnings"));
+            let width = |n: u64| n as f64 * 100.0 / peak as f64;
+            html.push_str(&format!(
+                "<span>{}</span><span title=\"errors\">{errors}</span><span title=\"warnings\">{warnings}</span><span class=\"bar\"><span class=\"errors\" style=\"width:{:.1}%\"></span><span class=\"warnings\" style=\"width:{:.1}%\"></span></span>\n",
+                display_bucket(&bucket["start"], bucket_width),
+                width(errors),
+                width(warnings)
+            ));
+        }
+        html.push_str("</div>\n");
+    }
+    let events = report.first_occurrences();
+    if !events.is_empty() {
+        html.push_str("<h3>First occurrences</h3>\n<table>\n<thead><tr><th>First seen</th><th>Level</th><th>Signature</th><th class=\"number\">Count</th></tr></thead>\n<tbody>\n");
+        for (level, signature) in events {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td class=\"number\">{}</td></tr>\n",
+                escape(&display_seen(&signature["first_seen"])),
+                severity_pill(Some(level)),
+                escape(signature["signature"].as_str().unwrap_or_default()),
+                signature["count"]
+            ));
+        }
+        html.push_str("</tbody>\n</table>\n");
+    }
+    html.push_str("</section>\n");
+    html
+}
+
+fn signatures_section(scan: &Value) -> String {
+    let mut html = String::from(
+        "<section class=\"section-card\" id=\"signatures\">\n<h2>Top signatures</h2>\n",
+    );
+    for (heading, key) in [("Errors", "errors"), ("Warnings", "warnings")] {
+        let signatures = &scan["signatures"][key];
+        let top = signatures["top"].as_array().map_or(&[][..], Vec::as_slice);
+        html.push_str(&format!(
+            "<h3>{heading} ({} of {} signatures)</h3>\n",
+            top.len(),
+            signatures["distinct"].as_u64().unwrap_or_default()
+        ));
+        if top.is_empty() {
+            html.push_str("<p>None.</p>\n");
+            continue;
+        }
+        html.push_str("<table>\n<thead><tr><th class=\"number\">Count</th><th>Signature</th><th>First seen</th><th>Last seen</th><th>Example</th></tr></thead>\n<tbody>\n");
+        for signature in top {
+            html.push_str(&format!(
+                "<tr><td class=\"number\">{}</td><td>{}</td><td>{}</td><td>{}</td><td><code>{}</code></td></tr>\n",
+                signature["count"],
+                escape(signature["signature"].as_str().unwrap_or_default()),
+                escape(&display_seen(&signature["first_seen"])),
+                escape(&display_seen(&signature["last_seen"])),
+                escape(signature["example"].as_str().unwrap_or_default())
+            ));
+        }
+        html.push_str("</tbody>\n</table>\n");
+    }
+    html.push_str("</section>\n");
+    html
+}
+
+fn validation_section(report: &IncidentReport) -> String {
+    let mut html = String::from(
+        "<section class=\"section-card\" id=\"validation\">\n<h2>Validation outcomes</h2>\n",
+    );
+    if report.validations.is_empty() {
+        html.push_str("<p>No FHIR files were validated.</p>\n</section>\n");
+        return html;
+    }
+    html.push_str("<table>\n<thead><tr><th>File</th><th>Result</th><th class=\"number\">Errors</th><th class=\"number\">Warnings</th><th class=\"number\">Info</th></tr></thead>\n<tbody>\n");
+    for validation in &report.validations {
+        let counts = match &validation.outcome {
+            Ok(report) => format!(
+                "<td class=\"number\">{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td>",
+                report.error_count, report.warning_count, report.info_count
+            ),
+            Err(_) => "<td class=\"number\">-</td>".repeat(3),
+        };
+        html.push_str(&format!(
+            "<tr><td>{}</td><td><span class=\"pill {status}\">{status}</span></td>{counts}</tr>\n",
+            escape(&validation.file),
+            status = validation.status()
+        ));
+    }
+    html.push_str("</tbody>\n</table>\n");
+    for validation in &report.validations {
+        html.push_str(&format!("<h3>{}</h3>\n", escape(&validation.file)));
+        match &validation.outcome {
+            Err(message) => html.push_str(&format!("<p>{}</p>\n", escape(&message.to_string()))),
+            Ok(report) if report.groups.is_empty() => html.push_str("<p>No issues reported.</p>\n"),
+            Ok(report) => {
+                html.push_str("<ul>\n");
+                for (key, count) in validation.top_groups() {
+                    html.push_str(&format!("<li>{} (x{count})</li>\n", escape(&key)));
+                }
+                html.push_str("</ul>\n");
+                for (theme, hint) in &report.theme_hints {
+                    for text in [&hint.hint, &hint.recommendation].into_iter().flatten() {
+                        html.push_str(&format!(
+                            "<p class=\"callout\"><strong>{}:</strong> {}</p>\n",
+                            escape(theme),
+                            escape(text)
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    html.push_str("</section>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::tests::sample;
+
+    #[test]
+    fn renders_a_self_contained_escaped_page() {
+        let html = render(&sample());
+        assert!(html.starts_with("<!doctype html>"));
+        assert!(html.contains("<title>Lab interface outage &lt;ORU&gt;</title>"));
+        assert!(html.contains("--accent: #0f766e;"));
+        assert!(!html.contains("<link"));
+        assert!(html.contains("Retry &lt;n&gt; of &lt;n&gt;"));
+        assert!(html.contains("<span>2026-01-07 08:05</span>"));
+        assert!(html.contains("<span class=\"pill error\">error</span>"));
+        assert!(html.contains("connection refused"));
+    }
+}
@@ -0,0 +1,215 @@
+//! Markdown incident reports, for wikis and change tickets.
+
+use serde_json::Value;
+
+use crate::report::{IncidentReport, display_bucket, display_seen};
+
+/// Widest timeline bar, in characters.
+const BAR_WIDTH: u64 = 30;
+
+pub fn render(report: &IncidentReport) -> String {
+    let scan = &report.scan;
+    let mut output = format!("# {}\n\n", inline(&report.title));
+    output.push_str(&format!(
+        "Generated {} UTC by ClinLogix {} from `{}`.\n\n",
+        report.generated,
+        env!("CARGO_PKG_VERSION"),
+        scan["file"].as_str().unwrap_or_default()
+    ));
+
+    output.push_str("## Log summary\n\n");
+    output.push_str("| Lines | Records | Errors | Warnings | Format |\n");
+    output.push_str("| ---: | ---: | ---: | ---: | --- |\n");
+    output.push_str(&format!(
+        "| {} | {} | {} | {} | {} |\n\n",
+        scan["total_lines"],
+        scan["records"],
+        scan["errors"],
+        scan["warnings"],
+        scan["format"].as_str().unwrap_or_default()
+    ));
+    let categories = scan["categories"].as_array().map_or(&[][..], Vec::as_slice);
+    if !categories.is_empty() {
+        output.push_str("| Category | Records | Severity |\n");
+        output.push_str("| --- | ---: | --- |\n");
+        for category in categories {
+            output.push_str(&format!(
+                "| {} | {} | {} |\n",
+                inline(category["name"].as_str().unwrap_or_default()),
+                category["count"],
+                category["severity"].as_str().unwrap_or("-")
+            ));
+        }
+        output.push('\n');
+    }
+
+    output.push_str("## Error timeline\n\n");
+    let buckets = scan["histogram"]["buckets"]
+        .as_array()
+        .map_or(&[][..], Vec::as_slice);
+    if buckets.is_empty() {
+        output.push_str("No timestamped errors or warnings.\n\n");
+    } else {
+        let bucket_width = scan["histogram"]["bucket"].as_str().unwrap_or("minute");
+        output.push_str(&format!(
+            "Errors and warnings per {bucket_width} (`#` error, `~` warning):\n\n"
+        ));
+        output.push_str("```text\n");
+        let count = |bucket: &Value, key: &str| bucket[key].as_u64().unwrap_or_default();
+        let peak = buckets
+            .iter()
+            .map(|bucket| count(bucket, "errors") + count(bucket, "warnings"))
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let scale = |n: u64| {
+            if peak <= BAR_WIDTH {
+                n
+            } else {
+                (n * BAR_WIDTH).div_ceil(peak)
+            }
+        };
+        for bucket in buckets {
+            let (errors, warnings) = (count(bucket, "errors"), count(bucket, "warnings"));
+            let line = format!(
+                "{}  {errors:>3} {warnings:>3}  {}{}",
+                display_bucket(&bucket["start"], bucket_width),
+                "#".repeat(scale(errors) as usize),
+                "~".repeat(scale(warnings) as usize)
+            );
+            output.push_str(line.trim_end());
+            output.push('\n');
+        }
+        output.push_str("```\n\n");
+    }
+    let events = report.first_occurrences();
+    if !events.is_empty() {
+        output.push_str("First occurrences:\n\n");
+        for (level, signature) in events {
+            output.push_str(&format!(
+                "- {} — {level}: {} (x{})\n",
+                display_seen(&signature["first_seen"]),
+                inline(signature["signature"].as_str().unwrap_or_default()),
+                signature["count"]
+            ));
+        }
+        output.push('\n');
+    }
+
+    output.push_str("## Top signatures\n\n");
+    for (heading, key) in [("Errors", "errors"), ("Warnings", "warnings")] {
+        let signatures = &scan["signatures"][key];
+        let top = signatures["top"].as_array().map_or(&[][..], Vec::as_slice);
+        output.push_str(&format!(
+            "### {heading} ({} of {} signatures)\n\n",
+            top.len(),
+            signatures["distinct"].as_u64().unwrap_or_default()
+        ));
+        if top.is_empty() {
+            output.push_str("None.\n\n");
+            continue;
+        }
+        output.push_str("| Count | Signature | First seen | Last seen | Example |\n");
+        output.push_str("| ---: | --- | --- | --- | --- |\n");
+        for signature in top {
+            output.push_str(&format!(
+                "| {} | {} | {} | {} | {} |\n",
+                signature["count"],
+                inline(signature["signature"].as_str().unwrap_or_default()),
+                display_seen(&signature["first_seen"]),
+                display_seen(&signature["last_seen"]),
+                code_cell(signature["example"].as_str().unwrap_or_default())
+            ));
+        }
+        output.push('\n');
+    }
+
+    output.push_str("## Validation outcomes\n\n");
+    if report.validations.is_empty() {
+        output.push_str("No FHIR files were validated.\n");
+        return output;
+    }
+    output.push_str("| File | Result | Errors | Warnings | Info |\n");
+    output.push_str("| --- | --- | ---: | ---: | ---: |\n");
+    for validation in &report.validations {
+        let counts = match &validation.outcome {
+            Ok(report) => format!(
+                "{} | {} | {}",
+                report.error_count, report.warning_count, report.info_count
+            ),
+            Err(_) => "- | - | -".to_string(),
+        };
+        output.push_str(&format!(
+            "| {} | {} | {counts} |\n",
+            inline(&validation.file),
+            validation.status().to_uppercase()
+        ));
+    }
+    for validation in &report.validations {
+        output.push_str(&format!("\n### {}\n\n", inline(&validation.file)));
+        match &validation.outcome {
+            Err(message) => output.push_str(&format!("{}\n", inline(&message.to_string()))),
+            Ok(report) if report.groups.is_empty() => output.push_str("No issues reported.\n"),
+            Ok(report) => {
+                for (key, count) in validation.top_groups() {
+                    output.push_str(&format!("- {} (x{count})\n", inline(&key)));
+                }
+                for (theme, hint) in &report.theme_hints {
+                    let text = [&hint.hint, &hint.recommendation]
+                        .into_iter()
+                        .flatten()
+                        .map(String::as_str)
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    if !text.is_empty() {
+                        output.push_str(&format!("\n> **{}:** {}\n", inline(theme), inline(&text)));
+                    }
+                }
+            }
+        }
+    }
+    output
+}
+
+/// Text with Markdown's inline markup characters escaped.
+fn inline(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped.replace('\n', " ")
+}
+
+/// A table cell shown as code; pipes still need escaping inside backticks.
+fn code_cell(text: &str) -> String {
+    let text = text.replace('|', "\\|").replace('\n', " ");
+    let fence = if text.contains('`') { "`` " } else { "`" };
+    format!("{fence}{text}{}", fence.chars().rev().collect::<String>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::tests::sample;
+
+    #[test]
+    fn renders_sections_with_escaped_cells() {
+        let markdown = render(&sample());
+        assert!(markdown.starts_with("# Lab interface outage \\<ORU\\>\n"));
+        assert!(markdown.contains("| 7 | 7 | 2 | 1 | plain |"));
+        assert!(markdown.contains("2026-01-07 08:05    1   0  #\n"));
+        assert!(
+            markdown.contains(
+                "- 2026-01-07 08:03:22 (line 3) — warning: Retry \\<n\\> of \\<n\\> (x1)"
+            )
+        );
+        assert!(markdown.contains("| `ERROR Database connection failed` |"));
+        assert!(markdown.contains("| patient.json | ERROR | - | - | - |"));
+    }
+}
@@ -0,0 +1,375 @@
+//! `clinlogix report`: one incident report (HTML or Markdown) combining a log
+//! scan with FHIR validation results.
+
+mod html;
+mod markdown;
+
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use serde_json::Value;
+
+use crate::civil::unix_seconds;
+use crate::scan::{self, Bucket, CategoryRules, ScanOptions, Timestamp};
+use crate::validate::{
+    FailurePolicy, ValidateError, ValidateOptions, ValidationReport, is_failure, sorted_groups,
+    validate_report,
+};
+
+/// Issue groups listed per validated file.
+const TOP_ISSUE_GROUPS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Html,
+    Markdown,
+}
+
+impl ReportFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReportFormat::Html => "html",
+            ReportFormat::Markdown => "markdown",
+        }
+    }
+
+    /// The format an output file name asks for: Markdown for `.md`, HTML for
+    /// `.html`, none for other names.
+    pub fn for_path(path: &str) -> Option<Self> {
+        let lower = path.to_ascii_lowercase();
+        if lower.ends_with(".md") || lower.ends_with(".markdown") {
+            Some(ReportFormat::Markdown)
+        } else if lower.ends_with(".html") || lower.ends_with(".htm") {
+            Some(ReportFormat::Html)
+        } else {
+            None
+        }
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "html" => Ok(ReportFormat::Html),
+            "markdown" | "md" => Ok(ReportFormat::Markdown),
+            other => Err(format!(
+                "unknown report format '{other}' (expected html or markdown)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Settings for `clinlogix report`.
+#[derive(Debug, Clone)]
+pub struct ReportOptions {
+    /// Output format; taken from `output`, then `default_format`, when not given.
+    pub format: Option<ReportFormat>,
+    /// Format used when neither `format` nor the name of `output` decides.
+    pub default_format: ReportFormat,
+    /// Write the report here instead of stdout.
+    pub output: Option<String>,
+    pub title: String,
+    /// FHIR server the resources are validated against.
+    pub base_url: String,
+    /// Error and warning signatures listed.
+    pub top: usize,
+    /// Width of the error timeline buckets.
+    pub bucket: Bucket,
+    /// Categories log records are sorted into.
+    pub rules: CategoryRules,
+    /// Decides which validated files count as failed.
+    pub policy: FailurePolicy,
+}
+
+impl Default for ReportOptions {
+    fn default() -> Self {
+        Self {
+            format: None,
+            default_format: ReportFormat::Html,
+            output: None,
+            title: "Incident report".to_string(),
+            base_url: "https://server.fire.ly".to_string(),
+            top: 5,
+            bucket: Bucket::Minute,
+            rules: CategoryRules::default(),
+            policy: FailurePolicy::default(),
+        }
+    }
+}
+
+impl ReportOptions {
+    pub fn effective_format(&self) -> ReportFormat {
+        self.format
+            .or_else(|| self.output.as_deref().and_then(ReportFormat::for_path))
+            .unwrap_or(self.default_format)
+    }
+}
+
+/// How one FHIR file validated.
+pub struct FileValidation {
+    pub file: String,
+    /// The report, or why there is none (unreadable file, server unreachable).
+    pub outcome: Result<ValidationReport, ValidateError>,
+}
+
+impl FileValidation {
+    pub fn status(&self) -> &'static str {
+        match &self.outcome {
+            Ok(report) if is_failure(report) => "fail",
+            Ok(_) => "pass",
+            Err(_) => "error",
+        }
+    }
+
+    /// The most frequent issue groups, as `(key, count)`.
+    pub fn top_groups(&self) -> Vec<(String, usize)> {
+        match &self.outcome {
+            Ok(report) => sorted_groups(&report.groups)
+                .into_iter()
+                .take(TOP_ISSUE_GROUPS)
+                .map(|(key, items)| (key, items.len()))
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+/// Everything an incident report shows.
+pub struct IncidentReport {
+    pub title: String,
+    /// When the report was generated.
+    pub generated: Timestamp,
+    /// The `scan --json` summary of the log.
+    pub scan: Value,
+    pub validations: Vec<FileValidation>,
+}
+
+impl IncidentReport {
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Html => html::render(self),
+            ReportFormat::Markdown => markdown::render(self),
+        }
+    }
+
+    pub fn failed_validations(&self) -> usize {
+        self.validations
+            .iter()
+            .filter(|validation| validation.status() != "pass")
+            .count()
+    }
+
+    /// Error and warning signatures by when they were first seen, as
+    /// `(level, signature)` pairs: the order the incident unfolded in.
+    pub fn first_occurrences(&self) -> Vec<(&'static str, &Value)> {
+        let mut events: Vec<(&'static str, &Value)> =
+            [("error", "errors"), ("warning", "warnings")]
+                .into_iter()
+                .flat_map(|(level, key)| {
+                    self.scan["signatures"][key]["top"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .map(move |signature| (level, signature))
+                })
+                .collect();
+        events.sort_by_key(|(_, signature)| {
+            (
+                signature["first_seen"]["time"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                signature["first_seen"]["file"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                signature["first_seen"]["line"].as_u64().unwrap_or_default(),
+            )
+        });
+        events
+    }
+}
+
+/// `2026-01-07 08:05:40` for a JSON timestamp, `-` when there is none.
+fn display_time(value: &Value) -> String {
+    value
+        .as_str()
+        .map_or("-".to_string(), |time| time.replacen('T', " ", 1))
+}
+
+/// `2026-01-07 08:05` for a minute bucket, `2026-01-07 08:00` for an hour.
+fn display_bucket(start: &Value, bucket: &str) -> String {
+    let time = display_time(start);
+    match (bucket, time.get(..13), time.get(..16)) {
+        ("hour", Some(hour), _) => format!("{hour}:00"),
+        (_, _, Some(minute)) => minute.to_string(),
+        _ => time,
+    }
+}
+
+/// `line 4`, `app.log.1 line 4` or `08:05:40 (line 4)` for a JSON `Seen`.
+fn display_seen(seen: &Value) -> String {
+    let location = match seen["file"].as_str() {
+        Some(file) => format!("{file} line {}", seen["line"]),
+        None => format!("line {}", seen["line"]),
+    };
+    match seen["time"].as_str() {
+        Some(_) => format!("{} ({location})", display_time(&seen["time"])),
+        None => location,
+    }
+}
+
+/// Scans the log, validates `fhir_files` and writes the report.
+///
+/// The report is written even when files fail validation; the command then
+/// exits like `validate` would.
+pub async fn run_report(
+    logfile: &str,
+    fhir_files: &[String],
+    options: &ReportOptions,
+) -> Result<(), ValidateError> {
+    let scan_options = ScanOptions {
+        histogram: Some(options.bucket),
+        top: options.top,
+        rules: options.rules.clone(),
+        ..Default::default()
+    };
+    let scan = scan::scan_summary(logfile, &scan_options)
+        .map_err(|err| ValidateError::Input(format!("{logfile}: {err}")))?;
+
+    let validate_options = ValidateOptions {
+        policy: options.policy.clone(),
+        ..Default::default()
+    };
+    let mut validations = Vec::with_capacity(fhir_files.len());
+    for file in fhir_files {
+        let outcome = validate_report(file, &options.base_url, &validate_options).await;
+        validations.push(FileValidation {
+            file: file.clone(),
+            outcome,
+        });
+    }
+
+    let report = IncidentReport {
+        title: options.title.clone(),
+        generated: Timestamp::from_seconds(unix_seconds(SystemTime::now())),
+        scan,
+        validations,
+    };
+    let format = options.effective_format();
+    let text = report.render(format);
+    match &options.output {
+        Some(output) => {
+            fs::write(output, text)
+                .map_err(|err| ValidateError::Input(format!("{output}: {err}")))?;
+            eprintln!(
+                "Wrote {} incident report to {output} ({} of {} files failed validation)",
+                format,
+                report.failed_validations(),
+                report.validations.len()
+            );
+        }
+        None => print!("{text}"),
+    }
+
+    // A file that could not be validated decides the exit code, as in `validate`.
+    let failed = report.failed_validations();
+    if let Some(err) = report
+        .validations
+        .into_iter()
+        .find_map(|validation| validation.outcome.err())
+    {
+        return Err(err);
+    }
+    if failed > 0 {
+        return Err(ValidateError::Failed("FHIR"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    pub(super) fn sample() -> IncidentReport {
+        IncidentReport {
+            title: "Lab interface outage <ORU>".to_string(),
+            generated: Timestamp::from_seconds(1_767_776_400),
+            scan: json!({
+                "file": "engine.log",
+                "format": "plain",
+                "total_lines": 7,
+                "records": 7,
+                "errors": 2,
+                "warnings": 1,
+                "categories": [
+                    {"name": "database", "severity": "error", "count": 1},
+                ],
+                "signatures": {
+                    "errors": {"distinct": 2, "top": [
+                        {"signature": "FHIR validation failed for Patient/<id>", "count": 1,
+                         "first_seen": {"line": 7, "time": "2026-01-07T08:07:12"},
+                         "last_seen": {"line": 7, "time": "2026-01-07T08:07:12"},
+                         "example": "ERROR FHIR validation failed for Patient/123"},
+                        {"signature": "Database connection failed", "count": 1,
+                         "first_seen": {"line": 4, "time": "2026-01-07T08:05:40"},
+                         "last_seen": {"line": 4, "time": "2026-01-07T08:05:40"},
+                         "example": "ERROR Database connection failed"},
+                    ]},
+                    "warnings": {"distinct": 1, "top": [
+                        {"signature": "Retry <n> of <n>", "count": 1,
+                         "first_seen": {"line": 3, "time": "2026-01-07T08:03:22"},
+                         "last_seen": {"line": 3, "time": "2026-01-07T08:03:22"},
+                         "example": "WARNING Retry 1 of 3"},
+                    ]},
+                },
+                "histogram": {"bucket": "minute", "buckets": [
+                    {"start": "2026-01-07T08:03:00", "errors": 0, "warnings": 1},
+                    {"start": "2026-01-07T08:04:00", "errors": 0, "warnings": 0},
+                    {"start": "2026-01-07T08:05:00", "errors": 1, "warnings": 0},
+                ]},
+            }),
+            validations: vec![FileValidation {
+                file: "patient.json".to_string(),
+                outcome: Err(ValidateError::Transport("connection refused".to_string())),
+            }],
+        }
+    }
+
+    #[test]
+    fn orders_first_occurrences_by_time() {
+        let report = sample();
+        let order: Vec<_> = report
+            .first_occurrences()
+            .into_iter()
+            .map(|(level, signature)| (level, signature["first_seen"]["line"].as_u64().unwrap()))
+            .collect();
+        assert_eq!(order, [("warning", 3), ("error", 4), ("error", 7)]);
+        assert_eq!(report.failed_validations(), 1);
+        assert_eq!(
+            ReportFormat::for_path("cab/2026-01-07.md"),
+            Some(ReportFormat::Markdown)
+        );
+        assert_eq!(
+            ReportFormat::for_path("report.html"),
+            Some(ReportFormat::Html)
+        );
+        let options = ReportOptions {
+            output: Some("incident.txt".to_string()),
+            default_format: ReportFormat::Markdown,
+            ..Default::default()
+        };
+        assert_eq!(options.effective_format(), ReportFormat::Markdown);
+    }
+}
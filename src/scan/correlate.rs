@@ -0,0 +1,522 @@
+//! Ties the FHIR resources named in log errors (`Patient/123`) to the
+//! resources themselves: looked up in a directory, on a FHIR server or in the
+//! log record, and validated.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+use crate::error::Error;
+use crate::scan::extract::{PayloadKind, extract_payloads};
+use crate::scan::format::{Level, LogRecord};
+use crate::scan::signature::{Seen, signature};
+use crate::scan::{ScanOptions, Summary, Verdict};
+use crate::validate::{
+    ValidateError, ValidateOptions, ValidationReport, parse_request, sorted_groups,
+    validate_request,
+};
+
+/// `Type/id` with a CamelCase type, so `TCP/IP` and `N/A` are not references.
+static REFERENCE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b((?:[A-Z][a-z]+)+)/([A-Za-z0-9\-.]{1,64})").unwrap());
+/// Inline JSON bodies, whose references point elsewhere (`subject`, `encounter`).
+static INLINE_JSON: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{.*\}").unwrap());
+
+/// Validation issue groups listed per resource.
+const TOP_ISSUES: usize = 3;
+
+/// The `Type/id` references in a log message, outside inline JSON, in order.
+pub fn references(message: &str) -> Vec<String> {
+    let text = INLINE_JSON.replace_all(message, " ");
+    let mut found: Vec<String> = Vec::new();
+    for caps in REFERENCE.captures_iter(&text) {
+        let reference = format!("{}/{}", &caps[1], caps[2].trim_end_matches('.'));
+        if !found.contains(&reference) {
+            found.push(reference);
+        }
+    }
+    found
+}
+
+/// Where `scan --correlate` looks referenced resources up (`--resources`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResourceSource {
+    /// JSON files, one resource each, found by their `resourceType` and `id`.
+    Directory(PathBuf),
+    /// A FHIR base URL, read with `GET [base]/Type/id`.
+    Server(String),
+}
+
+impl FromStr for ResourceSource {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value.is_empty() {
+            return Err("expected a directory or a FHIR base URL".to_string());
+        }
+        if value.starts_with("http://") || value.starts_with("https://") {
+            Ok(ResourceSource::Server(
+                value.trim_end_matches('/').to_string(),
+            ))
+        } else {
+            Ok(ResourceSource::Directory(PathBuf::from(value)))
+        }
+    }
+}
+
+impl fmt::Display for ResourceSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResourceSource::Directory(dir) => write!(f, "{}", dir.display()),
+            ResourceSource::Server(base) => f.write_str(base),
+        }
+    }
+}
+
+/// Settings for `scan --correlate`.
+#[derive(Debug, Clone, Default)]
+pub struct CorrelateOptions {
+    /// Where referenced resources are looked up; without it only copies
+    /// logged with the error are found.
+    pub resources: Option<ResourceSource>,
+    /// Validate each resource found against `base_url`.
+    pub validate: bool,
+    pub base_url: String,
+}
+
+/// A resource named in error and warning records.
+#[derive(Debug, Clone)]
+pub struct ReferencedResource {
+    pub reference: String,
+    pub errors: u64,
+    pub warnings: u64,
+    pub first: Seen,
+    pub last: Seen,
+    /// Categories of the records naming it, in the order first seen.
+    pub categories: Vec<String>,
+    /// Signatures of the records naming it, in the order first seen.
+    pub signatures: Vec<String>,
+    /// The first copy of the resource written to the log with an error.
+    pub logged: Option<(Seen, String)>,
+}
+
+/// Resources named in a scan, in the order first seen.
+#[derive(Debug, Clone, Default)]
+pub struct References {
+    resources: Vec<ReferencedResource>,
+}
+
+impl References {
+    /// Notes the references in an error or warning record, and the resources
+    /// logged with it (which count as named).
+    pub fn add(&mut self, record: &LogRecord, seen: Seen, level: Level, category: Option<&str>) {
+        let logged: Vec<_> = extract_payloads(&record.raw)
+            .into_iter()
+            .filter(|payload| payload.kind == PayloadKind::Fhir && payload.label.contains('/'))
+            .collect();
+        let mut named = references(&record.message);
+        for payload in &logged {
+            if !named.contains(&payload.label) {
+                named.push(payload.label.clone());
+            }
+        }
+        if named.is_empty() {
+            return;
+        }
+
+        let signature = signature(&record.message);
+        for reference in named {
+            let index = match self
+                .resources
+                .iter()
+                .position(|resource| resource.reference == reference)
+            {
+                Some(index) => index,
+                None => {
+                    self.resources.push(ReferencedResource {
+                        reference: reference.clone(),
+                        errors: 0,
+                        warnings: 0,
+                        first: seen.clone(),
+                        last: seen.clone(),
+                        categories: Vec::new(),
+                        signatures: Vec::new(),
+                        logged: None,
+                    });
+                    self.resources.len() - 1
+                }
+            };
+            let resource = &mut self.resources[index];
+            match level {
+                Level::Error => resource.errors += 1,
+                _ => resource.warnings += 1,
+            }
+            resource.last = seen.clone();
+            if let Some(category) = category
+                && !resource.categories.iter().any(|known| known == category)
+            {
+                resource.categories.push(category.to_string());
+            }
+            if !resource.signatures.contains(&signature) {
+                resource.signatures.push(signature.clone());
+            }
+            if resource.logged.is_none()
+                && let Some(payload) = logged.iter().find(|payload| payload.label == reference)
+            {
+                resource.logged = Some((seen.clone(), payload.text.clone()));
+            }
+        }
+    }
+
+    pub fn resources(&self) -> &[ReferencedResource] {
+        &self.resources
+    }
+}
+
+/// What looking a resource up found.
+enum Lookup {
+    /// The resource JSON and where it came from (a file, URL or log line).
+    Found {
+        source: String,
+        text: String,
+    },
+    Missing,
+    Error(String),
+}
+
+/// Finds resources in the `--resources` source, then among logged copies.
+struct Resolver<'a> {
+    source: Option<&'a ResourceSource>,
+    /// `Type/id` to file, for a directory source.
+    index: HashMap<String, PathBuf>,
+    client: reqwest::Client,
+}
+
+impl<'a> Resolver<'a> {
+    fn new(source: Option<&'a ResourceSource>) -> io::Result<Self> {
+        let mut index = HashMap::new();
+        if let Some(ResourceSource::Directory(dir)) = source {
+            index_directory(dir, &mut index)?;
+        }
+        Ok(Self {
+            source,
+            index,
+            client: reqwest::Client::new(),
+        })
+    }
+
+    async fn lookup(&self, resource: &ReferencedResource) -> Lookup {
+        let found = match self.source {
+            Some(ResourceSource::Directory(_)) => match self.index.get(&resource.reference) {
+                Some(path) => match fs::read_to_string(path) {
+                    Ok(text) => Lookup::Found {
+                        source: path.display().to_string(),
+                        text,
+                    },
+                    Err(err) => Lookup::Error(format!("{}: {err}", path.display())),
+                },
+                None => Lookup::Missing,
+            },
+            Some(ResourceSource::Server(base)) => self.fetch(base, &resource.reference).await,
+            None => Lookup::Missing,
+        };
+        match (found, &resource.logged) {
+            (Lookup::Missing, Some((seen, text))) => Lookup::Found {
+                source: format!("log {}", seen.location()),
+                text: text.clone(),
+            },
+            (found, _) => found,
+        }
+    }
+
+    async fn fetch(&self, base: &str, reference: &str) -> Lookup {
+        let url = format!("{base}/{reference}");
+        let response = match self
+            .client
+            .get(&url)
+            .header("Accept", "application/fhir+json")
+            .send()
+            .await
+        {
+            Ok(response) => response,
+            Err(err) => return Lookup::Error(err.to_string()),
+        };
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND || status == StatusCode::GONE {
+            return Lookup::Missing;
+        }
+        if !status.is_success() {
+            return Lookup::Error(format!("{url} answered HTTP {status}"));
+        }
+        match response.text().await {
+            Ok(text) => Lookup::Found { source: url, text },
+            Err(err) => Lookup::Error(err.to_string()),
+        }
+    }
+}
+
+/// Adds every JSON resource with an `id` under `dir` to `index`; the first
+/// file (in path order) wins when two hold the same resource.
+fn index_directory(dir: &Path, index: &mut HashMap<String, PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            index_directory(&path, index)?;
+            continue;
+        }
+        if path.extension().is_none_or(|extension| extension != "json") {
+            continue;
+        }
+        let Ok(text) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let Ok(resource) = serde_json::from_str::<Value>(&text) else {
+            continue;
+        };
+        if let (Some(resource_type), Some(id)) = (
+            resource.get("resourceType").and_then(Value::as_str),
+            resource.get("id").and_then(Value::as_str),
+        ) {
+            index.entry(format!("{resource_type}/{id}")).or_insert(path);
+        }
+    }
+    Ok(())
+}
+
+async fn check(text: &str, source: &str, base_url: &str) -> Verdict {
+    let request = match parse_request(text.to_string()) {
+        Ok(request) => request,
+        Err(err) => return Verdict::Error(ValidateError::Input(format!("{source}: {err}"))),
+    };
+    Verdict::from_result(
+        validate_request(request, source, base_url, &ValidateOptions::default()).await,
+    )
+}
+
+/// A referenced resource with what was found out about it.
+struct Correlation<'a> {
+    resource: &'a ReferencedResource,
+    lookup: Lookup,
+    verdict: Option<Verdict>,
+}
+
+/// `scan --correlate`: the usual summary plus, for every resource named in an
+/// error or warning, where it was found and how it validates.
+pub async fn run_correlate(
+    input: &str,
+    options: &ScanOptions,
+    correlate: &CorrelateOptions,
+) -> Result<(), Error> {
+    let summary =
+        Summary::collect(input, options).map_err(|err| Error::Input(format!("{input}: {err}")))?;
+    let resolver = Resolver::new(correlate.resources.as_ref()).map_err(|err| {
+        let source = correlate.resources.as_ref().map(ToString::to_string);
+        Error::Input(format!("{}: {err}", source.unwrap_or_default()))
+    })?;
+
+    let resources = summary
+        .tally
+        .references
+        .as_ref()
+        .map_or(&[][..], References::resources);
+    let mut correlations = Vec::with_capacity(resources.len());
+    for resource in resources {
+        let lookup = resolver.lookup(resource).await;
+        let verdict = match &lookup {
+            Lookup::Found { source, text } if correlate.validate => {
+                Some(check(text, source, &correlate.base_url).await)
+            }
+            // A resource that could not be fetched could not be validated either.
+            Lookup::Error(message) if correlate.validate => {
+                Some(Verdict::Error(ValidateError::Transport(message.clone())))
+            }
+            _ => None,
+        };
+        correlations.push(Correlation {
+            resource,
+            lookup,
+            verdict,
+        });
+    }
+
+    if options.json {
+        let mut value = summary.to_json(options);
+        value["correlations"] = correlations.iter().map(correlation_json).collect();
+        println!("{value}");
+    } else {
+        print!("{}", summary.format(options));
+        print!("{}", format_correlations(&correlations));
+    }
+
+    Ok(Verdict::outcome(
+        correlations
+            .into_iter()
+            .filter_map(|correlation| correlation.verdict),
+        "Correlated resource",
+    )?)
+}
+
+/// The most frequent issue groups of a report, as `(key, count)`.
+fn top_issues(report: &ValidationReport) -> Vec<(String, usize)> {
+    sorted_groups(&report.groups)
+        .into_iter()
+        .take(TOP_ISSUES)
+        .map(|(key, items)| (key, items.len()))
+        .collect()
+}
+
+fn format_correlations(correlations: &[Correlation<'_>]) -> String {
+    if correlations.is_empty() {
+        return "Correlated resources: none\n".to_string();
+    }
+    let mut output = format!("Correlated resources ({}):\n", correlations.len());
+    for correlation in correlations {
+        let resource = correlation.resource;
+        let mut context = vec![format!(
+            "{} errors, {} warnings",
+            resource.errors, resource.warnings
+        )];
+        context.push(format!("first {}", resource.first.location()));
+        if resource.categories.is_empty() {
+            output.push_str(&format!(
+                "  {} ({})\n",
+                resource.reference,
+                context.join(", ")
+            ));
+        } else {
+            output.push_str(&format!(
+                "  {} ({}; {})\n",
+                resource.reference,
+                context.join(", "),
+                resource.categories.join(", ")
+            ));
+        }
+        for signature in &resource.signatures {
+            output.push_str(&format!("    in: {signature}\n"));
+        }
+        match &correlation.lookup {
+            Lookup::Found { source, .. } => {
+                output.push_str(&format!("    resource: {source}\n"));
+            }
+            Lookup::Missing => output.push_str("    resource: not found\n"),
+            Lookup::Error(message) => {
+                output.push_str(&format!("    resource: lookup failed ({message})\n"));
+            }
+        }
+        if let Some(verdict) = &correlation.verdict {
+            output.push_str(&format!("    validation: {}\n", verdict.describe()));
+            if let Verdict::Pass(report) | Verdict::Fail(report) = verdict {
+                for (key, count) in top_issues(report) {
+                    output.push_str(&format!("      - {key} (x{count})\n"));
+                }
+            }
+        }
+    }
+    output
+}
+
+fn correlation_json(correlation: &Correlation<'_>) -> Value {
+    let resource = correlation.resource;
+    let mut item = json!({
+        "reference": resource.reference,
+        "errors": resource.errors,
+        "warnings": resource.warnings,
+        "first_seen": resource.first.to_json(),
+        "last_seen": resource.last.to_json(),
+        "categories": resource.categories,
+        "signatures": resource.signatures,
+    });
+    item["resource"] = match &correlation.lookup {
+        Lookup::Found { source, .. } => json!({"status": "found", "source": source}),
+        Lookup::Missing => json!({"status": "missing"}),
+        Lookup::Error(message) => json!({"status": "error", "message": message}),
+    };
+    if let Some(verdict) = &correlation.verdict {
+        item["validation"] = verdict.to_json();
+        if let Verdict::Pass(report) | Verdict::Fail(report) = verdict {
+            item["validation"]["top_issues"] = top_issues(report)
+                .into_iter()
+                .map(|(group, count)| json!({"group": group, "count": count}))
+                .collect();
+        }
+    }
+    item
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_references_outside_inline_json() {
+        assert_eq!(
+            references(
+                "FHIR validation failed for Patient/123. body={\"subject\":{\"reference\":\"Patient/9\"}}"
+            ),
+            ["Patient/123"]
+        );
+        assert_eq!(
+            references(
+                "PUT https://fhir.example.org/r4/MedicationRequest/mr-7/_history/2 failed, see Encounter/e1 and Patient/123"
+            ),
+            ["MedicationRequest/mr-7", "Encounter/e1", "Patient/123"]
+        );
+        assert!(references("TCP/IP error, value N/A, ratio 3/4").is_empty());
+        assert_eq!(
+            "https://hapi.fhir.org/baseR4/".parse(),
+            Ok(ResourceSource::Server(
+                "https://hapi.fhir.org/baseR4".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn counts_mentions_and_keeps_logged_copies() {
+        let mut references = References::default();
+        let seen = |line| Seen {
+            file: None,
+            line,
+            time: None,
+        };
+        let logged = LogRecord::plain(
+            3,
+            r#"ERROR FHIR write rejected body={"resourceType":"Observation","id":"o1","subject":{"reference":"Patient/123"}}"#,
+        );
+        references.add(&logged, seen(3), Level::Error, Some("fhir-validation"));
+        let named = LogRecord::plain(5, "WARNING retrying Observation/o1 for Patient/123");
+        references.add(&named, seen(5), Level::Warning, None);
+
+        let resources = references.resources();
+        let summary: Vec<_> = resources
+            .iter()
+            .map(|resource| {
+                (
+                    resource.reference.as_str(),
+                    resource.errors,
+                    resource.warnings,
+                    resource.logged.as_ref().map(|(seen, _)| seen.line),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("Observation/o1", 1, 1, Some(3)),
+                ("Patient/123", 0, 1, None)
+            ]
+        );
+        assert_eq!(resources[0].categories, ["fhir-validation"]);
+        assert_eq!(resources[0].signatures.len(), 2);
+    }
+}
@@ -0,0 +1,469 @@
+//! Pulls HL7 v2 messages and FHIR JSON resources out of log text.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::hl7v2::{MLLP_END, MLLP_START, parse_message};
+
+/// Longest run of log lines searched for the end of a pretty-printed JSON object.
+const MAX_JSON_LINES: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadKind {
+    Hl7v2,
+    Fhir,
+}
+
+impl PayloadKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            PayloadKind::Hl7v2 => "hl7v2",
+            PayloadKind::Fhir => "fhir",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            PayloadKind::Hl7v2 => "hl7",
+            PayloadKind::Fhir => "json",
+        }
+    }
+}
+
+/// A message or resource found in the log.
+#[derive(Debug, Clone)]
+pub struct Payload {
+    /// 1-based log line where the payload starts.
+    pub line: usize,
+    pub kind: PayloadKind,
+    /// ER7 with `\r` segment separators, or pretty-printed JSON.
+    pub text: String,
+    /// `ADT^A01 MSG00001` or `Patient/example`.
+    pub label: String,
+}
+
+/// Finds every HL7 v2 message and FHIR resource in `text`.
+///
+/// HL7 v2 messages are recognized by their MSH header and may be MLLP-framed,
+/// separated by carriage returns (real or written as `\r` / `<CR>`), or have
+/// their segments on the following log lines. JSON is searched for objects with
+/// a `resourceType`, including resources nested in JSON log records or escaped
+/// inside JSON strings.
+pub fn extract_payloads(text: &str) -> Vec<Payload> {
+    let lines: Vec<&str> = text
+        .split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .collect();
+    let mut payloads = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        index += scan_line(&lines, index, &mut payloads);
+    }
+    payloads
+}
+
+/// Scans `lines[index]` (and any continuation lines); returns how many lines were consumed.
+fn scan_line(lines: &[&str], index: usize, payloads: &mut Vec<Payload>) -> usize {
+    let line = lines[index];
+    let mut consumed = 1;
+    let mut position = 0;
+    while position < line.len() {
+        let hl7 = find_msh(line, position);
+        let json = line[position..].find('{').map(|offset| position + offset);
+        match (hl7, json) {
+            (Some(start), json) if json.is_none_or(|json| start < json) => {
+                let (message, end, continued) = hl7_message(lines, index, start);
+                consumed = consumed.max(1 + continued);
+                push_hl7(payloads, index + 1, &message);
+                position = end;
+            }
+            (_, Some(start)) => match json_object(lines, index, start) {
+                Some((value, end, continued)) => {
+                    consumed = consumed.max(1 + continued);
+                    collect_json(&value, index + 1, payloads);
+                    position = end;
+                }
+                None => position = start + 1,
+            },
+            _ => break,
+        }
+        if consumed > 1 {
+            break;
+        }
+    }
+    consumed
+}
+
+/// Byte offset of the next `MSH` followed by a field separator and encoding characters.
+pub(super) fn find_msh(line: &str, from: usize) -> Option<usize> {
+    let mut search = from;
+    while let Some(offset) = line[search..].find("MSH") {
+        let start = search + offset;
+        if is_msh_header(&line[start + 3..]) {
+            return Some(start);
+        }
+        search = start + 3;
+    }
+    None
+}
+
+fn is_msh_header(rest: &str) -> bool {
+    msh_delimiters(rest).is_some()
+}
+
+/// The field separator and encoding characters that follow `MSH` in `rest`.
+pub(super) fn msh_delimiters(rest: &str) -> Option<(char, &str)> {
+    let delimiter = |c: &char| !c.is_alphanumeric() && !c.is_whitespace();
+    let field = rest.chars().next().filter(delimiter)?;
+    let rest = &rest[field.len_utf8()..];
+    let encoding = &rest[..rest.find(field)?];
+    ((3..=5).contains(&encoding.chars().count()) && encoding.chars().all(|c| delimiter(&c)))
+        .then_some((field, encoding))
+}
+
+/// Returns the normalized message, the end offset in the line and the continuation lines used.
+fn hl7_message(lines: &[&str], index: usize, start: usize) -> (String, usize, usize) {
+    let line = lines[index];
+    let quote = line[..start]
+        .chars()
+        .next_back()
+        .filter(|c| *c == '"' || *c == '\'');
+    let rest = &line[start..];
+    let mut end = rest.find(MLLP_END).unwrap_or(rest.len());
+    if let Some(quote) = quote
+        && let Some(close) = rest[..end].find(quote)
+    {
+        end = close;
+    }
+    let mut message = normalize_separators(&rest[..end]);
+
+    let mut continued = 0;
+    if end == rest.len() {
+        let field = rest[3..].chars().next().unwrap_or('|');
+        while let Some(next) = lines.get(index + 1 + continued) {
+            let segment = next.trim_start().trim_start_matches(MLLP_START);
+            if !is_segment_start(segment, field) || segment.starts_with("MSH") {
+                break;
+            }
+            if !message.ends_with('\r') {
+                message.push('\r');
+            }
+            let segment_end = segment.find(MLLP_END).unwrap_or(segment.len());
+            message.push_str(&normalize_separators(&segment[..segment_end]));
+            continued += 1;
+            if segment_end < segment.len() {
+                break;
+            }
+        }
+    }
+    (message, start + end, continued)
+}
+
+fn is_segment_start(text: &str, field: char) -> bool {
+    let bytes = text.as_bytes();
+    bytes.len() > 3
+        && bytes[0].is_ascii_uppercase()
+        && bytes[1..3]
+            .iter()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+        && text[3..].starts_with(field)
+}
+
+/// Turns the ways logs write segment separators into `\r`.
+fn normalize_separators(text: &str) -> String {
+    // HL7 escape sequences are uppercase (`\R\`) or `\.br\`, so a lowercase `\r` is a log escape.
+    text.replace("\\r\\n", "\r")
+        .replace("\\r", "\r")
+        .replace("\\n", "\r")
+        .replace("<CR>", "\r")
+        .replace(['\u{0b}', '\u{1c}'], "")
+        .trim_end()
+        .to_string()
+}
+
+fn push_hl7(payloads: &mut Vec<Payload>, line: usize, text: &str) {
+    let label = match parse_message(text) {
+        Ok(message) => format!(
+            "{} {}",
+            message.message_type().unwrap_or_else(|| "?".to_string()),
+            message.control_id().unwrap_or("?")
+        ),
+        Err(_) => "unparsed message".to_string(),
+    };
+    let mut text = text.to_string();
+    text.push('\r');
+    payloads.push(Payload {
+        line,
+        kind: PayloadKind::Hl7v2,
+        text,
+        label,
+    });
+}
+
+/// Parses the JSON object starting at `start`, continuing onto later lines when the
+/// line ends with an open `{` (pretty-printed JSON).
+fn json_object(lines: &[&str], index: usize, start: usize) -> Option<(Value, usize, usize)> {
+    let line = &lines[index][start..];
+    let mut balance = Balance::new(line.starts_with("{\\\""));
+    if let Some(end) = balance.feed(line) {
+        return Some((parse_object(&line[..end])?, start + end, 0));
+    }
+    if line.trim_end() != "{" {
+        return None;
+    }
+
+    let mut text = line.to_string();
+    for continued in 1..=MAX_JSON_LINES {
+        let next = lines.get(index + continued)?;
+        text.push('\n');
+        if let Some(end) = balance.feed(&format!("\n{next}")) {
+            text.push_str(&next[..end - 1]);
+            return Some((parse_object(&text)?, end - 1, continued));
+        }
+        text.push_str(next);
+    }
+    None
+}
+
+/// Parses an object, falling back to unescaping for JSON embedded in a string (`{\"...`).
+fn parse_object(text: &str) -> Option<Value> {
+    if let Ok(value @ Value::Object(_)) = serde_json::from_str(text) {
+        return Some(value);
+    }
+    let unescaped: String = serde_json::from_str(&format!("\"{text}\"")).ok()?;
+    match serde_json::from_str(&unescaped) {
+        Ok(value @ Value::Object(_)) => Some(value),
+        _ => None,
+    }
+}
+
+/// Tracks `{`/`}` nesting across chunks, ignoring braces inside strings.
+struct Balance {
+    depth: usize,
+    in_string: bool,
+    /// Strings are delimited by `\"` because the object itself sits inside a JSON string.
+    escaped_quotes: bool,
+}
+
+impl Balance {
+    fn new(escaped_quotes: bool) -> Self {
+        Balance {
+            depth: 0,
+            in_string: false,
+            escaped_quotes,
+        }
+    }
+
+    /// Returns the offset just past the closing brace once the object is complete.
+    fn feed(&mut self, chunk: &str) -> Option<usize> {
+        let bytes = chunk.as_bytes();
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let byte = bytes[offset];
+            if byte == b'\\' {
+                let next = bytes.get(offset + 1).copied();
+                if self.escaped_quotes && next == Some(b'"') {
+                    self.in_string = !self.in_string;
+                }
+                offset += 2;
+                continue;
+            }
+            if byte == b'"' && !self.escaped_quotes {
+                self.in_string = !self.in_string;
+            } else if !self.in_string {
+                match byte {
+                    b'{' => self.depth += 1,
+                    b'}' => {
+                        self.depth = self.depth.saturating_sub(1);
+                        if self.depth == 0 {
+                            return Some(offset + 1);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            offset += 1;
+        }
+        None
+    }
+}
+
+/// Records FHIR resources in `value`, or searches a non-FHIR JSON log record for them.
+fn collect_json(value: &Value, line: usize, payloads: &mut Vec<Payload>) {
+    match value {
+        Value::Object(object) => {
+            if let Some(resource_type) = object.get("resourceType").and_then(Value::as_str) {
+                let label = match object.get("id").and_then(Value::as_str) {
+                    Some(id) => format!("{resource_type}/{id}"),
+                    None => resource_type.to_string(),
+                };
+                payloads.push(Payload {
+                    line,
+                    kind: PayloadKind::Fhir,
+                    text: serde_json::to_string_pretty(value).unwrap_or_default() + "\n",
+                    label,
+                });
+                return;
+            }
+            for nested in object.values() {
+                collect_json(nested, line, payloads);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                collect_json(item, line, payloads);
+            }
+        }
+        Value::String(text) if text.contains("MSH") || text.contains('{') => {
+            let lines: Vec<&str> = text.split('\n').collect();
+            let mut nested = Vec::new();
+            let mut index = 0;
+            while index < lines.len() {
+                index += scan_line(&lines, index, &mut nested);
+            }
+            payloads.extend(
+                nested
+                    .into_iter()
+                    .map(|payload| Payload { line, ..payload }),
+            );
+        }
+        _ => {}
+    }
+}
+
+/// Writes each payload to `dir` as `L<line>-<kind>-<label>.<ext>` and returns the paths.
+pub fn write_payloads(payloads: &[Payload], dir: &Path) -> io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir)?;
+    let mut paths: Vec<PathBuf> = Vec::with_capacity(payloads.len());
+    for payload in payloads {
+        let stem = format!(
+            "L{:06}-{}-{}",
+            payload.line,
+            payload.kind.label(),
+            file_safe(&payload.label)
+        );
+        let mut path = dir.join(format!("{stem}.{}", payload.kind.extension()));
+        let mut counter = 2;
+        while paths.contains(&path) {
+            path = dir.join(format!("{stem}-{counter}.{}", payload.kind.extension()));
+            counter += 1;
+        }
+        fs::write(&path, &payload.text)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+fn file_safe(label: &str) -> String {
+    let safe: String = label
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(60)
+        .collect();
+    safe.trim_matches('_').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_inline_hl7_with_escaped_and_real_separators() {
+        let log = "2026-01-07 08:01:12 INFO inbound MSH|^~\\&|EHR|HOSP|||20260107||ADT^A01|MSG1|P|2.5.1\\rPID|1||123||Doe^Jane\\rPV1|1|I\n\
+2026-01-07 08:01:13 ERROR rejected \u{0b}MSH|^~\\&|LAB|HOSP|||20260107||ORU^R01|LAB9|P|2.5.1\rOBX|1|NM|GLU||95\u{1c}\r trailing text\n";
+        let payloads = extract_payloads(log);
+        assert_eq!(payloads.len(), 2);
+        assert_eq!(payloads[0].kind, PayloadKind::Hl7v2);
+        assert_eq!(payloads[0].label, "ADT^A01 MSG1");
+        assert_eq!(payloads[0].text.matches('\r').count(), 3);
+        assert_eq!(payloads[1].line, 2);
+        assert_eq!(payloads[1].label, "ORU^R01 LAB9");
+        assert!(!payloads[1].text.contains("trailing"));
+    }
+
+    #[test]
+    fn follows_segments_on_continuation_lines() {
+        let log = "INFO sending message:\n\
+MSH|^~\\&|EHR|HOSP|||20260107||ADT^A08|MSG2|P|2.4\n\
+EVN|A08|20260107\n\
+PID|1||123||Doe^Jane\n\
+INFO done\n";
+        let payloads = extract_payloads(log);
+        assert_eq!(payloads.len(), 1);
+        assert_eq!(payloads[0].line, 2);
+        assert_eq!(
+            payloads[0].text,
+            "MSH|^~\\&|EHR|HOSP|||20260107||ADT^A08|MSG2|P|2.4\rEVN|A08|20260107\rPID|1||123||Doe^Jane\r"
+        );
+    }
+
+    #[test]
+    fn finds_fhir_json_inline_nested_escaped_and_pretty_printed() {
+        let log = r#"WARN POST /Patient body={"resourceType":"Patient","id":"p1","name":[{"family":"Doe {jr}"}]} status=422
+{"level":"error","msg":"rejected","payload":{"resourceType":"Observation","id":"o1","status":"final"}}
+ERROR body="{\"resourceType\":\"Encounter\",\"id\":\"e1\"}"
+DEBUG config {"retries": 3}
+INFO response {
+  "resourceType": "OperationOutcome",
+  "issue": []
+}
+"#;
+        let payloads = extract_payloads(log);
+        let labels: Vec<(usize, &str)> = payloads
+            .iter()
+            .map(|payload| (payload.line, payload.label.as_str()))
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                (1, "Patient/p1"),
+                (2, "Observation/o1"),
+                (3, "Encounter/e1"),
+                (5, "OperationOutcome"),
+            ]
+        );
+        assert!(
+            payloads
+                .iter()
+                .all(|payload| payload.kind == PayloadKind::Fhir)
+        );
+    }
+
+    #[test]
+    fn ignores_msh_without_encoding_characters() {
+        assert!(extract_payloads("MSH value missing | nothing here\nthe MSH|x|y\n").is_empty());
+    }
+
+    #[test]
+    fn writes_uniquely_named_files() {
+        let dir = std::env::temp_dir().join(format!("clinlogix-extract-{}", std::process::id()));
+        let payload = Payload {
+            line: 7,
+            kind: PayloadKind::Fhir,
+            text: "{}\n".to_string(),
+            label: "Patient/p 1".to_string(),
+        };
+        let paths = write_payloads(&[payload.clone(), payload], &dir).expect("write");
+        let names: Vec<String> = paths
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "L000007-fhir-Patient_p_1.json",
+                "L000007-fhir-Patient_p_1-2.json"
+            ]
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,539 @@
+//! `scan --follow`: tails a log as it is written, keeps rolling counts and
+//! raises an alert when errors over a sliding window reach a threshold.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use regex::Regex;
+use serde_json::{Value, json};
+
+use crate::civil::unix_seconds;
+use crate::error::Error;
+use crate::scan::format::{DETECT_LINES, Level, LogFormat, LogParser, LogRecord};
+use crate::scan::grok::GrokPattern;
+use crate::scan::records::RecordAssembler;
+use crate::scan::rules::CategoryRules;
+use crate::scan::timeline::Timestamp;
+
+/// What happens when the error rate crosses the threshold (`--alert`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AlertAction {
+    /// Print the alert on stdout.
+    Print,
+    /// Stop following and exit with status 1.
+    Exit,
+    /// Run a shell command with the alert in `CLINLOGIX_ALERT` (JSON).
+    Command(String),
+    /// Append the JSON payload a webhook would receive to a file, one per line.
+    Webhook(PathBuf),
+}
+
+impl FromStr for AlertAction {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.split_once(':') {
+            _ if value == "print" => Ok(AlertAction::Print),
+            _ if value == "exit" => Ok(AlertAction::Exit),
+            Some(("command", command)) if !command.trim().is_empty() => {
+                Ok(AlertAction::Command(command.to_string()))
+            }
+            Some(("webhook", path)) if !path.is_empty() => {
+                Ok(AlertAction::Webhook(PathBuf::from(path)))
+            }
+            _ => Err(format!(
+                "unknown alert action '{value}' (expected print, exit, command:CMD or webhook:FILE)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for AlertAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlertAction::Print => f.write_str("print"),
+            AlertAction::Exit => f.write_str("exit"),
+            AlertAction::Command(command) => write!(f, "command:{command}"),
+            AlertAction::Webhook(path) => write!(f, "webhook:{}", path.display()),
+        }
+    }
+}
+
+/// Settings for `scan --follow`.
+#[derive(Debug, Clone)]
+pub struct FollowOptions {
+    pub json: bool,
+    pub format: LogFormat,
+    pub patterns: Vec<GrokPattern>,
+    pub record_start: Option<Regex>,
+    pub rules: CategoryRules,
+    /// Length of the sliding window errors are counted over.
+    pub window: Duration,
+    /// Errors within the window that raise an alert; `None` never alerts.
+    pub threshold: Option<u64>,
+    pub alerts: Vec<AlertAction>,
+    /// How long to wait for new lines before looking again.
+    pub poll: Duration,
+}
+
+impl Default for FollowOptions {
+    fn default() -> Self {
+        Self {
+            json: false,
+            format: LogFormat::Auto,
+            patterns: Vec::new(),
+            record_start: None,
+            rules: CategoryRules::default(),
+            window: Duration::from_secs(60),
+            threshold: None,
+            alerts: vec![AlertAction::Print],
+            poll: Duration::from_millis(250),
+        }
+    }
+}
+
+/// Follows `logfile` until an `exit` alert fires or the process is stopped.
+///
+/// Only lines written after the start are counted. The format is detected
+/// from the lines already in the file.
+pub fn run_follow(logfile: &str, options: &FollowOptions) -> Result<(), Error> {
+    let input = |err: io::Error| Error::Input(format!("{logfile}: {err}"));
+    let mut tail = Tail::open(Path::new(logfile)).map_err(input)?;
+    let parser = LogParser::new(options.format, options.patterns.clone(), &tail.sample);
+    let mut assembler = RecordAssembler::new(&parser, options.record_start.clone());
+    let mut monitor = Monitor::new(logfile, options);
+    eprintln!(
+        "Following {logfile} ({}) from line {}; errors are counted over the last {}s",
+        parser.format(),
+        tail.line + 1,
+        options.window.as_secs()
+    );
+
+    loop {
+        let now = Instant::now();
+        match tail.poll().map_err(input)? {
+            Poll::Lines(lines) => {
+                for (number, line) in lines {
+                    if let Some(record) = assembler.push(number, &line) {
+                        monitor.record(&record, now)?;
+                    }
+                }
+            }
+            Poll::Reopened(reason) => {
+                if let Some(record) = assembler.finish() {
+                    monitor.record(&record, now)?;
+                }
+                eprintln!("{logfile} was {reason}; reading it from the start");
+            }
+            Poll::Idle => {
+                // Nothing more is coming for the record being assembled.
+                if let Some(record) = assembler.finish() {
+                    monitor.record(&record, now)?;
+                }
+                thread::sleep(options.poll);
+            }
+        }
+        monitor.check(now)?;
+    }
+}
+
+enum Poll {
+    /// Complete lines with their 1-based numbers.
+    Lines(Vec<(u64, String)>),
+    /// The file was truncated or replaced; later lines come from the start.
+    Reopened(&'static str),
+    Idle,
+}
+
+/// A followed file: notices truncation (shorter than what was read) and
+/// rotation (the path now names another file).
+struct Tail {
+    path: PathBuf,
+    reader: BufReader<File>,
+    id: Option<u64>,
+    position: u64,
+    /// Lines read so far.
+    line: u64,
+    /// A last line still waiting for its newline.
+    partial: String,
+    /// The first lines of the file, for format detection.
+    sample: Vec<String>,
+}
+
+impl Tail {
+    /// Opens `path` positioned after its current last complete line.
+    fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let id = file_id(&file.metadata()?);
+        let mut tail = Self {
+            path: path.to_path_buf(),
+            reader: BufReader::new(file),
+            id,
+            position: 0,
+            line: 0,
+            partial: String::new(),
+            sample: Vec::new(),
+        };
+        loop {
+            let mut text = String::new();
+            let read = tail.reader.read_line(&mut text)?;
+            if read == 0 || !text.ends_with('\n') {
+                // Written from the next poll on; keep it with the lines after it.
+                tail.partial = text;
+                tail.position += read as u64;
+                break;
+            }
+            tail.position += read as u64;
+            tail.line += 1;
+            if tail.sample.len() < DETECT_LINES {
+                tail.sample
+                    .push(text.trim_end_matches(['\r', '\n']).to_string());
+            }
+        }
+        Ok(tail)
+    }
+
+    fn poll(&mut self) -> io::Result<Poll> {
+        let mut lines = Vec::new();
+        loop {
+            let read = self.reader.read_line(&mut self.partial)?;
+            if read == 0 {
+                break;
+            }
+            self.position += read as u64;
+            if !self.partial.ends_with('\n') {
+                break;
+            }
+            self.line += 1;
+            let line = self.partial.trim_end_matches(['\r', '\n']).to_string();
+            lines.push((self.line, line));
+            self.partial.clear();
+        }
+        if !lines.is_empty() {
+            return Ok(Poll::Lines(lines));
+        }
+
+        // Rotated away and not yet recreated: keep waiting on the old file.
+        let Ok(metadata) = fs::metadata(&self.path) else {
+            return Ok(Poll::Idle);
+        };
+        let reason = if file_id(&metadata) != self.id {
+            "rotated"
+        } else if metadata.len() < self.position {
+            "truncated"
+        } else {
+            return Ok(Poll::Idle);
+        };
+        let file = File::open(&self.path)?;
+        self.id = file_id(&file.metadata()?);
+        self.reader = BufReader::new(file);
+        self.reader.seek(SeekFrom::Start(0))?;
+        self.position = 0;
+        self.line = 0;
+        self.partial.clear();
+        Ok(Poll::Reopened(reason))
+    }
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.ino())
+}
+
+/// Rotation is then only noticed when the new file is shorter.
+#[cfg(not(unix))]
+fn file_id(_metadata: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// Arrival times of recent events, dropped once older than the window.
+#[derive(Debug)]
+struct RateWindow {
+    span: Duration,
+    times: VecDeque<Instant>,
+}
+
+impl RateWindow {
+    fn new(span: Duration) -> Self {
+        Self {
+            span,
+            times: VecDeque::new(),
+        }
+    }
+
+    fn add(&mut self, now: Instant) {
+        self.times.push_back(now);
+    }
+
+    fn count(&mut self, now: Instant) -> u64 {
+        while self
+            .times
+            .front()
+            .is_some_and(|time| now.duration_since(*time) >= self.span)
+        {
+            self.times.pop_front();
+        }
+        self.times.len() as u64
+    }
+}
+
+/// Rolling counts and alert state.
+struct Monitor<'a> {
+    file: &'a str,
+    options: &'a FollowOptions,
+    errors: RateWindow,
+    warnings: RateWindow,
+    total_records: u64,
+    total_errors: u64,
+    total_warnings: u64,
+    last_error: Option<String>,
+    /// Above the threshold; the next alert waits until the rate drops below it.
+    alerting: bool,
+}
+
+impl<'a> Monitor<'a> {
+    fn new(file: &'a str, options: &'a FollowOptions) -> Self {
+        Self {
+            file,
+            options,
+            errors: RateWindow::new(options.window),
+            warnings: RateWindow::new(options.window),
+            total_records: 0,
+            total_errors: 0,
+            total_warnings: 0,
+            last_error: None,
+            alerting: false,
+        }
+    }
+
+    fn record(&mut self, record: &LogRecord, now: Instant) -> Result<(), Error> {
+        self.total_records += 1;
+        let classified = self.options.rules.classify(record);
+        let (level, in_window, total) = match classified.level {
+            Some(Level::Error) => {
+                self.total_errors += 1;
+                self.errors.add(now);
+                self.last_error = Some(record.head().trim().to_string());
+                ("error", self.errors.count(now), self.total_errors)
+            }
+            Some(Level::Warning) => {
+                self.total_warnings += 1;
+                self.warnings.add(now);
+                ("warning", self.warnings.count(now), self.total_warnings)
+            }
+            _ => return Ok(()),
+        };
+        if self.options.json {
+            println!(
+                "{}",
+                json!({
+                    "event": level,
+                    "category": classified.category,
+                    "line": record.line,
+                    "time": record.timestamp.map(Timestamp::iso),
+                    "text": record.head().trim(),
+                    "in_window": in_window,
+                    "total": total,
+                })
+            );
+        } else {
+            let label = match classified.category {
+                Some(category) => format!("{level}: {category}"),
+                None => level.to_string(),
+            };
+            println!(
+                "[{label}] {} ({in_window} {level}s in the last {}s, {total} total)",
+                record.head().trim(),
+                self.options.window.as_secs()
+            );
+        }
+        self.check(now)
+    }
+
+    /// Raises an alert when errors in the window reach the threshold, and
+    /// notes the recovery when they fall below it again.
+    fn check(&mut self, now: Instant) -> Result<(), Error> {
+        let Some(threshold) = self.options.threshold else {
+            return Ok(());
+        };
+        let errors = self.errors.count(now);
+        if errors >= threshold && !self.alerting {
+            self.alerting = true;
+            return self.alert(errors, threshold);
+        }
+        if errors < threshold && self.alerting {
+            self.alerting = false;
+            if self.options.json {
+                println!(
+                    "{}",
+                    json!({"event": "recovered", "errors_in_window": errors})
+                );
+            } else {
+                println!(
+                    "Recovered: {errors} errors in the last {}s (threshold {threshold})",
+                    self.options.window.as_secs()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn payload(&self, errors: u64, threshold: u64) -> Value {
+        json!({
+            "alert": "error-rate",
+            "file": self.file,
+            "time": Timestamp::from_seconds(unix_seconds(SystemTime::now())).iso(),
+            "errors_in_window": errors,
+            "window_seconds": self.options.window.as_secs(),
+            "threshold": threshold,
+            "records": self.total_records,
+            "errors": self.total_errors,
+            "warnings": self.total_warnings,
+            "last_error": self.last_error,
+        })
+    }
+
+    fn alert(&self, errors: u64, threshold: u64) -> Result<(), Error> {
+        let payload = self.payload(errors, threshold);
+        let message = format!(
+            "ALERT: {errors} errors in the last {}s (threshold {threshold}); since start: {} records, {} errors, {} warnings",
+            self.options.window.as_secs(),
+            self.total_records,
+            self.total_errors,
+            self.total_warnings
+        );
+        let mut exit = false;
+        for action in &self.options.alerts {
+            match action {
+                AlertAction::Print if self.options.json => {
+                    let mut event = payload.clone();
+                    event["event"] = json!("alert");
+                    println!("{event}");
+                }
+                AlertAction::Print => println!("{message}"),
+                AlertAction::Exit => exit = true,
+                AlertAction::Command(command) => {
+                    if let Err(err) = run_command(command, &payload) {
+                        eprintln!("Alert command '{command}' failed: {err}");
+                    }
+                }
+                AlertAction::Webhook(path) => {
+                    let written = OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(path)
+                        .and_then(|mut file| writeln!(file, "{payload}"));
+                    if let Err(err) = written {
+                        eprintln!("Alert webhook {}: {err}", path.display());
+                    }
+                }
+            }
+        }
+        if exit {
+            if !self.options.alerts.contains(&AlertAction::Print) {
+                eprintln!("{message}");
+            }
+            return Err(Error::Alert);
+        }
+        Ok(())
+    }
+}
+
+fn run_command(command: &str, payload: &Value) -> io::Result<()> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let status = shell
+        .arg(command)
+        .env("CLINLOGIX_ALERT", payload.to_string())
+        .env(
+            "CLINLOGIX_ALERT_ERRORS",
+            payload["errors_in_window"].to_string(),
+        )
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("exited with {status}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_forgets_old_errors() {
+        let start = Instant::now();
+        let mut window = RateWindow::new(Duration::from_secs(60));
+        window.add(start);
+        window.add(start + Duration::from_secs(30));
+        assert_eq!(window.count(start + Duration::from_secs(59)), 2);
+        assert_eq!(window.count(start + Duration::from_secs(60)), 1);
+        assert_eq!(window.count(start + Duration::from_secs(120)), 0);
+    }
+
+    #[test]
+    fn parses_alert_actions() {
+        assert_eq!("exit".parse(), Ok(AlertAction::Exit));
+        assert_eq!(
+            "command:notify-send clinlogix".parse(),
+            Ok(AlertAction::Command("notify-send clinlogix".to_string()))
+        );
+        assert!("webhook:".parse::<AlertAction>().is_err());
+    }
+
+    #[test]
+    fn tail_notices_truncation_and_rotation() {
+        let dir = std::env::temp_dir().join(format!("clinlogix-tail-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("app.log");
+        fs::write(&path, "INFO old\nINFO par").unwrap();
+        let mut tail = Tail::open(&path).unwrap();
+        assert_eq!(tail.sample, ["INFO old"]);
+        assert!(matches!(tail.poll().unwrap(), Poll::Idle));
+
+        let append = |text: &str| {
+            let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+            file.write_all(text.as_bytes()).unwrap();
+        };
+        append("tial\nERROR new\n");
+        let Poll::Lines(lines) = tail.poll().unwrap() else {
+            panic!("expected lines");
+        };
+        assert_eq!(
+            lines,
+            [
+                (2, "INFO partial".to_string()),
+                (3, "ERROR new".to_string())
+            ]
+        );
+
+        fs::write(&path, "X\n").unwrap();
+        assert!(matches!(tail.poll().unwrap(), Poll::Reopened("truncated")));
+        assert!(
+            matches!(tail.poll().unwrap(), Poll::Lines(lines) if lines == [(1, "X".to_string())])
+        );
+
+        if cfg!(unix) {
+            fs::rename(&path, dir.join("app.log.1")).unwrap();
+            fs::write(&path, "ERROR after rotation and longer than before\n").unwrap();
+            assert!(matches!(tail.poll().unwrap(), Poll::Reopened("rotated")));
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
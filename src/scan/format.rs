@@ -0,0 +1,519 @@
+//! Log formats: plain text, JSON lines, RFC 5424 syslog, Log4j-style layouts
+//! and user patterns, all parsed into a [`LogRecord`].
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::scan::grok::GrokPattern;
+use crate::scan::records::CONTINUATION;
+use crate::scan::timeline::{Timestamp, parse_timestamp};
+
+/// Lines read to detect the format.
+pub const DETECT_LINES: usize = 20;
+
+static SYSLOG_5424: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^<(?P<pri>\d{1,3})>(?P<version>\d{1,2}) (?P<timestamp>\S+) (?P<host>\S+) (?P<app>\S+) (?P<procid>\S+) (?P<msgid>\S+) (?P<sd>-|(?:\[(?:[^\]\\]|\\.)*\])+)(?: (?P<message>.*))?$",
+    )
+    .unwrap()
+});
+/// `%d %-5p [%t] %c - %m` and `%d [%t] %-5p %c - %m`.
+static LOG4J: LazyLock<[GrokPattern; 2]> = LazyLock::new(|| {
+    [
+        r"%{TIMESTAMP_ISO8601:timestamp}\s+%{LOGLEVEL:level}\s+\[%{DATA:thread}\]\s+%{JAVACLASS:logger}\s+-\s+%{GREEDYDATA:message}",
+        r"%{TIMESTAMP_ISO8601:timestamp}\s+\[%{DATA:thread}\]\s+%{LOGLEVEL:level}\s+%{JAVACLASS:logger}\s+-\s+%{GREEDYDATA:message}",
+    ]
+    .map(|pattern| GrokPattern::compile(pattern).expect("built-in Log4j patterns compile"))
+});
+
+const TIME_FIELDS: &[&str] = &["timestamp", "time", "@timestamp", "ts", "datetime", "date"];
+const LEVEL_FIELDS: &[&str] = &[
+    "level",
+    "severity",
+    "lvl",
+    "log.level",
+    "levelname",
+    "loglevel",
+];
+const MESSAGE_FIELDS: &[&str] = &["message", "msg", "@message", "log", "text"];
+const SOURCE_FIELDS: &[&str] = &["source", "logger", "logger_name", "service", "app", "host"];
+
+/// Normalized severity of a record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Error,
+    Warning,
+    Info,
+    Debug,
+}
+
+impl Level {
+    /// Level names from common frameworks (`ERROR`, `warn`, `SEVERE`, `crit`, ...)
+    /// and single-letter levels (`E`, `W`, `I`, `D`).
+    pub fn parse(value: &str) -> Option<Level> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "emerg" | "emergency" | "alert" | "crit" | "critical" | "fatal" | "severe" | "err"
+            | "error" | "f" | "e" => Some(Level::Error),
+            "warn" | "warning" | "w" => Some(Level::Warning),
+            "notice" | "info" | "information" | "informational" | "i" => Some(Level::Info),
+            "debug" | "trace" | "fine" | "finer" | "finest" | "d" | "t" | "v" => Some(Level::Debug),
+            _ => None,
+        }
+    }
+
+    /// Syslog severities 0–7.
+    pub fn from_syslog(severity: u8) -> Level {
+        match severity {
+            0..=3 => Level::Error,
+            4 => Level::Warning,
+            5 | 6 => Level::Info,
+            _ => Level::Debug,
+        }
+    }
+
+    /// Numeric levels in JSON logs: syslog severities 0–7, or the pino and
+    /// bunyan levels (10 trace, 20 debug, 30 info, 40 warn, 50 error, 60 fatal).
+    pub fn from_number(level: u64) -> Option<Level> {
+        match level {
+            0..=7 => Some(Level::from_syslog(level as u8)),
+            10 | 20 => Some(Level::Debug),
+            30 => Some(Level::Info),
+            40 => Some(Level::Warning),
+            50 | 60 => Some(Level::Error),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Info => "info",
+            Level::Debug => "debug",
+        }
+    }
+}
+
+/// One log entry, whatever format it was written in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRecord {
+    /// 1-based line number where the record starts.
+    pub line: u64,
+    /// Physical lines in the record; more than one once continuation lines
+    /// (stack frames, HL7 segments) are joined to it.
+    pub lines: u64,
+    pub timestamp: Option<Timestamp>,
+    /// `None` when the format has no level field (plain text).
+    pub level: Option<Level>,
+    /// Host, application or logger that wrote the record.
+    pub source: Option<String>,
+    pub message: String,
+    /// Other named fields (thread, msgid, ...).
+    pub fields: BTreeMap<String, String>,
+    /// The text as written, lines joined with `\n`.
+    pub raw: String,
+}
+
+impl LogRecord {
+    /// A plain-text record: the timestamp is taken from anywhere in the line.
+    pub fn plain(line: u64, raw: &str) -> Self {
+        Self {
+            line,
+            lines: 1,
+            timestamp: parse_timestamp(raw),
+            level: None,
+            source: None,
+            message: raw.to_string(),
+            fields: BTreeMap::new(),
+            raw: raw.to_string(),
+        }
+    }
+
+    /// Appends a continuation line.
+    pub fn push_line(&mut self, raw: &str) {
+        self.raw.push('\n');
+        self.raw.push_str(raw);
+        self.lines += 1;
+    }
+
+    /// The first line, as written.
+    pub fn head(&self) -> &str {
+        self.raw.split('\n').next().unwrap_or_default()
+    }
+
+    /// The level, or for plain text the classic rule: a line mentioning
+    /// "error" is an error, otherwise one mentioning "warning" is a warning.
+    pub fn severity(&self) -> Option<Level> {
+        if self.level.is_some() {
+            return self.level;
+        }
+        let lower = self.head().to_lowercase();
+        if lower.contains("error") {
+            Some(Level::Error)
+        } else if lower.contains("warning") {
+            Some(Level::Warning)
+        } else {
+            None
+        }
+    }
+}
+
+/// `--format` values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Pick the format matching most of the first [`DETECT_LINES`] lines.
+    #[default]
+    Auto,
+    Plain,
+    JsonLines,
+    Syslog,
+    Log4j,
+}
+
+impl LogFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogFormat::Auto => "auto",
+            LogFormat::Plain => "plain",
+            LogFormat::JsonLines => "json-lines",
+            LogFormat::Syslog => "syslog",
+            LogFormat::Log4j => "log4j",
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "auto" => Ok(LogFormat::Auto),
+            "plain" | "text" => Ok(LogFormat::Plain),
+            "json" | "jsonl" | "json-lines" | "ndjson" => Ok(LogFormat::JsonLines),
+            "syslog" | "rfc5424" => Ok(LogFormat::Syslog),
+            "log4j" | "logback" => Ok(LogFormat::Log4j),
+            other => Err(format!(
+                "unknown log format '{other}' (expected auto, plain, json, syslog or log4j)"
+            )),
+        }
+    }
+}
+
+/// Turns lines into records: user patterns first, then the format.
+#[derive(Debug, Clone)]
+pub struct LogParser {
+    format: LogFormat,
+    patterns: Vec<GrokPattern>,
+}
+
+impl LogParser {
+    /// Resolves [`LogFormat::Auto`] against `sample`, the first lines of the log.
+    pub fn new(format: LogFormat, patterns: Vec<GrokPattern>, sample: &[String]) -> Self {
+        let format = match format {
+            LogFormat::Auto => detect(sample),
+            format => format,
+        };
+        Self { format, patterns }
+    }
+
+    pub fn format(&self) -> LogFormat {
+        self.format
+    }
+
+    pub fn has_patterns(&self) -> bool {
+        !self.patterns.is_empty()
+    }
+
+    pub fn parse(&self, line: u64, raw: &str) -> LogRecord {
+        self.recognize(line, raw)
+            .unwrap_or_else(|| LogRecord::plain(line, raw))
+    }
+
+    /// A record only when a user pattern or the (structured) format matches.
+    pub fn recognize(&self, line: u64, raw: &str) -> Option<LogRecord> {
+        for pattern in &self.patterns {
+            if let Some(fields) = pattern.captures(raw) {
+                return Some(from_fields(line, raw, fields));
+            }
+        }
+        match self.format {
+            LogFormat::Auto | LogFormat::Plain => None,
+            format => parse_as(format, line, raw),
+        }
+    }
+}
+
+/// The structured format most sample lines parse as; plain text otherwise.
+///
+/// Continuation lines (stack frames and the like) are not counted.
+pub fn detect(sample: &[String]) -> LogFormat {
+    let lines: Vec<&String> = sample
+        .iter()
+        .filter(|line| !CONTINUATION.is_match(line))
+        .collect();
+    [LogFormat::JsonLines, LogFormat::Syslog, LogFormat::Log4j]
+        .into_iter()
+        .map(|format| {
+            let matched = lines
+                .iter()
+                .filter(|line| parse_as(format, 0, line).is_some())
+                .count();
+            (format, matched)
+        })
+        .filter(|(_, matched)| *matched * 2 > lines.len())
+        .max_by_key(|(_, matched)| *matched)
+        .map_or(LogFormat::Plain, |(format, _)| format)
+}
+
+/// `None` when `raw` is not in `format`.
+fn parse_as(format: LogFormat, line: u64, raw: &str) -> Option<LogRecord> {
+    match format {
+        LogFormat::Auto | LogFormat::Plain => Some(LogRecord::plain(line, raw)),
+        LogFormat::JsonLines => json_record(line, raw),
+        LogFormat::Syslog => syslog_record(line, raw),
+        LogFormat::Log4j => LOG4J
+            .iter()
+            .find_map(|pattern| pattern.captures(raw))
+            .map(|fields| from_fields(line, raw, fields)),
+    }
+}
+
+fn json_record(line: u64, raw: &str) -> Option<LogRecord> {
+    let trimmed = raw.trim();
+    if !trimmed.starts_with('{') {
+        return None;
+    }
+    let Ok(Value::Object(object)) = serde_json::from_str::<Value>(trimmed) else {
+        return None;
+    };
+    let text = |value: &Value| match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    };
+    let find = |keys: &[&'static str]| {
+        keys.iter()
+            .find_map(|key| object.get(*key).and_then(text).map(|value| (*key, value)))
+    };
+    let timestamp = TIME_FIELDS.iter().find_map(|key| match object.get(*key)? {
+        Value::String(text) => parse_timestamp(text),
+        // Epoch seconds or milliseconds.
+        Value::Number(number) => {
+            let value = number.as_f64()?;
+            let seconds = if value > 1e11 { value / 1000.0 } else { value };
+            Some(Timestamp::from_seconds(seconds as i64))
+        }
+        _ => None,
+    });
+    let level = match find(LEVEL_FIELDS) {
+        Some((_, value)) => match value.parse::<u64>() {
+            Ok(number) => Level::from_number(number),
+            Err(_) => Level::parse(&value),
+        },
+        None => None,
+    };
+    let message = find(MESSAGE_FIELDS);
+    let source = find(SOURCE_FIELDS);
+    let used: Vec<&str> = [message.as_ref(), source.as_ref()]
+        .into_iter()
+        .flatten()
+        .map(|(key, _)| *key)
+        .chain(TIME_FIELDS.iter().copied())
+        .chain(LEVEL_FIELDS.iter().copied())
+        .collect();
+    let fields = object
+        .iter()
+        .filter(|(key, _)| !used.contains(&key.as_str()))
+        .filter_map(|(key, value)| Some((key.clone(), text(value)?)))
+        .collect();
+    Some(LogRecord {
+        line,
+        lines: 1,
+        timestamp,
+        level,
+        source: source.map(|(_, value)| value),
+        message: message.map_or_else(|| trimmed.to_string(), |(_, value)| value),
+        fields,
+        raw: raw.to_string(),
+    })
+}
+
+fn syslog_record(line: u64, raw: &str) -> Option<LogRecord> {
+    let caps = SYSLOG_5424.captures(raw.trim_end())?;
+    let priority: u16 = caps["pri"].parse().ok()?;
+    if priority > 191 {
+        return None;
+    }
+    let nil = |name: &str| {
+        caps.name(name)
+            .map(|value| value.as_str())
+            .filter(|value| *value != "-")
+            .map(str::to_string)
+    };
+    let mut fields = BTreeMap::new();
+    fields.insert("facility".to_string(), (priority / 8).to_string());
+    for name in ["procid", "msgid", "sd"] {
+        if let Some(value) = nil(name) {
+            fields.insert(name.to_string(), value);
+        }
+    }
+    if let Some(host) = nil("host") {
+        fields.insert("host".to_string(), host);
+    }
+    Some(LogRecord {
+        line,
+        lines: 1,
+        timestamp: nil("timestamp").and_then(|value| parse_timestamp(&value)),
+        level: Some(Level::from_syslog((priority % 8) as u8)),
+        source: nil("app").or_else(|| nil("host")),
+        message: caps
+            .name("message")
+            .map_or("", |message| message.as_str())
+            .trim_start_matches('\u{feff}')
+            .to_string(),
+        fields,
+        raw: raw.to_string(),
+    })
+}
+
+/// A record from named captures (user patterns and Log4j layouts).
+fn from_fields(line: u64, raw: &str, captured: Vec<(&str, &str)>) -> LogRecord {
+    let mut record = LogRecord::plain(line, raw);
+    let mut message = None;
+    for (name, value) in captured {
+        if TIME_FIELDS.contains(&name) {
+            record.timestamp = parse_timestamp(value)
+                .or_else(|| parse_timestamp(&format!("[{value}]")))
+                .or(record.timestamp);
+        } else if LEVEL_FIELDS.contains(&name) {
+            record.level = Level::parse(value);
+        } else if MESSAGE_FIELDS.contains(&name) {
+            message = Some(value.to_string());
+        } else if SOURCE_FIELDS.contains(&name) && record.source.is_none() {
+            record.source = Some(value.to_string());
+        } else {
+            record.fields.insert(name.to_string(), value.to_string());
+        }
+    }
+    if let Some(message) = message {
+        record.message = message;
+    }
+    record
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn parses_each_format_into_records() {
+        let json = parse_as(
+            LogFormat::JsonLines,
+            1,
+            r#"{"level":"WARN","@timestamp":"2026-01-07T08:09:00Z","logger":"ca.uhn.fhir","message":"Slow search","took_ms":2300}"#,
+        )
+        .expect("json");
+        assert_eq!(json.level, Some(Level::Warning));
+        assert_eq!(json.message, "Slow search");
+        assert_eq!(json.source.as_deref(), Some("ca.uhn.fhir"));
+        assert_eq!(json.fields.get("took_ms").map(String::as_str), Some("2300"));
+        assert_eq!(
+            json.timestamp.map(|t| t.to_string()).as_deref(),
+            Some("2026-01-07 08:09:00")
+        );
+
+        let syslog = parse_as(
+            LogFormat::Syslog,
+            2,
+            r#"<187>1 2026-01-07T08:05:40.003Z lb01 haproxy 811 - [meta sequenceId="29"] backend fhir has no server available"#,
+        )
+        .expect("syslog");
+        assert_eq!(syslog.level, Some(Level::Error));
+        assert_eq!(syslog.source.as_deref(), Some("haproxy"));
+        assert_eq!(syslog.message, "backend fhir has no server available");
+        assert_eq!(
+            syslog.fields.get("facility").map(String::as_str),
+            Some("23")
+        );
+        assert!(parse_as(LogFormat::Syslog, 2, "<999>1 - - - - - -").is_none());
+
+        let log4j = parse_as(
+            LogFormat::Log4j,
+            3,
+            "2026-01-07 08:06:45,120 ERROR [audit-writer] org.example.AuditLog - Timeout while writing audit log",
+        )
+        .expect("log4j");
+        assert_eq!(log4j.level, Some(Level::Error));
+        assert_eq!(log4j.source.as_deref(), Some("org.example.AuditLog"));
+        assert_eq!(
+            log4j.fields.get("thread").map(String::as_str),
+            Some("audit-writer")
+        );
+
+        // Plain text keeps the substring rule.
+        let plain = LogRecord::plain(4, "INFO 2026-01-07 08:06:10 Retrying after error");
+        assert_eq!(plain.severity(), Some(Level::Error));
+        assert_eq!(log4j.severity(), Some(Level::Error));
+    }
+
+    #[test]
+    fn reads_numeric_json_levels() {
+        let level = |raw: &str| parse_as(LogFormat::JsonLines, 1, raw).expect("json").level;
+        // pino / bunyan
+        assert_eq!(
+            level(
+                r#"{"level":50,"time":1767773340000,"pid":7,"hostname":"fhir01","msg":"HAPI-0389: Failed to call access method"}"#
+            ),
+            Some(Level::Error)
+        );
+        assert_eq!(level(r#"{"level":60,"msg":"a"}"#), Some(Level::Error));
+        assert_eq!(level(r#"{"level":40,"msg":"a"}"#), Some(Level::Warning));
+        assert_eq!(level(r#"{"level":30,"msg":"a"}"#), Some(Level::Info));
+        assert_eq!(level(r#"{"level":10,"msg":"a"}"#), Some(Level::Debug));
+        // syslog severities
+        assert_eq!(level(r#"{"severity":3,"msg":"a"}"#), Some(Level::Error));
+        assert_eq!(level(r#"{"severity":7,"msg":"a"}"#), Some(Level::Debug));
+        assert_eq!(level(r#"{"level":35,"msg":"a"}"#), None);
+    }
+
+    #[test]
+    fn detects_the_dominant_format() {
+        let demo = lines(include_str!("../../demo-healthit.log"));
+        assert_eq!(detect(&demo), LogFormat::Plain);
+        let json = lines(
+            "{\"level\":\"info\",\"msg\":\"a\"}\n{\"level\":\"error\",\"msg\":\"b\"}\nplain trailer",
+        );
+        assert_eq!(detect(&json), LogFormat::JsonLines);
+        let syslog = lines(
+            "<14>1 2026-01-07T08:00:00Z h app - - - started\n<11>1 2026-01-07T08:00:01Z h app - - - failed",
+        );
+        assert_eq!(detect(&syslog), LogFormat::Syslog);
+
+        let parser = LogParser::new(
+            LogFormat::Auto,
+            vec![GrokPattern::compile(r"%{LOGLEVEL:level}\|%{GREEDYDATA:message}").unwrap()],
+            &demo,
+        );
+        let record = parser.parse(9, "E|Interface engine stopped");
+        assert_eq!(record.level, None);
+        let record = parser.parse(9, "ERROR|Interface engine stopped");
+        assert_eq!(record.level, Some(Level::Error));
+        assert_eq!(record.message, "Interface engine stopped");
+        assert_eq!("ndjson".parse(), Ok(LogFormat::JsonLines));
+    }
+}
@@ -0,0 +1,129 @@
+//! Grok-like line patterns: regexes with `%{NAME}` / `%{NAME:field}` macros.
+//!
+//! ```text
+//! %{TIMESTAMP_ISO8601:timestamp} %{LOGLEVEL:level} \[%{DATA:thread}\] %{GREEDYDATA:message}
+//! ```
+//!
+//! Captures named `timestamp`, `level`, `message` and `source` (or their
+//! aliases, see [`crate::scan::LogRecord`]) fill the record; the rest are kept
+//! as extra fields.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+static MACRO: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"%\{([A-Z0-9_]+)(?::([A-Za-z_][A-Za-z0-9_]*))?\}").unwrap());
+
+/// The macros a pattern can use.
+const DEFINITIONS: &[(&str, &str)] = &[
+    (
+        "TIMESTAMP_ISO8601",
+        r"\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}(?::\d{2}(?:[.,]\d+)?)?(?:Z|[+-]\d{2}:?\d{2})?",
+    ),
+    (
+        "HTTPDATE",
+        r"\d{2}/[A-Z][a-z]{2}/\d{4}:\d{2}:\d{2}:\d{2} [+-]\d{4}",
+    ),
+    (
+        "LOGLEVEL",
+        r"(?i:trace|debug|info|notice|warn(?:ing)?|err(?:or)?|severe|crit(?:ical)?|alert|fatal|emerg(?:ency)?)",
+    ),
+    ("WORD", r"\w+"),
+    ("NOTSPACE", r"\S+"),
+    ("SPACE", r"\s*"),
+    ("DATA", r".*?"),
+    ("GREEDYDATA", r".*"),
+    ("INT", r"[+-]?\d+"),
+    ("NUMBER", r"[+-]?\d+(?:\.\d+)?"),
+    ("IP", r"\d{1,3}(?:\.\d{1,3}){3}"),
+    ("HOSTNAME", r"[A-Za-z0-9][A-Za-z0-9.-]*"),
+    (
+        "UUID",
+        r"[0-9A-Fa-f]{8}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{12}",
+    ),
+    ("JAVACLASS", r"[A-Za-z0-9_$.]+"),
+    ("QUOTEDSTRING", r#""(?:[^"\\]|\\.)*""#),
+];
+
+/// A compiled pattern; it must match the whole line.
+#[derive(Debug, Clone)]
+pub struct GrokPattern {
+    source: String,
+    regex: Regex,
+}
+
+impl GrokPattern {
+    pub fn compile(pattern: &str) -> Result<Self, String> {
+        let mut expanded = String::from("^");
+        let mut cursor = 0;
+        for caps in MACRO.captures_iter(pattern) {
+            let whole = caps.get(0).unwrap();
+            expanded.push_str(&pattern[cursor..whole.start()]);
+            let name = &caps[1];
+            let definition = DEFINITIONS
+                .iter()
+                .find(|(known, _)| *known == name)
+                .map(|(_, definition)| *definition)
+                .ok_or_else(|| format!("unknown pattern %{{{name}}} in '{pattern}'"))?;
+            match caps.get(2) {
+                Some(field) => expanded.push_str(&format!("(?P<{}>{definition})", field.as_str())),
+                None => expanded.push_str(&format!("(?:{definition})")),
+            }
+            cursor = whole.end();
+        }
+        expanded.push_str(&pattern[cursor..]);
+        expanded.push('$');
+        let regex =
+            Regex::new(&expanded).map_err(|err| format!("invalid pattern '{pattern}': {err}"))?;
+        Ok(Self {
+            source: pattern.to_string(),
+            regex,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// `(field, value)` pairs when the whole line matches.
+    pub fn captures<'a>(&self, line: &'a str) -> Option<Vec<(&str, &'a str)>> {
+        let caps = self.regex.captures(line)?;
+        Some(
+            self.regex
+                .capture_names()
+                .flatten()
+                .filter_map(|name| Some((name, caps.name(name)?.as_str())))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_macros_into_named_groups() {
+        let pattern = GrokPattern::compile(
+            r"%{TIMESTAMP_ISO8601:timestamp} \[%{DATA:thread}\] %{LOGLEVEL:level} %{JAVACLASS:logger} - %{GREEDYDATA:message}",
+        )
+        .expect("pattern");
+        let fields = pattern
+            .captures("2026-01-07 08:05:40,123 [main] ERROR c.e.Db - Connection refused")
+            .expect("match");
+        assert_eq!(
+            fields,
+            vec![
+                ("timestamp", "2026-01-07 08:05:40,123"),
+                ("thread", "main"),
+                ("level", "ERROR"),
+                ("logger", "c.e.Db"),
+                ("message", "Connection refused"),
+            ]
+        );
+        assert!(pattern.captures("INFO plain text").is_none());
+        assert!(GrokPattern::compile("%{NOPE:x}").is_err());
+        assert!(GrokPattern::compile("%{WORD:a} %{WORD:a}").is_err());
+    }
+}
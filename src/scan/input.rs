@@ -0,0 +1,167 @@
+//! Log input: gzip/zstd decompression and rotation sets given as a
+//! directory or glob.
+
+use std::cmp::Reverse;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use flate2::read::MultiGzDecoder;
+
+use crate::scan::format::DETECT_LINES;
+use crate::scan::timeline::{Timestamp, parse_timestamp};
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Opens a log, decompressing gzip and zstd files (recognized by their
+/// magic bytes, whatever the extension).
+pub fn open_log(path: &Path) -> io::Result<Box<dyn BufRead>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let head = reader.fill_buf()?;
+    if head.starts_with(GZIP_MAGIC) {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+    } else if head.starts_with(ZSTD_MAGIC) {
+        let decoder = zstd::Decoder::with_buffer(reader)?;
+        Ok(Box::new(BufReader::new(decoder)))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+/// The files `input` names, oldest first.
+///
+/// `input` is a file, a directory (its non-hidden files) or a glob such as
+/// `logs/app.log*`. Files are ordered by their first timestamp when every one
+/// has one, otherwise by rotation number: `app.log.2.gz`, `app.log.1`,
+/// `app.log`.
+pub fn resolve_inputs(input: &str) -> io::Result<Vec<PathBuf>> {
+    let path = Path::new(input);
+    let mut paths = if path.is_dir() {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            if !hidden && entry.file_type()?.is_file() {
+                paths.push(entry.path());
+            }
+        }
+        paths
+    } else if !path.exists() && input.contains(['*', '?', '[']) {
+        let invalid = |err: String| io::Error::new(io::ErrorKind::InvalidInput, err);
+        glob::glob(input)
+            .map_err(|err| invalid(format!("invalid glob '{input}': {err}")))?
+            .filter_map(|entry| entry.ok())
+            .filter(|path| path.is_file())
+            .collect()
+    } else {
+        return Ok(vec![path.to_path_buf()]);
+    };
+    if paths.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no log files match '{input}'"),
+        ));
+    }
+
+    let firsts = paths
+        .iter()
+        .map(|path| first_timestamp(path))
+        .collect::<io::Result<Vec<_>>>()?;
+    let mut keyed: Vec<_> = paths.drain(..).zip(firsts).collect();
+    if keyed.iter().all(|(_, first)| first.is_some()) {
+        keyed.sort_by_cached_key(|(path, first)| (*first, Reverse(rotation(path)), path.clone()));
+    } else {
+        keyed.sort_by_cached_key(|(path, _)| (Reverse(rotation(path)), path.clone()));
+    }
+    Ok(keyed.into_iter().map(|(path, _)| path).collect())
+}
+
+/// `3` for `app.log.3` and `app.log.3.gz`; `0` for the live file.
+fn rotation(path: &Path) -> u32 {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let name = name
+        .strip_suffix(".gz")
+        .or_else(|| name.strip_suffix(".zst"))
+        .unwrap_or(&name);
+    name.rsplit_once('.')
+        .and_then(|(_, suffix)| suffix.parse().ok())
+        .unwrap_or(0)
+}
+
+fn first_timestamp(path: &Path) -> io::Result<Option<Timestamp>> {
+    let mut sample = Vec::new();
+    open_log(path)?.take(64 * 1024).read_to_end(&mut sample)?;
+    // Lossy, so a multi-byte character cut at the limit (or a stray invalid
+    // byte) does not lose the whole sample.
+    let text = String::from_utf8_lossy(&sample);
+    Ok(text.lines().take(DETECT_LINES).find_map(parse_timestamp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn reads_compressed_rotations_oldest_first() {
+        let dir = std::env::temp_dir().join(format!("clinlogix-input-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("app.log"), "no timestamp\n").unwrap();
+        fs::write(dir.join("app.log.1"), "INFO 2026-01-07 08:00:00 b\n").unwrap();
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(b"INFO 2026-01-06 08:00:00 a\n").unwrap();
+        fs::write(dir.join("app.log.2.gz"), gzip.finish().unwrap()).unwrap();
+        fs::write(
+            dir.join("app.log.10.zst"),
+            zstd::encode_all(&b"INFO 2026-01-05 08:00:00 z\n"[..], 0).unwrap(),
+        )
+        .unwrap();
+        fs::write(dir.join(".hidden"), "skip").unwrap();
+
+        let names = |paths: Vec<PathBuf>| -> Vec<String> {
+            paths
+                .iter()
+                .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+                .collect()
+        };
+        let all = resolve_inputs(dir.to_str().unwrap()).unwrap();
+        assert_eq!(
+            names(all),
+            ["app.log.10.zst", "app.log.2.gz", "app.log.1", "app.log"]
+        );
+        let rotated = resolve_inputs(&format!("{}/app.log.*", dir.display())).unwrap();
+        assert_eq!(
+            names(rotated),
+            ["app.log.10.zst", "app.log.2.gz", "app.log.1"]
+        );
+
+        let mut text = String::new();
+        open_log(&dir.join("app.log.2.gz"))
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "INFO 2026-01-06 08:00:00 a\n");
+        assert!(resolve_inputs(&format!("{}/nothing*", dir.display())).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn first_timestamp_survives_a_character_cut_at_the_sample_limit() {
+        let path =
+            std::env::temp_dir().join(format!("clinlogix-sample-{}.log", std::process::id()));
+        let mut text = b"INFO 2026-01-04 08:00:00 Patient M\xc3\xbcller admitted\n".to_vec();
+        // Two-byte characters, starting one byte off so the limit splits one.
+        text.push(b'x');
+        while text.len() < 64 * 1024 + 8 {
+            text.extend_from_slice("\u{fc}".as_bytes());
+        }
+        fs::write(&path, &text).unwrap();
+        let first = first_timestamp(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(
+            first.map(|t| t.to_string()).as_deref(),
+            Some("2026-01-04 08:00:00")
+        );
+    }
+}
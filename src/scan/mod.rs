@@ -0,0 +1,785 @@
+mod correlate;
+mod extract;
+mod follow;
+mod format;
+mod grok;
+mod input;
+mod phi;
+mod records;
+mod redact;
+mod rules;
+mod signature;
+mod timeline;
+
+use std::io::{self, BufRead, Read};
+use std::path::Path;
+use std::rc::Rc;
+
+use regex::Regex;
+use serde_json::json;
+
+use crate::error::Error;
+use crate::validate::{ValidateError, ValidateOptions, ValidationReport, is_failure};
+
+#[allow(unused_imports)]
+pub use correlate::{
+    CorrelateOptions, ReferencedResource, References, ResourceSource, references, run_correlate,
+};
+#[allow(unused_imports)]
+pub use extract::{Payload, PayloadKind, extract_payloads, write_payloads};
+#[allow(unused_imports)]
+pub use follow::{AlertAction, FollowOptions, run_follow};
+#[allow(unused_imports)]
+pub use format::{DETECT_LINES, Level, LogFormat, LogParser, LogRecord, detect};
+#[allow(unused_imports)]
+pub use grok::GrokPattern;
+#[allow(unused_imports)]
+pub use input::{open_log, resolve_inputs};
+#[allow(unused_imports)]
+pub use phi::{DEFAULT_MRN_PATTERN, PhiCategory, PhiDetector, PhiFinding};
+#[allow(unused_imports)]
+pub use records::RecordAssembler;
+#[allow(unused_imports)]
+pub use redact::{
+    RedactMode, RedactOptions, Redactor, SECRET_ENV, load_or_create_secret, random_secret,
+    run_redact,
+};
+#[allow(unused_imports)]
+pub use rules::{CategoryRule, CategoryRules, Classified};
+#[allow(unused_imports)]
+pub use signature::{Seen, SignatureStats, Signatures, signature};
+#[allow(unused_imports)]
+pub use timeline::{Bucket, Histogram, TimeBound, TimeWindow, Timestamp, parse_timestamp};
+
+/// Settings for a plain `scan`.
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+    /// Print only error lines.
+    pub errors_only: bool,
+    pub json: bool,
+    /// Report PHI found by this detector (`--phi`).
+    pub phi: Option<PhiDetector>,
+    /// Only lines stamped at or after this time (`--since`).
+    pub since: Option<TimeBound>,
+    /// Only lines stamped before this time (`--until`).
+    pub until: Option<TimeBound>,
+    /// Count errors and warnings per minute or hour (`--histogram`).
+    pub histogram: Option<Bucket>,
+    /// Report this many of the most frequent error and warning signatures
+    /// (`--top`); `0` leaves them out.
+    pub top: usize,
+    /// How lines are parsed (`--format`).
+    pub format: LogFormat,
+    /// User patterns tried on every line before the format (`--pattern`).
+    pub patterns: Vec<GrokPattern>,
+    /// Operational categories records are sorted into (`--rules`).
+    pub rules: CategoryRules,
+    /// Lines matching this start a record; others continue the one before
+    /// (`--record-start`). By default continuation lines are recognized by
+    /// their shape.
+    pub record_start: Option<Regex>,
+    /// Note the FHIR resources named in error and warning records
+    /// (`--correlate`).
+    pub correlate: bool,
+}
+
+pub fn run_scan(logfile: &str, errors_only: bool, json: bool) -> io::Result<()> {
+    let options = ScanOptions {
+        errors_only,
+        json,
+        ..Default::default()
+    };
+    run_scan_with(logfile, &options)
+}
+
+/// Scans `input`: a log file (plain, gzip or zstd), a directory or a glob.
+/// Several files are read oldest first into one summary with a per-file
+/// breakdown.
+pub fn run_scan_with(input: &str, options: &ScanOptions) -> io::Result<()> {
+    let summary = Summary::collect(input, options)?;
+    if options.errors_only {
+        return Ok(());
+    }
+    if options.json {
+        println!("{}", summary.to_json(options));
+    } else {
+        print!("{}", summary.format(options));
+    }
+    Ok(())
+}
+
+/// The `scan --json` summary of `input`, for reports built on top of a scan.
+pub fn scan_summary(input: &str, options: &ScanOptions) -> io::Result<serde_json::Value> {
+    Ok(Summary::collect(input, options)?.to_json(options))
+}
+
+/// Everything a scan found, ready to print.
+struct Summary<'a> {
+    input: &'a str,
+    tally: Tally,
+    files: Vec<FileSummary>,
+}
+
+impl<'a> Summary<'a> {
+    fn collect(input: &'a str, options: &ScanOptions) -> io::Result<Self> {
+        let paths = resolve_inputs(input)?;
+        let multiple = paths.len() > 1;
+        let mut tally = Tally::new(options);
+        let mut files = Vec::with_capacity(paths.len());
+
+        for path in &paths {
+            let name: Rc<str> = path.display().to_string().into();
+            let mut lines = open_log(path)?.lines();
+            let sample = lines
+                .by_ref()
+                .take(DETECT_LINES)
+                .collect::<io::Result<Vec<String>>>()?;
+            let parser = LogParser::new(options.format, options.patterns.clone(), &sample);
+            let mut assembler = RecordAssembler::new(&parser, options.record_start.clone());
+            let file = multiple.then(|| name.clone());
+            let before = tally.counts;
+            tally.start_file(file);
+
+            for (index, line) in sample.into_iter().map(Ok).chain(lines).enumerate() {
+                if let Some(record) = assembler.push(index as u64 + 1, &line?) {
+                    tally.add(record, options);
+                }
+            }
+            if let Some(record) = assembler.finish() {
+                tally.add(record, options);
+            }
+            files.push(FileSummary {
+                name,
+                format: parser.format(),
+                patterns: parser.has_patterns(),
+                counts: tally.counts.since(before),
+            });
+        }
+        Ok(Self {
+            input,
+            tally,
+            files,
+        })
+    }
+
+    fn multiple(&self) -> bool {
+        self.files.len() > 1
+    }
+
+    /// One format for a single file or a set in the same format.
+    fn format_of_files(&self) -> Option<LogFormat> {
+        let first = self.files.first()?.format;
+        self.files
+            .iter()
+            .all(|file| file.format == first)
+            .then_some(first)
+    }
+
+    fn to_json(&self, options: &ScanOptions) -> serde_json::Value {
+        let tally = &self.tally;
+        let counts = tally.counts;
+        let mut summary = json!({
+            "file": self.input,
+            "format": self.format_of_files().map_or("mixed", LogFormat::as_str),
+            "total_lines": counts.lines,
+            "records": counts.records,
+            "errors": counts.errors,
+            "warnings": counts.warnings,
+        });
+        if self.multiple() {
+            summary["files"] = self
+                .files
+                .iter()
+                .map(|file| {
+                    json!({
+                        "file": file.name.as_ref(),
+                        "format": file.format.as_str(),
+                        "total_lines": file.counts.lines,
+                        "records": file.counts.records,
+                        "errors": file.counts.errors,
+                        "warnings": file.counts.warnings,
+                    })
+                })
+                .collect();
+        }
+        if tally.window.is_active() {
+            let (since, until) = tally.window.bounds();
+            summary["since"] = json!(since.map(Timestamp::iso));
+            summary["until"] = json!(until.map(Timestamp::iso));
+        }
+        summary["categories"] = categories_json(&tally.categories, options.top);
+        if options.top > 0 {
+            summary["signatures"] = json!({
+                "errors": tally.error_signatures.to_json(options.top),
+                "warnings": tally.warning_signatures.to_json(options.top),
+            });
+        }
+        if let Some(histogram) = &tally.histogram {
+            summary["histogram"] = histogram.to_json();
+        }
+        if options.phi.is_some() {
+            summary["phi"] = phi_json(&tally.phi_lines);
+        }
+        summary
+    }
+
+    fn format(&self, options: &ScanOptions) -> String {
+        let tally = &self.tally;
+        let counts = tally.counts;
+        let patterns = self.files.iter().any(|file| file.patterns);
+        let mut output = String::new();
+        output.push_str("ClinLogix Report\n");
+        output.push_str("----------------\n");
+        output.push_str(&format!("File: {}\n", self.input));
+        match self.format_of_files() {
+            Some(LogFormat::Plain) if !patterns => {}
+            Some(format) => {
+                output.push_str(&format!("Format: {}\n", describe_format(format, patterns)))
+            }
+            None => output.push_str("Format: mixed\n"),
+        }
+        if tally.window.is_active() {
+            let (since, until) = tally.window.bounds();
+            let show = |bound: Option<Timestamp>| bound.map_or("-".to_string(), |t| t.to_string());
+            output.push_str(&format!("Time range: {} to {}\n", show(since), show(until)));
+        }
+        output.push_str(&format!("Total lines: {}\n", counts.lines));
+        output.push_str(&format!("Records: {}\n", counts.records));
+        output.push_str(&format!("Errors: {}\n", counts.errors));
+        output.push_str(&format!("Warnings: {}\n", counts.warnings));
+        if self.multiple() {
+            output.push_str(&format!("Files ({}, oldest first):\n", self.files.len()));
+            for file in &self.files {
+                output.push_str(&format!(
+                    "  {}: {} lines, {} records, {} errors, {} warnings ({})\n",
+                    file.name,
+                    file.counts.lines,
+                    file.counts.records,
+                    file.counts.errors,
+                    file.counts.warnings,
+                    describe_format(file.format, file.patterns)
+                ));
+            }
+        }
+        output.push_str(&format_categories(&tally.categories, options.top));
+        output.push_str(&tally.error_signatures.format("Top errors", options.top));
+        output.push_str(&tally.warning_signatures.format("Top warnings", options.top));
+        if let Some(histogram) = &tally.histogram {
+            output.push_str(&histogram.format());
+        }
+        if options.phi.is_some() {
+            output.push_str(&format_phi(&tally.phi_lines));
+        }
+        output
+    }
+}
+
+/// Line, record, error and warning counts.
+#[derive(Debug, Clone, Copy, Default)]
+struct Counts {
+    lines: u64,
+    records: u64,
+    errors: u64,
+    warnings: u64,
+}
+
+impl Counts {
+    /// What was added after `before` was taken.
+    fn since(self, before: Counts) -> Counts {
+        Counts {
+            lines: self.lines - before.lines,
+            records: self.records - before.records,
+            errors: self.errors - before.errors,
+            warnings: self.warnings - before.warnings,
+        }
+    }
+}
+
+/// One file's share of a multi-file scan.
+struct FileSummary {
+    name: Rc<str>,
+    format: LogFormat,
+    /// User patterns were given (`--pattern`).
+    patterns: bool,
+    counts: Counts,
+}
+
+/// Records of one operational category.
+struct CategoryTally {
+    name: String,
+    /// The category's severity; its records keep their own levels without one.
+    severity: Option<Level>,
+    count: u64,
+    signatures: Signatures,
+}
+
+/// Categories by count, then name.
+fn ranked(categories: &[CategoryTally]) -> Vec<&CategoryTally> {
+    let mut ranked: Vec<&CategoryTally> = categories.iter().collect();
+    ranked.sort_by(|left, right| {
+        right
+            .count
+            .cmp(&left.count)
+            .then_with(|| left.name.cmp(&right.name))
+    });
+    ranked
+}
+
+fn format_categories(categories: &[CategoryTally], top: usize) -> String {
+    if categories.is_empty() {
+        return String::new();
+    }
+    let mut output = String::from("Categories:\n");
+    for category in ranked(categories) {
+        let severity = category.severity.map_or(String::new(), |severity| {
+            format!(" ({})", severity.as_str())
+        });
+        output.push_str(&format!(
+            "  {}: {}{severity}\n",
+            category.name, category.count
+        ));
+        for stats in category.signatures.top(top) {
+            output.push_str(&format!("    [{}] {}\n", stats.count, stats.signature));
+        }
+    }
+    output
+}
+
+fn categories_json(categories: &[CategoryTally], top: usize) -> serde_json::Value {
+    ranked(categories)
+        .into_iter()
+        .map(|category| {
+            let mut item = json!({
+                "name": category.name,
+                "severity": category.severity.map(Level::as_str),
+                "count": category.count,
+            });
+            if top > 0 {
+                item["signatures"] = category.signatures.to_json(top);
+            }
+            item
+        })
+        .collect()
+}
+
+/// A line with PHI findings.
+type PhiLine = (Seen, String, Vec<PhiFinding>);
+
+/// Counts for one scan, fed a record at a time.
+struct Tally {
+    /// Counts inside the time window.
+    counts: Counts,
+    phi_lines: Vec<PhiLine>,
+    window: TimeWindow,
+    histogram: Option<Histogram>,
+    error_signatures: Signatures,
+    warning_signatures: Signatures,
+    /// Categories seen so far, in the order first seen.
+    categories: Vec<CategoryTally>,
+    /// With `--phi`, signatures and examples never show the values it finds.
+    redactor: Option<Redactor>,
+    /// The file being read, in a multi-file scan.
+    file: Option<Rc<str>>,
+    /// Records without a timestamp belong to the last one seen in their file.
+    timestamp: Option<Timestamp>,
+    /// Resources named in errors and warnings, with `--correlate`.
+    references: Option<References>,
+}
+
+impl Tally {
+    fn new(options: &ScanOptions) -> Self {
+        Self {
+            counts: Counts::default(),
+            phi_lines: Vec::new(),
+            window: TimeWindow::new(options.since, options.until),
+            histogram: options.histogram.map(Histogram::new),
+            error_signatures: Signatures::default(),
+            warning_signatures: Signatures::default(),
+            categories: Vec::new(),
+            redactor: options.phi.clone().map(Redactor::placeholders),
+            file: None,
+            timestamp: None,
+            references: options.correlate.then(References::default),
+        }
+    }
+
+    fn start_file(&mut self, file: Option<Rc<str>>) {
+        self.file = file;
+        self.timestamp = None;
+    }
+
+    fn seen(&self, line: u64) -> Seen {
+        Seen {
+            file: self.file.clone(),
+            line,
+            time: self.timestamp,
+        }
+    }
+
+    fn add(&mut self, record: LogRecord, options: &ScanOptions) {
+        self.timestamp = record.timestamp.or(self.timestamp);
+        if !self.window.contains(self.timestamp) {
+            return;
+        }
+        self.counts.lines += record.lines;
+        self.counts.records += 1;
+
+        if let Some(detector) = &options.phi {
+            for (offset, line) in record.raw.split('\n').enumerate() {
+                let findings = detector.detect(line);
+                if !findings.is_empty() {
+                    let seen = self.seen(record.line + offset as u64);
+                    self.phi_lines.push((seen, line.to_string(), findings));
+                }
+            }
+        }
+
+        let classified = options.rules.classify(&record);
+        let (errors, warnings) = match classified.level {
+            Some(Level::Error) => {
+                if options.errors_only {
+                    match &self.file {
+                        Some(file) => record
+                            .raw
+                            .split('\n')
+                            .for_each(|line| println!("{file}:{line}")),
+                        None => println!("{}", record.raw),
+                    }
+                }
+                (1, 0)
+            }
+            Some(Level::Warning) => (0, 1),
+            _ => (0, 0),
+        };
+        self.counts.errors += errors;
+        self.counts.warnings += warnings;
+        let category = classified.category.map(|name| {
+            let index = match self.categories.iter().position(|tally| tally.name == name) {
+                Some(index) => index,
+                None => {
+                    self.categories.push(CategoryTally {
+                        name: name.to_string(),
+                        severity: classified.severity,
+                        count: 0,
+                        signatures: Signatures::default(),
+                    });
+                    self.categories.len() - 1
+                }
+            };
+            self.categories[index].count += 1;
+            index
+        });
+        if let Some(level @ (Level::Error | Level::Warning)) = classified.level
+            && self.references.is_some()
+        {
+            let seen = self.seen(record.line);
+            if let Some(references) = &mut self.references {
+                references.add(&record, seen, level, classified.category);
+            }
+        }
+        if options.top > 0 && (errors + warnings > 0 || category.is_some()) {
+            let seen = self.seen(record.line);
+            let (message, example) = match &self.redactor {
+                Some(redactor) => (
+                    redactor.redact_line(&record.message).0,
+                    redactor.redact_line(record.head()).0,
+                ),
+                None => (record.message.clone(), record.head().to_string()),
+            };
+            if let Some(index) = category {
+                self.categories[index]
+                    .signatures
+                    .add(&message, &example, seen.clone());
+            }
+            if errors > 0 {
+                self.error_signatures.add(&message, &example, seen);
+            } else if warnings > 0 {
+                self.warning_signatures.add(&message, &example, seen);
+            }
+        }
+        if let (Some(histogram), Some(timestamp)) = (&mut self.histogram, self.timestamp) {
+            histogram.add(timestamp, errors, warnings);
+        }
+    }
+}
+
+fn describe_format(format: LogFormat, patterns: bool) -> String {
+    if patterns {
+        format!("{format} with custom patterns")
+    } else {
+        format.to_string()
+    }
+}
+
+/// Counts per category, in [`PhiCategory::ALL`] order, skipping empty ones.
+fn phi_counts(lines: &[PhiLine]) -> Vec<(PhiCategory, usize)> {
+    PhiCategory::ALL
+        .iter()
+        .map(|category| {
+            let count = lines
+                .iter()
+                .flat_map(|(_, _, findings)| findings)
+                .filter(|finding| finding.category == *category)
+                .count();
+            (*category, count)
+        })
+        .filter(|(_, count)| *count > 0)
+        .collect()
+}
+
+/// 1-based character column of a finding, so multi-byte text lines up in editors.
+fn column(line: &str, finding: &PhiFinding) -> usize {
+    line[..finding.start].chars().count() + 1
+}
+
+fn format_phi(lines: &[PhiLine]) -> String {
+    let total: usize = lines.iter().map(|(_, _, findings)| findings.len()).sum();
+    let mut output = format!("PHI findings: {total} on {} lines\n", lines.len());
+    for (category, count) in phi_counts(lines) {
+        output.push_str(&format!("  {}: {count}\n", category.as_str()));
+    }
+    for (seen, line, findings) in lines {
+        let described: Vec<String> = findings
+            .iter()
+            .map(|finding| {
+                format!(
+                    "{} (column {})",
+                    finding.category.as_str(),
+                    column(line, finding)
+                )
+            })
+            .collect();
+        output.push_str(&format!(
+            "  {}: {}\n",
+            seen.location(),
+            described.join(", ")
+        ));
+    }
+    output
+}
+
+fn phi_json(lines: &[PhiLine]) -> serde_json::Value {
+    let by_category: serde_json::Map<String, serde_json::Value> = phi_counts(lines)
+        .into_iter()
+        .map(|(category, count)| (category.as_str().to_string(), json!(count)))
+        .collect();
+    let findings: Vec<_> = lines
+        .iter()
+        .flat_map(|(seen, line, findings)| {
+            findings.iter().map(move |finding| {
+                let mut item = json!({
+                    "line": seen.line,
+                    "column": column(line, finding),
+                    "length": line[finding.start..finding.end].chars().count(),
+                    "category": finding.category.as_str(),
+                });
+                if let Some(file) = &seen.file {
+                    item["file"] = json!(file.as_ref());
+                }
+                item
+            })
+        })
+        .collect();
+    json!({
+        "total": findings.len(),
+        "lines": lines.len(),
+        "by_category": by_category,
+        "findings": findings,
+    })
+}
+
+/// Settings for `scan --extract`.
+#[derive(Debug, Clone)]
+pub struct ExtractOptions {
+    /// Directory the extracted messages and resources are written to.
+    pub dir: String,
+    /// Validate each payload: HL7 v2 locally, FHIR against `base_url`.
+    pub validate: bool,
+    pub base_url: String,
+    pub json: bool,
+}
+
+/// Outcome of validating one extracted payload.
+enum Verdict {
+    Pass(ValidationReport),
+    Fail(ValidationReport),
+    Error(ValidateError),
+}
+
+impl Verdict {
+    fn describe(&self) -> String {
+        match self {
+            Verdict::Pass(report) | Verdict::Fail(report) => format!(
+                "{} ({} errors, {} warnings)",
+                self.status().to_uppercase(),
+                report.error_count,
+                report.warning_count
+            ),
+            Verdict::Error(err) => format!("ERROR ({err})"),
+        }
+    }
+
+    fn status(&self) -> &'static str {
+        match self {
+            Verdict::Pass(_) => "pass",
+            Verdict::Fail(_) => "fail",
+            Verdict::Error(_) => "error",
+        }
+    }
+
+    fn from_result(result: Result<ValidationReport, ValidateError>) -> Self {
+        match result {
+            Ok(report) if is_failure(&report) => Verdict::Fail(report),
+            Ok(report) => Verdict::Pass(report),
+            Err(err) => Verdict::Error(err),
+        }
+    }
+
+    /// How a run that validated `verdicts` ends: with the most serious error
+    /// (a transport error over bad input), else `Failed(kind)` when any failed.
+    fn outcome(
+        verdicts: impl IntoIterator<Item = Verdict>,
+        kind: &'static str,
+    ) -> Result<(), ValidateError> {
+        let mut failed = false;
+        let mut error: Option<ValidateError> = None;
+        for verdict in verdicts {
+            match verdict {
+                Verdict::Pass(_) => {}
+                Verdict::Fail(_) => failed = true,
+                Verdict::Error(err) => {
+                    if error
+                        .as_ref()
+                        .is_none_or(|worst| err.exit_code() > worst.exit_code())
+                    {
+                        error = Some(err);
+                    }
+                }
+            }
+        }
+        match error {
+            Some(err) => Err(err),
+            None if failed => Err(ValidateError::Failed(kind)),
+            None => Ok(()),
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        match self {
+            Verdict::Pass(report) | Verdict::Fail(report) => json!({
+                "status": self.status(),
+                "errors": report.error_count,
+                "warnings": report.warning_count,
+            }),
+            Verdict::Error(err) => json!({"status": self.status(), "message": err.to_string()}),
+        }
+    }
+}
+
+/// `scan --extract`: writes every HL7 v2 message and FHIR resource in the log to
+/// its own file and optionally validates each one.
+pub async fn run_extract(logfile: &str, options: &ExtractOptions) -> Result<(), Error> {
+    let paths = resolve_inputs(logfile).map_err(|err| Error::Input(format!("{logfile}: {err}")))?;
+    let multiple = paths.len() > 1;
+    let mut payloads = Vec::new();
+    // The file each payload came from, in a multi-file extraction.
+    let mut sources = Vec::new();
+    for path in &paths {
+        let mut bytes = Vec::new();
+        open_log(path)
+            .and_then(|mut reader| reader.read_to_end(&mut bytes))
+            .map_err(|err| Error::Input(format!("{}: {err}", path.display())))?;
+        let found = extract_payloads(&String::from_utf8_lossy(&bytes));
+        let source = multiple.then(|| path.display().to_string());
+        sources.extend(std::iter::repeat_n(source, found.len()));
+        payloads.extend(found);
+    }
+    let written = write_payloads(&payloads, Path::new(&options.dir))
+        .map_err(|err| Error::Input(format!("{}: {err}", options.dir)))?;
+
+    let mut verdicts = Vec::with_capacity(payloads.len());
+    for (payload, path) in payloads.iter().zip(&written) {
+        if !options.validate {
+            verdicts.push(None);
+            continue;
+        }
+        let path = path.to_string_lossy();
+        let verdict = match payload.kind {
+            PayloadKind::Hl7v2 => Ok(crate::hl7v2::validate_text(&payload.text, &path)),
+            PayloadKind::Fhir => {
+                crate::validate::validate_report(
+                    &path,
+                    &options.base_url,
+                    &ValidateOptions::default(),
+                )
+                .await
+            }
+        };
+        verdicts.push(Some(Verdict::from_result(verdict)));
+    }
+
+    if options.json {
+        let extracted: Vec<_> = payloads
+            .iter()
+            .zip(&written)
+            .zip(&verdicts)
+            .zip(&sources)
+            .map(|(((payload, path), verdict), source)| {
+                let mut item = json!({
+                    "line": payload.line,
+                    "kind": payload.kind.label(),
+                    "label": payload.label,
+                    "path": path.to_string_lossy(),
+                });
+                if let Some(source) = source {
+                    item["file"] = json!(source);
+                }
+                if let Some(verdict) = verdict {
+                    item["validation"] = verdict.to_json();
+                }
+                item
+            })
+            .collect();
+        println!(
+            "{}",
+            json!({"file": logfile, "dir": options.dir, "extracted": extracted})
+        );
+    } else {
+        let hl7_count = payloads
+            .iter()
+            .filter(|payload| payload.kind == PayloadKind::Hl7v2)
+            .count();
+        println!("ClinLogix Extraction");
+        println!("--------------------");
+        println!("File: {}", logfile);
+        println!(
+            "Extracted: {} (HL7 v2: {}, FHIR: {}) to {}",
+            payloads.len(),
+            hl7_count,
+            payloads.len() - hl7_count,
+            options.dir
+        );
+        let listed = payloads.iter().zip(&written).zip(&verdicts).zip(&sources);
+        for (((payload, path), verdict), source) in listed {
+            let location = match source {
+                Some(source) => format!("{source}:{}", payload.line),
+                None => format!("line {}", payload.line),
+            };
+            let mut line = format!(
+                "- {location}: {} {} -> {}",
+                payload.kind.label(),
+                payload.label,
+                path.display()
+            );
+            if let Some(verdict) = verdict {
+                line.push_str(&format!(" [{}]", verdict.describe()));
+            }
+            println!("{line}");
+        }
+    }
+
+    Ok(Verdict::outcome(
+        verdicts.into_iter().flatten(),
+        "Extracted payload",
+    )?)
+}
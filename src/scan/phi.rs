@@ -0,0 +1,455 @@
+//! Pattern- and checksum-based recognizers for PHI in log lines.
+//!
+//! Findings carry the category and byte span only; callers report or replace
+//! the span without ever printing the matched value.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use super::extract::{find_msh, msh_delimiters};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PhiCategory {
+    Email,
+    Ssn,
+    Identifier,
+    Phone,
+    Mrn,
+    PatientId,
+    Date,
+    Name,
+    Address,
+}
+
+impl PhiCategory {
+    pub const ALL: [PhiCategory; 9] = [
+        PhiCategory::Email,
+        PhiCategory::Ssn,
+        PhiCategory::Identifier,
+        PhiCategory::Phone,
+        PhiCategory::Mrn,
+        PhiCategory::PatientId,
+        PhiCategory::Date,
+        PhiCategory::Name,
+        PhiCategory::Address,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PhiCategory::Email => "email",
+            PhiCategory::Ssn => "ssn",
+            PhiCategory::Identifier => "identifier",
+            PhiCategory::Phone => "phone",
+            PhiCategory::Mrn => "mrn",
+            PhiCategory::PatientId => "patient-id",
+            PhiCategory::Date => "date",
+            PhiCategory::Name => "name",
+            PhiCategory::Address => "address",
+        }
+    }
+}
+
+/// One recognized value: `line[start..end]` is the sensitive text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhiFinding {
+    pub category: PhiCategory,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Default MRN recognizer: a value with at least one digit labelled `MRN`.
+pub const DEFAULT_MRN_PATTERN: &str = r"(?i:\bMRN\b)[\s:#=]*([A-Z0-9]*\d[A-Z0-9-]{2,})";
+
+static EMAIL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\b[a-z0-9._%+-]+@[a-z0-9.-]+\.[a-z]{2,}\b").unwrap());
+static SSN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\b(\d{3})-(\d{2})-(\d{4})\b").unwrap());
+static SSN_LABELLED: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(?:ssn|social security(?: number)?)\b\D{0,5}(\d{9})\b").unwrap()
+});
+static CARD_NUMBER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b\d(?:[ -]?\d){12,18}\b").unwrap());
+static NPI_LABELLED: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\bNPI\b\D{0,5}(\d{10})\b").unwrap());
+static PHONE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:\+1[-. ]?)?(?:\(\d{3}\)\s?|\b\d{3}[-. ])\d{3}[-. ]\d{4}\b").unwrap()
+});
+static PATIENT_ID: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\bPatient/([A-Za-z0-9\-.]{1,64})").unwrap());
+/// A JSON `id` member, also when the JSON is escaped inside a string.
+static JSON_ID: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"\\?"id\\?"\s*:\s*\\?"([A-Za-z0-9\-.]{1,64})\\?""#).unwrap());
+static ISO_DATE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(\d{4})-(\d{2})-(\d{2})\b").unwrap());
+static US_DATE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(\d{1,2})/(\d{1,2})/(\d{4})\b").unwrap());
+static BIRTH_DATE_COMPACT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(?:dob|birth\w*)\b\D{0,12}((\d{4})(\d{2})(\d{2}))\b").unwrap()
+});
+static NAME_LABELLED: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"\b(?i:patient[ _]?name|name|family|given)"?\s*[:=]\s*\[?"?([A-Z][A-Za-z'-]+(?:[ ,^]{1,2}[A-Z][A-Za-z'-]+){0,3})"#,
+    )
+    .unwrap()
+});
+
+/// How logs write HL7 v2 segment separators: real or escaped line breaks,
+/// `<CR>`, and the MLLP frame bytes.
+const SEGMENT_BREAKS: [&str; 7] = ["\r", "\n", "\\r", "\\n", "<CR>", "\u{0b}", "\u{1c}"];
+
+/// Recognizes PHI in single log lines.
+#[derive(Debug, Clone)]
+pub struct PhiDetector {
+    mrn_patterns: Vec<Regex>,
+}
+
+impl Default for PhiDetector {
+    fn default() -> Self {
+        Self {
+            mrn_patterns: vec![Regex::new(DEFAULT_MRN_PATTERN).unwrap()],
+        }
+    }
+}
+
+impl PhiDetector {
+    /// Adds site-specific MRN regexes to the default one. A pattern with a
+    /// capture group flags only the group (e.g. the value after a label).
+    pub fn with_mrn_patterns(patterns: &[String]) -> Result<Self, String> {
+        let mut detector = Self::default();
+        for pattern in patterns {
+            detector
+                .mrn_patterns
+                .push(Regex::new(pattern).map_err(|err| format!("invalid MRN pattern: {err}"))?);
+        }
+        Ok(detector)
+    }
+
+    /// Findings in `line`, ordered by position and never overlapping.
+    pub fn detect(&self, line: &str) -> Vec<PhiFinding> {
+        let mut found = Vec::new();
+        let mut push = |category, start, end| {
+            found.push(PhiFinding {
+                category,
+                start,
+                end,
+            })
+        };
+
+        for m in EMAIL.find_iter(line) {
+            push(PhiCategory::Email, m.start(), m.end());
+        }
+        for caps in SSN.captures_iter(line) {
+            let whole = caps.get(0).unwrap();
+            if valid_ssn(&caps[1], &caps[2], &caps[3]) {
+                push(PhiCategory::Ssn, whole.start(), whole.end());
+            }
+        }
+        for caps in SSN_LABELLED.captures_iter(line) {
+            let digits = caps.get(1).unwrap();
+            let value = digits.as_str();
+            if valid_ssn(&value[..3], &value[3..5], &value[5..]) {
+                push(PhiCategory::Ssn, digits.start(), digits.end());
+            }
+        }
+        let timestamps = hl7_fields(line, &mut push);
+        for m in CARD_NUMBER.find_iter(line) {
+            let digits: String = m.as_str().chars().filter(char::is_ascii_digit).collect();
+            let in_timestamp = timestamps
+                .iter()
+                .any(|&(start, end)| start <= m.start() && m.end() <= end);
+            if luhn_valid(&digits) && !in_timestamp {
+                push(PhiCategory::Identifier, m.start(), m.end());
+            }
+        }
+        for caps in NPI_LABELLED.captures_iter(line) {
+            let digits = caps.get(1).unwrap();
+            // NPIs are Luhn-checked with the 80840 card issuer prefix.
+            if luhn_valid(&format!("80840{}", digits.as_str())) {
+                push(PhiCategory::Identifier, digits.start(), digits.end());
+            }
+        }
+        for m in PHONE.find_iter(line) {
+            push(PhiCategory::Phone, m.start(), m.end());
+        }
+        for pattern in &self.mrn_patterns {
+            for caps in pattern.captures_iter(line) {
+                let span = caps.get(1).or_else(|| caps.get(0)).unwrap();
+                push(PhiCategory::Mrn, span.start(), span.end());
+            }
+        }
+        let mut patient_ids = Vec::new();
+        for caps in PATIENT_ID.captures_iter(line) {
+            let id = caps.get(1).unwrap();
+            push(PhiCategory::PatientId, id.start(), id.end());
+            patient_ids.push(id.as_str());
+        }
+        // The resource a `Patient/<id>` reference names often follows it in the line.
+        for caps in JSON_ID.captures_iter(line) {
+            let id = caps.get(1).unwrap();
+            if patient_ids.contains(&id.as_str()) {
+                push(PhiCategory::PatientId, id.start(), id.end());
+            }
+        }
+        for caps in ISO_DATE.captures_iter(line) {
+            let whole = caps.get(0).unwrap();
+            if valid_date(&caps[2], &caps[3]) && !followed_by_time(&line[whole.end()..]) {
+                push(PhiCategory::Date, whole.start(), whole.end());
+            }
+        }
+        for caps in US_DATE.captures_iter(line) {
+            let whole = caps.get(0).unwrap();
+            if valid_date(&caps[1], &caps[2]) && !followed_by_time(&line[whole.end()..]) {
+                push(PhiCategory::Date, whole.start(), whole.end());
+            }
+        }
+        for caps in BIRTH_DATE_COMPACT.captures_iter(line) {
+            let value = caps.get(1).unwrap();
+            if valid_date(&caps[3], &caps[4]) {
+                push(PhiCategory::Date, value.start(), value.end());
+            }
+        }
+        for caps in NAME_LABELLED.captures_iter(line) {
+            let name = caps.get(1).unwrap();
+            // An unquoted value followed by lowercase words is a sentence
+            // (`name: Database pool exhausted`), not a name.
+            let quoted = line[..name.start()].ends_with('"');
+            let rest = &line[name.end()..];
+            let sentence = rest.starts_with(char::is_whitespace)
+                && rest.trim_start().starts_with(|c: char| c.is_lowercase());
+            if quoted || !sentence {
+                push(PhiCategory::Name, name.start(), name.end());
+            }
+        }
+
+        // Earlier, then longer, then higher-priority findings win overlaps.
+        found.sort_by_key(|finding| {
+            (
+                finding.start,
+                std::cmp::Reverse(finding.end),
+                finding.category,
+            )
+        });
+        let mut kept: Vec<PhiFinding> = Vec::with_capacity(found.len());
+        for finding in found {
+            if kept.last().is_none_or(|last| finding.start >= last.end) {
+                kept.push(finding);
+            }
+        }
+        kept
+    }
+}
+
+/// Flags the patient's identifiers, names, birth date, address and phone in
+/// the PID segments of HL7 v2 messages in `line`, and returns the spans of
+/// date/time values so their digits are not mistaken for card numbers.
+fn hl7_fields(line: &str, push: &mut impl FnMut(PhiCategory, usize, usize)) -> Vec<(usize, usize)> {
+    let mut timestamps = Vec::new();
+    let mut search = 0;
+    while let Some(start) = find_msh(line, search) {
+        let Some((field, encoding)) = msh_delimiters(&line[start + 3..]) else {
+            break;
+        };
+        let mut encoding = encoding.chars();
+        let component = encoding.next().unwrap_or('^');
+        let repetition = encoding.next().unwrap_or('~');
+        let end = find_msh(line, start + 3).unwrap_or(line.len());
+
+        let mut position = start;
+        while position < end {
+            let (segment_end, next) = SEGMENT_BREAKS
+                .iter()
+                .filter_map(|brk| {
+                    let offset = line[position..end].find(brk)?;
+                    Some((position + offset, position + offset + brk.len()))
+                })
+                .min()
+                .unwrap_or((end, end));
+            let fields = split(line, (position, segment_end), field);
+            let is_pid = &line[fields[0].0..fields[0].1] == "PID";
+            for (index, &span) in fields.iter().enumerate().skip(1) {
+                let repetitions = split(line, span, repetition);
+                let category = match index {
+                    3 => Some(PhiCategory::Mrn),
+                    5 | 6 => Some(PhiCategory::Name),
+                    7 => Some(PhiCategory::Date),
+                    11 => Some(PhiCategory::Address),
+                    13 => Some(PhiCategory::Phone),
+                    _ => None,
+                };
+                for repeated in repetitions {
+                    let components = split(line, repeated, component);
+                    for &(start, end) in &components {
+                        if is_hl7_timestamp(&line[start..end]) {
+                            timestamps.push((start, end));
+                        }
+                    }
+                    // Identifiers and birth dates are the first component; the
+                    // rest of a CX is the assigning authority and type code.
+                    let (start, end) = match category {
+                        Some(PhiCategory::Mrn | PhiCategory::Date) => components[0],
+                        _ => repeated,
+                    };
+                    let value = &line[start..end];
+                    // `[MRN]` or `[MRN-3f9a2c1b]` is a value `redact` already replaced.
+                    let redacted = value.starts_with('[') && value.ends_with(']');
+                    if let Some(category) = category.filter(|_| is_pid)
+                        && !matches!(value, "" | "\"\"")
+                        && !redacted
+                    {
+                        push(category, start, end);
+                    }
+                }
+            }
+            position = next.max(segment_end + 1);
+        }
+        search = end;
+    }
+    timestamps
+}
+
+/// Splits `line[span]` at `separator`, returning the spans of the pieces.
+fn split(line: &str, (start, end): (usize, usize), separator: char) -> Vec<(usize, usize)> {
+    let mut pieces = Vec::new();
+    let mut piece_start = start;
+    for (offset, c) in line[start..end].char_indices() {
+        if c == separator {
+            pieces.push((piece_start, start + offset));
+            piece_start = start + offset + c.len_utf8();
+        }
+    }
+    pieces.push((piece_start, end));
+    pieces
+}
+
+/// An HL7 `DTM` down to the minute (`202601070801`), with optional seconds,
+/// fraction and zone.
+fn is_hl7_timestamp(value: &str) -> bool {
+    let digits = value.bytes().take_while(u8::is_ascii_digit).count();
+    digits >= 12
+        && valid_date(&value[4..6], &value[6..8])
+        && value[8..10] < *"24"
+        && value[10..12] < *"60"
+}
+
+/// Area 000, 666 and 9xx, group 00 and serial 0000 are never issued.
+fn valid_ssn(area: &str, group: &str, serial: &str) -> bool {
+    area != "000" && area != "666" && !area.starts_with('9') && group != "00" && serial != "0000"
+}
+
+fn luhn_valid(digits: &str) -> bool {
+    let mut sum = 0;
+    for (index, byte) in digits.bytes().rev().enumerate() {
+        let mut digit = u32::from(byte - b'0');
+        if index % 2 == 1 {
+            digit *= 2;
+            if digit > 9 {
+                digit -= 9;
+            }
+        }
+        sum += digit;
+    }
+    !digits.is_empty() && sum % 10 == 0
+}
+
+fn valid_date(month: &str, day: &str) -> bool {
+    let month: u32 = month.parse().unwrap_or(0);
+    let day: u32 = day.parse().unwrap_or(0);
+    (1..=12).contains(&month) && (1..=31).contains(&day)
+}
+
+/// Log timestamps (`2026-01-07 08:00:01`, `2026-01-07T08:00:01Z`) are not PHI.
+fn followed_by_time(rest: &str) -> bool {
+    let rest = rest.strip_prefix(['T', ' ']).unwrap_or(rest);
+    let bytes = rest.as_bytes();
+    bytes.len() >= 3 && bytes[0].is_ascii_digit() && bytes[1].is_ascii_digit() && bytes[2] == b':'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn categories(line: &str) -> Vec<&'static str> {
+        PhiDetector::default()
+            .detect(line)
+            .iter()
+            .map(|finding| finding.category.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn recognizes_each_category_and_skips_log_timestamps() {
+        assert_eq!(
+            categories("ERROR 2026-01-07 08:07:12 FHIR validation failed for Patient/123"),
+            vec!["patient-id"]
+        );
+        assert_eq!(
+            categories(
+                "WARN lookup ssn=123-45-6789 email jane.doe@example.org phone (555) 123-4567"
+            ),
+            vec!["ssn", "email", "phone"]
+        );
+        assert_eq!(
+            categories("INFO 2026-01-07T08:00:01Z MRN: A123456 DOB 19800101 admitted 01/07/2026"),
+            vec!["mrn", "date", "date"]
+        );
+        assert_eq!(
+            categories(r#"DEBUG {"family":"Doe","given":["Jane"],"birthDate":"1980-01-01"}"#),
+            vec!["name", "name", "date"]
+        );
+    }
+
+    #[test]
+    fn applies_checksums_and_validity_rules() {
+        // 4111 1111 1111 1111 is the classic Luhn-valid test card.
+        assert_eq!(categories("card 4111 1111 1111 1111"), vec!["identifier"]);
+        assert!(categories("order 4111 1111 1111 1112").is_empty());
+        assert_eq!(categories("NPI 1234567893"), vec!["identifier"]);
+        assert!(categories("NPI 1234567890").is_empty());
+        assert!(categories("ssn 000-12-3456 or 666-12-3456").is_empty());
+        assert!(categories("build 2026-13-40").is_empty());
+    }
+
+    #[test]
+    fn ignores_labels_followed_by_ordinary_words() {
+        assert!(categories("ERROR MRN lookup failed for order").is_empty());
+        assert!(categories("ERROR mrn: unknown").is_empty());
+        assert!(categories("ERROR name: Database pool exhausted").is_empty());
+        assert_eq!(
+            categories("INFO mrn=A77812 name: Jane Doe"),
+            vec!["mrn", "name"]
+        );
+    }
+
+    #[test]
+    fn recognizes_pid_fields_in_logged_hl7() {
+        let log = include_str!("../../examples/interface-engine.log");
+        let line = log
+            .lines()
+            .find(|line| line.contains("Rejected message"))
+            .expect("example line");
+        let findings = PhiDetector::default().detect(line);
+        let flagged: Vec<(&str, &str)> = findings
+            .iter()
+            .map(|finding| (finding.category.as_str(), &line[finding.start..finding.end]))
+            .collect();
+        assert_eq!(flagged, [("mrn", "123456"), ("name", "Doe^Jane^Q")]);
+
+        let admit = "MSH|^~\\&|EHR|GH|||20260107080240||ADT^A01|M1|P|2.5.1\rPID|1||A1~B2^^^GH^MR||Doe^Jane||19850520|F|||1 Main St^^Springfield^IL^62701||^PRN^PH^^^555^1234567\r";
+        assert_eq!(
+            categories(admit),
+            vec!["mrn", "mrn", "name", "date", "address", "phone"]
+        );
+    }
+
+    #[test]
+    fn custom_mrn_patterns_flag_capture_groups() {
+        let detector =
+            PhiDetector::with_mrn_patterns(&[r"\bHOSP-(\d{6})\b".to_string()]).expect("pattern");
+        let line = "INFO chart HOSP-004211 opened";
+        let findings = detector.detect(line);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].category, PhiCategory::Mrn);
+        assert_eq!(&line[findings[0].start..findings[0].end], "004211");
+        assert!(PhiDetector::with_mrn_patterns(&["(".to_string()]).is_err());
+    }
+}
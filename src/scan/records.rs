@@ -0,0 +1,141 @@
+//! Multi-line record assembly: stack traces, `Caused by:` chains and HL7
+//! segment dumps are joined to the line that started them, so one exception
+//! counts once.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::scan::format::{LogFormat, LogParser, LogRecord};
+
+/// Plain-text lines that continue the record before them.
+pub(crate) static CONTINUATION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?:\s|$|at\s|Caused by:|Suppressed:|\.\.\. \d+ (?:more|common frames omitted)|[A-Z][A-Z0-9]{2}\||(?:[a-z_][\w$]*\.)+[A-Z][\w$]*(?:Exception|Error|Throwable)\b)",
+    )
+    .unwrap()
+});
+
+/// Groups lines into records.
+///
+/// A line starts a record when it matches `start` (`--record-start`). Without
+/// one, structured formats start a record on every line they parse, and plain
+/// text on every line that does not look like a continuation (indented, `at
+/// ...`, `Caused by:`, `... 12 more`, an HL7 segment or an exception class).
+#[derive(Debug)]
+pub struct RecordAssembler<'a> {
+    parser: &'a LogParser,
+    start: Option<Regex>,
+    pending: Option<LogRecord>,
+}
+
+impl<'a> RecordAssembler<'a> {
+    pub fn new(parser: &'a LogParser, start: Option<Regex>) -> Self {
+        Self {
+            parser,
+            start,
+            pending: None,
+        }
+    }
+
+    /// Adds line `line` (1-based); returns the record it completes, if any.
+    pub fn push(&mut self, line: u64, raw: &str) -> Option<LogRecord> {
+        let recognized = match &self.start {
+            Some(start) => start.is_match(raw).then(|| self.parser.parse(line, raw)),
+            None => self.parser.recognize(line, raw).or_else(|| {
+                (self.parser.format() == LogFormat::Plain && !CONTINUATION.is_match(raw))
+                    .then(|| LogRecord::plain(line, raw))
+            }),
+        };
+        match (recognized, &mut self.pending) {
+            (Some(record), _) => self.pending.replace(record),
+            (None, Some(pending)) => {
+                pending.push_line(raw);
+                None
+            }
+            // Continuation lines before any record start one of their own.
+            (None, None) => {
+                self.pending = Some(self.parser.parse(line, raw));
+                None
+            }
+        }
+    }
+
+    /// The last record.
+    pub fn finish(&mut self) -> Option<LogRecord> {
+        self.pending.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::format::Level;
+
+    fn assemble(parser: &LogParser, start: Option<Regex>, text: &str) -> Vec<LogRecord> {
+        let mut assembler = RecordAssembler::new(parser, start);
+        let mut records: Vec<LogRecord> = text
+            .lines()
+            .enumerate()
+            .filter_map(|(index, line)| assembler.push(index as u64 + 1, line))
+            .collect();
+        records.extend(assembler.finish());
+        records
+    }
+
+    const TRACE: &str = "\
+INFO 2026-01-07 08:05:39 Opening connection
+ERROR 2026-01-07 08:05:40 Request failed
+java.lang.IllegalStateException: error reading Patient/123
+\tat org.example.Dao.read(Dao.java:42)
+\tat org.example.Api.get(Api.java:17)
+Caused by: java.sql.SQLException: error in connection
+\t... 12 more
+WARNING 2026-01-07 08:05:41 Dumping message
+MSH|^~\\&|EHR|GH|LAB|GH|20260107||ADT^A01|MSG1|P|2.5.1
+PID|1||123456^^^GH^MR||Doe^Jane";
+
+    #[test]
+    fn joins_stack_traces_and_segments() {
+        let parser = LogParser::new(LogFormat::Plain, Vec::new(), &[]);
+        let records = assemble(&parser, None, TRACE);
+        assert_eq!(records.len(), 3);
+        assert_eq!((records[1].line, records[1].lines), (2, 6));
+        assert_eq!(
+            records[1].head(),
+            "ERROR 2026-01-07 08:05:40 Request failed"
+        );
+        assert!(records[1].raw.ends_with("\t... 12 more"));
+        assert_eq!(records[2].lines, 3);
+        // Only the first line decides the level.
+        assert_eq!(
+            records.iter().map(LogRecord::severity).collect::<Vec<_>>(),
+            [None, Some(Level::Error), Some(Level::Warning)]
+        );
+
+        let start = Regex::new(r"^(?:INFO|WARNING|ERROR) ").unwrap();
+        let records = assemble(&parser, Some(start), "  orphan\nERROR a\nMSH|x\nERROR b");
+        assert_eq!(
+            records
+                .iter()
+                .map(|r| (r.line, r.lines))
+                .collect::<Vec<_>>(),
+            [(1, 1), (2, 2), (4, 1)]
+        );
+    }
+
+    #[test]
+    fn structured_records_end_at_the_next_parsed_line() {
+        let log = "2026-01-07 08:05:40,120 ERROR [main] org.example.Db - Query failed\n\
+                   org.example.DbException: timeout\n\
+                   \tat org.example.Db.run(Db.java:9)\n\
+                   2026-01-07 08:05:41,000 INFO [main] org.example.Db - Retrying";
+        let sample: Vec<String> = log.lines().map(str::to_string).collect();
+        let parser = LogParser::new(LogFormat::Auto, Vec::new(), &sample);
+        assert_eq!(parser.format(), LogFormat::Log4j);
+        let records = assemble(&parser, None, log);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].lines, 3);
+        assert_eq!(records[0].message, "Query failed");
+    }
+}
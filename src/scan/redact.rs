@@ -0,0 +1,296 @@
+//! `clinlogix redact`: rewrites a log with PHI replaced.
+//!
+//! Only the spans found by [`PhiDetector`] change, so level words, timestamps
+//! and line breaks stay where `scan` expects them.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Write as _};
+use std::path::Path;
+use std::str::FromStr;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::error::Error;
+use crate::scan::phi::{PhiCategory, PhiDetector, PhiFinding};
+
+/// Environment variable holding the pseudonym secret when no file is given.
+pub const SECRET_ENV: &str = "CLINLOGIX_REDACT_SECRET";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RedactMode {
+    /// `[SSN]`, `[EMAIL]`, ...
+    #[default]
+    Placeholder,
+    /// `[MRN-3f9a2c1b]`: the same value always gets the same token for a secret.
+    Pseudonym,
+}
+
+impl FromStr for RedactMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "placeholder" | "placeholders" => Ok(RedactMode::Placeholder),
+            "pseudonym" | "pseudonyms" => Ok(RedactMode::Pseudonym),
+            other => Err(format!(
+                "unknown redaction mode '{other}' (expected placeholder or pseudonym)"
+            )),
+        }
+    }
+}
+
+/// Replaces detected PHI in log lines.
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    detector: PhiDetector,
+    /// HMAC key; pseudonyms are produced only when set.
+    key: Option<Vec<u8>>,
+}
+
+impl Redactor {
+    pub fn placeholders(detector: PhiDetector) -> Self {
+        Self {
+            detector,
+            key: None,
+        }
+    }
+
+    pub fn pseudonyms(detector: PhiDetector, secret: &[u8]) -> Self {
+        Self {
+            detector,
+            key: Some(secret.to_vec()),
+        }
+    }
+
+    /// The redacted line and the findings that were replaced.
+    pub fn redact_line(&self, line: &str) -> (String, Vec<PhiFinding>) {
+        let findings = self.detector.detect(line);
+        let mut output = String::with_capacity(line.len());
+        let mut cursor = 0;
+        for finding in &findings {
+            output.push_str(&line[cursor..finding.start]);
+            output.push_str(&self.token(finding.category, &line[finding.start..finding.end]));
+            cursor = finding.end;
+        }
+        output.push_str(&line[cursor..]);
+        (output, findings)
+    }
+
+    fn token(&self, category: PhiCategory, value: &str) -> String {
+        let label = category.as_str().to_ascii_uppercase();
+        let Some(key) = &self.key else {
+            return format!("[{label}]");
+        };
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+        mac.update(category.as_str().as_bytes());
+        mac.update(b":");
+        mac.update(normalize(category, value).as_bytes());
+        let digest = mac.finalize().into_bytes();
+        let mut token = format!("[{label}-");
+        for byte in &digest[..4] {
+            let _ = write!(token, "{byte:02x}");
+        }
+        token.push(']');
+        token
+    }
+}
+
+/// Formatting differences (`555-123-4567` vs `(555) 123-4567`) map to one pseudonym.
+fn normalize(category: PhiCategory, value: &str) -> String {
+    match category {
+        PhiCategory::Ssn | PhiCategory::Phone | PhiCategory::Identifier | PhiCategory::Date => {
+            value.chars().filter(char::is_ascii_digit).collect()
+        }
+        PhiCategory::Email | PhiCategory::Name | PhiCategory::Address => value.to_lowercase(),
+        PhiCategory::Mrn | PhiCategory::PatientId => value.to_string(),
+    }
+}
+
+/// Reads the secret from `path`, creating it with 32 random bytes when missing.
+///
+/// A new file is created readable by its owner only, and never replaces a
+/// file another process created in the meantime.
+pub fn load_or_create_secret(path: &Path) -> io::Result<Vec<u8>> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    match options.open(path) {
+        Ok(mut file) => {
+            let secret = random_secret()?;
+            file.write_all(format!("{secret}\n").as_bytes())?;
+            Ok(secret.into_bytes())
+        }
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+            let secret = fs::read_to_string(path)?.trim().as_bytes().to_vec();
+            if secret.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "secret file is empty",
+                ));
+            }
+            Ok(secret)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// 32 random bytes from the operating system, hex-encoded.
+pub fn random_secret() -> io::Result<String> {
+    let mut random = [0u8; 32];
+    getrandom::fill(&mut random)?;
+    Ok(random.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+/// Settings for `clinlogix redact`.
+#[derive(Debug, Clone, Default)]
+pub struct RedactOptions {
+    pub mode: RedactMode,
+    /// Secret for pseudonyms; falls back to [`SECRET_ENV`].
+    pub secret_file: Option<String>,
+    /// Write here instead of stdout.
+    pub output: Option<String>,
+    pub detector: PhiDetector,
+}
+
+/// Redacts `logfile` line by line, keeping every line ending as it was.
+///
+/// A summary goes to stderr so stdout can be redirected to the redacted file.
+pub fn run_redact(logfile: &str, options: &RedactOptions) -> Result<(), Error> {
+    let redactor = match options.mode {
+        RedactMode::Placeholder => Redactor::placeholders(options.detector.clone()),
+        RedactMode::Pseudonym => {
+            let secret = match (&options.secret_file, std::env::var(SECRET_ENV)) {
+                (Some(path), _) => load_or_create_secret(Path::new(path))
+                    .map_err(|err| Error::Input(format!("{path}: {err}")))?,
+                (None, Ok(secret)) if !secret.trim().is_empty() => {
+                    secret.trim().as_bytes().to_vec()
+                }
+                (None, _) => {
+                    return Err(Error::Input(format!(
+                        "pseudonyms need a secret: pass --secret-file or set {SECRET_ENV}"
+                    )));
+                }
+            };
+            Redactor::pseudonyms(options.detector.clone(), &secret)
+        }
+    };
+
+    let bytes = fs::read(logfile).map_err(|err| Error::Input(format!("{logfile}: {err}")))?;
+    let text = String::from_utf8_lossy(&bytes);
+    let mut redacted = String::with_capacity(text.len());
+    let mut counts = [0usize; PhiCategory::ALL.len()];
+    let mut lines = 0;
+    for line in text.split_inclusive('\n') {
+        let (content, ending) = split_line_ending(line);
+        let (output, findings) = redactor.redact_line(content);
+        if !findings.is_empty() {
+            lines += 1;
+        }
+        for finding in &findings {
+            let index = PhiCategory::ALL
+                .iter()
+                .position(|category| *category == finding.category)
+                .unwrap_or_default();
+            counts[index] += 1;
+        }
+        redacted.push_str(&output);
+        redacted.push_str(ending);
+    }
+
+    match &options.output {
+        Some(path) => {
+            fs::write(path, &redacted).map_err(|err| Error::Input(format!("{path}: {err}")))?
+        }
+        None => print!("{redacted}"),
+    }
+
+    let total: usize = counts.iter().sum();
+    let by_category: Vec<String> = PhiCategory::ALL
+        .iter()
+        .zip(counts)
+        .filter(|(_, count)| *count > 0)
+        .map(|(category, count)| format!("{}: {count}", category.as_str()))
+        .collect();
+    let mut summary = format!("Redacted {total} values on {lines} lines");
+    if !by_category.is_empty() {
+        summary.push_str(&format!(" ({})", by_category.join(", ")));
+    }
+    if let Some(path) = &options.output {
+        summary.push_str(&format!(" to {path}"));
+    }
+    eprintln!("{summary}");
+    Ok(())
+}
+
+fn split_line_ending(line: &str) -> (&str, &str) {
+    let content = line.trim_end_matches(['\r', '\n']);
+    (content, &line[content.len()..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINE: &str = "ERROR 2026-01-07 08:07:12 FHIR validation failed for Patient/123 (MRN: A77812, phone 555-123-4567)";
+
+    #[test]
+    fn placeholders_keep_log_structure() {
+        let redactor = Redactor::placeholders(PhiDetector::default());
+        let (output, findings) = redactor.redact_line(LINE);
+        assert_eq!(findings.len(), 3);
+        assert_eq!(
+            output,
+            "ERROR 2026-01-07 08:07:12 FHIR validation failed for Patient/[PATIENT-ID] (MRN: [MRN], phone [PHONE])"
+        );
+    }
+
+    #[test]
+    fn pseudonyms_are_consistent_per_secret() {
+        let redactor = Redactor::pseudonyms(PhiDetector::default(), b"local secret");
+        let (first, _) = redactor.redact_line("call 555-123-4567 about MRN A77812");
+        let (second, _) = redactor.redact_line("MRN A77812 callback (555) 123-4567");
+        let token = |text: &str, prefix: &str| {
+            let start = text.find(prefix).expect(prefix);
+            text[start..start + prefix.len() + 9].to_string()
+        };
+        assert_eq!(token(&first, "[MRN-"), token(&second, "[MRN-"));
+        assert_eq!(token(&first, "[PHONE-"), token(&second, "[PHONE-"));
+        assert!(!first.contains("A77812"));
+
+        let other = Redactor::pseudonyms(PhiDetector::default(), b"another secret");
+        let (third, _) = other.redact_line("MRN A77812");
+        assert_ne!(token(&first, "[MRN-"), token(&third, "[MRN-"));
+    }
+
+    #[test]
+    fn parses_modes() {
+        assert_eq!("Pseudonym".parse(), Ok(RedactMode::Pseudonym));
+        assert!("hash".parse::<RedactMode>().is_err());
+        assert_eq!(split_line_ending("a\r\n"), ("a", "\r\n"));
+    }
+
+    #[test]
+    fn creates_a_private_secret_once() {
+        let path = std::env::temp_dir().join(format!("clinlogix-secret-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let created = load_or_create_secret(&path).expect("create secret");
+        assert_eq!(created.len(), 64);
+        assert_eq!(load_or_create_secret(&path).expect("read secret"), created);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path)
+                .expect("secret metadata")
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let _ = fs::remove_file(&path);
+    }
+}
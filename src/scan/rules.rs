@@ -0,0 +1,283 @@
+//! Operational categories for log records (`fhir-validation`, `database`,
+//! `timeout`, ...), from the bundled `categories.toml` and `--rules` files.
+
+use std::fs;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::scan::format::{Level, LogRecord};
+
+/// One category as written in a rule file (`[[category]]` tables).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CategoryRule {
+    pub name: String,
+    /// Level matching records are counted with; they keep their own without it.
+    #[serde(default)]
+    pub severity: Option<String>,
+    /// Only records at these levels match; any level when empty.
+    #[serde(default)]
+    pub levels: Vec<String>,
+    /// Regexes on the record's message.
+    #[serde(default)]
+    pub patterns: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    /// Drop the built-in categories instead of layering on top of them.
+    #[serde(default)]
+    replace_builtin: bool,
+    #[serde(default)]
+    category: Vec<CategoryRule>,
+}
+
+#[derive(Debug, Clone)]
+struct CompiledCategory {
+    name: String,
+    severity: Option<Level>,
+    levels: Vec<Level>,
+    patterns: Vec<Regex>,
+}
+
+impl CompiledCategory {
+    fn compile(rule: CategoryRule) -> Result<Self, String> {
+        let level = |value: &str| {
+            Level::parse(value)
+                .ok_or_else(|| format!("category '{}': unknown level '{value}'", rule.name))
+        };
+        let severity = rule.severity.as_deref().map(level).transpose()?;
+        let levels = rule
+            .levels
+            .iter()
+            .map(|value| level(value))
+            .collect::<Result<Vec<_>, _>>()?;
+        let patterns = rule
+            .patterns
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern)
+                    .map_err(|err| format!("category '{}': invalid pattern: {err}", rule.name))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            name: rule.name,
+            severity,
+            levels,
+            patterns,
+        })
+    }
+
+    fn matches(&self, record: &LogRecord, level: Option<Level>) -> bool {
+        let level_matches =
+            self.levels.is_empty() || level.is_some_and(|level| self.levels.contains(&level));
+        level_matches
+            && self
+                .patterns
+                .iter()
+                .any(|pattern| pattern.is_match(&record.message))
+    }
+}
+
+/// How a record was classified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Classified<'a> {
+    /// The matching category, if any.
+    pub category: Option<&'a str>,
+    /// The matching category's own severity.
+    pub severity: Option<Level>,
+    /// The level the record counts with.
+    pub level: Option<Level>,
+}
+
+/// Ordered categories; the first matching one wins.
+#[derive(Debug, Clone)]
+pub struct CategoryRules {
+    categories: Vec<CompiledCategory>,
+}
+
+static BUILTIN: LazyLock<CategoryRules> = LazyLock::new(|| {
+    let file: RuleFile =
+        toml::from_str(include_str!("categories.toml")).expect("built-in categories parse");
+    CategoryRules::from_rules(file.category).expect("built-in categories compile")
+});
+
+impl Default for CategoryRules {
+    fn default() -> Self {
+        CategoryRules::builtin().clone()
+    }
+}
+
+impl CategoryRules {
+    pub fn builtin() -> &'static CategoryRules {
+        &BUILTIN
+    }
+
+    pub fn from_rules(rules: Vec<CategoryRule>) -> Result<Self, String> {
+        let categories = rules
+            .into_iter()
+            .map(CompiledCategory::compile)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { categories })
+    }
+
+    /// Parses a TOML rule file and layers it over the built-in categories.
+    ///
+    /// Categories whose name matches a built-in replace it in place; new ones
+    /// are checked before the built-ins so team rules win ties.
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let file: RuleFile =
+            toml::from_str(text).map_err(|err| format!("invalid category rules: {err}"))?;
+        let user = CategoryRules::from_rules(file.category)?;
+        if file.replace_builtin {
+            return Ok(user);
+        }
+        Ok(CategoryRules::builtin().clone().with_overrides(user))
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("{path}: {err}"))?;
+        CategoryRules::from_toml(&text).map_err(|err| format!("{path}: {err}"))
+    }
+
+    fn with_overrides(mut self, overrides: CategoryRules) -> Self {
+        let mut added = Vec::new();
+        for category in overrides.categories {
+            match self
+                .categories
+                .iter_mut()
+                .find(|existing| existing.name == category.name)
+            {
+                Some(existing) => *existing = category,
+                None => added.push(category),
+            }
+        }
+        added.extend(self.categories);
+        Self { categories: added }
+    }
+
+    pub fn classify(&self, record: &LogRecord) -> Classified<'_> {
+        let level = record.severity();
+        match self
+            .categories
+            .iter()
+            .find(|category| category.matches(record, level))
+        {
+            Some(category) => Classified {
+                category: Some(&category.name),
+                severity: category.severity,
+                level: category.severity.or(level),
+            },
+            None => Classified {
+                category: None,
+                severity: None,
+                level,
+            },
+        }
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.categories
+            .iter()
+            .map(|category| category.name.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify<'a>(rules: &'a CategoryRules, line: &str) -> (Option<&'a str>, Option<Level>) {
+        let classified = rules.classify(&LogRecord::plain(1, line));
+        (classified.category, classified.level)
+    }
+
+    #[test]
+    fn builtin_categories_sort_the_demo_log() {
+        let rules = CategoryRules::builtin();
+        assert_eq!(
+            classify(
+                rules,
+                "ERROR 2026-01-07 08:05:40 Database connection failed"
+            ),
+            (Some("database"), Some(Level::Error))
+        );
+        assert_eq!(
+            classify(
+                rules,
+                "ERROR 2026-01-07 08:06:45 Timeout while writing audit log"
+            ),
+            (Some("timeout"), Some(Level::Error))
+        );
+        assert_eq!(
+            classify(
+                rules,
+                "ERROR 2026-01-07 08:07:12 FHIR validation failed for Patient/123"
+            ),
+            (Some("fhir-validation"), Some(Level::Error))
+        );
+        assert_eq!(
+            classify(
+                rules,
+                "ERROR 2026-01-07 08:02:40 Rejected message: MSH|^~\\&|LAB"
+            ),
+            (Some("hl7-interface"), Some(Level::Error))
+        );
+        // Informational lines stay out of the failure categories.
+        assert_eq!(
+            classify(
+                rules,
+                "INFO 2026-01-07 08:06:10 Retrying after database connection lost"
+            ),
+            (None, None)
+        );
+    }
+
+    #[test]
+    fn rule_files_layer_over_the_builtins() {
+        let rules = CategoryRules::from_toml(
+            r#"
+[[category]]
+name = "audit"
+severity = "error"
+patterns = ["(?i)audit"]
+
+[[category]]
+name = "capacity"
+severity = "warning"
+patterns = ["(?i)disk (?:full|usage)"]
+"#,
+        )
+        .expect("rules");
+        assert_eq!(
+            rules.names().collect::<Vec<_>>(),
+            [
+                "capacity",
+                "fhir-validation",
+                "database",
+                "timeout",
+                "hl7-interface",
+                "audit"
+            ]
+        );
+        assert_eq!(
+            classify(&rules, "INFO disk usage at 91%"),
+            (Some("capacity"), Some(Level::Warning))
+        );
+        assert_eq!(
+            classify(&rules, "INFO audit trail rotated"),
+            (Some("audit"), Some(Level::Error))
+        );
+
+        let replaced =
+            CategoryRules::from_toml("replace_builtin = true\n[[category]]\nname = \"x\"\n")
+                .expect("rules");
+        assert_eq!(replaced.names().count(), 1);
+        assert!(
+            CategoryRules::from_toml("[[category]]\nname = \"x\"\nseverity = \"loud\"").is_err()
+        );
+    }
+}
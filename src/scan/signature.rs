@@ -0,0 +1,290 @@
+//! Message signatures: log lines with their variable parts (timestamps, ids,
+//! numbers, payloads) replaced, so repeats of the same problem group together.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde_json::{Value, json};
+
+use crate::scan::timeline::{Timestamp, strip_timestamps};
+
+/// Signatures longer than this are cut; the example line keeps the full text.
+const MAX_SIGNATURE_CHARS: usize = 160;
+
+static LEVEL_PREFIX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*[\[<]?(?i:fatal|severe|critical|error|err|warning|warn|info|notice|debug|trace)[\]>]?(?:\s*[:|-])?\s+")
+        .unwrap()
+});
+/// Normalization steps, applied in order; earlier ones protect their text
+/// from the later, more general ones.
+static REPLACEMENTS: LazyLock<Vec<(Regex, &'static str)>> = LazyLock::new(|| {
+    [
+        // Inline JSON bodies and HL7 v2 messages.
+        (r"\{.*\}", "<json>"),
+        (r"MSH\|\S*(?:\s.*)?$", "<hl7>"),
+        (
+            r"(?i)\b[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\b",
+            "<uuid>",
+        ),
+        (r"(?i)\b[a-z0-9._%+-]+@[a-z0-9.-]+\.[a-z]{2,}\b", "<email>"),
+        (r"\b([A-Z][A-Za-z]+)/[A-Za-z0-9\-.]{1,64}", "$1/<id>"),
+        (r"\b\d{1,3}(?:\.\d{1,3}){3}(?::\d+)?\b", "<ip>"),
+        (r"\b(?:0x)?[0-9a-fA-F]{8,}\b", "<hex>"),
+        (r"\b[A-Za-z][A-Za-z_-]*\d{3,}[A-Za-z0-9_-]*\b", "<id>"),
+        (r"\b\d+(?:\.\d+)?\b", "<n>"),
+        (r"\s+", " "),
+    ]
+    .into_iter()
+    .map(|(pattern, replacement)| (Regex::new(pattern).unwrap(), replacement))
+    .collect()
+});
+
+/// The normalized form of a log line.
+///
+/// JSON-lines records are reduced to their `msg`/`message` field first.
+pub fn signature(line: &str) -> String {
+    let message = json_message(line);
+    let line = strip_timestamps(message.as_deref().unwrap_or(line));
+    let mut text = LEVEL_PREFIX.replace(&line, "").into_owned();
+    for (pattern, replacement) in REPLACEMENTS.iter() {
+        text = pattern
+            .replace_all(&text, |caps: &regex::Captures<'_>| {
+                let found = &caps[0];
+                // Hex ids mix letters and digits; plain numbers and words stay for later steps.
+                let hex_like = found.bytes().any(|b| b.is_ascii_digit())
+                    && found.bytes().any(|b| b.is_ascii_alphabetic());
+                if *replacement == "<hex>" && !hex_like {
+                    found.to_string()
+                } else {
+                    let mut expanded = String::new();
+                    caps.expand(replacement, &mut expanded);
+                    expanded
+                }
+            })
+            .into_owned();
+    }
+    let text = text.trim();
+    if text.chars().count() > MAX_SIGNATURE_CHARS {
+        let cut: String = text.chars().take(MAX_SIGNATURE_CHARS).collect();
+        format!("{cut}…")
+    } else {
+        text.to_string()
+    }
+}
+
+fn json_message(line: &str) -> Option<String> {
+    let line = line.trim();
+    if !line.starts_with('{') {
+        return None;
+    }
+    let record: Value = serde_json::from_str(line).ok()?;
+    ["msg", "message"]
+        .iter()
+        .find_map(|key| record.get(*key)?.as_str())
+        .map(str::to_string)
+}
+
+/// Where a line was seen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Seen {
+    /// Set when several files are scanned together.
+    pub file: Option<Rc<str>>,
+    pub line: u64,
+    pub time: Option<Timestamp>,
+}
+
+impl Seen {
+    /// `line 12`, or `app.log.1 line 12` in a multi-file scan.
+    pub fn location(&self) -> String {
+        match &self.file {
+            Some(file) => format!("{file} line {}", self.line),
+            None => format!("line {}", self.line),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self.time {
+            Some(time) => format!("{time} ({})", self.location()),
+            None => self.location(),
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut value = json!({"line": self.line, "time": self.time.map(Timestamp::iso)});
+        if let Some(file) = &self.file {
+            value["file"] = json!(file.as_ref());
+        }
+        value
+    }
+}
+
+/// Occurrences of one signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureStats {
+    pub signature: String,
+    pub count: u64,
+    pub first: Seen,
+    pub last: Seen,
+    /// The first line with this signature, as written.
+    pub example: String,
+}
+
+/// Signatures of one level (errors or warnings).
+#[derive(Debug, Clone, Default)]
+pub struct Signatures {
+    stats: HashMap<String, SignatureStats>,
+}
+
+impl Signatures {
+    /// Counts `message` under its signature; `example` is the line as written.
+    pub fn add(&mut self, message: &str, example: &str, seen: Seen) {
+        let signature = signature(message);
+        let stats = self
+            .stats
+            .entry(signature.clone())
+            .or_insert_with(|| SignatureStats {
+                signature,
+                count: 0,
+                first: seen.clone(),
+                last: seen.clone(),
+                example: example.trim().to_string(),
+            });
+        stats.count += 1;
+        stats.last = seen;
+    }
+
+    pub fn len(&self) -> usize {
+        self.stats.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stats.is_empty()
+    }
+
+    /// The `n` most frequent signatures; ties go to the alphabetically first,
+    /// as in the validation report's group ranking.
+    pub fn top(&self, n: usize) -> Vec<&SignatureStats> {
+        let mut ranked: Vec<&SignatureStats> = self.stats.values().collect();
+        ranked.sort_by(|left, right| {
+            right
+                .count
+                .cmp(&left.count)
+                .then_with(|| left.signature.cmp(&right.signature))
+        });
+        ranked.truncate(n);
+        ranked
+    }
+
+    pub fn format(&self, heading: &str, n: usize) -> String {
+        let top = self.top(n);
+        if top.is_empty() {
+            return String::new();
+        }
+        let mut output = format!("{heading} ({} of {} signatures):\n", top.len(), self.len());
+        for (rank, stats) in top.iter().enumerate() {
+            output.push_str(&format!(
+                "  {}. [{}] {}\n",
+                rank + 1,
+                stats.count,
+                stats.signature
+            ));
+            if stats.count > 1 {
+                output.push_str(&format!(
+                    "     first seen {}, last seen {}\n",
+                    stats.first.describe(),
+                    stats.last.describe()
+                ));
+            } else {
+                output.push_str(&format!("     seen {}\n", stats.first.describe()));
+            }
+            output.push_str(&format!("     example: {}\n", stats.example));
+        }
+        output
+    }
+
+    pub fn to_json(&self, n: usize) -> Value {
+        let top: Vec<Value> = self
+            .top(n)
+            .into_iter()
+            .map(|stats| {
+                json!({
+                    "signature": stats.signature,
+                    "count": stats.count,
+                    "first_seen": stats.first.to_json(),
+                    "last_seen": stats.last.to_json(),
+                    "example": stats.example,
+                })
+            })
+            .collect();
+        json!({"distinct": self.len(), "top": top})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_variable_parts() {
+        assert_eq!(
+            signature("ERROR 2026-01-07 08:07:12 FHIR validation failed for Patient/123"),
+            "FHIR validation failed for Patient/<id>"
+        );
+        assert_eq!(
+            signature("[WARN] 2026-01-07T08:09:00Z Retry 3 of 5 for 10.0.4.12:8443 after 250.5 ms"),
+            "Retry <n> of <n> for <ip> after <n> ms"
+        );
+        assert_eq!(
+            signature(
+                "ERROR 2026-01-07 08:02:40 Rejected message MSG00002 (ADT^A01): MSH|^~\\&|LAB|GH"
+            ),
+            "Rejected message <id> (ADT^A01): <hl7>"
+        );
+        assert_eq!(
+            signature(
+                "error: request 8b1a9b5b-9a7d-4d0d-8f3e-1b5e0ab56b6a trace 5f3c9a0e7d21b4c8 body={\"id\":\"1\"}"
+            ),
+            "request <uuid> trace <hex> body=<json>"
+        );
+        assert_eq!(
+            signature(
+                r#"{"level":"error","time":"2026-01-07T08:09:00Z","msg":"FHIR write rejected","payload":{}}"#
+            ),
+            "FHIR write rejected"
+        );
+        assert_eq!(
+            signature("ERROR 2026-01-07 08:06:45 Timeout while writing audit log"),
+            signature("ERROR 2026-01-07 09:16:02 Timeout while writing   audit log")
+        );
+    }
+
+    #[test]
+    fn ranks_by_count_then_signature() {
+        let mut signatures = Signatures::default();
+        let seen = |line, minute: i64| Seen {
+            file: None,
+            line,
+            time: Some(Timestamp::from_seconds(minute * 60)),
+        };
+        let mut add = |line, seen| signatures.add(line, line, seen);
+        add("ERROR Timeout while writing audit log", seen(1, 1));
+        add("ERROR FHIR validation failed for Patient/1", seen(2, 2));
+        add("ERROR FHIR validation failed for Patient/2", seen(5, 7));
+        add("ERROR Database connection failed", seen(6, 8));
+
+        let top = signatures.top(2);
+        assert_eq!(top[0].signature, "FHIR validation failed for Patient/<id>");
+        assert_eq!(top[0].count, 2);
+        assert_eq!(top[0].first.line, 2);
+        assert_eq!(top[0].last.line, 5);
+        assert_eq!(top[0].example, "ERROR FHIR validation failed for Patient/1");
+        assert_eq!(top[1].signature, "Database connection failed");
+        assert_eq!(signatures.len(), 3);
+        assert_eq!(
+            signatures.to_json(1)["top"][0]["last_seen"],
+            json!({"line": 5, "time": "1970-01-01T00:07:00"})
+        );
+    }
+}
@@ -0,0 +1,469 @@
+//! Timestamps in log lines, `--since`/`--until` bounds and per-minute or
+//! per-hour counts of errors and warnings.
+//!
+//! Timestamps with a UTC offset are converted to UTC; timestamps without one
+//! are compared as written, which is what a single log file needs.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde_json::{Value, json};
+
+use crate::civil;
+
+static ISO_TIMESTAMP: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"\b(\d{4})-(\d{2})-(\d{2})[T ](\d{2}):(\d{2})(?::(\d{2}))?(?:[.,]\d+)?(?:\s?(Z|[+-]\d{2}:?\d{2})\b)?",
+    )
+    .unwrap()
+});
+/// Apache/NCSA access logs: `[07/Jan/2026:08:05:40 +0000]`.
+static COMMON_LOG_TIMESTAMP: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\[(\d{2})/([A-Z][a-z]{2})/(\d{4}):(\d{2}):(\d{2}):(\d{2})(?: ([+-]\d{4}))?\]")
+        .unwrap()
+});
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Above this many buckets the histogram lists only the non-empty ones.
+const MAX_FILLED_BUCKETS: i64 = 1440;
+const BAR_WIDTH: u64 = 50;
+
+/// Seconds since 1970-01-01 00:00:00, to the second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(i64);
+
+impl Timestamp {
+    pub fn from_seconds(seconds: i64) -> Self {
+        Timestamp(seconds)
+    }
+
+    fn from_parts(date: (i64, u32, u32), hour: u32, minute: u32, second: u32) -> Option<Self> {
+        let (year, month, day) = date;
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+            return None;
+        }
+        // Leap seconds are folded into the last second of the minute.
+        let second = second.min(59);
+        let days = civil::days_from_civil(year, month, day);
+        Some(Timestamp(
+            days * 86_400 + i64::from(hour * 3600 + minute * 60 + second),
+        ))
+    }
+
+    pub fn seconds(self) -> i64 {
+        self.0
+    }
+
+    pub fn date(self) -> (i64, u32, u32) {
+        civil::civil_from_days(self.0.div_euclid(86_400))
+    }
+
+    /// `2026-01-07T08:05:40`, for JSON output.
+    pub fn iso(self) -> String {
+        self.to_string().replacen(' ', "T", 1)
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = self.date();
+        let rest = self.0.rem_euclid(86_400);
+        write!(
+            f,
+            "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+            rest / 3600,
+            rest % 3600 / 60,
+            rest % 60
+        )
+    }
+}
+
+/// `+05:00`, `-0500` or `Z` as seconds east of UTC.
+fn utc_offset(value: &str) -> i64 {
+    if value == "Z" {
+        return 0;
+    }
+    let sign = if value.starts_with('-') { -1 } else { 1 };
+    let digits: String = value.chars().filter(char::is_ascii_digit).collect();
+    let hours: i64 = digits.get(..2).and_then(|h| h.parse().ok()).unwrap_or(0);
+    let minutes: i64 = digits.get(2..4).and_then(|m| m.parse().ok()).unwrap_or(0);
+    sign * (hours * 3600 + minutes * 60)
+}
+
+/// The first timestamp in `line`, if any.
+pub fn parse_timestamp(line: &str) -> Option<Timestamp> {
+    let iso = ISO_TIMESTAMP.captures(line);
+    let common = COMMON_LOG_TIMESTAMP.captures(line);
+    match (iso, common) {
+        (Some(iso), Some(common)) if common.get(0)?.start() < iso.get(0)?.start() => {
+            common_log_timestamp(&common)
+        }
+        (Some(iso), _) => iso_timestamp(&iso),
+        (None, Some(common)) => common_log_timestamp(&common),
+        (None, None) => None,
+    }
+}
+
+/// `line` with every recognized timestamp removed.
+pub fn strip_timestamps(line: &str) -> String {
+    let line = ISO_TIMESTAMP.replace_all(line, "");
+    COMMON_LOG_TIMESTAMP.replace_all(&line, "").into_owned()
+}
+
+fn number<T: FromStr>(caps: &regex::Captures<'_>, index: usize) -> Option<T> {
+    caps.get(index)?.as_str().parse().ok()
+}
+
+fn iso_timestamp(caps: &regex::Captures<'_>) -> Option<Timestamp> {
+    let date = (number(caps, 1)?, number(caps, 2)?, number(caps, 3)?);
+    let local = Timestamp::from_parts(
+        date,
+        number(caps, 4)?,
+        number(caps, 5)?,
+        number(caps, 6).unwrap_or(0),
+    )?;
+    let offset = caps.get(7).map_or(0, |offset| utc_offset(offset.as_str()));
+    Some(Timestamp(local.0 - offset))
+}
+
+fn common_log_timestamp(caps: &regex::Captures<'_>) -> Option<Timestamp> {
+    let month = MONTHS.iter().position(|month| *month == &caps[2])? as u32 + 1;
+    let date = (number(caps, 3)?, month, number(caps, 1)?);
+    let local = Timestamp::from_parts(date, number(caps, 4)?, number(caps, 5)?, number(caps, 6)?)?;
+    let offset = caps.get(7).map_or(0, |offset| utc_offset(offset.as_str()));
+    Some(Timestamp(local.0 - offset))
+}
+
+/// A `--since`/`--until` value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeBound {
+    /// `2026-01-07 08:00`, `2026-01-07T08:00:00Z` or `2026-01-07` (midnight).
+    At(Timestamp),
+    /// `08:00` or `08:00:30`: on the date of the first timestamp in the log.
+    TimeOfDay(i64),
+}
+
+impl TimeBound {
+    /// The bound as a timestamp; time-of-day bounds take `reference`'s date.
+    pub fn resolve(self, reference: Timestamp) -> Timestamp {
+        match self {
+            TimeBound::At(timestamp) => timestamp,
+            TimeBound::TimeOfDay(seconds) => {
+                Timestamp(reference.0.div_euclid(86_400) * 86_400 + seconds)
+            }
+        }
+    }
+}
+
+impl FromStr for TimeBound {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let invalid = || {
+            format!(
+                "invalid time '{value}' (expected YYYY-MM-DD, YYYY-MM-DD HH:MM[:SS] or HH:MM[:SS])"
+            )
+        };
+        if let Some(caps) = ISO_TIMESTAMP.captures(value)
+            && caps.get(0).is_some_and(|whole| whole.as_str() == value)
+        {
+            return iso_timestamp(&caps).map(TimeBound::At).ok_or_else(invalid);
+        }
+        let parts: Vec<&str> = value.split(['-', ':']).collect();
+        let numbers: Option<Vec<u32>> = parts
+            .iter()
+            .map(|part| {
+                (1..=4)
+                    .contains(&part.len())
+                    .then(|| part.parse().ok())
+                    .flatten()
+            })
+            .collect();
+        let numbers = numbers.ok_or_else(invalid)?;
+        match (value.contains('-'), numbers.as_slice()) {
+            (true, [year, month, day]) => {
+                Timestamp::from_parts((i64::from(*year), *month, *day), 0, 0, 0)
+                    .map(TimeBound::At)
+                    .ok_or_else(invalid)
+            }
+            (false, [hour, minute]) | (false, [hour, minute, _]) if *hour < 24 && *minute < 60 => {
+                let second = numbers.get(2).copied().unwrap_or(0).min(59);
+                Ok(TimeBound::TimeOfDay(i64::from(
+                    hour * 3600 + minute * 60 + second,
+                )))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Keeps lines with `since <= timestamp < until`.
+#[derive(Debug, Clone, Default)]
+pub struct TimeWindow {
+    since: Option<TimeBound>,
+    until: Option<TimeBound>,
+    resolved: Option<(Option<Timestamp>, Option<Timestamp>)>,
+}
+
+impl TimeWindow {
+    pub fn new(since: Option<TimeBound>, until: Option<TimeBound>) -> Self {
+        Self {
+            since,
+            until,
+            resolved: None,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.since.is_some() || self.until.is_some()
+    }
+
+    /// Whether a line stamped `timestamp` is inside the window. Lines before
+    /// the first timestamp are only inside an unbounded window.
+    pub fn contains(&mut self, timestamp: Option<Timestamp>) -> bool {
+        if !self.is_active() {
+            return true;
+        }
+        let Some(timestamp) = timestamp else {
+            return false;
+        };
+        let (since, until) = *self.resolved.get_or_insert_with(|| {
+            (
+                self.since.map(|bound| bound.resolve(timestamp)),
+                self.until.map(|bound| bound.resolve(timestamp)),
+            )
+        });
+        since.is_none_or(|since| timestamp >= since) && until.is_none_or(|until| timestamp < until)
+    }
+
+    /// The resolved bounds, once a timestamped line has been seen.
+    pub fn bounds(&self) -> (Option<Timestamp>, Option<Timestamp>) {
+        self.resolved.unwrap_or((
+            self.since.and_then(|bound| match bound {
+                TimeBound::At(timestamp) => Some(timestamp),
+                TimeBound::TimeOfDay(_) => None,
+            }),
+            self.until.and_then(|bound| match bound {
+                TimeBound::At(timestamp) => Some(timestamp),
+                TimeBound::TimeOfDay(_) => None,
+            }),
+        ))
+    }
+}
+
+/// Histogram bucket width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bucket {
+    Minute,
+    Hour,
+}
+
+impl Bucket {
+    pub fn seconds(self) -> i64 {
+        match self {
+            Bucket::Minute => 60,
+            Bucket::Hour => 3600,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Bucket::Minute => "minute",
+            Bucket::Hour => "hour",
+        }
+    }
+}
+
+impl FromStr for Bucket {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "minute" | "min" | "m" => Ok(Bucket::Minute),
+            "hour" | "h" => Ok(Bucket::Hour),
+            other => Err(format!(
+                "unknown histogram bucket '{other}' (expected minute or hour)"
+            )),
+        }
+    }
+}
+
+/// Errors and warnings per time bucket.
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    bucket: Bucket,
+    counts: BTreeMap<i64, (u64, u64)>,
+}
+
+impl Histogram {
+    pub fn new(bucket: Bucket) -> Self {
+        Self {
+            bucket,
+            counts: BTreeMap::new(),
+        }
+    }
+
+    /// Counts a record; info-only records leave the histogram alone.
+    pub fn add(&mut self, timestamp: Timestamp, errors: u64, warnings: u64) {
+        if errors + warnings == 0 {
+            return;
+        }
+        let width = self.bucket.seconds();
+        let start = timestamp.0.div_euclid(width) * width;
+        let counts = self.counts.entry(start).or_default();
+        counts.0 += errors;
+        counts.1 += warnings;
+    }
+
+    /// `(bucket start, errors, warnings)` from the first to the last bucket with
+    /// counts, including the quiet ones in between so bursts stand out.
+    pub fn rows(&self) -> Vec<(Timestamp, u64, u64)> {
+        let (Some((first, _)), Some((last, _))) =
+            (self.counts.first_key_value(), self.counts.last_key_value())
+        else {
+            return Vec::new();
+        };
+        let width = self.bucket.seconds();
+        if (last - first) / width >= MAX_FILLED_BUCKETS {
+            return self
+                .counts
+                .iter()
+                .map(|(start, (errors, warnings))| (Timestamp(*start), *errors, *warnings))
+                .collect();
+        }
+        (0..=(last - first) / width)
+            .map(|index| {
+                let start = first + index * width;
+                let (errors, warnings) = self.counts.get(&start).copied().unwrap_or_default();
+                (Timestamp(start), errors, warnings)
+            })
+            .collect()
+    }
+
+    pub fn format(&self) -> String {
+        let rows = self.rows();
+        let mut output = format!(
+            "Errors/warnings per {} (# error, ~ warning):\n",
+            self.bucket.as_str()
+        );
+        if rows.is_empty() {
+            output.push_str("  no timestamped errors or warnings\n");
+            return output;
+        }
+        let peak = rows
+            .iter()
+            .map(|(_, errors, warnings)| errors + warnings)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let scale = |count: u64| {
+            if peak <= BAR_WIDTH {
+                count
+            } else {
+                (count * BAR_WIDTH).div_ceil(peak)
+            }
+        };
+        for (start, errors, warnings) in rows {
+            let label = &start.to_string()[..16];
+            let label = match self.bucket {
+                Bucket::Minute => label.to_string(),
+                Bucket::Hour => format!("{}:00", &label[..13]),
+            };
+            let bar = format!(
+                "{}{}",
+                "#".repeat(scale(errors) as usize),
+                "~".repeat(scale(warnings) as usize)
+            );
+            output.push_str(format!("  {label}  {errors:>3} {warnings:>3}  {bar}").trim_end());
+            output.push('\n');
+        }
+        output
+    }
+
+    pub fn to_json(&self) -> Value {
+        let buckets: Vec<Value> = self
+            .rows()
+            .into_iter()
+            .map(|(start, errors, warnings)| {
+                json!({"start": start.iso(), "errors": errors, "warnings": warnings})
+            })
+            .collect();
+        json!({"bucket": self.bucket.as_str(), "buckets": buckets})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> Timestamp {
+        match text.parse::<TimeBound>() {
+            Ok(TimeBound::At(timestamp)) => timestamp,
+            other => panic!("{text}: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_common_log_timestamps() {
+        let plain = parse_timestamp("ERROR 2026-01-07 08:05:40 Database connection failed");
+        assert_eq!(
+            plain.map(|t| t.to_string()).as_deref(),
+            Some("2026-01-07 08:05:40")
+        );
+        let json = parse_timestamp(r#"{"level":"error","time":"2026-01-07T08:09:00.123Z"}"#);
+        assert_eq!(json, Some(at("2026-01-07 08:09")));
+        let offset = parse_timestamp("2026-01-07T03:09:00-05:00 appliance rebooted");
+        assert_eq!(offset, Some(at("2026-01-07 08:09")));
+        let access =
+            parse_timestamp(r#"10.0.0.1 - - [07/Jan/2026:09:09:00 +0100] "GET /fhir/Patient""#);
+        assert_eq!(access, Some(at("2026-01-07 08:09")));
+        assert_eq!(parse_timestamp("INFO retrying in 20 ms"), None);
+        assert_eq!(parse_timestamp("build 2026-13-07 08:00:00"), None);
+    }
+
+    #[test]
+    fn windows_resolve_times_of_day_against_the_log() {
+        assert!("25:00".parse::<TimeBound>().is_err());
+        assert!("yesterday".parse::<TimeBound>().is_err());
+        assert_eq!(
+            "2026-01-07".parse(),
+            Ok(TimeBound::At(at("2026-01-07 00:00")))
+        );
+
+        let mut window = TimeWindow::new(Some("08:05".parse().unwrap()), "08:07".parse().ok());
+        assert!(!window.contains(None));
+        assert!(!window.contains(Some(at("2026-01-07 08:03:22"))));
+        assert!(window.contains(Some(at("2026-01-07 08:05:40"))));
+        assert!(window.contains(Some(at("2026-01-07 08:06:45"))));
+        assert!(!window.contains(Some(at("2026-01-07 08:07:12"))));
+        assert_eq!(window.bounds().0, Some(at("2026-01-07 08:05")));
+        assert!(TimeWindow::default().contains(None));
+    }
+
+    #[test]
+    fn histogram_fills_quiet_buckets() {
+        let mut histogram = Histogram::new(Bucket::Minute);
+        histogram.add(at("2026-01-07 08:00:01"), 0, 0);
+        histogram.add(at("2026-01-07 08:03:22"), 0, 1);
+        histogram.add(at("2026-01-07 08:05:40"), 1, 0);
+        histogram.add(at("2026-01-07 08:05:59"), 1, 0);
+        let rows = histogram.rows();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[1], (at("2026-01-07 08:04"), 0, 0));
+        assert_eq!(
+            histogram.format(),
+            "Errors/warnings per minute (# error, ~ warning):\n  2026-01-07 08:03    0   1  ~\n  2026-01-07 08:04    0   0\n  2026-01-07 08:05    2   0  ##\n"
+        );
+        assert_eq!(
+            histogram.to_json()["buckets"][2],
+            json!({"start": "2026-01-07T08:05:00", "errors": 2, "warnings": 0})
+        );
+    }
+}
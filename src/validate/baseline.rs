@@ -0,0 +1,273 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use crate::validate::report::{IssueSummary, ValidationReport, recount};
+
+const BASELINE_VERSION: u32 = 1;
+
+/// Accepted issues written by `validate --write-baseline` and applied with `--baseline`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Baseline {
+    pub version: u32,
+    #[serde(default)]
+    pub entries: Vec<BaselineEntry>,
+}
+
+/// One accepted issue group, optionally narrowed to matching expressions.
+///
+/// `expression` is a pattern where `*` matches any run of characters; generated
+/// baselines replace array indices with `[*]` so reordered Bundle entries still match.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct BaselineEntry {
+    pub group_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expression: Option<String>,
+}
+
+impl BaselineEntry {
+    fn matches(&self, group_key: &str, item: &IssueSummary) -> bool {
+        if self.group_key != group_key {
+            return false;
+        }
+        let Some(pattern) = &self.expression else {
+            return true;
+        };
+        issue_paths(item).any(|path| glob_matches(pattern, path))
+    }
+}
+
+impl Baseline {
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let text = fs::read_to_string(path)?;
+        let baseline: Baseline = serde_json::from_str(&text)
+            .map_err(|err| format!("{path}: invalid baseline: {err}"))?;
+        if baseline.version != BASELINE_VERSION {
+            return Err(format!(
+                "{path}: unsupported baseline version {} (expected {BASELINE_VERSION})",
+                baseline.version
+            )
+            .into());
+        }
+        Ok(baseline)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut text = serde_json::to_string_pretty(self)?;
+        text.push('\n');
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Accepts every issue currently in the report.
+    pub fn from_report(report: &ValidationReport) -> Self {
+        let mut entries: Vec<BaselineEntry> = report
+            .groups
+            .iter()
+            .flat_map(|(key, items)| {
+                items.iter().map(move |item| BaselineEntry {
+                    group_key: key.clone(),
+                    expression: issue_paths(item).next().map(generalize_indices),
+                })
+            })
+            .collect();
+        entries.sort();
+        entries.dedup();
+
+        Baseline {
+            version: BASELINE_VERSION,
+            entries,
+        }
+    }
+}
+
+/// Moves baselined issues out of the report's groups and recounts it.
+///
+/// Suppressed issues no longer count towards `is_failure`; entries that matched
+/// nothing are recorded as stale so the baseline can be pruned.
+pub fn apply_baseline(report: &mut ValidationReport, baseline: &Baseline) {
+    let mut used = vec![false; baseline.entries.len()];
+    let mut suppressed: BTreeMap<String, Vec<IssueSummary>> = BTreeMap::new();
+
+    for (key, items) in report.groups.iter_mut() {
+        items.retain(|item| {
+            let matched = baseline
+                .entries
+                .iter()
+                .position(|entry| entry.matches(key, item));
+            match matched {
+                Some(index) => {
+                    used[index] = true;
+                    suppressed
+                        .entry(key.clone())
+                        .or_default()
+                        .push(item.clone());
+                    false
+                }
+                None => true,
+            }
+        });
+    }
+    report.groups.retain(|_, items| !items.is_empty());
+
+    report.suppressed_count = suppressed.values().map(Vec::len).sum();
+    report.suppressed = suppressed;
+    report.stale_baseline = baseline
+        .entries
+        .iter()
+        .zip(used)
+        .filter(|(_, used)| !used)
+        .map(|(entry, _)| entry.clone())
+        .collect();
+    recount(report);
+}
+
+fn issue_paths(item: &IssueSummary) -> impl Iterator<Item = &str> {
+    let paths = if item.expression.is_empty() {
+        &item.location
+    } else {
+        &item.expression
+    };
+    paths.iter().map(String::as_str)
+}
+
+fn generalize_indices(path: &str) -> String {
+    let mut output = String::with_capacity(path.len());
+    let mut rest = path;
+    while let Some(start) = rest.find('[') {
+        output.push_str(&rest[..=start]);
+        rest = &rest[start + 1..];
+        match rest.find(']') {
+            Some(end) if end > 0 && rest[..end].bytes().all(|b| b.is_ascii_digit()) => {
+                output.push('*');
+                rest = &rest[end..];
+            }
+            _ => {}
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let remaining: Vec<&str> = parts.collect();
+    let Some((last, middle)) = remaining.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate::report::build_report;
+    use crate::validate::types::OperationOutcome;
+    use reqwest::StatusCode;
+
+    fn report(json: &str) -> ValidationReport {
+        let outcome: OperationOutcome = serde_json::from_str(json).expect("outcome parse");
+        build_report(&outcome, StatusCode::OK, "test.json", "base", "validate")
+    }
+
+    const SYNTHEA_OUTCOME: &str = r#"{
+        "resourceType":"OperationOutcome",
+        "issue":[
+            {"severity":"warning","code":"invalid","diagnostics":"Unknown identifier system","expression":["Bundle.entry[0].resource.identifier[0]"]},
+            {"severity":"warning","code":"invalid","diagnostics":"Unknown identifier system","expression":["Bundle.entry[3].resource.identifier[1]"]},
+            {"severity":"error","code":"invalid","diagnostics":"Missing id","expression":["Bundle.entry[2].resource"]}
+        ]
+    }"#;
+
+    #[test]
+    fn generalizes_array_indices_only() {
+        assert_eq!(
+            generalize_indices("Bundle.entry[12].resource.name[0].given[1]"),
+            "Bundle.entry[*].resource.name[*].given[*]"
+        );
+        assert_eq!(
+            generalize_indices("Patient.extension[url='x']"),
+            "Patient.extension[url='x']"
+        );
+    }
+
+    #[test]
+    fn glob_matches_stars_anywhere() {
+        assert!(glob_matches(
+            "Bundle.entry[*].resource",
+            "Bundle.entry[7].resource"
+        ));
+        assert!(glob_matches(
+            "*identifier*",
+            "Bundle.entry[0].resource.identifier[1]"
+        ));
+        assert!(glob_matches("Patient.id", "Patient.id"));
+        assert!(!glob_matches("Patient.id", "Patient.identifier"));
+        assert!(!glob_matches(
+            "Bundle.entry[*].resource",
+            "Bundle.entry[7].resource.id"
+        ));
+    }
+
+    #[test]
+    fn written_baseline_suppresses_everything_it_saw() {
+        let mut report = report(SYNTHEA_OUTCOME);
+        let baseline = Baseline::from_report(&report);
+        assert_eq!(baseline.entries.len(), 2);
+
+        apply_baseline(&mut report, &baseline);
+        assert_eq!(report.total, 0);
+        assert_eq!(report.error_count, 0);
+        assert_eq!(report.suppressed_count, 3);
+        assert!(report.stale_baseline.is_empty());
+        assert!(report.theme_counts.is_empty());
+    }
+
+    #[test]
+    fn new_issues_still_count_and_unused_entries_are_stale() {
+        let mut report = report(SYNTHEA_OUTCOME);
+        let baseline = Baseline {
+            version: BASELINE_VERSION,
+            entries: vec![
+                BaselineEntry {
+                    group_key: "warning | invalid | Unknown identifier system".to_string(),
+                    expression: Some("Bundle.entry[*].resource.identifier[*]".to_string()),
+                },
+                BaselineEntry {
+                    group_key: "error | invalid | Fixed long ago".to_string(),
+                    expression: None,
+                },
+            ],
+        };
+
+        apply_baseline(&mut report, &baseline);
+        assert_eq!(report.suppressed_count, 2);
+        assert_eq!(report.total, 1);
+        assert_eq!(report.error_count, 1);
+        assert_eq!(report.warning_count, 0);
+        assert_eq!(report.stale_baseline.len(), 1);
+        assert_eq!(
+            report.stale_baseline[0].group_key,
+            "error | invalid | Fixed long ago"
+        );
+    }
+
+    #[test]
+    fn baseline_round_trips_through_json() {
+        let baseline = Baseline::from_report(&report(SYNTHEA_OUTCOME));
+        let text = serde_json::to_string(&baseline).expect("serialize");
+        let parsed: Baseline = serde_json::from_str(&text).expect("parse");
+        assert_eq!(parsed, baseline);
+    }
+}
@@ -1,12 +1,16 @@
 use std::fs;
 
 use reqwest::StatusCode;
+use serde_json::Value;
 
 use crate::validate::types::FhirResource;
+use crate::validate::version::{FhirVersion, detect_version};
 
 pub struct ValidateRequest {
     pub raw: String,
     pub resource_type: String,
+    pub resource: Value,
+    pub fhir_version: Option<FhirVersion>,
 }
 
 pub struct ValidateResponse {
@@ -16,12 +20,20 @@ pub struct ValidateResponse {
 }
 
 pub fn load_request(fhir_file: &str) -> Result<ValidateRequest, Box<dyn std::error::Error>> {
-    let raw = fs::read_to_string(fhir_file)?;
-    let resource: FhirResource = serde_json::from_str(&raw)?;
+    parse_request(fs::read_to_string(fhir_file)?)
+}
+
+/// Builds a request from resource JSON that did not come from a file.
+pub fn parse_request(raw: String) -> Result<ValidateRequest, Box<dyn std::error::Error>> {
+    let resource: Value = serde_json::from_str(&raw)?;
+    let header: FhirResource = serde_json::from_value(resource.clone())?;
+    let fhir_version = detect_version(&resource);
 
     Ok(ValidateRequest {
         raw,
-        resource_type: resource.resource_type,
+        resource_type: header.resource_type,
+        resource,
+        fhir_version,
     })
 }
 
@@ -35,11 +47,16 @@ pub async fn post_validate(
         request.resource_type
     );
 
+    let mime_type = request
+        .fhir_version
+        .map(FhirVersion::mime_type)
+        .unwrap_or_else(|| "application/fhir+json".to_string());
+
     let client = reqwest::Client::new();
     let response = client
         .post(&url)
-        .header("Accept", "application/fhir+json")
-        .header("Content-Type", "application/fhir+json")
+        .header("Accept", &mime_type)
+        .header("Content-Type", &mime_type)
         .body(request.raw.clone())
         .send()
         .await?;
@@ -0,0 +1,384 @@
+//! Self-contained HTML validation reports, for sharing with people who do not
+//! read terminal output.
+
+use std::collections::BTreeMap;
+
+use crate::html::{escape, page, severity_pill};
+use crate::validate::report::{
+    IssueSummary, ValidationReport, is_failure, sorted_groups, sorted_theme_counts,
+};
+
+/// Issue groups expanded when the page opens; the rest start collapsed.
+const OPEN_GROUPS: usize = 3;
+
+/// Hides issues (and groups left empty) whose severity is unchecked, and opens
+/// the source listing before following a line link into it.
+const SCRIPT: &str = r#"<script>
+const filters = document.querySelectorAll(".filters input");
+filters.forEach((box) => box.addEventListener("change", () => {
+  const shown = new Set([...filters].filter((input) => input.checked).map((input) => input.value));
+  document.querySelectorAll(".issue").forEach((issue) => { issue.hidden = !shown.has(issue.dataset.severity); });
+  document.querySelectorAll(".issue-group").forEach((group) => { group.hidden = !group.querySelector(".issue:not([hidden])"); });
+}));
+document.querySelectorAll("a.line-link").forEach((link) => link.addEventListener("click", () => {
+  const line = document.querySelector(link.getAttribute("href"));
+  if (line) line.closest("details").open = true;
+}));
+</script>
+"#;
+
+/// One page for one or more reports. `source` is the text that was validated;
+/// when given it is listed with the lines issues point at highlighted.
+pub fn format_html_report(reports: &[(&ValidationReport, Option<&str>)]) -> String {
+    let title = match reports {
+        [(report, _)] => format!("{}: {}", report.title, report.file),
+        _ => format!("Validation of {} files", reports.len()),
+    };
+    let mut body = format!(
+        "<header class=\"report-header\">\n<p class=\"eyebrow\">ClinLogix validation report</p>\n<h1>{}</h1>\n<p>Generated by ClinLogix {}</p>\n</header>\n",
+        escape(&title),
+        env!("CARGO_PKG_VERSION")
+    );
+    body.push_str("<form class=\"filters\">\n<span>Show</span>\n");
+    for (value, label) in [
+        ("error", "Errors"),
+        ("warning", "Warnings"),
+        ("information", "Information"),
+    ] {
+        body.push_str(&format!(
+            "<label><input type=\"checkbox\" value=\"{value}\" checked /> {label}</label>\n"
+        ));
+    }
+    body.push_str("</form>\n");
+    if reports.len() > 1 {
+        body.push_str(&batch_section(reports));
+    }
+    for (index, (report, source)) in reports.iter().enumerate() {
+        body.push_str(&report_section(
+            &format!("report-{}", index + 1),
+            report,
+            *source,
+        ));
+    }
+    body.push_str(SCRIPT);
+    page(&title, &body)
+}
+
+fn batch_section(reports: &[(&ValidationReport, Option<&str>)]) -> String {
+    let mut html = String::from(
+        "<section class=\"section-card\" id=\"summary\">\n<h2>Summary</h2>\n<table>\n<thead><tr><th>File</th><th>Result</th><th class=\"number\">Errors</th><th class=\"number\">Warnings</th><th class=\"number\">Info</th></tr></thead>\n<tbody>\n",
+    );
+    for (index, (report, _)) in reports.iter().enumerate() {
+        html.push_str(&format!(
+            "<tr><td><a href=\"#report-{}\">{}</a></td><td>{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td></tr>\n",
+            index + 1,
+            escape(&report.file),
+            result_pill(report),
+            report.error_count,
+            report.warning_count,
+            report.info_count
+        ));
+    }
+    html.push_str("</tbody>\n</table>\n</section>\n");
+    html
+}
+
+fn report_section(id: &str, report: &ValidationReport, source: Option<&str>) -> String {
+    let mut html = format!(
+        "<section class=\"section-card\" id=\"{id}\">\n<h2>{} {}</h2>\n<div class=\"stats\">\n",
+        escape(&report.file),
+        result_pill(report)
+    );
+    let mut stats = vec![
+        ("Errors", report.error_count),
+        ("Warnings", report.warning_count),
+        ("Information", report.info_count),
+    ];
+    if report.suppressed_count > 0 {
+        stats.push(("Suppressed by baseline", report.suppressed_count));
+    }
+    for (label, count) in stats {
+        html.push_str(&format!(
+            "<div class=\"stat\"><strong>{count}</strong><span>{label}</span></div>\n"
+        ));
+    }
+    html.push_str("</div>\n<p class=\"validator\">");
+    if report.base_url.is_empty() {
+        html.push_str(&format!("Validator: {}", escape(&report.validate_url)));
+    } else {
+        html.push_str(&format!(
+            "Validated at <code class=\"inline-code\">{}</code> (HTTP {})",
+            escape(&report.validate_url),
+            report.status
+        ));
+    }
+    if let Some(version) = report.fhir_version {
+        html.push_str(&format!(" as FHIR {version}"));
+    }
+    if !report.policy.is_default() {
+        html.push_str(&format!("; policy: {}", escape(&report.policy.describe())));
+    }
+    html.push_str("</p>\n");
+
+    html.push_str(&themes_section(report));
+    if !report.stale_baseline.is_empty() {
+        html.push_str(&format!(
+            "<h3>Stale baseline entries ({})</h3>\n<p>No longer reported; remove them from the baseline.</p>\n<ul>\n",
+            report.stale_baseline.len()
+        ));
+        for entry in &report.stale_baseline {
+            match &entry.expression {
+                Some(expression) => html.push_str(&format!(
+                    "<li>{} @ <code>{}</code></li>\n",
+                    escape(&entry.group_key),
+                    escape(expression)
+                )),
+                None => html.push_str(&format!("<li>{}</li>\n", escape(&entry.group_key))),
+            }
+        }
+        html.push_str("</ul>\n");
+    }
+    html.push_str(&groups_section(id, report, source.is_some()));
+    if let Some(source) = source {
+        html.push_str(&source_section(id, report, source));
+    }
+    html.push_str("</section>\n");
+    html
+}
+
+fn themes_section(report: &ValidationReport) -> String {
+    if report.theme_counts.is_empty() {
+        return String::new();
+    }
+    let mut html = String::from(
+        "<h3>Themes</h3>\n<table>\n<thead><tr><th>Theme</th><th class=\"number\">Issues</th></tr></thead>\n<tbody>\n",
+    );
+    let themes = sorted_theme_counts(&report.theme_counts);
+    for (theme, count) in &themes {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td class=\"number\">{count}</td></tr>\n",
+            escape(theme)
+        ));
+    }
+    html.push_str("</tbody>\n</table>\n");
+    for (theme, _) in &themes {
+        let Some(hint) = report.theme_hints.get(theme) else {
+            continue;
+        };
+        for text in [&hint.hint, &hint.recommendation].into_iter().flatten() {
+            html.push_str(&format!(
+                "<p class=\"callout\"><strong>{}:</strong> {}</p>\n",
+                escape(theme),
+                escape(text)
+            ));
+        }
+    }
+    html
+}
+
+fn groups_section(id: &str, report: &ValidationReport, linked: bool) -> String {
+    if report.groups.is_empty() {
+        return "<h3>Issues</h3>\n<p>No issues reported.</p>\n".to_string();
+    }
+    let mut html = format!(
+        "<h3>Issues ({} in {} groups)</h3>\n",
+        report.total,
+        report.groups.len()
+    );
+    for (index, (key, items)) in sorted_groups(&report.groups).into_iter().enumerate() {
+        html.push_str(&format!(
+            "<details class=\"issue-group\"{}>\n<summary>{} {} <span class=\"count\">x{}</span></summary>\n<ul>\n",
+            if index < OPEN_GROUPS { " open" } else { "" },
+            severity_pill(items.first().map(|item| item.severity.as_str())),
+            escape(&key),
+            items.len()
+        ));
+        for item in &items {
+            html.push_str(&issue_item(id, item, linked));
+        }
+        html.push_str("</ul>\n</details>\n");
+    }
+    html
+}
+
+fn issue_item(id: &str, item: &IssueSummary, linked: bool) -> String {
+    let mut details = Vec::new();
+    if !item.expression.is_empty() {
+        details.push(format!(
+            "expression <code>{}</code>",
+            escape(&item.expression.join(", "))
+        ));
+    }
+    if !item.location.is_empty() {
+        details.push(format!(
+            "location <code>{}</code>",
+            escape(&item.location.join(", "))
+        ));
+    }
+    if let Some(line) = item.line {
+        let position = match item.column {
+            Some(column) => format!("line {line}, column {column}"),
+            None => format!("line {line}"),
+        };
+        details.push(if linked {
+            format!("<a class=\"line-link\" href=\"#{id}-line-{line}\">{position}</a>")
+        } else {
+            position
+        });
+    }
+    if let Some(message_id) = &item.message_id {
+        details.push(format!("rule {}", escape(message_id)));
+    }
+    details.push(format!("theme {}", escape(&item.theme)));
+    format!(
+        "<li class=\"issue\" data-severity=\"{}\">{}<br /><small>{}</small></li>\n",
+        filter_class(&item.severity),
+        if item.message.is_empty() {
+            "(no diagnostics provided)".to_string()
+        } else {
+            escape(&item.message)
+        },
+        details.join(" · ")
+    )
+}
+
+/// The source listing; lines an issue points at are highlighted with the
+/// worst severity on them and carry the messages as a tooltip.
+fn source_section(id: &str, report: &ValidationReport, source: &str) -> String {
+    let mut flagged: BTreeMap<u32, (&str, Vec<&str>)> = BTreeMap::new();
+    for item in report.groups.values().flatten() {
+        if let Some(line) = item.line {
+            let (severity, messages) = flagged
+                .entry(line)
+                .or_insert((item.severity.as_str(), Vec::new()));
+            if severity_rank(&item.severity) < severity_rank(severity) {
+                *severity = &item.severity;
+            }
+            messages.push(&item.message);
+        }
+    }
+    let mut html = format!(
+        "<details class=\"source\"{}>\n<summary>Validated source ({} flagged lines)</summary>\n<ol class=\"source-lines\">\n",
+        if flagged.is_empty() { "" } else { " open" },
+        flagged.len()
+    );
+    for (number, text) in (1..).zip(source.lines()) {
+        match flagged.get(&number) {
+            Some((severity, messages)) => html.push_str(&format!(
+                "<li id=\"{id}-line-{number}\" class=\"flagged {}\" title=\"{}\">{}</li>\n",
+                filter_class(severity),
+                escape(&messages.join("\n")),
+                escape(text)
+            )),
+            None => html.push_str(&format!(
+                "<li id=\"{id}-line-{number}\">{}</li>\n",
+                escape(text)
+            )),
+        }
+    }
+    html.push_str("</ol>\n</details>\n");
+    html
+}
+
+fn result_pill(report: &ValidationReport) -> &'static str {
+    if is_failure(report) {
+        "<span class=\"pill fail\">fail</span>"
+    } else {
+        "<span class=\"pill pass\">pass</span>"
+    }
+}
+
+/// The severity filter an issue falls under: fatal counts as an error, like
+/// in the report's counts, and anything unrecognised as information.
+pub(super) fn filter_class(severity: &str) -> &'static str {
+    match severity {
+        "error" | "fatal" => "error",
+        "warning" => "warning",
+        _ => "information",
+    }
+}
+
+fn severity_rank(severity: &str) -> u8 {
+    match severity {
+        "fatal" => 0,
+        "error" => 1,
+        "warning" => 2,
+        _ => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate::report::build_report;
+    use crate::validate::types::{Issue, OperationOutcome};
+    use reqwest::StatusCode;
+
+    fn report() -> ValidationReport {
+        let outcome = OperationOutcome {
+            resource_type: Some("OperationOutcome".to_string()),
+            issue: vec![
+                Issue::local(
+                    "error",
+                    "invalid",
+                    "Unknown code <XYZ>".to_string(),
+                    "Patient.gender",
+                )
+                .with_line(4),
+                Issue::local("warning", "invariant", "dom-6".to_string(), "Patient").with_line(1),
+                Issue::local(
+                    "information",
+                    "informational",
+                    "Note".to_string(),
+                    "Patient",
+                ),
+            ],
+            ..Default::default()
+        };
+        build_report(
+            &outcome,
+            StatusCode::OK,
+            "patient.json",
+            "https://fhir.example",
+            "https://fhir.example/Patient/$validate",
+        )
+    }
+
+    #[test]
+    fn renders_groups_filters_and_highlighted_source() {
+        let report = report();
+        let source =
+            "{\n  \"resourceType\": \"Patient\",\n  \"id\": \"p1\",\n  \"gender\": \"XYZ\"\n}";
+        let html = format_html_report(&[(&report, Some(source))]);
+        assert!(html.starts_with("<!doctype html>"));
+        assert!(html.contains("<title>FHIR Validation: patient.json</title>"));
+        assert!(html.contains("<span class=\"pill fail\">fail</span>"));
+        assert!(html.contains("value=\"warning\" checked"));
+        assert_eq!(
+            html.matches("<details class=\"issue-group\" open>").count(),
+            3
+        );
+        assert!(
+            html.contains("<li class=\"issue\" data-severity=\"error\">Unknown code &lt;XYZ&gt;")
+        );
+        assert!(html.contains("<a class=\"line-link\" href=\"#report-1-line-4\">line 4</a>"));
+        assert!(html.contains(
+            "<li id=\"report-1-line-4\" class=\"flagged error\" title=\"Unknown code &lt;XYZ&gt;\">  &quot;gender&quot;: &quot;XYZ&quot;</li>"
+        ));
+        assert!(html.contains("<li id=\"report-1-line-2\">"));
+        assert!(!html.contains("id=\"summary\""));
+    }
+
+    #[test]
+    fn batches_link_each_file_from_a_summary() {
+        let first = report();
+        let mut second = report();
+        second.file = "observation.json".to_string();
+        let html = format_html_report(&[(&first, None), (&second, None)]);
+        assert!(html.contains("<title>Validation of 2 files</title>"));
+        assert!(html.contains("<a href=\"#report-2\">observation.json</a>"));
+        assert!(html.contains("<section class=\"section-card\" id=\"report-2\">"));
+        assert!(!html.contains("<a class=\"line-link\""));
+        assert!(!html.contains("<ol class=\"source-lines\">"));
+    }
+}
@@ -1,34 +1,235 @@
+mod baseline;
 mod client;
+mod html;
+mod policy;
 mod report;
+mod theme;
+mod tui;
 mod types;
+mod version;
 
+use std::io::IsTerminal;
+
+#[allow(unused_imports)]
+pub use baseline::{Baseline, BaselineEntry, apply_baseline};
 #[allow(unused_imports)]
-pub use client::{ValidateRequest, ValidateResponse, load_request, post_validate};
+pub use client::{ValidateRequest, ValidateResponse, load_request, parse_request, post_validate};
+pub use html::format_html_report;
+#[allow(unused_imports)]
+pub use policy::{FailOn, FailurePolicy};
+pub(crate) use report::sorted_groups;
 #[allow(unused_imports)]
 pub use report::{
-    IssueSummary, PROFILE_RESOLUTION_THEME, ValidationReport, build_report, format_report,
-    is_failure, parse_operation_outcome, print_report,
+    IssueSummary, ValidationReport, build_local_report, build_report, build_report_with_themes,
+    format_report, is_failure, parse_operation_outcome, print_report,
+};
+#[allow(unused_imports)]
+pub use theme::{
+    GENERAL_THEME, PROFILE_RESOLUTION_THEME, ThemeHint, ThemeInput, ThemeRule, ThemeRules,
+};
+#[allow(unused_imports)]
+pub use types::{
+    CodeableConcept, Coding, Extension, FhirResource, ISSUE_COL_EXTENSION, ISSUE_LINE_EXTENSION,
+    ISSUE_SOURCE_EXTENSION, Issue, LOCAL_ISSUE_SOURCE, MESSAGE_ID_EXTENSION, Meta, Narrative,
+    OperationOutcome,
 };
 #[allow(unused_imports)]
-pub use types::{CodeableConcept, FhirResource, Issue, OperationOutcome};
+pub use version::{FhirVersion, check_structure, detect_version};
+
+/// Options for a single `validate` run beyond the file and server.
+#[derive(Debug, Clone, Default)]
+pub struct ValidateOptions {
+    /// Overrides the FHIR version detected from the resource.
+    pub fhir_version: Option<FhirVersion>,
+    /// TOML theme rule file layered over the built-in taxonomy.
+    pub themes: Option<String>,
+    /// Baseline file of accepted issues to suppress.
+    pub baseline: Option<String>,
+    /// Writes every issue of this run to a new baseline file, then applies it.
+    pub write_baseline: Option<String>,
+    /// Decides which issues fail the run.
+    pub policy: FailurePolicy,
+}
+
+pub use crate::error::{EXIT_BAD_INPUT, EXIT_TRANSPORT_FAILED, EXIT_VALIDATION_FAILED};
+
+/// Why a `validate` run did not pass, so callers (and CI) can react differently.
+#[derive(Debug)]
+pub enum ValidateError {
+    /// The resource, a rule file or a baseline could not be read or parsed.
+    Input(String),
+    /// The server (or MLLP peer) could not be reached or did not answer as expected.
+    Transport(String),
+    /// The report fails the failure policy; holds what was validated ("FHIR", "HL7 v2").
+    Failed(&'static str),
+}
+
+impl ValidateError {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ValidateError::Failed(_) => EXIT_VALIDATION_FAILED,
+            ValidateError::Input(_) => EXIT_BAD_INPUT,
+            ValidateError::Transport(_) => EXIT_TRANSPORT_FAILED,
+        }
+    }
+
+    fn input(err: impl std::fmt::Display) -> Self {
+        ValidateError::Input(err.to_string())
+    }
+}
+
+impl std::fmt::Display for ValidateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidateError::Input(message) => write!(f, "invalid input: {message}"),
+            ValidateError::Transport(message) => write!(f, "transport error: {message}"),
+            ValidateError::Failed(kind) => write!(f, "{kind} validation failed"),
+        }
+    }
+}
+
+impl std::error::Error for ValidateError {}
 
 pub async fn run_validate(
     fhir_file: &str,
     base_url: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let request = client::load_request(fhir_file)?;
-    let response = client::post_validate(&request, base_url).await?;
-    let outcome = report::parse_operation_outcome(&response.body_text);
-    let report = report::build_report(
+    options: &ValidateOptions,
+) -> Result<(), ValidateError> {
+    run_validate_files(&[fhir_file.to_string()], base_url, options, None).await
+}
+
+/// Validates and prints each file in turn, then writes one HTML page for all
+/// of them to `html` if given.
+///
+/// A file that cannot be read or validated stops the run; files that fail
+/// the policy do not, so the page covers the whole batch.
+pub async fn run_validate_files(
+    fhir_files: &[String],
+    base_url: &str,
+    options: &ValidateOptions,
+    html: Option<&str>,
+) -> Result<(), ValidateError> {
+    if fhir_files.len() > 1 && options.write_baseline.is_some() {
+        return Err(ValidateError::Input(
+            "--write-baseline takes a single file".to_string(),
+        ));
+    }
+    let mut reports = Vec::with_capacity(fhir_files.len());
+    for file in fhir_files {
+        let report = validate_report(file, base_url, options).await?;
+        report::print_report(&report);
+        // The page lists the source too; a file that vanished since is shown without it.
+        let source = html.and_then(|_| std::fs::read_to_string(file).ok());
+        reports.push((report, source));
+    }
+    if let Some(path) = html {
+        let pages: Vec<_> = reports
+            .iter()
+            .map(|(report, source)| (report, source.as_deref()))
+            .collect();
+        std::fs::write(path, format_html_report(&pages))
+            .map_err(|err| ValidateError::Input(format!("{path}: {err}")))?;
+        eprintln!("Wrote HTML validation report to {path}");
+    }
+    if reports.iter().any(|(report, _)| report::is_failure(report)) {
+        return Err(ValidateError::Failed("FHIR"));
+    }
+    Ok(())
+}
+
+/// Validates a file and opens the report in the terminal browser instead of
+/// printing it; the exit status is the same as for [`run_validate`].
+pub async fn run_validate_tui(
+    fhir_file: &str,
+    base_url: &str,
+    options: &ValidateOptions,
+) -> Result<(), ValidateError> {
+    if !std::io::stdout().is_terminal() {
+        return Err(ValidateError::Input(
+            "--tui needs an interactive terminal".to_string(),
+        ));
+    }
+    let report = validate_report(fhir_file, base_url, options).await?;
+    let source = std::fs::read_to_string(fhir_file)
+        .map_err(|err| ValidateError::Input(format!("{fhir_file}: {err}")))?;
+    tui::browse(&report, &source)
+        .map_err(|err| ValidateError::Input(format!("terminal: {err}")))?;
+    if report::is_failure(&report) {
+        return Err(ValidateError::Failed("FHIR"));
+    }
+    Ok(())
+}
+
+/// Validates a file and returns the report (with baselines applied) without printing it.
+///
+/// A non-2xx answer that is not an OperationOutcome is a [`ValidateError::Transport`].
+pub async fn validate_report(
+    fhir_file: &str,
+    base_url: &str,
+    options: &ValidateOptions,
+) -> Result<ValidationReport, ValidateError> {
+    let request = client::load_request(fhir_file)
+        .map_err(|err| ValidateError::Input(format!("{fhir_file}: {err}")))?;
+    validate_request(request, fhir_file, base_url, options).await
+}
+
+/// Validates a loaded resource; `file` names where it came from in the report.
+pub async fn validate_request(
+    mut request: ValidateRequest,
+    file: &str,
+    base_url: &str,
+    options: &ValidateOptions,
+) -> Result<ValidationReport, ValidateError> {
+    let baseline = options
+        .baseline
+        .as_deref()
+        .map(Baseline::load)
+        .transpose()
+        .map_err(ValidateError::input)?;
+    let themes = match &options.themes {
+        Some(path) => ThemeRules::load(path).map_err(ValidateError::input)?,
+        None => ThemeRules::builtin().clone(),
+    };
+    if options.fhir_version.is_some() {
+        request.fhir_version = options.fhir_version;
+    }
+    let response = client::post_validate(&request, base_url)
+        .await
+        .map_err(|err| ValidateError::Transport(err.to_string()))?;
+    let mut outcome = report::parse_operation_outcome(&response.body_text);
+    if !response.status.is_success() && outcome.resource_type.as_deref() != Some("OperationOutcome")
+    {
+        return Err(ValidateError::Transport(format!(
+            "{} answered HTTP {} without an OperationOutcome",
+            response.url, response.status
+        )));
+    }
+    if let Some(version) = request.fhir_version {
+        outcome
+            .issue
+            .extend(version::check_structure(&request.resource, version));
+    }
+    let mut report = report::build_report_with_themes(
         &outcome,
         response.status,
-        fhir_file,
+        file,
         base_url,
         &response.url,
+        &themes,
     );
-    report::print_report(&report);
-    if report::is_failure(&report) {
-        return Err("FHIR validation failed".into());
+    report.fhir_version = request.fhir_version;
+    report.policy = options.policy.clone();
+    if let Some(path) = &options.write_baseline {
+        let baseline = Baseline::from_report(&report);
+        baseline.save(path).map_err(ValidateError::input)?;
+        eprintln!(
+            "Wrote baseline with {} entries to {}",
+            baseline.entries.len(),
+            path
+        );
+        apply_baseline(&mut report, &baseline);
+    } else if let Some(baseline) = &baseline {
+        apply_baseline(&mut report, baseline);
     }
-    Ok(())
+    Ok(report)
 }
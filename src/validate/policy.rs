@@ -0,0 +1,245 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::validate::report::{IssueSummary, ValidationReport};
+
+/// Lowest severity that can fail a run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FailOn {
+    Information,
+    Warning,
+    #[default]
+    Error,
+}
+
+impl FromStr for FailOn {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "error" | "errors" | "fatal" => Ok(FailOn::Error),
+            "warning" | "warnings" => Ok(FailOn::Warning),
+            "information" | "info" => Ok(FailOn::Information),
+            other => Err(format!(
+                "unknown severity '{other}' (expected error, warning or information)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for FailOn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FailOn::Information => "information",
+            FailOn::Warning => "warning",
+            FailOn::Error => "error",
+        })
+    }
+}
+
+/// When a validation report counts as a failure.
+///
+/// The default matches ClinLogix's original behavior: any error or a non-2xx response fails.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FailurePolicy {
+    pub fail_on: FailOn,
+    /// Errors tolerated before the run fails.
+    pub max_errors: usize,
+    /// Warnings tolerated before the run fails (only with `fail_on` warning or lower).
+    pub max_warnings: usize,
+    /// Themes (case-insensitive substrings) whose issues never fail the run.
+    pub non_fatal_themes: Vec<String>,
+    /// When set, only issues in these themes (case-insensitive substrings) can fail the run.
+    pub fail_themes: Vec<String>,
+    /// When set, only issues with these OperationOutcome codes can fail the run.
+    pub fail_codes: Vec<String>,
+    /// Judge non-2xx responses that carry an OperationOutcome by their issues alone.
+    pub ignore_http_status: bool,
+}
+
+impl FailurePolicy {
+    pub fn is_default(&self) -> bool {
+        self == &FailurePolicy::default()
+    }
+
+    pub fn is_failure(&self, report: &ValidationReport) -> bool {
+        if !report.status.is_success() {
+            let explained = report.total + report.suppressed_count > 0;
+            // Servers such as HAPI answer 412 for any error, so a validation
+            // response whose issues the baseline all accepted is accepted with
+            // them. Server errors (5xx) still fail.
+            let validation_status = matches!(report.status.as_u16(), 400 | 412 | 422);
+            let all_suppressed =
+                validation_status && report.suppressed_count > 0 && report.total == 0;
+            if !all_suppressed && (!self.ignore_http_status || !explained) {
+                return true;
+            }
+        }
+
+        let mut errors = 0;
+        let mut warnings = 0;
+        let mut information = 0;
+        for item in report.groups.values().flatten() {
+            if !self.counts(item) {
+                continue;
+            }
+            match item.severity.as_str() {
+                "error" | "fatal" => errors += 1,
+                "warning" => warnings += 1,
+                "information" => information += 1,
+                _ => {}
+            }
+        }
+
+        errors > self.max_errors
+            || (self.fail_on <= FailOn::Warning && warnings > self.max_warnings)
+            || (self.fail_on == FailOn::Information && information > 0)
+    }
+
+    fn counts(&self, item: &IssueSummary) -> bool {
+        if theme_matches(&self.non_fatal_themes, &item.theme) {
+            return false;
+        }
+        if self.fail_themes.is_empty() && self.fail_codes.is_empty() {
+            return true;
+        }
+        theme_matches(&self.fail_themes, &item.theme)
+            || self.fail_codes.iter().any(|code| code == &item.code)
+    }
+
+    /// One-line description for the report header.
+    pub fn describe(&self) -> String {
+        let mut parts = vec![format!("fail on {}", self.fail_on)];
+        if self.max_errors > 0 {
+            parts.push(format!("allow {} errors", self.max_errors));
+        }
+        if self.max_warnings > 0 && self.fail_on <= FailOn::Warning {
+            parts.push(format!("allow {} warnings", self.max_warnings));
+        }
+        if !self.non_fatal_themes.is_empty() {
+            parts.push(format!("non-fatal: {}", self.non_fatal_themes.join(", ")));
+        }
+        if !self.fail_themes.is_empty() {
+            parts.push(format!("only themes: {}", self.fail_themes.join(", ")));
+        }
+        if !self.fail_codes.is_empty() {
+            parts.push(format!("only codes: {}", self.fail_codes.join(", ")));
+        }
+        if self.ignore_http_status {
+            parts.push("ignore HTTP status".to_string());
+        }
+        parts.join("; ")
+    }
+}
+
+fn theme_matches(patterns: &[String], theme: &str) -> bool {
+    let theme = theme.to_lowercase();
+    patterns
+        .iter()
+        .any(|pattern| theme.contains(&pattern.to_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate::report::{build_report, parse_operation_outcome};
+    use reqwest::StatusCode;
+
+    fn report(status: StatusCode, body: &str) -> ValidationReport {
+        let outcome = parse_operation_outcome(body);
+        build_report(&outcome, status, "test.json", "base", "validate")
+    }
+
+    const MIXED: &str = r#"{
+        "resourceType":"OperationOutcome",
+        "issue":[
+            {"severity":"error","code":"processing","diagnostics":"Unable to resolve reference to profile 'http://hl7.org/fhir/us/core/StructureDefinition/us-core-patient'"},
+            {"severity":"warning","code":"code-invalid","diagnostics":"Unknown code in value set"}
+        ]
+    }"#;
+
+    #[test]
+    fn default_policy_matches_original_failure_logic() {
+        let policy = FailurePolicy::default();
+        assert!(policy.is_failure(&report(StatusCode::OK, MIXED)));
+        assert!(policy.is_failure(&report(
+            StatusCode::BAD_REQUEST,
+            r#"{"resourceType":"OperationOutcome","issue":[]}"#
+        )));
+        assert!(!policy.is_failure(&report(
+            StatusCode::OK,
+            r#"{"resourceType":"OperationOutcome","issue":[{"severity":"warning","code":"invalid"}]}"#
+        )));
+    }
+
+    #[test]
+    fn profile_resolution_can_be_non_fatal_while_warnings_fail() {
+        let lenient = FailurePolicy {
+            non_fatal_themes: vec!["profile resolution".to_string()],
+            ..Default::default()
+        };
+        assert!(!lenient.is_failure(&report(StatusCode::OK, MIXED)));
+
+        let strict_warnings = FailurePolicy {
+            fail_on: FailOn::Warning,
+            ..lenient
+        };
+        assert!(strict_warnings.is_failure(&report(StatusCode::OK, MIXED)));
+    }
+
+    #[test]
+    fn caps_and_code_filters_limit_what_fails() {
+        let capped = FailurePolicy {
+            max_errors: 1,
+            ..Default::default()
+        };
+        assert!(!capped.is_failure(&report(StatusCode::OK, MIXED)));
+
+        let only_terminology_codes = FailurePolicy {
+            fail_on: FailOn::Warning,
+            fail_codes: vec!["code-invalid".to_string()],
+            ..Default::default()
+        };
+        assert!(only_terminology_codes.is_failure(&report(StatusCode::OK, MIXED)));
+
+        let only_invariants = FailurePolicy {
+            fail_themes: vec!["invariant".to_string()],
+            ..Default::default()
+        };
+        assert!(!only_invariants.is_failure(&report(StatusCode::OK, MIXED)));
+    }
+
+    #[test]
+    fn ignoring_http_status_still_fails_unexplained_responses() {
+        let policy = FailurePolicy {
+            non_fatal_themes: vec!["profile".to_string()],
+            ignore_http_status: true,
+            ..Default::default()
+        };
+        let profile_only = r#"{"resourceType":"OperationOutcome","issue":[{"severity":"error","code":"processing","diagnostics":"Unable to resolve reference to profile 'x'"}]}"#;
+        assert!(!policy.is_failure(&report(StatusCode::PRECONDITION_FAILED, profile_only)));
+        assert!(policy.is_failure(&report(StatusCode::INTERNAL_SERVER_ERROR, "<html>")));
+    }
+
+    #[test]
+    fn baselined_issues_only_excuse_validation_statuses() {
+        let policy = FailurePolicy::default();
+        let empty = r#"{"resourceType":"OperationOutcome","issue":[]}"#;
+        for (status, fails) in [
+            (StatusCode::PRECONDITION_FAILED, false),
+            (StatusCode::UNPROCESSABLE_ENTITY, false),
+            (StatusCode::INTERNAL_SERVER_ERROR, true),
+        ] {
+            let mut baselined = report(status, empty);
+            baselined.suppressed_count = 1;
+            assert_eq!(policy.is_failure(&baselined), fails, "{status}");
+        }
+    }
+
+    #[test]
+    fn parses_fail_on_values() {
+        assert_eq!("warning".parse::<FailOn>(), Ok(FailOn::Warning));
+        assert_eq!("INFO".parse::<FailOn>(), Ok(FailOn::Information));
+        assert!("debug".parse::<FailOn>().is_err());
+    }
+}
@@ -2,12 +2,11 @@ use std::collections::BTreeMap;
 
 use reqwest::StatusCode;
 
-use crate::validate::types::{Issue, OperationOutcome};
-
-pub const PROFILE_RESOLUTION_THEME: &str = "Profile resolution (missing profiles on server)";
-const PROFILE_RESOLUTION_HINT: &str = "This usually means the server doesn't have the required implementation guide packages (e.g., US Core) installed.";
-
-const PROFILE_RESOLUTION_RECOMMENDATION: &str = "Consider validating against a server that supports the required IG packages (e.g., US Core), or install those packages on your validator server.";
+use crate::validate::baseline::BaselineEntry;
+use crate::validate::policy::FailurePolicy;
+use crate::validate::theme::{ThemeHint, ThemeInput, ThemeRules};
+use crate::validate::types::{Coding, Issue, OperationOutcome};
+use crate::validate::version::FhirVersion;
 
 #[derive(Debug, Clone)]
 pub struct IssueSummary {
@@ -18,19 +17,34 @@ pub struct IssueSummary {
     pub expression: Vec<String>,
     pub theme: String,
     pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub source: Option<String>,
+    pub message_id: Option<String>,
+    pub codings: Vec<Coding>,
 }
 
 pub struct ValidationReport {
+    /// Heading printed above the report ("FHIR Validation", "HL7 v2 Validation", ...).
+    pub title: String,
     pub status: StatusCode,
     pub file: String,
     pub base_url: String,
     pub validate_url: String,
+    pub fhir_version: Option<FhirVersion>,
     pub total: usize,
     pub error_count: usize,
     pub warning_count: usize,
     pub info_count: usize,
     pub groups: BTreeMap<String, Vec<IssueSummary>>,
     pub theme_counts: BTreeMap<String, usize>,
+    pub theme_hints: BTreeMap<String, ThemeHint>,
+    /// Issues accepted by a baseline; excluded from the counts above.
+    pub suppressed: BTreeMap<String, Vec<IssueSummary>>,
+    pub suppressed_count: usize,
+    /// Baseline entries that matched no issue in this run.
+    pub stale_baseline: Vec<BaselineEntry>,
+    /// Decides whether this report fails the run.
+    pub policy: FailurePolicy,
 }
 
 pub fn parse_operation_outcome(body_text: &str) -> OperationOutcome {
@@ -45,43 +59,109 @@ pub fn build_report(
     base_url: &str,
     validate_url: &str,
 ) -> ValidationReport {
-    let mut groups: BTreeMap<String, Vec<IssueSummary>> = BTreeMap::new();
-    let mut theme_counts: BTreeMap<String, usize> = BTreeMap::new();
-    let mut error_count = 0;
-    let mut warning_count = 0;
-    let mut info_count = 0;
+    build_report_with_themes(
+        outcome,
+        status,
+        file,
+        base_url,
+        validate_url,
+        ThemeRules::builtin(),
+    )
+}
 
+/// Same as [`build_report`], classifying issues with a custom theme taxonomy.
+pub fn build_report_with_themes(
+    outcome: &OperationOutcome,
+    status: StatusCode,
+    file: &str,
+    base_url: &str,
+    validate_url: &str,
+    themes: &ThemeRules,
+) -> ValidationReport {
+    let mut groups: BTreeMap<String, Vec<IssueSummary>> = BTreeMap::new();
     for issue in &outcome.issue {
-        let summary = summarize_issue(issue);
-        match summary.severity.as_str() {
-            "error" | "fatal" => error_count += 1,
-            "warning" => warning_count += 1,
-            "information" => info_count += 1,
-            _ => {}
-        }
-
+        let summary = summarize_issue(issue, themes);
         let key = group_key(&summary);
-        groups.entry(key).or_default().push(summary.clone());
-        *theme_counts.entry(summary.theme.clone()).or_insert(0) += 1;
+        groups.entry(key).or_default().push(summary);
     }
 
-    ValidationReport {
+    let mut report = ValidationReport {
+        title: "FHIR Validation".to_string(),
         status,
         file: file.to_string(),
         base_url: base_url.to_string(),
         validate_url: validate_url.to_string(),
-        total: outcome.issue.len(),
-        error_count,
-        warning_count,
-        info_count,
+        fhir_version: None,
+        total: 0,
+        error_count: 0,
+        warning_count: 0,
+        info_count: 0,
         groups,
-        theme_counts,
+        theme_counts: BTreeMap::new(),
+        theme_hints: BTreeMap::new(),
+        suppressed: BTreeMap::new(),
+        suppressed_count: 0,
+        stale_baseline: Vec::new(),
+        policy: FailurePolicy::default(),
+    };
+    recount(&mut report);
+    report.theme_hints = report
+        .theme_counts
+        .keys()
+        .filter_map(|theme| Some((theme.clone(), themes.hint(theme)?)))
+        .collect();
+    report
+}
+
+/// Report for checks ClinLogix ran itself, without a validation server.
+///
+/// `validator` describes what ran (for example "HL7 v2.5.1 ADT_A01") and is
+/// printed in place of the server URLs.
+pub fn build_local_report(
+    outcome: &OperationOutcome,
+    title: &str,
+    file: &str,
+    validator: &str,
+    themes: &ThemeRules,
+) -> ValidationReport {
+    let mut report = build_report_with_themes(outcome, StatusCode::OK, file, "", validator, themes);
+    report.title = title.to_string();
+    report
+}
+
+/// Recomputes totals, severity and theme counts from `report.groups`.
+pub(crate) fn recount(report: &mut ValidationReport) {
+    report.total = 0;
+    report.error_count = 0;
+    report.warning_count = 0;
+    report.info_count = 0;
+    report.theme_counts.clear();
+
+    for item in report.groups.values().flatten() {
+        report.total += 1;
+        match item.severity.as_str() {
+            "error" | "fatal" => report.error_count += 1,
+            "warning" => report.warning_count += 1,
+            "information" => report.info_count += 1,
+            _ => {}
+        }
+        *report.theme_counts.entry(item.theme.clone()).or_insert(0) += 1;
     }
+
+    let theme_counts = &report.theme_counts;
+    report
+        .theme_hints
+        .retain(|theme, _| theme_counts.contains_key(theme));
 }
 
-fn summarize_issue(issue: &Issue) -> IssueSummary {
+fn summarize_issue(issue: &Issue, themes: &ThemeRules) -> IssueSummary {
     let severity = issue.severity.as_deref().unwrap_or("unknown").to_string();
     let code = issue.code.as_deref().unwrap_or("unknown").to_string();
+    let codings = issue
+        .details
+        .as_ref()
+        .map(|details| details.coding.clone())
+        .unwrap_or_default();
     let message = issue
         .diagnostics
         .clone()
@@ -91,9 +171,16 @@ fn summarize_issue(issue: &Issue) -> IssueSummary {
                 .as_ref()
                 .and_then(|details| details.text.clone())
         })
+        .or_else(|| codings.iter().find_map(coding_label))
         .unwrap_or_default();
-    let theme = classify_theme(&message);
-    let line = extract_line_number(&message);
+    let message_id = issue.message_id().map(str::to_string);
+    let theme = themes.classify(&ThemeInput {
+        code: &code,
+        message: &message,
+        message_id: message_id.as_deref(),
+        codings: &codings,
+    });
+    let line = issue.line().or_else(|| extract_line_number(&message));
 
     IssueSummary {
         severity,
@@ -103,23 +190,26 @@ fn summarize_issue(issue: &Issue) -> IssueSummary {
         expression: issue.expression.clone(),
         theme,
         line,
+        column: issue.column(),
+        source: issue.source().map(str::to_string),
+        message_id,
+        codings,
     }
 }
 
-fn classify_theme(message: &str) -> String {
-    if is_profile_resolution_issue(message) {
-        PROFILE_RESOLUTION_THEME.to_string()
-    } else {
-        "General".to_string()
+/// Falls back to `details.coding` when a server sends neither diagnostics nor text,
+/// so such issues still group by the rule they came from.
+fn coding_label(coding: &Coding) -> Option<String> {
+    match (&coding.display, &coding.code) {
+        (Some(display), _) => Some(display.clone()),
+        (None, Some(code)) => Some(match &coding.system {
+            Some(system) => format!("{system}#{code}"),
+            None => code.clone(),
+        }),
+        (None, None) => None,
     }
 }
 
-fn is_profile_resolution_issue(message: &str) -> bool {
-    let message_lower = message.to_lowercase();
-    message_lower.contains("unable to resolve reference to profile")
-        || (message_lower.contains("profile") && message_lower.contains("resolve"))
-}
-
 fn extract_line_number(message: &str) -> Option<u32> {
     let lower = message.to_lowercase();
     let needle = "line:";
@@ -153,12 +243,21 @@ fn extract_line_number(message: &str) -> Option<u32> {
 
 pub fn format_report(report: &ValidationReport) -> String {
     let mut output = String::new();
-    output.push_str("FHIR Validation\n");
+    output.push_str(&format!("{}\n", report.title));
     output.push_str("--------------\n");
     output.push_str(&format!("File: {}\n", report.file));
-    output.push_str(&format!("Base: {}\n", report.base_url));
-    output.push_str(&format!("Validate: {}\n", report.validate_url));
-    output.push_str(&format!("HTTP: {}\n", report.status));
+    if report.base_url.is_empty() {
+        output.push_str(&format!("Validator: {}\n", report.validate_url));
+    } else {
+        output.push_str(&format!("Base: {}\n", report.base_url));
+        output.push_str(&format!("Validate: {}\n", report.validate_url));
+    }
+    if let Some(version) = report.fhir_version {
+        output.push_str(&format!("FHIR version: {}\n", version));
+    }
+    if !report.base_url.is_empty() {
+        output.push_str(&format!("HTTP: {}\n", report.status));
+    }
     output.push_str(&format!(
         "Issues: {} (errors: {}, warnings: {}, info: {})\n",
         report.total, report.error_count, report.warning_count, report.info_count
@@ -168,8 +267,18 @@ pub fn format_report(report: &ValidationReport) -> String {
         report.total,
         report.groups.len()
     ));
+    if report.suppressed_count > 0 {
+        output.push_str(&format!(
+            "Suppressed by baseline: {} issues in {} categories\n",
+            report.suppressed_count,
+            report.suppressed.len()
+        ));
+    }
 
-    if report.error_count == 0 && report.status.is_success() {
+    if !report.policy.is_default() {
+        output.push_str(&format!("Policy: {}\n", report.policy.describe()));
+    }
+    if !is_failure(report) {
         output.push_str("Result: PASS ✅\n");
     } else {
         output.push_str("Result: FAIL ❌\n");
@@ -182,9 +291,28 @@ pub fn format_report(report: &ValidationReport) -> String {
         for (index, (theme, count)) in sorted_theme_counts(&report.theme_counts).iter().enumerate()
         {
             output.push_str(&format!("  {}. {} (x{})\n", index + 1, theme, count));
-            if theme == PROFILE_RESOLUTION_THEME {
-                output.push_str(&format!("  {}\n", PROFILE_RESOLUTION_HINT));
-                output.push_str(&format!("  {}\n", PROFILE_RESOLUTION_RECOMMENDATION));
+            if let Some(hint) = report.theme_hints.get(theme) {
+                if let Some(text) = &hint.hint {
+                    output.push_str(&format!("  {}\n", text));
+                }
+                if let Some(text) = &hint.recommendation {
+                    output.push_str(&format!("  {}\n", text));
+                }
+            }
+        }
+    }
+
+    if !report.stale_baseline.is_empty() {
+        output.push_str(&format!(
+            "\nStale baseline entries ({}; no longer reported, remove them from the baseline):\n",
+            report.stale_baseline.len()
+        ));
+        for entry in &report.stale_baseline {
+            match &entry.expression {
+                Some(expression) => {
+                    output.push_str(&format!("- {} @ {}\n", entry.group_key, expression))
+                }
+                None => output.push_str(&format!("- {}\n", entry.group_key)),
             }
         }
     }
@@ -225,8 +353,18 @@ pub fn format_report(report: &ValidationReport) -> String {
                     item.expression.join(", ")
                 ));
             }
-            if let Some(line) = item.line {
-                output.push_str(&format!("     line: {}\n", line));
+            match (item.line, item.column) {
+                (Some(line), Some(column)) => {
+                    output.push_str(&format!("     line: {}, column: {}\n", line, column));
+                }
+                (Some(line), None) => output.push_str(&format!("     line: {}\n", line)),
+                _ => {}
+            }
+            if let Some(message_id) = &item.message_id {
+                output.push_str(&format!("     rule: {}\n", message_id));
+            }
+            if let Some(source) = &item.source {
+                output.push_str(&format!("     source: {}\n", source));
             }
         }
     }
@@ -239,10 +377,10 @@ pub fn print_report(report: &ValidationReport) {
 }
 
 pub fn is_failure(report: &ValidationReport) -> bool {
-    report.error_count > 0 || !report.status.is_success()
+    report.policy.is_failure(report)
 }
 
-fn sorted_theme_counts(counts: &BTreeMap<String, usize>) -> Vec<(String, usize)> {
+pub(super) fn sorted_theme_counts(counts: &BTreeMap<String, usize>) -> Vec<(String, usize)> {
     let mut entries: Vec<(String, usize)> = counts
         .iter()
         .map(|(theme, count)| (theme.clone(), *count))
@@ -278,7 +416,9 @@ fn summarize_severity(items: &[IssueSummary]) -> BTreeMap<String, usize> {
     counts
 }
 
-fn sorted_groups(groups: &BTreeMap<String, Vec<IssueSummary>>) -> Vec<(String, Vec<IssueSummary>)> {
+pub(crate) fn sorted_groups(
+    groups: &BTreeMap<String, Vec<IssueSummary>>,
+) -> Vec<(String, Vec<IssueSummary>)> {
     let mut entries: Vec<(String, Vec<IssueSummary>)> = groups
         .iter()
         .map(|(key, items)| (key.clone(), items.clone()))
@@ -308,6 +448,7 @@ fn format_severity_summary(counts: &BTreeMap<String, usize>) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::validate::theme::PROFILE_RESOLUTION_THEME;
 
     fn issue_summary(message: &str, severity: &str, theme: &str) -> IssueSummary {
         IssueSummary {
@@ -318,6 +459,10 @@ mod tests {
             expression: vec![],
             theme: theme.to_string(),
             line: None,
+            column: None,
+            source: None,
+            message_id: None,
+            codings: vec![],
         }
     }
 
@@ -333,6 +478,7 @@ mod tests {
                     details: None,
                     location: vec!["Patient.id".to_string()],
                     expression: vec![],
+                    ..Default::default()
                 },
                 Issue {
                     severity: Some("warning".to_string()),
@@ -341,8 +487,10 @@ mod tests {
                     details: None,
                     location: vec!["Patient.identifier".to_string()],
                     expression: vec![],
+                    ..Default::default()
                 },
             ],
+            ..Default::default()
         };
 
         let report = build_report(
@@ -451,6 +599,7 @@ mod tests {
                     details: None,
                     location: vec![],
                     expression: vec![],
+                    ..Default::default()
                 },
                 Issue {
                     severity: Some("error".to_string()),
@@ -459,6 +608,7 @@ mod tests {
                     details: None,
                     location: vec![],
                     expression: vec![],
+                    ..Default::default()
                 },
                 Issue {
                     severity: Some("warning".to_string()),
@@ -467,8 +617,10 @@ mod tests {
                     details: None,
                     location: vec![],
                     expression: vec![],
+                    ..Default::default()
                 },
             ],
+            ..Default::default()
         };
 
         let report = build_report(
@@ -492,6 +644,10 @@ mod tests {
             expression: vec!["Patient.id".to_string()],
             theme: "General".to_string(),
             line: None,
+            column: None,
+            source: None,
+            message_id: None,
+            codings: vec![],
         };
 
         let key = group_key(&summary);
@@ -588,7 +744,9 @@ mod tests {
                 details: None,
                 location: vec![],
                 expression: vec![],
+                ..Default::default()
             }],
+            ..Default::default()
         };
 
         let report = build_report(
@@ -612,7 +770,9 @@ mod tests {
                 details: None,
                 location: vec![],
                 expression: vec![],
+                ..Default::default()
             }],
+            ..Default::default()
         };
 
         let report = build_report(
@@ -637,7 +797,9 @@ mod tests {
                 details: None,
                 location: vec![],
                 expression: vec![],
+                ..Default::default()
             }],
+            ..Default::default()
         };
 
         let report = build_report(
@@ -663,6 +825,7 @@ mod tests {
                     details: None,
                     location: vec!["Patient.id".to_string()],
                     expression: vec![],
+                    ..Default::default()
                 },
                 Issue {
                     severity: Some("error".to_string()),
@@ -671,8 +834,10 @@ mod tests {
                     details: None,
                     location: vec!["Patient.id".to_string()],
                     expression: vec![],
+                    ..Default::default()
                 },
             ],
+            ..Default::default()
         };
 
         let report = build_report(
@@ -702,7 +867,9 @@ mod tests {
                 details: None,
                 location: vec![],
                 expression: vec![],
+                ..Default::default()
             }],
+            ..Default::default()
         };
 
         let report = build_report(
@@ -714,7 +881,11 @@ mod tests {
         );
         let output = format_report(&report);
         assert!(output.contains(PROFILE_RESOLUTION_THEME));
-        assert!(output.contains(PROFILE_RESOLUTION_HINT));
+        let hint = ThemeRules::builtin()
+            .hint(PROFILE_RESOLUTION_THEME)
+            .and_then(|hint| hint.hint)
+            .expect("profile resolution hint");
+        assert!(output.contains(&hint));
     }
 
     #[test]
@@ -728,7 +899,9 @@ mod tests {
                 details: None,
                 location: vec![],
                 expression: vec![],
+                ..Default::default()
             }],
+            ..Default::default()
         };
         let error_report = build_report(
             &outcome,
@@ -742,6 +915,7 @@ mod tests {
         let ok_outcome = OperationOutcome {
             resource_type: Some("OperationOutcome".to_string()),
             issue: vec![],
+            ..Default::default()
         };
         let http_fail_report = build_report(
             &ok_outcome,
@@ -761,4 +935,65 @@ mod tests {
         );
         assert!(!is_failure(&ok_report));
     }
+
+    #[test]
+    fn line_and_column_come_from_extensions_before_message() {
+        let json = r#"{
+            "resourceType":"OperationOutcome",
+            "issue":[{
+                "extension":[
+                    {"url":"http://hl7.org/fhir/StructureDefinition/operationoutcome-issue-line","valueInteger":9},
+                    {"url":"http://hl7.org/fhir/StructureDefinition/operationoutcome-issue-col","valueInteger":4},
+                    {"url":"http://hl7.org/fhir/StructureDefinition/operationoutcome-message-id","valueString":"Type_Specific_Checks_DT_Code_WS"}
+                ],
+                "severity":"error",
+                "code":"invalid",
+                "diagnostics":"Bad code (line: 2)"
+            }]
+        }"#;
+
+        let outcome: OperationOutcome = serde_json::from_str(json).expect("outcome parse");
+        let report = build_report(
+            &outcome,
+            StatusCode::BAD_REQUEST,
+            "test.json",
+            "base",
+            "validate",
+        );
+        let item = &report.groups.values().next().unwrap()[0];
+        assert_eq!(item.line, Some(9));
+        assert_eq!(item.column, Some(4));
+        assert_eq!(
+            item.message_id.as_deref(),
+            Some("Type_Specific_Checks_DT_Code_WS")
+        );
+        let output = format_report(&report);
+        assert!(output.contains("line: 9, column: 4"));
+        assert!(output.contains("rule: Type_Specific_Checks_DT_Code_WS"));
+    }
+
+    #[test]
+    fn groups_by_details_coding_when_no_text_is_sent() {
+        let json = r#"{
+            "resourceType":"OperationOutcome",
+            "issue":[
+                {"severity":"error","code":"invalid","details":{"coding":[{"system":"http://example.org/rules","code":"R1"}]}},
+                {"severity":"error","code":"invalid","details":{"coding":[{"system":"http://example.org/rules","code":"R1"}]}}
+            ]
+        }"#;
+
+        let outcome: OperationOutcome = serde_json::from_str(json).expect("outcome parse");
+        let report = build_report(
+            &outcome,
+            StatusCode::BAD_REQUEST,
+            "test.json",
+            "base",
+            "validate",
+        );
+        assert_eq!(report.groups.len(), 1);
+        assert_eq!(
+            report.groups["error | invalid | http://example.org/rules#R1"].len(),
+            2
+        );
+    }
 }
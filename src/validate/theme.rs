@@ -0,0 +1,369 @@
+use std::fs;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::validate::types::Coding;
+
+pub const PROFILE_RESOLUTION_THEME: &str = "Profile resolution (missing profiles on server)";
+
+pub const GENERAL_THEME: &str = "General";
+
+/// One theme as written in a rule file (`[[theme]]` tables).
+///
+/// A rule matches an issue when any of its message regexes, issue codes or
+/// message ids match. Message ids also match `details.coding` codes, which is
+/// where some validators put them. A trailing `*` makes a message id a prefix.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThemeRule {
+    pub name: String,
+    #[serde(default)]
+    pub hint: Option<String>,
+    #[serde(default)]
+    pub recommendation: Option<String>,
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    #[serde(default)]
+    pub codes: Vec<String>,
+    #[serde(default)]
+    pub message_ids: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    /// Drop the built-in taxonomy instead of layering on top of it.
+    #[serde(default)]
+    replace_builtin: bool,
+    #[serde(default)]
+    theme: Vec<ThemeRule>,
+}
+
+/// Hint and recommendation printed under a theme in the report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThemeHint {
+    pub hint: Option<String>,
+    pub recommendation: Option<String>,
+}
+
+/// The fields of an issue a theme rule can look at.
+pub struct ThemeInput<'a> {
+    pub code: &'a str,
+    pub message: &'a str,
+    pub message_id: Option<&'a str>,
+    pub codings: &'a [Coding],
+}
+
+#[derive(Debug, Clone)]
+struct CompiledTheme {
+    rule: ThemeRule,
+    patterns: Vec<Regex>,
+}
+
+impl CompiledTheme {
+    fn compile(rule: ThemeRule) -> Result<Self, String> {
+        let patterns = rule
+            .patterns
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern)
+                    .map_err(|err| format!("theme '{}': invalid pattern: {err}", rule.name))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { rule, patterns })
+    }
+
+    fn matches(&self, input: &ThemeInput<'_>) -> bool {
+        if self.rule.codes.iter().any(|code| code == input.code) {
+            return true;
+        }
+        let ids = input.message_id.into_iter().chain(
+            input
+                .codings
+                .iter()
+                .filter_map(|coding| coding.code.as_deref()),
+        );
+        for id in ids {
+            if self
+                .rule
+                .message_ids
+                .iter()
+                .any(|wanted| message_id_matches(wanted, id))
+            {
+                return true;
+            }
+        }
+        self.patterns
+            .iter()
+            .any(|pattern| pattern.is_match(input.message))
+    }
+}
+
+fn message_id_matches(wanted: &str, id: &str) -> bool {
+    match wanted.strip_suffix('*') {
+        Some(prefix) => id.starts_with(prefix),
+        None => id == wanted,
+    }
+}
+
+/// Ordered theme taxonomy; the first matching theme wins, otherwise [`GENERAL_THEME`].
+#[derive(Debug, Clone)]
+pub struct ThemeRules {
+    themes: Vec<CompiledTheme>,
+}
+
+static BUILTIN: LazyLock<ThemeRules> = LazyLock::new(|| {
+    let file: ThemeFile =
+        toml::from_str(include_str!("themes.toml")).expect("built-in theme rules parse");
+    ThemeRules::from_rules(file.theme).expect("built-in theme rules compile")
+});
+
+impl ThemeRules {
+    pub fn builtin() -> &'static ThemeRules {
+        &BUILTIN
+    }
+
+    pub fn from_rules(rules: Vec<ThemeRule>) -> Result<Self, String> {
+        let themes = rules
+            .into_iter()
+            .map(CompiledTheme::compile)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { themes })
+    }
+
+    /// Parses a TOML rule file and layers it over the built-in taxonomy.
+    ///
+    /// Themes whose name matches a built-in replace it in place; new themes are
+    /// checked before the built-ins so team rules win ties.
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let file: ThemeFile =
+            toml::from_str(text).map_err(|err| format!("invalid theme rules: {err}"))?;
+        let user = ThemeRules::from_rules(file.theme)?;
+        if file.replace_builtin {
+            return Ok(user);
+        }
+        Ok(ThemeRules::builtin().clone().with_overrides(user))
+    }
+
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let text = fs::read_to_string(path)?;
+        Ok(ThemeRules::from_toml(&text).map_err(|err| format!("{path}: {err}"))?)
+    }
+
+    fn with_overrides(mut self, overrides: ThemeRules) -> Self {
+        let mut added = Vec::new();
+        for theme in overrides.themes {
+            match self
+                .themes
+                .iter_mut()
+                .find(|existing| existing.rule.name == theme.rule.name)
+            {
+                Some(existing) => *existing = theme,
+                None => added.push(theme),
+            }
+        }
+        added.extend(self.themes);
+        Self { themes: added }
+    }
+
+    pub fn classify(&self, input: &ThemeInput<'_>) -> String {
+        self.themes
+            .iter()
+            .find(|theme| theme.matches(input))
+            .map(|theme| theme.rule.name.clone())
+            .unwrap_or_else(|| GENERAL_THEME.to_string())
+    }
+
+    pub fn hint(&self, theme: &str) -> Option<ThemeHint> {
+        let rule = &self
+            .themes
+            .iter()
+            .find(|candidate| candidate.rule.name == theme)?
+            .rule;
+        if rule.hint.is_none() && rule.recommendation.is_none() {
+            return None;
+        }
+        Some(ThemeHint {
+            hint: rule.hint.clone(),
+            recommendation: rule.recommendation.clone(),
+        })
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.themes.iter().map(|theme| theme.rule.name.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input<'a>(code: &'a str, message: &'a str) -> ThemeInput<'a> {
+        ThemeInput {
+            code,
+            message,
+            message_id: None,
+            codings: &[],
+        }
+    }
+
+    #[test]
+    fn builtin_taxonomy_covers_common_validator_messages() {
+        let rules = ThemeRules::builtin();
+        let cases = [
+            (
+                "processing",
+                "Unable to resolve reference to profile 'http://hl7.org/fhir/us/core/StructureDefinition/us-core-bmi'",
+                PROFILE_RESOLUTION_THEME,
+            ),
+            (
+                "processing",
+                "Unknown extension http://hl7.org/fhir/us/core/StructureDefinition/us-core-race",
+                "Extension resolution (unknown extensions)",
+            ),
+            (
+                "processing",
+                "The value provided ('xx') is not in the value set 'AdministrativeGender'",
+                "Terminology (codes and value set bindings)",
+            ),
+            (
+                "processing",
+                "Patient.name: minimum required = 1, but only found 0",
+                "Cardinality (missing or repeated elements)",
+            ),
+            (
+                "processing",
+                "Constraint failed: dom-6: 'A resource should have narrative'",
+                "Invariant (FHIRPath constraints)",
+            ),
+            (
+                "processing",
+                "This element does not match any known slice defined in the profile",
+                "Slicing (profile slices)",
+            ),
+            (
+                "processing",
+                "Unable to resolve resource reference 'Patient/123'",
+                "Reference (unresolved or invalid references)",
+            ),
+            (
+                "processing",
+                "Not a valid date (2026-13-01)",
+                "Datatype (invalid primitive values)",
+            ),
+            (
+                "processing",
+                "Unrecognised property '@foo'",
+                "Unknown element (unrecognised properties)",
+            ),
+            (
+                "structure",
+                "Element 'Patient.birthDate' is not defined in FHIR R5; use 'birthdate'",
+                "Unknown element (unrecognised properties)",
+            ),
+            ("structure", "Something else happened", GENERAL_THEME),
+            ("processing", "Something else happened", GENERAL_THEME),
+        ];
+
+        for (code, message, expected) in cases {
+            assert_eq!(rules.classify(&input(code, message)), expected, "{message}");
+        }
+    }
+
+    #[test]
+    fn matches_message_ids_from_extension_or_details_coding() {
+        let rules = ThemeRules::builtin();
+        let by_extension = ThemeInput {
+            message_id: Some("Terminology_TX_NoValid_16"),
+            ..input("processing", "opaque")
+        };
+        assert_eq!(
+            rules.classify(&by_extension),
+            "Terminology (codes and value set bindings)"
+        );
+
+        let codings = [Coding {
+            code: Some("Reference_REF_CantResolve".to_string()),
+            ..Default::default()
+        }];
+        let by_coding = ThemeInput {
+            codings: &codings,
+            ..input("processing", "opaque")
+        };
+        assert_eq!(
+            rules.classify(&by_coding),
+            "Reference (unresolved or invalid references)"
+        );
+    }
+
+    #[test]
+    fn rule_file_overrides_builtin_and_adds_team_themes() {
+        let rules = ThemeRules::from_toml(
+            r#"
+[[theme]]
+name = "Terminology (codes and value set bindings)"
+hint = "Our terminology server is tx.example.org"
+patterns = ["(?i)value set"]
+
+[[theme]]
+name = "Local naming policy"
+patterns = ["(?i)human ?name"]
+"#,
+        )
+        .expect("rules parse");
+
+        assert_eq!(
+            rules.classify(&input("processing", "HumanName.family is required")),
+            "Local naming policy"
+        );
+        assert_eq!(
+            rules
+                .hint("Terminology (codes and value set bindings)")
+                .and_then(|hint| hint.hint),
+            Some("Our terminology server is tx.example.org".to_string())
+        );
+        assert_eq!(
+            rules.classify(&input("code-invalid", "opaque")),
+            GENERAL_THEME,
+            "overridden theme no longer matches the built-in issue code"
+        );
+        assert!(rules.names().any(|name| name == PROFILE_RESOLUTION_THEME));
+    }
+
+    #[test]
+    fn replace_builtin_drops_default_taxonomy() {
+        let rules = ThemeRules::from_toml(
+            r#"
+replace_builtin = true
+
+[[theme]]
+name = "Everything"
+patterns = ["."]
+"#,
+        )
+        .expect("rules parse");
+        assert_eq!(rules.names().collect::<Vec<_>>(), vec!["Everything"]);
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        let err = ThemeRules::from_toml(
+            r#"
+[[theme]]
+name = "Broken"
+patterns = ["("]
+"#,
+        )
+        .expect_err("invalid regex");
+        assert!(err.contains("Broken"));
+    }
+
+    #[test]
+    fn example_rule_file_parses() {
+        let rules = ThemeRules::from_toml(include_str!("../../examples/themes.toml"))
+            .expect("example rules parse");
+        assert_eq!(rules.names().next(), Some("Synthea identifiers"));
+    }
+}
@@ -0,0 +1,694 @@
+//! `validate --tui`: browse a report's issue groups in the terminal and jump
+//! to the part of the resource JSON each issue points at.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+
+use ratatui::Frame;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+
+use crate::validate::html::filter_class;
+use crate::validate::report::{
+    IssueSummary, ValidationReport, is_failure, sorted_groups, sorted_theme_counts,
+};
+
+/// Severity filters in the order of their toggle keys (`e`, `w`, `i`).
+const SEVERITIES: [&str; 3] = ["error", "warning", "information"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pane {
+    Groups,
+    Issues,
+    Source,
+}
+
+/// What the browser shows and where the cursor is; everything but drawing.
+pub(crate) struct Browser<'a> {
+    report: &'a ValidationReport,
+    groups: Vec<(String, Vec<IssueSummary>)>,
+    themes: Vec<String>,
+    shown: [bool; 3],
+    /// Index into `themes`; `None` shows every theme.
+    theme: Option<usize>,
+    /// Groups with at least one issue passing the filters, as indexes into `groups`.
+    visible: Vec<usize>,
+    group: usize,
+    issue: usize,
+    focus: Pane,
+    source: Vec<&'a str>,
+    /// Where each JSON path (`entry[1].resource.code`) starts, as a 1-based line.
+    paths: HashMap<String, usize>,
+    /// Lines some issue points at, with the worst severity among them.
+    flagged: BTreeMap<usize, &'static str>,
+    /// The line the selected issue points at.
+    target: Option<usize>,
+    /// First source line shown (0-based) and how many fit.
+    scroll: usize,
+    height: usize,
+}
+
+impl<'a> Browser<'a> {
+    pub(crate) fn new(report: &'a ValidationReport, source: &'a str) -> Self {
+        let paths = json_paths(source);
+        let mut flagged = BTreeMap::new();
+        for item in report.groups.values().flatten() {
+            if let Some(line) = issue_line(item, &paths) {
+                let class = filter_class(&item.severity);
+                let worst = flagged.entry(line).or_insert(class);
+                if SEVERITIES.iter().position(|s| *s == class)
+                    < SEVERITIES.iter().position(|s| s == worst)
+                {
+                    *worst = class;
+                }
+            }
+        }
+        let mut browser = Browser {
+            report,
+            groups: sorted_groups(&report.groups),
+            themes: sorted_theme_counts(&report.theme_counts)
+                .into_iter()
+                .map(|(theme, _)| theme)
+                .collect(),
+            shown: [true; 3],
+            theme: None,
+            visible: Vec::new(),
+            group: 0,
+            issue: 0,
+            focus: Pane::Groups,
+            source: source.lines().collect(),
+            paths,
+            flagged,
+            target: None,
+            scroll: 0,
+            height: 20,
+        };
+        browser.refilter();
+        browser
+    }
+
+    /// Applies a key press; `false` once the user quits.
+    pub(crate) fn handle(&mut self, key: KeyCode) -> bool {
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Pane::Groups => Pane::Issues,
+                    Pane::Issues => Pane::Source,
+                    Pane::Source => Pane::Groups,
+                }
+            }
+            KeyCode::BackTab => {
+                self.focus = match self.focus {
+                    Pane::Groups => Pane::Source,
+                    Pane::Issues => Pane::Groups,
+                    Pane::Source => Pane::Issues,
+                }
+            }
+            KeyCode::Enter => {
+                self.focus = match self.focus {
+                    Pane::Groups => Pane::Issues,
+                    _ => Pane::Source,
+                };
+                self.jump();
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.step(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.step(1),
+            KeyCode::PageUp => self.step(-(self.height.max(1) as isize)),
+            KeyCode::PageDown => self.step(self.height.max(1) as isize),
+            KeyCode::Char(c @ ('e' | 'w' | 'i')) => {
+                let index = "ewi".find(c).expect("severity key");
+                self.shown[index] = !self.shown[index];
+                self.refilter();
+            }
+            KeyCode::Char('t') => {
+                self.theme = match self.theme {
+                    None if !self.themes.is_empty() => Some(0),
+                    Some(index) if index + 1 < self.themes.len() => Some(index + 1),
+                    _ => None,
+                };
+                self.refilter();
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn matches(&self, item: &IssueSummary) -> bool {
+        let severity = SEVERITIES
+            .iter()
+            .position(|s| *s == filter_class(&item.severity))
+            .expect("filter class is a known severity");
+        self.shown[severity]
+            && self
+                .theme
+                .is_none_or(|theme| self.themes[theme] == item.theme)
+    }
+
+    fn refilter(&mut self) {
+        let selected = self.visible.get(self.group).copied();
+        self.visible = (0..self.groups.len())
+            .filter(|&index| self.groups[index].1.iter().any(|item| self.matches(item)))
+            .collect();
+        self.group = selected
+            .and_then(|selected| self.visible.iter().position(|&index| index == selected))
+            .unwrap_or(0);
+        self.issue = 0;
+        self.jump();
+    }
+
+    fn step(&mut self, delta: isize) {
+        let moved = |position: usize, len: usize| {
+            position
+                .saturating_add_signed(delta)
+                .min(len.saturating_sub(1))
+        };
+        match self.focus {
+            Pane::Groups => {
+                self.group = moved(self.group, self.visible.len());
+                self.issue = 0;
+                self.jump();
+            }
+            Pane::Issues => {
+                self.issue = moved(self.issue, self.issues().len());
+                self.jump();
+            }
+            Pane::Source => {
+                let last = self.source.len().saturating_sub(self.height);
+                self.scroll = self.scroll.saturating_add_signed(delta).min(last);
+            }
+        }
+    }
+
+    /// The selected group's issues that pass the filters.
+    fn issues(&self) -> Vec<&IssueSummary> {
+        match self.visible.get(self.group) {
+            Some(&index) => self.groups[index]
+                .1
+                .iter()
+                .filter(|item| self.matches(item))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn selected(&self) -> Option<&IssueSummary> {
+        self.issues().get(self.issue).copied()
+    }
+
+    /// Points the source view at the selected issue, keeping its line in the
+    /// upper third of the pane.
+    fn jump(&mut self) {
+        self.target = self
+            .selected()
+            .and_then(|item| issue_line(item, &self.paths));
+        if let Some(line) = self.target {
+            let last = self.source.len().saturating_sub(self.height);
+            self.scroll = line.saturating_sub(1 + self.height / 3).min(last);
+        }
+    }
+
+    pub(crate) fn draw(&mut self, frame: &mut Frame) {
+        let [header, body, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(body);
+        let [issues, source] =
+            Layout::vertical([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(right);
+        let height = source.height.saturating_sub(2) as usize;
+        if height != self.height {
+            self.height = height;
+            self.jump();
+        }
+
+        frame.render_widget(Paragraph::new(self.header()), header);
+        self.draw_groups(frame, left);
+        self.draw_issues(frame, issues);
+        self.draw_source(frame, source);
+        frame.render_widget(
+            Paragraph::new(
+                " ↑↓ move  Tab pane  Enter jump to JSON  e/w/i severity  t theme  q quit".dim(),
+            ),
+            footer,
+        );
+    }
+
+    fn header(&self) -> Line<'static> {
+        let report = self.report;
+        let result = if is_failure(report) {
+            " FAIL ".white().on_red().bold()
+        } else {
+            " PASS ".black().on_green().bold()
+        };
+        let filters = SEVERITIES
+            .iter()
+            .zip(self.shown)
+            .filter(|(_, shown)| *shown)
+            .map(|(severity, _)| *severity)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let theme = self
+            .theme
+            .map_or("all themes", |theme| self.themes[theme].as_str());
+        Line::from(vec![
+            result,
+            format!(
+                " {}  errors {}  warnings {}  info {}  │ showing {}; {}",
+                report.file,
+                report.error_count,
+                report.warning_count,
+                report.info_count,
+                if filters.is_empty() {
+                    "nothing"
+                } else {
+                    &filters
+                },
+                theme
+            )
+            .into(),
+        ])
+    }
+
+    fn draw_groups(&self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .visible
+            .iter()
+            .map(|&index| {
+                let (key, items) = &self.groups[index];
+                let count = items.iter().filter(|item| self.matches(item)).count();
+                let severity = items.first().map_or("information", |item| &item.severity);
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("x{count:<4}"), severity_style(severity)),
+                    Span::raw(key.clone()),
+                ]))
+            })
+            .collect();
+        let title = format!(
+            " Issue groups ({}/{}) ",
+            self.visible.len(),
+            self.groups.len()
+        );
+        let list = List::new(items)
+            .block(self.block(title, Pane::Groups))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default().with_selected(Some(self.group));
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn draw_issues(&self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .issues()
+            .into_iter()
+            .map(|item| {
+                let mut details = Vec::new();
+                if !item.expression.is_empty() {
+                    details.push(item.expression.join(", "));
+                } else if !item.location.is_empty() {
+                    details.push(item.location.join(", "));
+                }
+                if let Some(line) = item.line {
+                    details.push(format!("line {line}"));
+                }
+                details.push(item.theme.clone());
+                let message = if item.message.is_empty() {
+                    "(no diagnostics provided)"
+                } else {
+                    item.message.as_str()
+                };
+                ListItem::new(vec![
+                    Line::from(vec![
+                        Span::styled(
+                            format!("[{}] ", item.severity),
+                            severity_style(&item.severity),
+                        ),
+                        Span::raw(message.to_string()),
+                    ]),
+                    Line::from(format!("  {}", details.join(" · ")).dim()),
+                ])
+            })
+            .collect();
+        let list = List::new(items)
+            .block(self.block(" Issues ".to_string(), Pane::Issues))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default().with_selected(Some(self.issue));
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn draw_source(&self, frame: &mut Frame, area: Rect) {
+        let width = self.source.len().to_string().len();
+        let lines: Vec<Line> = (self.scroll + 1..)
+            .zip(self.source.iter().skip(self.scroll).take(self.height))
+            .map(|(number, text)| {
+                let number_style = self
+                    .flagged
+                    .get(&number)
+                    .map_or(Style::new().dim(), |severity| severity_style(severity));
+                let line = Line::from(vec![
+                    Span::styled(format!("{number:>width$} "), number_style),
+                    Span::raw(text.to_string()),
+                ]);
+                if self.target == Some(number) {
+                    line.style(Style::new().add_modifier(Modifier::REVERSED))
+                } else {
+                    line
+                }
+            })
+            .collect();
+        let title = match self.target {
+            Some(line) => format!(" Resource JSON (line {line}) "),
+            None => " Resource JSON ".to_string(),
+        };
+        frame.render_widget(
+            Paragraph::new(lines).block(self.block(title, Pane::Source)),
+            area,
+        );
+    }
+
+    fn block(&self, title: String, pane: Pane) -> Block<'static> {
+        let block = Block::bordered().title(title);
+        if self.focus == pane {
+            block.border_style(Style::new().fg(Color::Cyan))
+        } else {
+            block
+        }
+    }
+}
+
+fn severity_style(severity: &str) -> Style {
+    match filter_class(severity) {
+        "error" => Style::new().fg(Color::Red),
+        "warning" => Style::new().fg(Color::Yellow),
+        _ => Style::new().fg(Color::Blue),
+    }
+}
+
+/// The line an issue points at: the one the validator reported, else where
+/// its expression (or location) leads in the JSON.
+fn issue_line(item: &IssueSummary, paths: &HashMap<String, usize>) -> Option<usize> {
+    if let Some(line) = item.line {
+        return Some(line as usize);
+    }
+    item.expression
+        .iter()
+        .chain(&item.location)
+        .find_map(|expression| locate(expression, paths))
+}
+
+/// The line of the deepest element on a FHIRPath expression's way that exists
+/// in the JSON. `Bundle.entry[1].resource/*Observation/a1*/.code` leads to
+/// `entry[1].resource.code`; an element the issue says is missing leads to its
+/// parent. Function calls (`where(...)`) end the walk.
+fn locate(expression: &str, paths: &HashMap<String, usize>) -> Option<usize> {
+    let mut plain = String::with_capacity(expression.len());
+    let mut rest = expression;
+    while let Some(start) = rest.find("/*") {
+        plain.push_str(&rest[..start]);
+        rest = rest[start..]
+            .find("*/")
+            .map_or("", |end| &rest[start + end + 2..]);
+    }
+    plain.push_str(rest);
+
+    let mut segments = plain.split('.');
+    let first = segments.next()?;
+    if !first.starts_with(|c: char| c.is_ascii_uppercase()) {
+        return None;
+    }
+    let mut path = String::new();
+    let mut line = *paths.get("")?;
+    for segment in segments {
+        if segment.contains('(') || segment.is_empty() {
+            break;
+        }
+        let (name, index) = match segment.split_once('[') {
+            Some((name, index)) => (name, Some(index.trim_end_matches(']'))),
+            None => (segment, None),
+        };
+        let mut next = if path.is_empty() {
+            name.to_string()
+        } else {
+            format!("{path}.{name}")
+        };
+        let Some(&found) = paths.get(&next) else {
+            break;
+        };
+        (path, line) = (next.clone(), found);
+        if let Some(index) = index {
+            next.push_str(&format!("[{index}]"));
+            let Some(&found) = paths.get(&next) else {
+                break;
+            };
+            (path, line) = (next, found);
+        }
+    }
+    Some(line)
+}
+
+enum Container {
+    Object(Option<String>),
+    Array(usize),
+}
+
+/// Maps the path of every value in `source` (`""` for the root, then
+/// `name[0].given`) to the 1-based line it starts on. Assumes valid JSON.
+fn json_paths(source: &str) -> HashMap<String, usize> {
+    let mut paths = HashMap::new();
+    let mut stack: Vec<Container> = Vec::new();
+    let mut expecting_key = false;
+    let mut line = 1;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => {}
+            ':' => {}
+            ',' => match stack.last_mut() {
+                Some(Container::Array(index)) => *index += 1,
+                Some(Container::Object(_)) => expecting_key = true,
+                None => {}
+            },
+            '}' | ']' => {
+                stack.pop();
+                expecting_key = false;
+            }
+            '"' if expecting_key => {
+                let key = read_string(&mut chars, &mut line);
+                if let Some(Container::Object(current)) = stack.last_mut() {
+                    *current = Some(key);
+                }
+                expecting_key = false;
+            }
+            _ => {
+                paths.entry(path_of(&stack)).or_insert(line);
+                match c {
+                    '{' => {
+                        stack.push(Container::Object(None));
+                        expecting_key = true;
+                    }
+                    '[' => stack.push(Container::Array(0)),
+                    '"' => {
+                        read_string(&mut chars, &mut line);
+                    }
+                    _ => {
+                        while chars.peek().is_some_and(|next| {
+                            !matches!(next, ',' | '}' | ']') && !next.is_whitespace()
+                        }) {
+                            chars.next();
+                        }
+                    }
+                }
+            }
+        }
+    }
+    paths
+}
+
+/// Reads a JSON string after its opening quote, counting escaped newlines out.
+fn read_string(chars: &mut impl Iterator<Item = char>, line: &mut usize) -> String {
+    let mut text = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => break,
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    text.push(escaped);
+                }
+            }
+            '\n' => {
+                *line += 1;
+                text.push(c);
+            }
+            _ => text.push(c),
+        }
+    }
+    text
+}
+
+fn path_of(stack: &[Container]) -> String {
+    let mut path = String::new();
+    for frame in stack {
+        match frame {
+            Container::Object(Some(key)) => {
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(key);
+            }
+            Container::Object(None) => {}
+            Container::Array(index) => path.push_str(&format!("[{index}]")),
+        }
+    }
+    path
+}
+
+/// Runs the browser until the user quits, restoring the terminal either way.
+pub(crate) fn browse(report: &ValidationReport, source: &str) -> io::Result<()> {
+    let mut browser = Browser::new(report, source);
+    let mut terminal = ratatui::try_init()?;
+    let result = (|| loop {
+        terminal.draw(|frame| browser.draw(frame))?;
+        if let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+            && !browser.handle(key.code)
+        {
+            return Ok(());
+        }
+    })();
+    ratatui::try_restore()?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate::report::build_report;
+    use crate::validate::types::{Issue, OperationOutcome};
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+    use reqwest::StatusCode;
+
+    const BUNDLE: &str = r#"{
+  "resourceType": "Bundle",
+  "entry": [
+    {
+      "resource": {"resourceType": "Patient", "id": "p1"}
+    },
+    {
+      "resource": {
+        "resourceType": "Observation",
+        "code": {
+          "text": "a \"quoted\" name"
+        },
+        "status": "final"
+      }
+    }
+  ]
+}"#;
+
+    fn report() -> ValidationReport {
+        let outcome = OperationOutcome {
+            resource_type: Some("OperationOutcome".to_string()),
+            issue: vec![
+                Issue::local(
+                    "error",
+                    "required",
+                    "Missing subject".to_string(),
+                    "Bundle.entry[1].resource/*Observation/o1*/.subject",
+                ),
+                Issue::local(
+                    "error",
+                    "required",
+                    "Missing subject".to_string(),
+                    "Bundle.entry[1].resource.code.coding",
+                ),
+                Issue::local(
+                    "warning",
+                    "invariant",
+                    "dom-6".to_string(),
+                    "Bundle.entry[0].resource",
+                )
+                .with_line(5),
+                Issue::local("information", "informational", "Note".to_string(), "Bundle"),
+            ],
+            ..Default::default()
+        };
+        build_report(
+            &outcome,
+            StatusCode::OK,
+            "bundle.json",
+            "https://fhir.example",
+            "validate",
+        )
+    }
+
+    #[test]
+    fn locates_expressions_in_the_json() {
+        let paths = json_paths(BUNDLE);
+        assert_eq!(paths[""], 1);
+        assert_eq!(paths["entry[1].resource.code.text"], 11);
+        assert_eq!(paths["entry[1].resource.status"], 13);
+        assert_eq!(
+            locate("Bundle.entry[1].resource/*Observation/o1*/.status", &paths),
+            Some(13)
+        );
+        assert_eq!(locate("Bundle.entry[1].resource.subject", &paths), Some(8));
+        assert_eq!(
+            locate("Bundle.entry[0].resource.where(id = 'p1')", &paths),
+            Some(5)
+        );
+        assert_eq!(locate("entry[0]", &paths), None);
+    }
+
+    #[test]
+    fn filters_groups_and_jumps_to_the_selected_issue() {
+        let report = report();
+        let mut browser = Browser::new(&report, BUNDLE);
+        assert_eq!(browser.visible.len(), 3);
+        assert_eq!(
+            browser.groups[browser.visible[0]].0,
+            "error | required | Missing subject"
+        );
+        assert_eq!(browser.target, Some(8));
+
+        browser.handle(KeyCode::Enter);
+        browser.handle(KeyCode::Down);
+        assert_eq!(browser.target, Some(10));
+
+        browser.handle(KeyCode::Char('e'));
+        browser.handle(KeyCode::Char('i'));
+        assert_eq!(browser.visible.len(), 1);
+        assert_eq!(browser.target, Some(5));
+
+        browser.handle(KeyCode::Char('t'));
+        let theme = &browser.themes[0];
+        assert_eq!(browser.theme, Some(0));
+        assert!(browser.issues().iter().all(|item| item.theme == *theme));
+        assert!(!browser.handle(KeyCode::Char('q')));
+    }
+
+    #[test]
+    fn draws_header_groups_and_highlighted_source() {
+        let report = report();
+        let mut browser = Browser::new(&report, BUNDLE);
+        let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+        terminal.draw(|frame| browser.draw(frame)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains(" FAIL  bundle.json  errors 2  warnings 1  info 1"));
+        assert!(screen.contains("x2   error | required | Missing subject"));
+        assert!(screen.contains("Resource JSON (line 8)"));
+    }
+}
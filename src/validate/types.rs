@@ -1,4 +1,7 @@
-use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 #[derive(Debug, Deserialize, PartialEq, Eq)]
 pub struct FhirResource {
@@ -6,41 +9,225 @@ pub struct FhirResource {
     pub resource_type: String,
 }
 
-#[derive(Debug, Deserialize)]
+pub const ISSUE_LINE_EXTENSION: &str =
+    "http://hl7.org/fhir/StructureDefinition/operationoutcome-issue-line";
+pub const ISSUE_COL_EXTENSION: &str =
+    "http://hl7.org/fhir/StructureDefinition/operationoutcome-issue-col";
+pub const ISSUE_SOURCE_EXTENSION: &str =
+    "http://hl7.org/fhir/StructureDefinition/operationoutcome-issue-source";
+pub const MESSAGE_ID_EXTENSION: &str =
+    "http://hl7.org/fhir/StructureDefinition/operationoutcome-message-id";
+
+/// `issue-source` value for issues ClinLogix raises itself rather than the server.
+pub const LOCAL_ISSUE_SOURCE: &str = "clinlogix";
+
+/// R4 OperationOutcome, including the Resource/DomainResource elements servers fill in.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct OperationOutcome {
-    #[serde(rename = "resourceType")]
-    #[allow(dead_code)]
     pub resource_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub implicit_rules: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<Narrative>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contained: Vec<Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extension: Vec<Extension>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modifier_extension: Vec<Extension>,
     #[serde(default)]
     pub issue: Vec<Issue>,
 }
 
 impl OperationOutcome {
     pub fn empty() -> Self {
-        Self {
-            resource_type: None,
-            issue: Vec::new(),
-        }
+        Self::default()
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Issue {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extension: Vec<Extension>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modifier_extension: Vec<Extension>,
     pub severity: Option<String>,
     pub code: Option<String>,
-    pub diagnostics: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub details: Option<CodeableConcept>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diagnostics: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub location: Vec<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub expression: Vec<String>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+impl Issue {
+    /// Issue raised by ClinLogix's own checks, tagged with [`LOCAL_ISSUE_SOURCE`].
+    ///
+    /// An empty `expression` (a document that did not parse) leaves it out.
+    pub fn local(severity: &str, code: &str, diagnostics: String, expression: &str) -> Self {
+        Issue {
+            extension: vec![Extension::string(
+                ISSUE_SOURCE_EXTENSION,
+                LOCAL_ISSUE_SOURCE,
+            )],
+            severity: Some(severity.to_string()),
+            code: Some(code.to_string()),
+            diagnostics: Some(diagnostics),
+            expression: (!expression.is_empty())
+                .then(|| expression.to_string())
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Adds an `operationoutcome-issue-line` extension.
+    pub fn with_line(mut self, line: u32) -> Self {
+        self.extension.push(Extension {
+            url: ISSUE_LINE_EXTENSION.to_string(),
+            value: BTreeMap::from([("valueInteger".to_string(), Value::from(line))]),
+            ..Default::default()
+        });
+        self
+    }
+
+    /// Line reported by the `operationoutcome-issue-line` extension.
+    pub fn line(&self) -> Option<u32> {
+        self.extension_value(ISSUE_LINE_EXTENSION)
+            .and_then(Extension::value_integer)
+    }
+
+    /// Column reported by the `operationoutcome-issue-col` extension.
+    pub fn column(&self) -> Option<u32> {
+        self.extension_value(ISSUE_COL_EXTENSION)
+            .and_then(Extension::value_integer)
+    }
+
+    /// Component that raised the issue (`operationoutcome-issue-source`).
+    pub fn source(&self) -> Option<&str> {
+        self.extension_value(ISSUE_SOURCE_EXTENSION)
+            .and_then(Extension::value_str)
+    }
+
+    /// Identifier of the validator rule/message that fired (`operationoutcome-message-id`).
+    pub fn message_id(&self) -> Option<&str> {
+        self.extension_value(MESSAGE_ID_EXTENSION)
+            .and_then(Extension::value_str)
+    }
+
+    fn extension_value(&self, url: &str) -> Option<&Extension> {
+        self.extension.iter().find(|extension| extension.url == url)
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct CodeableConcept {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extension: Vec<Extension>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub coding: Vec<Coding>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub text: Option<String>,
 }
 
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Coding {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extension: Vec<Extension>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_selected: Option<bool>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Meta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extension: Vec<Extension>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_updated: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profile: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub security: Vec<Coding>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tag: Vec<Coding>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Narrative {
+    pub status: Option<String>,
+    pub div: Option<String>,
+}
+
+/// FHIR Extension. `value[x]` keeps its JSON key (`valueInteger`, `valueString`, ...).
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Extension {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extension: Vec<Extension>,
+    #[serde(flatten)]
+    pub value: BTreeMap<String, Value>,
+}
+
+impl Extension {
+    pub fn string(url: &str, value: &str) -> Self {
+        Extension {
+            url: url.to_string(),
+            value: BTreeMap::from([("valueString".to_string(), Value::from(value))]),
+            ..Default::default()
+        }
+    }
+
+    pub fn value_integer(&self) -> Option<u32> {
+        self.value
+            .iter()
+            .find(|(key, _)| key.starts_with("value"))
+            .and_then(|(_, value)| value.as_u64())
+            .and_then(|value| u32::try_from(value).ok())
+    }
+
+    pub fn value_str(&self) -> Option<&str> {
+        self.value
+            .iter()
+            .find(|(key, _)| key.starts_with("value"))
+            .and_then(|(_, value)| value.as_str())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -64,4 +251,63 @@ mod tests {
         assert_eq!(outcome.issue.len(), 1);
         assert_eq!(outcome.issue[0].code.as_deref(), Some("invalid"));
     }
+
+    #[test]
+    fn parses_full_issue_with_extensions_and_codings() {
+        let json = r#"{
+            "resourceType":"OperationOutcome",
+            "id":"oo-1",
+            "meta":{"lastUpdated":"2026-01-07T08:07:12Z"},
+            "issue":[{
+                "id":"issue-1",
+                "extension":[
+                    {"url":"http://hl7.org/fhir/StructureDefinition/operationoutcome-issue-line","valueInteger":14},
+                    {"url":"http://hl7.org/fhir/StructureDefinition/operationoutcome-issue-col","valueInteger":6},
+                    {"url":"http://hl7.org/fhir/StructureDefinition/operationoutcome-issue-source","valueString":"InstanceValidator"},
+                    {"url":"http://hl7.org/fhir/StructureDefinition/operationoutcome-message-id","valueString":"Terminology_TX_NoValid_16"}
+                ],
+                "severity":"error",
+                "code":"code-invalid",
+                "details":{"coding":[{"system":"http://hl7.org/fhir/java-core-messageid","code":"Terminology_TX_NoValid_16"}]},
+                "expression":["Patient.gender"]
+            }]
+        }"#;
+        let outcome: OperationOutcome = serde_json::from_str(json).expect("outcome parse");
+        assert_eq!(outcome.id.as_deref(), Some("oo-1"));
+        assert_eq!(
+            outcome.meta.and_then(|meta| meta.last_updated).as_deref(),
+            Some("2026-01-07T08:07:12Z")
+        );
+
+        let issue = &outcome.issue[0];
+        assert_eq!(issue.id.as_deref(), Some("issue-1"));
+        assert_eq!(issue.line(), Some(14));
+        assert_eq!(issue.column(), Some(6));
+        assert_eq!(issue.source(), Some("InstanceValidator"));
+        assert_eq!(issue.message_id(), Some("Terminology_TX_NoValid_16"));
+        let coding = &issue.details.as_ref().expect("details").coding[0];
+        assert_eq!(coding.code.as_deref(), Some("Terminology_TX_NoValid_16"));
+    }
+
+    #[test]
+    fn serializes_back_without_empty_elements() {
+        let json = r#"{"resourceType":"OperationOutcome","issue":[{"severity":"warning","code":"informational","diagnostics":"FYI"}]}"#;
+        let outcome: OperationOutcome = serde_json::from_str(json).expect("outcome parse");
+        let round_trip = serde_json::to_value(&outcome).expect("serialize");
+        assert_eq!(
+            round_trip,
+            serde_json::from_str::<Value>(json).expect("expected json")
+        );
+    }
+
+    #[test]
+    fn local_issue_without_expression_has_none() {
+        let issue = Issue::local("fatal", "structure", "Not HL7".to_string(), "");
+        assert!(issue.expression.is_empty());
+        assert_eq!(issue.source(), Some(LOCAL_ISSUE_SOURCE));
+        assert_eq!(
+            Issue::local("error", "value", "Bad".to_string(), "PID-7").expression,
+            vec!["PID-7".to_string()]
+        );
+    }
 }
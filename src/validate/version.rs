@@ -0,0 +1,513 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::validate::types::Issue;
+
+const FHIR_VERSION_TAG_SYSTEM: &str = "http://hl7.org/fhir/FHIR-version";
+const CORE_PROFILE_PREFIX: &str = "http://hl7.org/fhir/StructureDefinition/";
+
+/// FHIR releases ClinLogix knows how to tell apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FhirVersion {
+    R4,
+    R4B,
+    R5,
+}
+
+impl FhirVersion {
+    pub fn label(self) -> &'static str {
+        match self {
+            FhirVersion::R4 => "R4",
+            FhirVersion::R4B => "R4B",
+            FhirVersion::R5 => "R5",
+        }
+    }
+
+    pub fn release(self) -> &'static str {
+        match self {
+            FhirVersion::R4 => "4.0.1",
+            FhirVersion::R4B => "4.3.0",
+            FhirVersion::R5 => "5.0.0",
+        }
+    }
+
+    /// Value for the `fhirVersion` MIME type parameter (major.minor only).
+    pub fn mime_parameter(self) -> &'static str {
+        match self {
+            FhirVersion::R4 => "4.0",
+            FhirVersion::R4B => "4.3",
+            FhirVersion::R5 => "5.0",
+        }
+    }
+
+    pub fn mime_type(self) -> String {
+        format!(
+            "application/fhir+json; fhirVersion={}",
+            self.mime_parameter()
+        )
+    }
+}
+
+impl fmt::Display for FhirVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.label(), self.release())
+    }
+}
+
+impl FromStr for FhirVersion {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let trimmed = value.trim();
+        match trimmed.to_ascii_uppercase().as_str() {
+            "R4" => return Ok(FhirVersion::R4),
+            "R4B" => return Ok(FhirVersion::R4B),
+            "R5" => return Ok(FhirVersion::R5),
+            _ => {}
+        }
+
+        let mut parts = trimmed.split(['.', '-']);
+        match (parts.next(), parts.next()) {
+            (Some("4"), Some("0")) => Ok(FhirVersion::R4),
+            (Some("4"), Some("3")) => Ok(FhirVersion::R4B),
+            (Some("5"), Some("0")) => Ok(FhirVersion::R5),
+            _ => Err(format!(
+                "unsupported FHIR version '{trimmed}' (expected R4, R4B, R5 or a release like 4.0.1)"
+            )),
+        }
+    }
+}
+
+/// Best-effort detection of the FHIR version a resource targets.
+///
+/// Looks at a `meta.tag` from the FHIR-version code system, versioned core profiles in
+/// `meta.profile`, and the `fhirVersion` element of conformance resources. Bundles are
+/// searched entry by entry until one of those yields a version.
+pub fn detect_version(resource: &Value) -> Option<FhirVersion> {
+    if let Some(version) = version_from_meta(resource.get("meta")) {
+        return Some(version);
+    }
+
+    if let Some(version) = resource
+        .get("fhirVersion")
+        .and_then(Value::as_str)
+        .and_then(|value| value.parse().ok())
+    {
+        return Some(version);
+    }
+
+    if resource.get("resourceType").and_then(Value::as_str) == Some("Bundle") {
+        return resource
+            .get("entry")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.get("resource"))
+            .find_map(detect_version);
+    }
+
+    None
+}
+
+fn version_from_meta(meta: Option<&Value>) -> Option<FhirVersion> {
+    let meta = meta?;
+
+    let from_tag = meta
+        .get("tag")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter(|tag| tag.get("system").and_then(Value::as_str) == Some(FHIR_VERSION_TAG_SYSTEM))
+        .filter_map(|tag| tag.get("code").and_then(Value::as_str))
+        .find_map(|code| code.parse().ok());
+    if from_tag.is_some() {
+        return from_tag;
+    }
+
+    meta.get("profile")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .filter(|profile| profile.starts_with(CORE_PROFILE_PREFIX))
+        .filter_map(|profile| profile.split_once('|').map(|(_, version)| version))
+        .find_map(|version| version.parse().ok())
+}
+
+/// Resource types that only exist in some FHIR releases: (type, first release, last release, note).
+const RESOURCE_TYPE_AVAILABILITY: &[(&str, FhirVersion, FhirVersion, &str)] = &[
+    (
+        "CatalogEntry",
+        FhirVersion::R4,
+        FhirVersion::R4B,
+        "removed in R5",
+    ),
+    (
+        "DeviceUseStatement",
+        FhirVersion::R4,
+        FhirVersion::R4B,
+        "use DeviceUsage in R5",
+    ),
+    (
+        "DocumentManifest",
+        FhirVersion::R4,
+        FhirVersion::R4B,
+        "use List or DocumentReference in R5",
+    ),
+    (
+        "EffectEvidenceSynthesis",
+        FhirVersion::R4,
+        FhirVersion::R4,
+        "replaced by Evidence in R4B",
+    ),
+    (
+        "Media",
+        FhirVersion::R4,
+        FhirVersion::R4B,
+        "use DocumentReference in R5",
+    ),
+    (
+        "MedicinalProduct",
+        FhirVersion::R4,
+        FhirVersion::R4,
+        "use MedicinalProductDefinition in R4B+",
+    ),
+    (
+        "RequestGroup",
+        FhirVersion::R4,
+        FhirVersion::R4B,
+        "use RequestOrchestration in R5",
+    ),
+    (
+        "RiskEvidenceSynthesis",
+        FhirVersion::R4,
+        FhirVersion::R4,
+        "replaced by Evidence in R4B",
+    ),
+    (
+        "SubstanceSpecification",
+        FhirVersion::R4,
+        FhirVersion::R4,
+        "use SubstanceDefinition in R4B+",
+    ),
+    (
+        "AdministrableProductDefinition",
+        FhirVersion::R4B,
+        FhirVersion::R5,
+        "added in R4B",
+    ),
+    (
+        "Citation",
+        FhirVersion::R4B,
+        FhirVersion::R5,
+        "added in R4B",
+    ),
+    (
+        "ClinicalUseDefinition",
+        FhirVersion::R4B,
+        FhirVersion::R5,
+        "added in R4B",
+    ),
+    (
+        "EvidenceReport",
+        FhirVersion::R4B,
+        FhirVersion::R5,
+        "added in R4B",
+    ),
+    (
+        "Ingredient",
+        FhirVersion::R4B,
+        FhirVersion::R5,
+        "added in R4B",
+    ),
+    (
+        "MedicinalProductDefinition",
+        FhirVersion::R4B,
+        FhirVersion::R5,
+        "added in R4B",
+    ),
+    (
+        "PackagedProductDefinition",
+        FhirVersion::R4B,
+        FhirVersion::R5,
+        "added in R4B",
+    ),
+    (
+        "RegulatedAuthorization",
+        FhirVersion::R4B,
+        FhirVersion::R5,
+        "added in R4B",
+    ),
+    (
+        "SubscriptionStatus",
+        FhirVersion::R4B,
+        FhirVersion::R5,
+        "added in R4B",
+    ),
+    (
+        "SubscriptionTopic",
+        FhirVersion::R4B,
+        FhirVersion::R5,
+        "added in R4B",
+    ),
+    (
+        "ActorDefinition",
+        FhirVersion::R5,
+        FhirVersion::R5,
+        "added in R5",
+    ),
+    (
+        "DeviceUsage",
+        FhirVersion::R5,
+        FhirVersion::R5,
+        "added in R5 (DeviceUseStatement before)",
+    ),
+    (
+        "GenomicStudy",
+        FhirVersion::R5,
+        FhirVersion::R5,
+        "added in R5",
+    ),
+    (
+        "ImagingSelection",
+        FhirVersion::R5,
+        FhirVersion::R5,
+        "added in R5",
+    ),
+    (
+        "Permission",
+        FhirVersion::R5,
+        FhirVersion::R5,
+        "added in R5",
+    ),
+    (
+        "RequestOrchestration",
+        FhirVersion::R5,
+        FhirVersion::R5,
+        "added in R5 (RequestGroup before)",
+    ),
+    (
+        "Requirements",
+        FhirVersion::R5,
+        FhirVersion::R5,
+        "added in R5",
+    ),
+    ("Transport", FhirVersion::R5, FhirVersion::R5, "added in R5"),
+];
+
+/// Elements renamed between R4/R4B and R5: (resource type, R4 names, R5 name).
+const RENAMED_ELEMENTS: &[(&str, &[&str], &str)] = &[
+    ("Encounter", &["period"], "actualPeriod"),
+    ("Encounter", &["hospitalization"], "admission"),
+    ("MedicationAdministration", MEDICATION_CHOICES, "medication"),
+    ("MedicationDispense", MEDICATION_CHOICES, "medication"),
+    ("MedicationRequest", MEDICATION_CHOICES, "medication"),
+    ("MedicationStatement", MEDICATION_CHOICES, "medication"),
+    ("Procedure", &["performedDateTime"], "occurrenceDateTime"),
+    ("Procedure", &["performedPeriod"], "occurrencePeriod"),
+];
+
+const MEDICATION_CHOICES: &[&str] = &["medicationCodeableConcept", "medicationReference"];
+
+/// Local structural checks that depend on the FHIR release.
+///
+/// These only catch the differences that commonly trip up cross-version data (resource
+/// types that were added or removed, renamed elements, `Encounter.class` cardinality); the
+/// server's `$validate` remains the source of truth for everything else.
+pub fn check_structure(resource: &Value, version: FhirVersion) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    if let Some(resource_type) = resource.get("resourceType").and_then(Value::as_str) {
+        check_resource(resource, version, resource_type, &mut issues);
+    }
+    issues
+}
+
+fn check_resource(resource: &Value, version: FhirVersion, path: &str, issues: &mut Vec<Issue>) {
+    let Some(resource_type) = resource.get("resourceType").and_then(Value::as_str) else {
+        return;
+    };
+
+    if let Some((_, first, last, note)) = RESOURCE_TYPE_AVAILABILITY
+        .iter()
+        .find(|(name, ..)| *name == resource_type)
+        && (version < *first || version > *last)
+    {
+        issues.push(structure_issue(
+            format!(
+                "Resource type '{resource_type}' is not defined in FHIR {} ({note})",
+                version.label()
+            ),
+            path,
+        ));
+        return;
+    }
+
+    for (owner, r4_names, r5_name) in RENAMED_ELEMENTS {
+        if *owner != resource_type {
+            continue;
+        }
+        let present = |name: &str| resource.get(name).is_some();
+        let misplaced = if version == FhirVersion::R5 {
+            r4_names
+                .iter()
+                .find(|name| present(name))
+                .map(|name| (*name, r5_name.to_string()))
+        } else if present(r5_name) {
+            Some((*r5_name, r4_names.join("' or '")))
+        } else {
+            None
+        };
+        if let Some((found, expected)) = misplaced {
+            issues.push(structure_issue(
+                format!(
+                    "Element '{resource_type}.{found}' is not defined in FHIR {}; use '{expected}'",
+                    version.label()
+                ),
+                &format!("{path}.{found}"),
+            ));
+        }
+    }
+
+    if resource_type == "Encounter"
+        && let Some(class) = resource.get("class")
+    {
+        let (shape_ok, expected) = match version {
+            FhirVersion::R5 => (class.is_array(), "an array of CodeableConcept (0..*)"),
+            FhirVersion::R4 | FhirVersion::R4B => (class.is_object(), "a single Coding (1..1)"),
+        };
+        if !shape_ok {
+            issues.push(structure_issue(
+                format!(
+                    "Encounter.class must be {expected} in FHIR {}",
+                    version.label()
+                ),
+                &format!("{path}.class"),
+            ));
+        }
+    }
+
+    if resource_type == "Bundle" {
+        let entries = resource.get("entry").and_then(Value::as_array);
+        for (index, entry) in entries.into_iter().flatten().enumerate() {
+            if let Some(inner) = entry.get("resource") {
+                let inner_path = format!("{path}.entry[{index}].resource");
+                check_resource(inner, version, &inner_path, issues);
+            }
+        }
+    }
+}
+
+fn structure_issue(message: String, expression: &str) -> Issue {
+    Issue::local("error", "structure", message, expression)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_labels_and_release_numbers() {
+        assert_eq!("r4".parse::<FhirVersion>(), Ok(FhirVersion::R4));
+        assert_eq!("4.0.1".parse::<FhirVersion>(), Ok(FhirVersion::R4));
+        assert_eq!("4.3.0".parse::<FhirVersion>(), Ok(FhirVersion::R4B));
+        assert_eq!("R5".parse::<FhirVersion>(), Ok(FhirVersion::R5));
+        assert_eq!("5.0.0-ballot".parse::<FhirVersion>(), Ok(FhirVersion::R5));
+        assert!("3.0.2".parse::<FhirVersion>().is_err());
+    }
+
+    #[test]
+    fn detects_version_from_meta_tag_profile_and_capability_statement() {
+        let tagged = json!({
+            "resourceType": "Patient",
+            "meta": {"tag": [{"system": FHIR_VERSION_TAG_SYSTEM, "code": "5.0.0"}]}
+        });
+        assert_eq!(detect_version(&tagged), Some(FhirVersion::R5));
+
+        let profiled = json!({
+            "resourceType": "Patient",
+            "meta": {"profile": ["http://hl7.org/fhir/StructureDefinition/Patient|4.3.0"]}
+        });
+        assert_eq!(detect_version(&profiled), Some(FhirVersion::R4B));
+
+        let capability = json!({"resourceType": "CapabilityStatement", "fhirVersion": "4.0.1"});
+        assert_eq!(detect_version(&capability), Some(FhirVersion::R4));
+
+        let plain = json!({"resourceType": "Patient"});
+        assert_eq!(detect_version(&plain), None);
+    }
+
+    #[test]
+    fn detects_version_from_bundle_entries() {
+        let bundle = json!({
+            "resourceType": "Bundle",
+            "entry": [
+                {"resource": {"resourceType": "Patient"}},
+                {"resource": {"resourceType": "Observation", "meta": {"tag": [{"system": FHIR_VERSION_TAG_SYSTEM, "code": "R5"}]}}}
+            ]
+        });
+        assert_eq!(detect_version(&bundle), Some(FhirVersion::R5));
+    }
+
+    #[test]
+    fn mime_type_uses_major_minor_parameter() {
+        assert_eq!(
+            FhirVersion::R4B.mime_type(),
+            "application/fhir+json; fhirVersion=4.3"
+        );
+    }
+
+    #[test]
+    fn r5_encounter_is_not_judged_by_r4_rules() {
+        let encounter = json!({
+            "resourceType": "Encounter",
+            "class": [{"coding": [{"code": "AMB"}]}],
+            "actualPeriod": {"start": "2026-01-07"}
+        });
+        assert!(check_structure(&encounter, FhirVersion::R5).is_empty());
+
+        let issues = check_structure(&encounter, FhirVersion::R4);
+        assert_eq!(issues.len(), 2);
+        assert!(
+            issues
+                .iter()
+                .all(|issue| issue.code.as_deref() == Some("structure"))
+        );
+        assert!(
+            issues
+                .iter()
+                .any(|issue| issue.expression == vec!["Encounter.actualPeriod".to_string()])
+        );
+    }
+
+    #[test]
+    fn flags_resource_types_missing_from_release() {
+        let media = json!({"resourceType": "Media"});
+        assert!(check_structure(&media, FhirVersion::R4).is_empty());
+        let issues = check_structure(&media, FhirVersion::R5);
+        assert_eq!(issues.len(), 1);
+        assert!(
+            issues[0]
+                .diagnostics
+                .as_deref()
+                .unwrap()
+                .contains("DocumentReference")
+        );
+    }
+
+    #[test]
+    fn bundle_entry_expressions_point_into_entries() {
+        let bundle = json!({
+            "resourceType": "Bundle",
+            "entry": [{"resource": {"resourceType": "Procedure", "performedDateTime": "2026-01-07"}}]
+        });
+        let issues = check_structure(&bundle, FhirVersion::R5);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(
+            issues[0].expression,
+            vec!["Bundle.entry[0].resource.performedDateTime".to_string()]
+        );
+    }
+}
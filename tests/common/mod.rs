@@ -0,0 +1,105 @@
+//! Helpers shared by the CLI test suites (`mod common;` in each).
+
+#![allow(dead_code)]
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Answers every request with `body` until no request arrives for a second.
+pub fn start_test_server(body: &str) -> (String, thread::JoinHandle<()>) {
+    start_test_server_with_status("200 OK", body)
+}
+
+/// Like [`start_test_server`], answering with `status` (e.g. `412 Precondition Failed`).
+pub fn start_test_server_with_status(status: &str, body: &str) -> (String, thread::JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind test server");
+    listener.set_nonblocking(true).expect("set nonblocking");
+    let addr = listener.local_addr().expect("server addr");
+    let status = status.to_string();
+    let body = body.to_string();
+    let handle = thread::spawn(move || {
+        let mut idle = Instant::now();
+        while idle.elapsed() < Duration::from_secs(1) {
+            match listener.accept() {
+                Ok((mut stream, _)) => {
+                    stream.set_nonblocking(false).expect("blocking stream");
+                    let mut buf = [0u8; 8192];
+                    let _ = stream.read(&mut buf);
+                    let response = format!(
+                        "HTTP/1.1 {status}\r\nContent-Type: application/fhir+json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = stream.write_all(response.as_bytes());
+                    idle = Instant::now();
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+                    thread::sleep(Duration::from_millis(10));
+                }
+                Err(_) => break,
+            }
+        }
+    });
+
+    (format!("http://{}", addr), handle)
+}
+
+/// The clinlogix binary, run from an empty directory with an empty user config
+/// home, so a developer's own `clinlogix.toml` cannot change the results.
+pub fn clinlogix() -> Command {
+    let root = std::env::temp_dir().join("clinlogix-tests-without-config");
+    let home = root.join("config");
+    std::fs::create_dir_all(&home).expect("create empty config home");
+    let mut command = Command::new(env!("CARGO_BIN_EXE_clinlogix"));
+    command.current_dir(&root).env("XDG_CONFIG_HOME", &home);
+    command
+}
+
+/// A file in the repository's `examples/` directory.
+pub fn example(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("examples")
+        .join(name)
+}
+
+/// The demo EHR log at the repository root.
+pub fn demo_log() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("demo-healthit.log")
+}
+
+/// A fresh directory under the system temp dir, removed again on drop.
+pub struct ScratchDir(PathBuf);
+
+impl Deref for ScratchDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for ScratchDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Creates `clinlogix-<name>-<pid>` in the temp dir, emptied of anything an
+/// earlier, aborted run left behind.
+pub fn scratch_dir(name: &str) -> ScratchDir {
+    let dir = std::env::temp_dir().join(format!("clinlogix-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("create scratch dir");
+    ScratchDir(dir)
+}
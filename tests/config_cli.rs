@@ -0,0 +1,152 @@
+mod common;
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+use common::{demo_log, example, scratch_dir, start_test_server};
+
+/// Runs clinlogix in `project` with `home` as the user-level config directory.
+fn clinlogix(project: &Path, home: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_clinlogix"))
+        .args(args)
+        .current_dir(project)
+        .env("XDG_CONFIG_HOME", home)
+        .output()
+        .expect("run clinlogix")
+}
+
+#[test]
+fn config_files_supply_server_and_policy_until_flags_override_them() {
+    let outcome = r#"{"resourceType":"OperationOutcome","issue":[{"severity":"error","code":"code-invalid","diagnostics":"Unknown gender","expression":["Patient.gender"]}]}"#;
+    let (base_url, handle) = start_test_server(outcome);
+    let root = scratch_dir("config");
+    let home = root.join("home");
+    let project = root.join("project");
+    std::fs::create_dir_all(home.join("clinlogix")).expect("user config dir");
+    std::fs::create_dir_all(project.join("nested")).expect("project dir");
+    std::fs::write(
+        home.join("clinlogix").join("clinlogix.toml"),
+        "default_server = \"firely\"\n\n[server.firely]\nbase_url = \"https://server.fire.ly\"\n\n[policy]\nnon_fatal_themes = [\"terminology\"]\n",
+    )
+    .expect("write user config");
+    std::fs::write(
+        project.join("clinlogix.toml"),
+        format!("default_server = \"local\"\n\n[server.local]\nbase_url = \"{base_url}\"\n"),
+    )
+    .expect("write project config");
+    let patient = example("patient-bad.json");
+    let patient = patient.to_str().expect("utf-8 path");
+    let nested = project.join("nested");
+
+    let configured = clinlogix(&nested, &home, &["validate", patient]);
+    let strict = clinlogix(
+        &nested,
+        &home,
+        &[
+            "validate",
+            patient,
+            "--non-fatal-theme",
+            "profile resolution",
+        ],
+    );
+    let unknown = clinlogix(&nested, &home, &["validate", patient, "--server", "hapi"]);
+    std::fs::write(
+        project.join("clinlogix.toml"),
+        format!("default_server = \"local\"\n\n[server.local]\nbase_url = \"{base_url}\"\n\n[policy]\nignore_http_status = true\n"),
+    )
+    .expect("write project config");
+    let ignored = clinlogix(&nested, &home, &["validate", patient]);
+    let strict_http = clinlogix(
+        &nested,
+        &home,
+        &["validate", patient, "--no-ignore-http-status"],
+    );
+    let show = clinlogix(&nested, &home, &["config", "show"]);
+    let _ = handle.join();
+
+    let stdout = String::from_utf8_lossy(&configured.stdout);
+    assert!(configured.status.success(), "{stdout}");
+    assert!(stdout.contains(&format!("Base: {base_url}")));
+    assert!(stdout.contains("Policy: fail on error; non-fatal: terminology"));
+
+    assert_eq!(strict.status.code(), Some(1));
+
+    assert_eq!(unknown.status.code(), Some(2));
+    assert!(
+        String::from_utf8_lossy(&unknown.stderr)
+            .contains("unknown server 'hapi' (configured: firely, local)")
+    );
+
+    assert!(
+        String::from_utf8_lossy(&ignored.stdout)
+            .contains("Policy: fail on error; non-fatal: terminology; ignore HTTP status\n")
+    );
+    let strict_http_out = String::from_utf8_lossy(&strict_http.stdout);
+    assert!(strict_http.status.success(), "{strict_http_out}");
+    assert!(strict_http_out.contains("Policy: fail on error; non-fatal: terminology\n"));
+
+    let shown = String::from_utf8_lossy(&show.stdout);
+    assert!(show.status.success());
+    assert!(shown.contains(&format!(
+        "# from {}\n",
+        home.join("clinlogix").join("clinlogix.toml").display()
+    )));
+    assert!(shown.contains(&format!(
+        "# from {}\n",
+        project.join("clinlogix.toml").display()
+    )));
+    assert!(shown.contains("default_server = \"local\"\n"));
+    assert!(shown.contains(&format!("[server.local]\nbase_url = \"{base_url}\"\n")));
+    assert!(shown.contains("non_fatal_themes = [\"terminology\"]\n"));
+}
+
+#[test]
+fn broken_config_only_stops_commands_that_need_it() {
+    let root = scratch_dir("config-broken");
+    let home = root.join("home");
+    std::fs::create_dir_all(home.join("clinlogix")).expect("user config dir");
+    let user = home.join("clinlogix").join("clinlogix.toml");
+    let log = demo_log();
+    let log = log.to_str().expect("utf-8 path");
+    let patient = example("patient.json");
+    let patient = patient.to_str().expect("utf-8 path");
+
+    std::fs::write(&user, "default_server = \"nope\"\n").expect("write user config");
+    let scan = clinlogix(&root, &home, &["scan", log]);
+    let validate = clinlogix(&root, &home, &["validate", patient]);
+    let shown_server = clinlogix(&root, &home, &["config", "show"]);
+
+    std::fs::write(&user, "bogus = 1\n").expect("write user config");
+    let message = example("adt-a01.hl7");
+    let parse = clinlogix(
+        &root,
+        &home,
+        &["hl7v2", "parse", message.to_str().expect("utf-8 path")],
+    );
+    let show = clinlogix(&root, &home, &["config", "show"]);
+    let broken_scan = clinlogix(&root, &home, &["scan", log]);
+
+    assert!(scan.status.success());
+    assert_eq!(validate.status.code(), Some(2));
+    assert!(
+        String::from_utf8_lossy(&validate.stderr)
+            .contains("default_server: unknown server 'nope' (no servers configured)")
+    );
+    assert!(shown_server.status.success());
+    assert!(String::from_utf8_lossy(&shown_server.stdout).contains(
+        "# base URL without --base-url or --server: error: default_server: unknown server 'nope'"
+    ));
+
+    assert!(
+        parse.status.success(),
+        "hl7v2 parse does not read the config"
+    );
+    assert_eq!(show.status.code(), Some(2));
+    let shown = String::from_utf8_lossy(&show.stdout);
+    assert!(shown.contains(&format!(
+        "# skipped {}: invalid configuration",
+        user.display()
+    )));
+    assert!(shown.contains("\n[policy]\nfail_on = \"error\"\n"));
+    assert_eq!(broken_scan.status.code(), Some(2));
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use std::process::Command;
+
+#[test]
+fn deidentify_is_repeatable_with_a_secret_file() {
+    let patient = common::example("patient.json");
+    let dir = common::scratch_dir("deid");
+    let secret = dir.join("secret");
+    let profile = dir.join("profile.toml");
+    std::fs::write(&profile, "[dates]\nbirth_date = \"shift\"\n").expect("write profile");
+
+    let run = || {
+        Command::new(env!("CARGO_BIN_EXE_clinlogix"))
+            .arg("deidentify")
+            .arg(&patient)
+            .arg("--secret-file")
+            .arg(&secret)
+            .arg("--profile")
+            .arg(&profile)
+            .output()
+            .expect("run clinlogix deidentify")
+    };
+    let first = run();
+    let second = run();
+
+    let stderr = String::from_utf8_lossy(&first.stderr);
+    assert!(first.status.success(), "{stderr}");
+    assert!(stderr.contains("De-identified Patient with profile Safe Harbor"));
+    assert_eq!(first.stdout, second.stdout);
+
+    let resource: serde_json::Value =
+        serde_json::from_slice(&first.stdout).expect("de-identified JSON");
+    assert_eq!(resource["resourceType"], "Patient");
+    assert_eq!(resource["name"][0]["family"], "REDACTED");
+    assert_ne!(resource["id"], "example");
+    let birth_date = resource["birthDate"].as_str().expect("birthDate");
+    assert_eq!(birth_date.len(), 10);
+    assert_ne!(birth_date, "1990-01-01");
+    assert!(!String::from_utf8_lossy(&first.stdout).contains("Alice"));
+
+    let unkeyed = Command::new(env!("CARGO_BIN_EXE_clinlogix"))
+        .arg("deidentify")
+        .arg(&patient)
+        .env_remove("CLINLOGIX_REDACT_SECRET")
+        .output()
+        .expect("run clinlogix deidentify");
+    assert!(unkeyed.status.success());
+    assert!(
+        String::from_utf8_lossy(&unkeyed.stderr).contains(
+            "warning: no --secret-file or CLINLOGIX_REDACT_SECRET; using a random secret"
+        )
+    );
+    assert!(
+        !stderr.contains("warning:"),
+        "a secret file keeps runs reproducible"
+    );
+
+    let missing = Command::new(env!("CARGO_BIN_EXE_clinlogix"))
+        .args(["deidentify", "does-not-exist.json"])
+        .output()
+        .expect("run clinlogix deidentify");
+    assert_eq!(missing.status.code(), Some(2));
+}
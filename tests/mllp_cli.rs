@@ -0,0 +1,104 @@
+mod common;
+
+use std::io::{BufRead, BufReader};
+use std::process::Stdio;
+
+use common::{example, scratch_dir};
+
+#[test]
+fn mllp_send_and_listen_exchange_acks_on_localhost() {
+    let dir = scratch_dir("mllp");
+    let log = dir.join("listener.log");
+
+    let mut listener = common::clinlogix()
+        .args([
+            "mllp",
+            "listen",
+            "--port",
+            "0",
+            "--max-messages",
+            "2",
+            "--log",
+        ])
+        .arg(&log)
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("start clinlogix mllp listen");
+    let mut stdout = BufReader::new(listener.stdout.take().expect("listener stdout"));
+    let mut first = String::new();
+    stdout.read_line(&mut first).expect("read startup line");
+    let port = first
+        .trim()
+        .rsplit(':')
+        .next()
+        .expect("listening address")
+        .to_string();
+    assert!(first.starts_with("INFO "), "startup line: {first}");
+
+    let send = common::clinlogix()
+        .args(["mllp", "send", "--port", &port, "--timeout", "5"])
+        .arg(example("adt-a01.hl7"))
+        .arg(example("oru-r01-invalid.hl7"))
+        .output()
+        .expect("run clinlogix mllp send");
+    let sent = String::from_utf8_lossy(&send.stdout);
+    assert_eq!(send.status.code(), Some(1), "send output: {sent}");
+    assert!(
+        sent.contains("ADT^A01 MSG00001 -> AA"),
+        "send output: {sent}"
+    );
+    assert!(
+        sent.contains("-> AE (1 validation error)"),
+        "send output: {sent}"
+    );
+    assert!(
+        sent.contains("1 accepted, 1 rejected"),
+        "send output: {sent}"
+    );
+
+    assert!(listener.wait().expect("listener exit").success());
+    let logged = std::fs::read_to_string(&log).expect("read listener log");
+    assert!(logged.contains("HL7 ACK sent: AE for ORU^R01"));
+    assert!(logged.contains("HL7 listener disconnected from"));
+    assert!(
+        logged
+            .lines()
+            .last()
+            .is_some_and(|line| line.ends_with("HL7 listener stopped after 2 messages")),
+        "log: {logged}"
+    );
+
+    let scan = common::clinlogix()
+        .arg("scan")
+        .arg(&log)
+        .arg("--json")
+        .output()
+        .expect("run clinlogix scan");
+    let summary: serde_json::Value =
+        serde_json::from_slice(&scan.stdout).expect("scan summary is JSON");
+    assert_eq!(summary["errors"], 2, "log: {logged}");
+}
+
+#[test]
+fn mllp_send_reports_unreachable_listener_as_transport_failure() {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("free port")
+        .port();
+    let output = common::clinlogix()
+        .args([
+            "mllp",
+            "send",
+            "--port",
+            &port.to_string(),
+            "--timeout",
+            "1",
+        ])
+        .arg(example("adt-a01.hl7"))
+        .output()
+        .expect("run clinlogix mllp send");
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Error: transport error: "), "{stderr}");
+    assert!(!stderr.contains("validation"), "{stderr}");
+}
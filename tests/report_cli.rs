@@ -0,0 +1,77 @@
+mod common;
+
+use common::{demo_log, example, scratch_dir, start_test_server};
+
+#[test]
+fn report_combines_scan_and_validation_in_markdown_and_html() {
+    let outcome = r#"{"resourceType":"OperationOutcome","issue":[{"severity":"error","code":"required","diagnostics":"Patient.name: minimum required = 1, but only found 0","expression":["Patient"]}]}"#;
+    let (base_url, handle) = start_test_server(outcome);
+    let dir = scratch_dir("report");
+    let markdown = dir.join("incident.md");
+    let html = dir.join("incident.html");
+
+    let run = |output: &std::path::Path| {
+        common::clinlogix()
+            .arg("report")
+            .arg(demo_log())
+            .arg(example("patient-bad.json"))
+            .args(["--title", "EHR outage 2026-01-07", "--base-url"])
+            .arg(&base_url)
+            .arg("--output")
+            .arg(output)
+            .output()
+            .expect("run clinlogix report")
+    };
+    let from_markdown = run(&markdown);
+    let from_html = run(&html);
+    let _ = handle.join();
+    let markdown = std::fs::read_to_string(&markdown).expect("markdown report");
+    let html = std::fs::read_to_string(&html).expect("HTML report");
+
+    // The report is written, and the failing file fails the run like `validate`.
+    assert_eq!(from_markdown.status.code(), Some(1));
+    assert_eq!(from_html.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&from_markdown.stderr).contains("1 of 1 files failed"));
+
+    assert!(markdown.starts_with("# EHR outage 2026-01-07\n"));
+    for section in [
+        "## Log summary",
+        "## Error timeline",
+        "## Top signatures",
+        "## Validation outcomes",
+    ] {
+        assert!(markdown.contains(section), "missing {section}");
+    }
+    assert!(markdown.contains("| 7 | 7 | 3 | 1 | plain |"));
+    assert!(markdown.contains("| database | 1 | error |"));
+    assert!(markdown.contains("2026-01-07 08:05    1   0  #"));
+    assert!(
+        markdown
+            .contains("- 2026-01-07 08:03:22 (line 3) — warning: FHIR endpoint slow response (x1)")
+    );
+    assert!(markdown.contains("patient-bad.json | FAIL | 1 | 0 | 0 |"));
+    assert!(markdown.contains("Patient.name: minimum required = 1, but only found 0 (x1)"));
+
+    assert!(html.starts_with("<!doctype html>"));
+    assert!(html.contains("<h1>EHR outage 2026-01-07</h1>"));
+    assert!(html.contains("--accent: #0f766e;"));
+    assert!(html.contains("<span class=\"pill fail\">fail</span>"));
+    assert!(html.contains("Timeout while writing audit log"));
+}
+
+#[test]
+fn report_keeps_the_transport_exit_code_of_validate() {
+    let output = common::clinlogix()
+        .arg("report")
+        .arg(demo_log())
+        .arg(example("patient.json"))
+        .args(["--format", "markdown", "--base-url", "http://127.0.0.1:1"])
+        .output()
+        .expect("run clinlogix report");
+    assert_eq!(output.status.code(), Some(3));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("patient.json | ERROR | - | - | - |"),
+        "{stdout}"
+    );
+}
@@ -0,0 +1,718 @@
+mod common;
+
+use std::io::Write;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::{demo_log, example, scratch_dir, start_test_server};
+
+#[test]
+fn scan_extract_writes_and_validates_payloads() {
+    let dir = scratch_dir("scan-extract");
+    let (base_url, handle) = start_test_server(r#"{"resourceType":"OperationOutcome","issue":[]}"#);
+
+    let output = common::clinlogix()
+        .arg("scan")
+        .arg(example("interface-engine.log"))
+        .arg("--extract")
+        .arg(&*dir)
+        .arg("--validate")
+        .arg("--base-url")
+        .arg(&base_url)
+        .output()
+        .expect("run clinlogix scan --extract");
+    let _ = handle.join();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{stdout}");
+    assert!(stdout.contains("Extracted: 4 (HL7 v2: 2, FHIR: 2)"));
+    assert!(stdout.contains("hl7v2 ADT^A01 MSG00002"));
+    assert!(stdout.contains("[FAIL (1 errors, 0 warnings)]"));
+    assert_eq!(stdout.matches("[PASS").count(), 3, "{stdout}");
+
+    let patient = std::fs::read_to_string(dir.join("L000006-fhir-Patient_123.json"))
+        .expect("extracted patient");
+    assert!(patient.contains("\"resourceType\": \"Patient\""));
+
+    // An unreachable server is a transport error, not a failed payload.
+    let unreachable = common::clinlogix()
+        .arg("scan")
+        .arg(example("interface-engine.log"))
+        .arg("--extract")
+        .arg(&*dir)
+        .args(["--validate", "--base-url", "http://127.0.0.1:1"])
+        .output()
+        .expect("run clinlogix scan --extract against an unreachable server");
+    assert_eq!(unreachable.status.code(), Some(3));
+
+    // A compressed log goes through the same input layer as a plain scan.
+    let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gzip.write_all(&std::fs::read(example("interface-engine.log")).expect("read example"))
+        .expect("gzip");
+    let compressed = dir.join("interface-engine.log.gz");
+    std::fs::write(&compressed, gzip.finish().expect("gzip")).expect("write .gz");
+    let from_gzip = common::clinlogix()
+        .arg("scan")
+        .arg(&compressed)
+        .arg("--extract")
+        .arg(dir.join("from-gzip"))
+        .output()
+        .expect("run clinlogix scan --extract on a .gz log");
+    let stdout = String::from_utf8_lossy(&from_gzip.stdout);
+    assert!(from_gzip.status.success(), "{stdout}");
+    assert!(
+        stdout.contains("Extracted: 4 (HL7 v2: 2, FHIR: 2)"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn scan_phi_reports_categories_without_values() {
+    let dir = scratch_dir("phi");
+    let log = dir.join("app.log");
+    std::fs::write(
+        &log,
+        "INFO 2026-01-07 08:00:01 EHR service started\n\
+         ERROR 2026-01-07 08:07:12 FHIR validation failed for Patient/pat-8842\n\
+         WARNING 2026-01-07 08:09:30 Duplicate SSN 219-09-9999 for jane.roe@example.org\n\
+         INFO 2026-01-07 08:10:02 Chart HOSP-004211 opened, DOB 03/14/1975\n",
+    )
+    .expect("write log");
+
+    let output = common::clinlogix()
+        .arg("scan")
+        .arg(&log)
+        .args(["--phi", "--json", "--mrn-pattern", r"\bHOSP-(\d{6})\b"])
+        .output()
+        .expect("run clinlogix scan --phi");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for value in ["pat-8842", "219-09-9999", "jane.roe", "004211", "1975"] {
+        assert!(!stdout.contains(value), "{value} leaked: {stdout}");
+    }
+    let summary: serde_json::Value = serde_json::from_str(&stdout).expect("JSON summary");
+    assert_eq!(summary["errors"], 1);
+    assert_eq!(summary["phi"]["total"], 5);
+    assert_eq!(summary["phi"]["lines"], 3);
+    assert_eq!(summary["phi"]["by_category"]["mrn"], 1);
+    assert_eq!(summary["phi"]["findings"][0]["line"], 2);
+    assert_eq!(summary["phi"]["findings"][0]["category"], "patient-id");
+}
+
+#[test]
+fn redact_keeps_scan_counts_and_pseudonyms_stable() {
+    let dir = scratch_dir("redact");
+    let log = dir.join("app.log");
+    std::fs::write(
+        &log,
+        "INFO 2026-01-07 08:00:01 Chart opened MRN: A77812\r\n\
+         ERROR 2026-01-07 08:07:12 FHIR validation failed for Patient/123\r\n\
+         WARNING 2026-01-07 08:09:30 Callback 555-123-4567 for MRN: A77812\r\n",
+    )
+    .expect("write log");
+    let redacted = dir.join("app.redacted.log");
+    let secret = dir.join("redact.key");
+
+    let output = common::clinlogix()
+        .arg("redact")
+        .arg(&log)
+        .args(["--mode", "pseudonym", "--secret-file"])
+        .arg(&secret)
+        .arg("--output")
+        .arg(&redacted)
+        .output()
+        .expect("run clinlogix redact");
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Redacted 4 values on 3 lines"), "{stderr}");
+    assert!(secret.exists());
+
+    let text = std::fs::read_to_string(&redacted).expect("read redacted log");
+    assert!(!text.contains("A77812") && !text.contains("555-123"));
+    assert_eq!(text.matches("\r\n").count(), 3);
+    let lines: Vec<&str> = text.lines().collect();
+    let token = |line: &str| line[line.find("[MRN-").expect("MRN token")..].to_string();
+    assert_eq!(token(lines[0]), token(lines[2]));
+
+    let scan = |path: &std::path::Path| {
+        let output = common::clinlogix()
+            .arg("scan")
+            .arg(path)
+            .args(["--phi", "--json"])
+            .output()
+            .expect("run clinlogix scan");
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).expect("JSON summary")
+    };
+    let (before, after) = (scan(&log), scan(&redacted));
+    for key in ["total_lines", "errors", "warnings"] {
+        assert_eq!(before[key], after[key], "{key}");
+    }
+    assert_eq!(after["phi"]["total"], 0);
+}
+
+#[test]
+fn redact_removes_every_identifier_from_the_interface_engine_log() {
+    let dir = scratch_dir("redact-ie");
+    let redacted = dir.join("interface-engine.redacted.log");
+
+    let output = common::clinlogix()
+        .arg("redact")
+        .arg(example("interface-engine.log"))
+        .arg("--output")
+        .arg(&redacted)
+        .output()
+        .expect("run clinlogix redact");
+    assert!(output.status.success());
+
+    let text = std::fs::read_to_string(&redacted).expect("read redacted log");
+    for value in [
+        "123456",
+        "Doe",
+        "Jane",
+        "19850520",
+        "1985-05-20",
+        "Patient/123",
+        "\"id\":\"123\"",
+    ] {
+        assert!(!text.contains(value), "{value} survived redaction:\n{text}");
+    }
+    assert!(text.contains("\"id\":\"[PATIENT-ID]\""));
+
+    let rescan = common::clinlogix()
+        .arg("scan")
+        .arg(&redacted)
+        .args(["--phi", "--json"])
+        .output()
+        .expect("run clinlogix scan");
+    let summary: serde_json::Value = serde_json::from_slice(&rescan.stdout).expect("JSON summary");
+    assert_eq!(summary["phi"]["total"], 0);
+}
+
+#[test]
+fn scan_filters_by_time_and_buckets_errors() {
+    let log = demo_log();
+    let output = common::clinlogix()
+        .arg("scan")
+        .arg(&log)
+        .args([
+            "--since",
+            "08:05",
+            "--until",
+            "2026-01-07 08:07",
+            "--histogram",
+            "minute",
+            "--json",
+        ])
+        .output()
+        .expect("run clinlogix scan --since --until");
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).expect("JSON summary");
+    assert_eq!(summary["since"], "2026-01-07T08:05:00");
+    assert_eq!(summary["errors"], 2);
+    assert_eq!(summary["warnings"], 0);
+    let buckets = summary["histogram"]["buckets"].as_array().expect("buckets");
+    assert_eq!(buckets.len(), 2);
+    assert_eq!(buckets[1]["start"], "2026-01-07T08:06:00");
+    assert_eq!(buckets[1]["errors"], 1);
+
+    let text = common::clinlogix()
+        .arg("scan")
+        .arg(&log)
+        .args(["--histogram", "minute"])
+        .output()
+        .expect("run clinlogix scan --histogram");
+    let stdout = String::from_utf8_lossy(&text.stdout);
+    assert!(
+        stdout.contains("  2026-01-07 08:03    0   1  ~\n"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("  2026-01-07 08:07    1   0  #\n"),
+        "{stdout}"
+    );
+
+    let invalid = common::clinlogix()
+        .arg("scan")
+        .arg(&log)
+        .args(["--since", "yesterday"])
+        .output()
+        .expect("run clinlogix scan --since yesterday");
+    assert!(!invalid.status.success());
+
+    let missing = common::clinlogix()
+        .args(["scan", "does-not-exist.log"])
+        .output()
+        .expect("run clinlogix scan on a missing file");
+    assert_eq!(missing.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("does-not-exist.log: "));
+}
+
+#[test]
+fn scan_ranks_error_signatures() {
+    let dir = scratch_dir("signatures");
+    let log = dir.join("app.log");
+    std::fs::write(
+        &log,
+        "ERROR 2026-01-07 08:05:40 Database connection failed\n\
+         ERROR 2026-01-07 08:07:12 FHIR validation failed for Patient/123\n\
+         WARNING 2026-01-07 08:07:30 Retry 1 of 3 for 10.0.4.12:8443\n\
+         ERROR 2026-01-07 08:08:02 FHIR validation failed for Patient/456\n\
+         WARNING 2026-01-07 08:08:30 Retry 2 of 3 for 10.0.4.12:8443\n",
+    )
+    .expect("write log");
+
+    let output = common::clinlogix()
+        .arg("scan")
+        .arg(&log)
+        .args(["--top", "1", "--json"])
+        .output()
+        .expect("run clinlogix scan --top");
+
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).expect("JSON summary");
+    let errors = &summary["signatures"]["errors"];
+    assert_eq!(errors["distinct"], 2);
+    assert_eq!(
+        errors["top"][0]["signature"],
+        "FHIR validation failed for Patient/<id>"
+    );
+    assert_eq!(errors["top"][0]["count"], 2);
+    assert_eq!(errors["top"][0]["first_seen"]["line"], 2);
+    assert_eq!(errors["top"][0]["last_seen"]["time"], "2026-01-07T08:08:02");
+    assert_eq!(
+        errors["top"][0]["example"],
+        "ERROR 2026-01-07 08:07:12 FHIR validation failed for Patient/123"
+    );
+    let warnings = &summary["signatures"]["warnings"];
+    assert_eq!(warnings["top"][0]["signature"], "Retry <n> of <n> for <ip>");
+    assert_eq!(warnings["top"][0]["count"], 2);
+}
+
+#[test]
+fn scan_sorts_records_into_categories_from_a_rules_file() {
+    let dir = scratch_dir("rules");
+    let log = dir.join("app.log");
+    std::fs::write(
+        &log,
+        "ERROR 2026-01-07 08:05:40 Database connection failed\n\
+         ERROR 2026-01-07 08:06:45 Timeout while writing audit log\n\
+         INFO 2026-01-07 08:07:00 Disk usage at 91% on /var/lib/fhir\n\
+         WARNING 2026-01-07 08:07:30 Audit event queue is 80% full\n",
+    )
+    .expect("write log");
+    let rules = dir.join("rules.toml");
+    std::fs::write(
+        &rules,
+        r#"
+[[category]]
+name = "capacity"
+severity = "warning"
+patterns = ["(?i)disk usage at (?:9\\d|100)%"]
+"#,
+    )
+    .expect("write rules");
+
+    let output = common::clinlogix()
+        .arg("scan")
+        .arg(&log)
+        .arg("--rules")
+        .arg(&rules)
+        .args(["--top", "0", "--json"])
+        .output()
+        .expect("run clinlogix scan --rules");
+
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).expect("JSON summary");
+    assert_eq!(summary["errors"], 3);
+    assert_eq!(summary["warnings"], 1);
+    let categories: Vec<_> = summary["categories"]
+        .as_array()
+        .expect("categories")
+        .iter()
+        .map(|category| {
+            (
+                category["name"].as_str().unwrap(),
+                category["severity"].as_str().unwrap(),
+                category["count"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        categories,
+        [
+            ("audit", "error", 1),
+            ("capacity", "warning", 1),
+            ("database", "error", 1),
+            ("timeout", "error", 1)
+        ]
+    );
+}
+
+#[test]
+fn scan_correlates_referenced_resources_and_validates_them() {
+    let dir = scratch_dir("correlate");
+    let resources = dir.join("resources");
+    std::fs::create_dir_all(&resources).expect("resources dir");
+    let log = dir.join("app.log");
+    std::fs::write(
+        &log,
+        "ERROR 2026-01-07 08:07:12 FHIR validation failed for Patient/123\n\
+         ERROR 2026-01-07 08:08:00 FHIR write rejected body={\"resourceType\":\"Observation\",\"id\":\"obs-1\",\"status\":\"final\"}\n\
+         WARNING 2026-01-07 08:09:00 Encounter/e9 missing upstream\n\
+         INFO 2026-01-07 08:10:00 Read Patient/456\n",
+    )
+    .expect("write log");
+    std::fs::write(
+        resources.join("patient.json"),
+        r#"{"resourceType":"Patient","id":"123"}"#,
+    )
+    .expect("write resource");
+    let (base_url, handle) = start_test_server(r#"{"resourceType":"OperationOutcome","issue":[]}"#);
+
+    let output = common::clinlogix()
+        .arg("scan")
+        .arg(&log)
+        .arg("--correlate")
+        .arg("--resources")
+        .arg(&resources)
+        .arg("--validate")
+        .arg("--base-url")
+        .arg(&base_url)
+        .arg("--json")
+        .output()
+        .expect("run clinlogix scan --correlate");
+    handle.join().expect("test server");
+
+    // Nothing was validated when the server is unreachable: a transport error.
+    let unreachable = common::clinlogix()
+        .arg("scan")
+        .arg(&log)
+        .arg("--correlate")
+        .arg("--resources")
+        .arg(&resources)
+        .args(["--validate", "--base-url", "http://127.0.0.1:1"])
+        .output()
+        .expect("run clinlogix scan --correlate against an unreachable server");
+    assert_eq!(unreachable.status.code(), Some(3));
+
+    // Neither is anything validated when the resources cannot be fetched.
+    let unfetched = common::clinlogix()
+        .arg("scan")
+        .arg(demo_log())
+        .args([
+            "--correlate",
+            "--resources",
+            "http://127.0.0.1:9",
+            "--validate",
+        ])
+        .output()
+        .expect("run clinlogix scan --correlate with an unreachable resource server");
+    let stdout = String::from_utf8_lossy(&unfetched.stdout);
+    assert!(stdout.contains("lookup failed"), "{stdout}");
+    assert_eq!(unfetched.status.code(), Some(3));
+
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).expect("JSON summary");
+    assert_eq!(summary["errors"], 2);
+    let correlations = summary["correlations"].as_array().expect("correlations");
+    let references: Vec<_> = correlations
+        .iter()
+        .map(|item| {
+            (
+                item["reference"].as_str().unwrap(),
+                item["resource"]["status"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        references,
+        [
+            ("Patient/123", "found"),
+            ("Observation/obs-1", "found"),
+            ("Encounter/e9", "missing")
+        ]
+    );
+    assert!(
+        correlations[0]["resource"]["source"]
+            .as_str()
+            .unwrap()
+            .ends_with("patient.json")
+    );
+    assert_eq!(correlations[0]["categories"][0], "fhir-validation");
+    assert_eq!(correlations[0]["validation"]["status"], "pass");
+    assert_eq!(correlations[1]["resource"]["source"], "log line 2");
+    assert_eq!(correlations[1]["validation"]["status"], "pass");
+    assert_eq!(correlations[2]["warnings"], 1);
+    assert!(correlations[2].get("validation").is_none());
+}
+
+#[test]
+fn scan_detects_structured_formats() {
+    let dir = scratch_dir("formats");
+    let jsonl = dir.join("api.jsonl");
+    std::fs::write(
+        &jsonl,
+        r#"{"time":"2026-01-07T08:00:01Z","level":"info","msg":"request ok"}
+{"time":"2026-01-07T08:00:02Z","level":"error","msg":"FHIR write rejected for Patient/9","service":"api"}
+{"time":"2026-01-07T08:00:03Z","level":"warn","msg":"slow query on error_log table"}
+"#,
+    )
+    .expect("write JSON lines");
+    let syslog = dir.join("lb.log");
+    std::fs::write(
+        &syslog,
+        "<134>1 2026-01-07T08:05:00Z lb01 haproxy 811 - - backend fhir is UP\n\
+         <131>1 2026-01-07T08:05:40Z lb01 haproxy 811 - - backend fhir has no server available\n\
+         <131>1 2026-01-07T08:05:41Z lb01 haproxy 811 - - backend fhir has no server available\n",
+    )
+    .expect("write syslog");
+    let custom = dir.join("engine.log");
+    std::fs::write(
+        &custom,
+        "08:00:01|I|engine started\n08:00:02|E|channel ADT stopped\n",
+    )
+    .expect("write custom log");
+
+    let scan = |path: &std::path::Path, extra: &[&str]| {
+        let output = common::clinlogix()
+            .arg("scan")
+            .arg(path)
+            .arg("--json")
+            .args(extra)
+            .output()
+            .expect("run clinlogix scan");
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).expect("JSON summary")
+    };
+
+    let summary = scan(&jsonl, &[]);
+    assert_eq!(summary["format"], "json-lines");
+    assert_eq!(summary["errors"], 1);
+    assert_eq!(summary["warnings"], 1);
+    assert_eq!(
+        summary["signatures"]["errors"]["top"][0]["signature"],
+        "FHIR write rejected for Patient/<id>"
+    );
+
+    let summary = scan(&syslog, &[]);
+    assert_eq!(summary["format"], "syslog");
+    assert_eq!(summary["errors"], 2);
+    assert_eq!(summary["signatures"]["errors"]["distinct"], 1);
+
+    assert_eq!(scan(&custom, &[])["errors"], 0);
+    let summary = scan(
+        &custom,
+        &[
+            "--pattern",
+            r"%{NOTSPACE}\|%{WORD:level}\|%{GREEDYDATA:message}",
+        ],
+    );
+    assert_eq!(summary["format"], "plain");
+    assert_eq!(summary["errors"], 1);
+    assert_eq!(
+        summary["signatures"]["errors"]["top"][0]["signature"],
+        "channel ADT stopped"
+    );
+
+    let invalid = common::clinlogix()
+        .arg("scan")
+        .arg(&custom)
+        .args(["--pattern", "%{NOPE:x}"])
+        .output()
+        .expect("run clinlogix scan --pattern");
+    assert_eq!(invalid.status.code(), Some(2));
+}
+
+#[test]
+fn scan_counts_stack_traces_once() {
+    let dir = scratch_dir("records");
+    let log = dir.join("app.log");
+    std::fs::write(
+        &log,
+        "INFO 2026-01-07 08:05:39 Opening connection\n\
+         ERROR 2026-01-07 08:05:40 Request failed for Patient/123\n\
+         java.lang.IllegalStateException: error reading resource\n\
+         \tat org.example.Dao.read(Dao.java:42)\n\
+         Caused by: java.sql.SQLException: error in connection\n\
+         \t... 12 more\n\
+         WARNING 2026-01-07 08:05:41 Connection pool low\n",
+    )
+    .expect("write log");
+    let scan = |extra: &[&str]| {
+        common::clinlogix()
+            .arg("scan")
+            .arg(&log)
+            .args(extra)
+            .output()
+            .expect("run clinlogix scan")
+    };
+
+    let output = scan(&["--json"]);
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).expect("JSON summary");
+    assert_eq!(summary["total_lines"], 7);
+    assert_eq!(summary["records"], 3);
+    assert_eq!(summary["errors"], 1);
+    assert_eq!(summary["warnings"], 1);
+
+    let errors = String::from_utf8_lossy(&scan(&["--errors-only"]).stdout).into_owned();
+    assert_eq!(errors.lines().count(), 5, "{errors}");
+    assert!(errors.ends_with("\t... 12 more\n"));
+
+    // Every line starts a record: back to line-by-line counts.
+    let output = scan(&["--json", "--record-start", "."]);
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).expect("JSON summary");
+    assert_eq!(summary["records"], 7);
+    assert_eq!(summary["errors"], 3);
+}
+
+#[test]
+fn scan_reads_a_compressed_rotation_set() {
+    use std::io::Write as _;
+
+    let dir = scratch_dir("rotation");
+    std::fs::write(
+        dir.join("app.log"),
+        "INFO 2026-01-07 09:00:00 EHR service started\n\
+         ERROR 2026-01-07 09:01:00 Database connection failed\n",
+    )
+    .expect("write live log");
+    let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gzip.write_all(b"ERROR 2026-01-07 08:00:00 Database connection failed\n")
+        .expect("gzip");
+    std::fs::write(dir.join("app.log.1.gz"), gzip.finish().expect("gzip")).expect("write .gz");
+    let zstd = zstd::encode_all(
+        &b"WARNING 2026-01-06 23:59:00 FHIR endpoint slow response\n"[..],
+        0,
+    )
+    .expect("zstd");
+    std::fs::write(dir.join("app.log.2.zst"), zstd).expect("write .zst");
+
+    let output = common::clinlogix()
+        .arg("scan")
+        .arg(dir.join("app.log*"))
+        .args(["--json", "--since", "2026-01-07"])
+        .output()
+        .expect("run clinlogix scan");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).expect("JSON summary");
+    assert_eq!(summary["errors"], 2);
+    assert_eq!(summary["warnings"], 0);
+    let files: Vec<(String, u64)> = summary["files"]
+        .as_array()
+        .expect("per-file breakdown")
+        .iter()
+        .map(|file| {
+            let name = file["file"].as_str().unwrap_or_default();
+            let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+            (
+                name.to_string(),
+                file["errors"].as_u64().unwrap_or_default(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        files,
+        [
+            ("app.log.2.zst".to_string(), 0),
+            ("app.log.1.gz".to_string(), 1),
+            ("app.log".to_string(), 1)
+        ]
+    );
+    let first = &summary["signatures"]["errors"]["top"][0]["first_seen"];
+    assert!(
+        first["file"]
+            .as_str()
+            .unwrap_or_default()
+            .ends_with("app.log.1.gz")
+    );
+}
+
+#[test]
+fn scan_follow_alerts_when_errors_cross_the_threshold() {
+    let dir = scratch_dir("follow");
+    let log = dir.join("app.log");
+    let hook = dir.join("alerts.jsonl");
+    std::fs::write(&log, "ERROR 2026-01-07 07:59:00 Already in the log\n").expect("write log");
+
+    let mut child = common::clinlogix()
+        .arg("scan")
+        .arg(&log)
+        .args(["--follow", "--threshold", "2", "--window", "60"])
+        .arg(format!("--alert=webhook:{}", hook.display()))
+        .args(["--alert", "exit", "--json"])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("run clinlogix scan --follow");
+    thread::sleep(Duration::from_millis(700));
+
+    let append = |text: &str| {
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&log)
+            .expect("open log");
+        file.write_all(text.as_bytes()).expect("append");
+    };
+    append("ERROR 2026-01-07 08:00:01 Database connection failed\n");
+    thread::sleep(Duration::from_millis(400));
+    // Truncated by a copy-truncate rotation, then written again.
+    std::fs::write(&log, "").expect("truncate");
+    thread::sleep(Duration::from_millis(400));
+    append("WARNING 2026-01-07 08:00:02 Slow response\nERROR 2026-01-07 08:00:03 Timeout\n");
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while child.try_wait().expect("poll child").is_none() && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(50));
+    }
+    let _ = child.kill();
+    let output = child.wait_with_output().expect("follow output");
+    let payload = std::fs::read_to_string(&hook).unwrap_or_default();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "{stdout}{stderr}");
+    assert!(stderr.contains("was truncated"), "{stderr}");
+    let events: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("JSON event"))
+        .collect();
+    assert_eq!(events.len(), 3, "{stdout}");
+    assert_eq!(
+        events[0]["text"],
+        "ERROR 2026-01-07 08:00:01 Database connection failed"
+    );
+    assert_eq!(events[1]["event"], "warning");
+    assert_eq!(events[2]["in_window"], 2);
+
+    let alert: serde_json::Value = serde_json::from_str(payload.trim()).expect("webhook payload");
+    assert_eq!(alert["errors_in_window"], 2);
+    assert_eq!(alert["records"], 3);
+    assert_eq!(alert["last_error"], "ERROR 2026-01-07 08:00:03 Timeout");
+    assert!(
+        stderr.contains("Error: stopped at an error rate alert"),
+        "{stderr}"
+    );
+
+    // A zero threshold would alert on an empty window.
+    let zero = common::clinlogix()
+        .arg("scan")
+        .arg(example("interface-engine.log"))
+        .args(["--follow", "--threshold", "0"])
+        .output()
+        .expect("run clinlogix scan --follow --threshold 0");
+    assert_eq!(zero.status.code(), Some(2));
+}
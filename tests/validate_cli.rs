@@ -1,54 +1,19 @@
-use std::io::{Read, Write};
+mod common;
+
 use std::net::TcpListener;
-use std::process::Command;
-use std::thread;
-use std::time::{Duration, Instant};
-
-fn start_test_server(body: &str) -> (String, thread::JoinHandle<()>) {
-    let listener = TcpListener::bind("127.0.0.1:0").expect("bind test server");
-    listener.set_nonblocking(true).expect("set nonblocking");
-    let addr = listener.local_addr().expect("server addr");
-    let body = body.to_string();
-    let handle = thread::spawn(move || {
-        let start = Instant::now();
-        loop {
-            match listener.accept() {
-                Ok((mut stream, _)) => {
-                    let mut buf = [0u8; 4096];
-                    let _ = stream.read(&mut buf);
-                    let response = format!(
-                        "HTTP/1.1 200 OK\r\nContent-Type: application/fhir+json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
-                        body.as_bytes().len(),
-                        body
-                    );
-                    let _ = stream.write_all(response.as_bytes());
-                    break;
-                }
-                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
-                    if start.elapsed() > Duration::from_secs(2) {
-                        break;
-                    }
-                    thread::sleep(Duration::from_millis(10));
-                }
-                Err(_) => break,
-            }
-        }
-    });
-
-    (format!("http://{}", addr), handle)
-}
+
+use common::{example, scratch_dir, start_test_server, start_test_server_with_status};
 
 #[test]
 fn validate_cli_exits_nonzero_on_error_outcome() {
     let outcome = r#"{"resourceType":"OperationOutcome","issue":[{"severity":"error","code":"invalid","diagnostics":"Missing id"}]}"#;
     let (base_url, handle) = start_test_server(outcome);
 
-    let manifest_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    let example = manifest_dir.join("examples").join("patient-bad.json");
+    let resource = example("patient-bad.json");
 
-    let output = Command::new(env!("CARGO_BIN_EXE_clinlogix"))
+    let output = common::clinlogix()
         .arg("validate")
-        .arg(example)
+        .arg(resource)
         .arg("--base-url")
         .arg(&base_url)
         .output()
@@ -57,9 +22,312 @@ fn validate_cli_exits_nonzero_on_error_outcome() {
     let _ = handle.join();
 
     assert!(!output.status.success());
+    assert_eq!(output.status.code(), Some(1));
     let stdout = String::from_utf8_lossy(&output.stdout);
     let stderr = String::from_utf8_lossy(&output.stderr);
     assert!(stdout.contains("FHIR Validation"));
     assert!(stdout.contains("Result: FAIL"));
     assert!(stderr.contains("FHIR validation failed") || stdout.contains("FHIR validation failed"));
 }
+
+#[test]
+fn validate_cli_passes_when_errors_are_baselined() {
+    let outcome = r#"{"resourceType":"OperationOutcome","issue":[{"severity":"error","code":"invalid","diagnostics":"Missing id","expression":["Patient"]}]}"#;
+    let resource = example("patient-bad.json");
+    let dir = scratch_dir("baseline");
+    let baseline = dir.join("baseline.json");
+
+    let (base_url, handle) = start_test_server(outcome);
+    let write = common::clinlogix()
+        .arg("validate")
+        .arg(&resource)
+        .arg("--base-url")
+        .arg(&base_url)
+        .arg("--write-baseline")
+        .arg(&baseline)
+        .output()
+        .expect("run clinlogix validate --write-baseline");
+    let _ = handle.join();
+    assert!(write.status.success());
+
+    let (base_url, handle) = start_test_server(outcome);
+    let apply = common::clinlogix()
+        .arg("validate")
+        .arg(&resource)
+        .arg("--base-url")
+        .arg(&base_url)
+        .arg("--baseline")
+        .arg(&baseline)
+        .output()
+        .expect("run clinlogix validate --baseline");
+    let _ = handle.join();
+
+    assert!(apply.status.success());
+    let stdout = String::from_utf8_lossy(&apply.stdout);
+    assert!(stdout.contains("Suppressed by baseline: 1 issues"));
+    assert!(stdout.contains("Result: PASS"));
+}
+
+#[test]
+fn validate_cli_passes_a_412_response_when_every_issue_is_baselined() {
+    // HAPI answers 412 whenever the OperationOutcome has errors.
+    let outcome = r#"{"resourceType":"OperationOutcome","issue":[{"severity":"error","code":"invalid","diagnostics":"Missing id","expression":["Patient"]}]}"#;
+    let resource = example("patient-bad.json");
+    let dir = scratch_dir("baseline-412");
+    let baseline = dir.join("baseline.json");
+
+    let (base_url, handle) = start_test_server_with_status("412 Precondition Failed", outcome);
+    let unbaselined = common::clinlogix()
+        .arg("validate")
+        .arg(&resource)
+        .arg("--base-url")
+        .arg(&base_url)
+        .output()
+        .expect("run clinlogix validate");
+    let write = common::clinlogix()
+        .arg("validate")
+        .arg(&resource)
+        .arg("--base-url")
+        .arg(&base_url)
+        .arg("--write-baseline")
+        .arg(&baseline)
+        .output()
+        .expect("run clinlogix validate --write-baseline");
+    let apply = common::clinlogix()
+        .arg("validate")
+        .arg(&resource)
+        .arg("--base-url")
+        .arg(&base_url)
+        .arg("--baseline")
+        .arg(&baseline)
+        .output()
+        .expect("run clinlogix validate --baseline");
+    let _ = handle.join();
+
+    let (base_url, handle) = start_test_server_with_status("500 Internal Server Error", outcome);
+    let server_error = common::clinlogix()
+        .arg("validate")
+        .arg(&resource)
+        .arg("--base-url")
+        .arg(&base_url)
+        .arg("--baseline")
+        .arg(&baseline)
+        .output()
+        .expect("run clinlogix validate --baseline");
+    let _ = handle.join();
+
+    assert_eq!(unbaselined.status.code(), Some(1));
+    assert!(write.status.success());
+    let stdout = String::from_utf8_lossy(&apply.stdout);
+    assert!(apply.status.success(), "{stdout}");
+    assert!(stdout.contains("Result: PASS"));
+    assert_eq!(server_error.status.code(), Some(1));
+}
+
+#[test]
+fn validate_cli_writes_one_html_page_for_several_files() {
+    let outcome = r#"{"resourceType":"OperationOutcome","issue":[{"severity":"error","code":"code-invalid","diagnostics":"Unknown gender <code> (line: 3)","expression":["Patient.gender"]}]}"#;
+    let dir = scratch_dir("validate");
+    let page = dir.join("page.html");
+
+    let (base_url, handle) = start_test_server(outcome);
+    let output = common::clinlogix()
+        .arg("validate")
+        .arg(example("patient.json"))
+        .arg(example("patient-bad.json"))
+        .arg("--base-url")
+        .arg(&base_url)
+        .arg("--html")
+        .arg(&page)
+        .output()
+        .expect("run clinlogix validate --html");
+    let _ = handle.join();
+    let html = std::fs::read_to_string(&page).expect("read HTML report");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Result: FAIL"));
+    assert!(html.contains("<title>Validation of 2 files</title>"));
+    assert!(html.contains("patient-bad.json</a>"));
+    assert!(html.contains("<a class=\"line-link\" href=\"#report-2-line-3\">line 3</a>"));
+    assert!(html.contains(
+        "<li id=\"report-2-line-3\" class=\"flagged error\" title=\"Unknown gender &lt;code&gt; (line: 3)\">  &quot;gender&quot;: &quot;invalid-gender-value&quot;</li>"
+    ));
+}
+
+#[test]
+fn validate_cli_tui_needs_a_terminal_and_a_single_file() {
+    let resource = example("patient.json");
+
+    let piped = common::clinlogix()
+        .arg("validate")
+        .arg(&resource)
+        .arg("--tui")
+        .output()
+        .expect("run clinlogix validate --tui without a terminal");
+    assert_eq!(piped.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&piped.stderr).contains("--tui needs an interactive terminal"));
+
+    let batch = common::clinlogix()
+        .arg("validate")
+        .arg(&resource)
+        .arg(&resource)
+        .arg("--tui")
+        .output()
+        .expect("run clinlogix validate --tui on two files");
+    assert_eq!(batch.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&batch.stderr).contains("--tui takes a single file"));
+}
+
+#[test]
+fn validate_cli_uses_distinct_exit_codes_for_input_and_transport_failures() {
+    let missing = common::clinlogix()
+        .arg("validate")
+        .arg("does-not-exist.json")
+        .output()
+        .expect("run clinlogix validate on missing file");
+    assert_eq!(missing.status.code(), Some(2));
+
+    let closed_port = {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind probe");
+        listener.local_addr().expect("probe addr")
+    };
+    let unreachable = common::clinlogix()
+        .arg("validate")
+        .arg(example("patient.json"))
+        .arg("--base-url")
+        .arg(format!("http://{}", closed_port))
+        .output()
+        .expect("run clinlogix validate against closed port");
+    assert_eq!(unreachable.status.code(), Some(3));
+}
+
+#[test]
+fn validate_cli_policy_can_make_profile_resolution_non_fatal() {
+    let outcome = r#"{"resourceType":"OperationOutcome","issue":[{"severity":"error","code":"processing","diagnostics":"Unable to resolve reference to profile 'http://hl7.org/fhir/us/core/StructureDefinition/us-core-patient'"}]}"#;
+    let (base_url, handle) = start_test_server(outcome);
+
+    let output = common::clinlogix()
+        .arg("validate")
+        .arg(example("patient.json"))
+        .arg("--base-url")
+        .arg(&base_url)
+        .arg("--non-fatal-theme")
+        .arg("profile resolution")
+        .output()
+        .expect("run clinlogix validate with policy");
+    let _ = handle.join();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Policy: fail on error; non-fatal: profile resolution"));
+    assert!(stdout.contains("Result: PASS"));
+}
+
+#[test]
+fn hl7v2_validate_reports_issues_and_exit_codes() {
+    let valid = common::clinlogix()
+        .args(["hl7v2", "validate"])
+        .arg(example("adt-a01.hl7"))
+        .output()
+        .expect("run clinlogix hl7v2 validate");
+    let stdout = String::from_utf8_lossy(&valid.stdout);
+    assert!(valid.status.success(), "{stdout}");
+    assert!(stdout.contains("HL7 v2 Validation"));
+    assert!(stdout.contains("Validator: HL7 v2.5.1 ADT_A01"));
+
+    let invalid = common::clinlogix()
+        .args(["hl7v2", "validate"])
+        .arg(example("oru-r01-invalid.hl7"))
+        .output()
+        .expect("run clinlogix hl7v2 validate");
+    let stdout = String::from_utf8_lossy(&invalid.stdout);
+    assert_eq!(invalid.status.code(), Some(1));
+    assert!(stdout.contains("expression: OBX[2]-5"));
+    assert!(stdout.contains("line: 5"));
+
+    let missing = common::clinlogix()
+        .args(["hl7v2", "validate", "does-not-exist.hl7"])
+        .output()
+        .expect("run clinlogix hl7v2 validate");
+    assert_eq!(missing.status.code(), Some(2));
+}
+
+#[test]
+fn converted_hl7v2_bundle_runs_through_validate() {
+    let dir = scratch_dir("converted");
+    let bundle = dir.join("bundle.json");
+
+    let convert = common::clinlogix()
+        .args(["convert", "hl7v2-to-fhir"])
+        .arg(example("adt-a01.hl7"))
+        .arg("--timezone=-0500")
+        .arg("--output")
+        .arg(&bundle)
+        .output()
+        .expect("run clinlogix convert");
+    assert!(
+        convert.status.success(),
+        "{}",
+        String::from_utf8_lossy(&convert.stderr)
+    );
+
+    let invalid = common::clinlogix()
+        .args(["convert", "hl7v2-to-fhir"])
+        .arg(example("adt-a01.hl7"))
+        .args(["--timezone", "Europe/Berlin"])
+        .output()
+        .expect("run clinlogix convert");
+    assert_eq!(invalid.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&invalid.stderr).contains("invalid --timezone"));
+
+    let (base_url, handle) = start_test_server(r#"{"resourceType":"OperationOutcome","issue":[]}"#);
+    let validate = common::clinlogix()
+        .arg("validate")
+        .arg(&bundle)
+        .arg("--base-url")
+        .arg(&base_url)
+        .output()
+        .expect("run clinlogix validate");
+    let _ = handle.join();
+
+    let stdout = String::from_utf8_lossy(&validate.stdout);
+    assert!(validate.status.success(), "{stdout}");
+    assert!(stdout.contains("No issues reported."));
+}
+
+#[test]
+fn cda_validate_checks_required_sections() {
+    let resource = example("discharge-summary.xml");
+
+    let valid = common::clinlogix()
+        .args(["cda", "validate"])
+        .arg(&resource)
+        .output()
+        .expect("run clinlogix cda validate");
+    let stdout = String::from_utf8_lossy(&valid.stdout);
+    assert!(valid.status.success(), "{stdout}");
+    assert!(stdout.contains("Validator: C-CDA Discharge Summary"));
+
+    let text = std::fs::read_to_string(&resource).expect("read example");
+    let start = text.find("<component>\n        <section>\n          <templateId root=\"1.3.6.1.4.1.19376.1.5.3.1.3.5\"/>").expect("hospital course section");
+    let end =
+        start + text[start..].find("</component>").expect("section end") + "</component>".len();
+    let dir = scratch_dir("cda");
+    let broken = dir.join("broken.xml");
+    std::fs::write(&broken, format!("{}{}", &text[..start], &text[end..]))
+        .expect("write broken copy");
+
+    let invalid = common::clinlogix()
+        .args(["cda", "validate"])
+        .arg(&broken)
+        .output()
+        .expect("run clinlogix cda validate");
+    let stdout = String::from_utf8_lossy(&invalid.stdout);
+    assert_eq!(invalid.status.code(), Some(1), "{stdout}");
+    assert!(
+        stdout.contains(
+            "Required section Hospital Course (8648-8) is missing from Discharge Summary"
+        )
+    );
+    assert!(stdout.contains("expression: ClinicalDocument/component/structuredBody"));
+}